@@ -1,11 +1,15 @@
 //! Defines a backtest, which determines what data is sent and the
 //! conditions that trigger it to be sent.
 
-use std::sync::mpsc;
+use std::sync::{mpsc, Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
 use uuid::Uuid;
 use {BacktestType, DataSource, DataDest};
 use simbroker::SimBrokerSettings;
 use tickgrinder_util::transport::tickstream::TickstreamCommand;
+use tickgrinder_util::trading::tick::Tick;
 
 /// Contains controls for pausing, resuming, and stopping a backtest as well as
 /// some data about it.
@@ -14,7 +18,14 @@ pub struct BacktestHandle {
     pub backtest_type: BacktestType,
     pub data_source: DataSource,
     pub endpoint: DataDest,
-    pub handle: mpsc::SyncSender<TickstreamCommand>,
+    pub handle: mpsc::Sender<TickstreamCommand>,
+    pub progress: Arc<Mutex<BacktestProgress>>,
+    /// Set after the backtest has been sought so that its progress is reset
+    pub sought: Arc<AtomicBool>,
+    /// Whether the backtest's tickstream can be sought
+    pub seekable: bool,
+    /// Controls the playback speed of `Live` backtests
+    pub speed: Option<Arc<Mutex<f64>>>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -24,16 +35,31 @@ pub struct SerializableBacktestHandle {
     pub backtest_type: BacktestType,
     pub data_source: DataSource,
     pub endpoint: DataDest,
+    pub progress: BacktestProgress,
+}
+
+impl SerializableBacktestHandle {
+    pub fn from_handle(handle: &BacktestHandle, uuid: Uuid) -> SerializableBacktestHandle {
+        SerializableBacktestHandle {
+            uuid: uuid,
+            symbol: handle.symbol.clone(),
+            backtest_type: handle.backtest_type.clone(),
+            data_source: handle.data_source.clone(),
+            endpoint: handle.endpoint.clone(),
+            progress: handle.progress.lock().unwrap().clone(),
+        }
+    }
 }
 
 impl From<(Uuid, BacktestHandle)> for SerializableBacktestHandle {
     fn from((uuid, handle): (Uuid, BacktestHandle)) -> Self {
         SerializableBacktestHandle {
-            uuid,
+            uuid: uuid,
             symbol: handle.symbol,
             backtest_type: handle.backtest_type,
             data_source: handle.data_source,
             endpoint: handle.endpoint,
+            progress: handle.progress.lock().unwrap().clone(),
         }
     }
 }
@@ -51,6 +77,177 @@ pub struct BacktestDefinition {
     pub data_source: DataSource,
     pub data_dest: DataDest,
     pub broker_settings: SimBrokerSettings,
+    /// Symbols that are backtested alongside `symbol`.  The ticks of all symbols are merged into a single stream
+    /// ordered by timestamp.
+    #[serde(default)]
+    pub additional_symbols: Vec<BacktestSymbol>,
+}
+
+impl BacktestDefinition {
+    /// Returns all of the backtest's symbols and their data sources, starting with `symbol`.
+    pub fn symbols(&self) -> Vec<BacktestSymbol> {
+        let primary = BacktestSymbol {
+            symbol: self.symbol.clone(),
+            data_source: self.data_source.clone(),
+        };
+
+        Some(primary).into_iter().chain(self.additional_symbols.iter().cloned()).collect()
+    }
+}
+
+/// A symbol of a multi-symbol backtest along with where to get its data
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BacktestSymbol {
+    pub symbol: String,
+    pub data_source: DataSource,
+}
+
+/// How far along a running backtest is.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BacktestProgress {
+    pub ticks_processed: usize,
+    /// Timestamp of the last tick that was processed
+    pub current_timestamp: Option<u64>,
+    /// How much of the backtest's tick or timestamp limit has been reached, or None if it has no limit
+    pub percent_complete: Option<f64>,
+    /// Processing rate over the last sampling interval
+    pub ticks_per_second: f64,
+}
+
+/// Minimum number of milliseconds between updates of the processing rate.
+const RATE_SAMPLE_MS: u64 = 1000;
+
+/// Keeps a backtest's `BacktestProgress` up to date as ticks are processed.
+pub struct ProgressTracker {
+    progress: Arc<Mutex<BacktestProgress>>,
+    sought: Arc<AtomicBool>,
+    definition: BacktestDefinition,
+    first_timestamp: Option<u64>,
+    sample_start: Instant,
+    sample_ticks: usize,
+}
+
+impl ProgressTracker {
+    pub fn new(definition: BacktestDefinition) -> ProgressTracker {
+        let progress = BacktestProgress {
+            ticks_processed: 0,
+            current_timestamp: None,
+            percent_complete: None,
+            ticks_per_second: 0.,
+        };
+
+        ProgressTracker {
+            progress: Arc::new(Mutex::new(progress)),
+            sought: Arc::new(AtomicBool::new(false)),
+            definition: definition,
+            first_timestamp: None,
+            sample_start: Instant::now(),
+            sample_ticks: 0,
+        }
+    }
+
+    /// Returns a handle to the progress that is shared with the tracker.
+    pub fn progress(&self) -> Arc<Mutex<BacktestProgress>> {
+        self.progress.clone()
+    }
+
+    /// Returns a flag that is set to make the tracker start over from the next tick after the backtest has been
+    /// sought to a different point in time.
+    pub fn sought(&self) -> Arc<AtomicBool> {
+        self.sought.clone()
+    }
+
+    /// Records that a tick was processed.
+    pub fn tick(&mut self, t: &Tick) {
+        if self.sought.swap(false, Ordering::SeqCst) {
+            self.reset();
+        }
+        let start = match self.first_timestamp {
+            Some(start) => start,
+            None => {
+                let start = self.definition.start_time.unwrap_or(t.timestamp);
+                self.first_timestamp = Some(start);
+                start
+            },
+        };
+        self.sample_ticks += 1;
+
+        let mut progress = self.progress.lock().unwrap();
+        progress.ticks_processed += 1;
+        progress.current_timestamp = Some(t.timestamp);
+
+        // if the backtest has both limits, whichever is closer to being reached determines the progress
+        let by_ticks = self.definition.max_tick_n
+            .map(|max| progress.ticks_processed as f64 / max as f64);
+        let by_time = match self.definition.max_timestamp {
+            Some(max) if max > start => Some(t.timestamp.saturating_sub(start) as f64 / (max - start) as f64),
+            _ => None,
+        };
+        progress.percent_complete = match (by_ticks, by_time) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        }.map(|fraction| (fraction * 100.).min(100.));
+
+        let elapsed = self.sample_start.elapsed();
+        let elapsed_ms = elapsed.as_secs() * 1000 + elapsed.subsec_nanos() as u64 / 1_000_000;
+        if elapsed_ms >= RATE_SAMPLE_MS {
+            progress.ticks_per_second = self.sample_ticks as f64 * 1000. / elapsed_ms as f64;
+            self.sample_start = Instant::now();
+            self.sample_ticks = 0;
+        }
+    }
+
+    /// Starts tracking over after a seek.  The number of processed ticks keeps counting towards the tick limit,
+    /// but the processing rate is sampled again and backtests without a start time measure their progress from
+    /// the first tick after the seek.
+    fn reset(&mut self) {
+        if self.definition.start_time.is_none() {
+            self.first_timestamp = None;
+        }
+        self.sample_start = Instant::now();
+        self.sample_ticks = 0;
+
+        let mut progress = self.progress.lock().unwrap();
+        progress.current_timestamp = None;
+        progress.percent_complete = None;
+        progress.ticks_per_second = 0.;
+    }
+}
+
+#[test]
+fn progress_tracking() {
+    use simbroker::SimBrokerSettings;
+
+    let definition = BacktestDefinition {
+        start_time: Some(1000),
+        max_timestamp: Some(2000),
+        max_tick_n: Some(10),
+        symbol: "TEST".to_string(),
+        backtest_type: BacktestType::Fast{delay_ms: 0},
+        data_source: DataSource::Random,
+        data_dest: DataDest::Null,
+        broker_settings: SimBrokerSettings::default(),
+        additional_symbols: Vec::new(),
+    };
+    let mut tracker = ProgressTracker::new(definition);
+    let progress = tracker.progress();
+
+    tracker.tick(&Tick {bid: 1, ask: 2, timestamp: 1100});
+    assert_eq!(progress.lock().unwrap().ticks_processed, 1);
+    // one of ten ticks and 100 of 1000 ms are both 10%
+    assert!((progress.lock().unwrap().percent_complete.unwrap() - 10.).abs() < 1e-9);
+
+    tracker.tick(&Tick {bid: 1, ask: 2, timestamp: 1500});
+    let p = progress.lock().unwrap().clone();
+    assert_eq!(p.current_timestamp, Some(1500));
+    assert!((p.percent_complete.unwrap() - 50.).abs() < 1e-9);
+
+    // seeking back moves the progress back with it
+    tracker.sought().store(true, Ordering::SeqCst);
+    tracker.tick(&Tick {bid: 1, ask: 2, timestamp: 1200});
+    let p = progress.lock().unwrap().clone();
+    assert_eq!((p.ticks_processed, p.current_timestamp), (3, Some(1200)));
+    assert!((p.percent_complete.unwrap() - 30.).abs() < 1e-9);
 }
 
 /// Ticks sent to the SimBroker should be re-broadcast to the client.
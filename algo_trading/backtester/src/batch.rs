@@ -0,0 +1,542 @@
+//! Runs batches of backtests in parallel on a pool of worker threads.  Batch runs aren't throttled; every run
+//! sends its ticks to its destination as fast as they can be produced.  Historical data is decoded once per
+//! source, symbol, and time range and shared read-only between all of the runs in the batch that use it.
+//!
+//! Runs that name a strategy are instead backtested against their own in-process SimBroker like the ones started
+//! from the command line, which gives them performance results, and are stored like them.  The SimBroker reads its
+//! data itself, so these runs don't use the cache.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use std::time::Instant;
+
+use futures::Stream;
+use num_cpus;
+use serde_json;
+use uuid::Uuid;
+
+use tickgrinder_util::transport::command_server::CommandServer;
+use tickgrinder_util::transport::tickstream::*;
+use tickgrinder_util::trading::tick::Tick;
+use tickgrinder_util::trading::performance::PerformanceReport;
+use tickgrinder_util::conf::CONF;
+
+use backtest::{BacktestDefinition, BacktestSymbol};
+use cli::RunDefinition;
+use runs::run_and_store;
+use {DataSource, DataDest, resolve_data_source, check_early_exit};
+
+/// How many ticks a run processes between updates of the batch's aggregate tick count.
+const PROGRESS_INTERVAL: usize = 10000;
+
+/// One run of a batch.  If `strategy` is set, the strategy is backtested against an in-process SimBroker and the
+/// backtest's destination and type are ignored.  Otherwise the backtest's ticks are sent to its destination.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BatchDefinition {
+    /// The name of the strategy as passed to `private::strategies::get_strategy()`
+    #[serde(default)]
+    pub strategy: Option<String>,
+    #[serde(default)]
+    pub strategy_settings: HashMap<String, String>,
+    /// Number of decimal places in the prices of the backtest's symbols
+    #[serde(default)]
+    pub decimal_precision: usize,
+    pub backtest: BacktestDefinition,
+}
+
+impl BatchDefinition {
+    /// Returns the definition of the strategy backtest run by this run of the batch or None if it has no strategy.
+    fn to_run_definition(&self) -> Option<RunDefinition> {
+        self.strategy.as_ref().map(|strategy| RunDefinition {
+            strategy: strategy.clone(),
+            strategy_settings: self.strategy_settings.clone(),
+            decimal_precision: self.decimal_precision,
+            backtest: self.backtest.clone(),
+        })
+    }
+}
+
+/// The outcome of one run of a batch.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BatchRunResult {
+    /// Index of the run's definition in the batch
+    pub index: usize,
+    /// Number of ticks that were sent to the run's destination
+    pub tick_count: usize,
+    /// Timestamp of the last tick that was sent or None if no ticks were sent
+    pub last_timestamp: Option<u64>,
+    pub elapsed_ms: u64,
+    /// The performance of the run's strategy or None if it didn't have one
+    pub report: Option<PerformanceReport>,
+    /// The ID that the run of the strategy was stored under, or None if it didn't have one or couldn't be stored
+    pub run_id: Option<Uuid>,
+    /// The reason that the run failed, if it did
+    pub error: Option<String>,
+}
+
+/// The progress of a batch and the results of all of its finished runs.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BatchStatus {
+    pub uuid: Uuid,
+    pub total_runs: usize,
+    pub completed_runs: usize,
+    pub failed_runs: usize,
+    /// Number of ticks processed by all runs so far, including runs that are still in progress
+    pub ticks_processed: usize,
+    /// Results of the finished runs in the order that they finished
+    pub results: Vec<BatchRunResult>,
+}
+
+impl BatchStatus {
+    pub fn is_finished(&self) -> bool {
+        self.completed_runs == self.total_runs
+    }
+}
+
+/// Identifies a set of decoded ticks by data source, symbol, and the limits of the backtest that loaded them.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct CacheKey {
+    /// The JSON encoding of the data source, which includes all of its settings
+    source: String,
+    symbol: String,
+    start_time: Option<u64>,
+    max_timestamp: Option<u64>,
+    max_tick_n: Option<usize>,
+}
+
+impl CacheKey {
+    fn new(def: &BacktestDefinition, symbol: &BacktestSymbol) -> Result<CacheKey, String> {
+        Ok(CacheKey {
+            source: serde_json::to_string(&symbol.data_source).map_err(|err| format!("{:?}", err))?,
+            symbol: symbol.symbol.clone(),
+            start_time: def.start_time,
+            max_timestamp: def.max_timestamp,
+            max_tick_n: def.max_tick_n,
+        })
+    }
+}
+
+/// Holds the ticks for one key once they have been loaded.
+type CacheSlot = Arc<Mutex<Option<Arc<Vec<Tick>>>>>;
+
+/// Read-only storage for historical ticks shared between the runs of a batch.  Each set of ticks is loaded by
+/// the first run that needs it while other runs that need the same ticks wait for it to finish.
+pub struct TickCache {
+    slots: Mutex<HashMap<CacheKey, CacheSlot>>,
+}
+
+impl TickCache {
+    pub fn new() -> TickCache {
+        TickCache {
+            slots: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the ticks for the symbol's source within the definition's limits, loading them if necessary.
+    pub fn get(&self, def: &BacktestDefinition, symbol: &BacktestSymbol) -> Result<Arc<Vec<Tick>>, String> {
+        let key = CacheKey::new(def, symbol)?;
+        let slot = {
+            let mut slots = self.slots.lock().unwrap();
+            slots.entry(key).or_insert_with(|| Arc::new(Mutex::new(None))).clone()
+        };
+
+        // only the slot is held while loading so runs over other data aren't blocked
+        let mut slot = slot.lock().unwrap();
+        if let Some(ref ticks) = *slot {
+            return Ok(ticks.clone());
+        }
+        let ticks = Arc::new(load_ticks(def, symbol)?);
+        *slot = Some(ticks.clone());
+        Ok(ticks)
+    }
+}
+
+/// Reads the ticks for the symbol's source from the definition's start time up to the first tick that meets one
+/// of its stop conditions.  No run of the definition can use any ticks past that, even if it merges several symbols.
+fn load_ticks(def: &BacktestDefinition, symbol: &BacktestSymbol) -> Result<Vec<Tick>, String> {
+    let mut ticks = Vec::new();
+    for t in raw_ticks(def, symbol)? {
+        ticks.push(t);
+        if check_early_exit(&t, def, ticks.len()) {
+            break;
+        }
+    }
+    if ticks.is_empty() {
+        return Err(format!("No data found for symbol {} in {:?}", symbol.symbol, symbol.data_source));
+    }
+
+    Ok(ticks)
+}
+
+/// Returns an iterator over the ticks of the symbol's source starting at the definition's start time.
+fn raw_ticks(def: &BacktestDefinition, symbol: &BacktestSymbol) -> Result<Box<Iterator<Item=Tick> + Send>, String> {
+    let mut src = resolve_data_source(&symbol.data_source, symbol.symbol.clone(), def.start_time);
+    let stream = src.get_raw()?;
+    Ok(Box::new(stream.wait().take_while(|t| t.is_ok()).map(|t| t.unwrap())))
+}
+
+/// Makes sure that a definition can be run as part of a batch.
+pub fn validate_definition(batch_def: &BatchDefinition) -> Result<(), String> {
+    if let Some(run_def) = batch_def.to_run_definition() {
+        return run_def.to_sim_backtest().map(|_| ());
+    }
+
+    let def = &batch_def.backtest;
+    let symbols = def.symbols();
+    for symbol in &symbols {
+        match symbol.data_source {
+            DataSource::RedisChannel{..} => return Err(String::from("Live Redis data sources can't be used in batches.")),
+            DataSource::Bootstrap{ref source, ..} if !source.is_finite() => {
+                return Err(String::from("Only data sources that end can be bootstrapped."));
+            },
+            ref source if !source.is_finite() && def.max_tick_n.is_none() && def.max_timestamp.is_none() => {
+                return Err(String::from("Batch runs over endless data such as random ticks need a tick or timestamp limit."));
+            },
+            DataSource::Faulty{ref faults, ..} => faults.validate()?,
+            _ => (),
+        }
+    }
+    match def.data_dest {
+        DataDest::SimBroker{..} => {
+            return Err(String::from("Batch runs can only be backtested against a SimBroker if they have a strategy."));
+        },
+        DataDest::RedisBars{..} if symbols.len() > 1 => {
+            return Err(String::from("Bars can only be published for single-symbol backtests."));
+        },
+        _ => (),
+    }
+
+    Ok(())
+}
+
+/// Returns the `TickSink` for a definition's destination that receives the ticks of one of its symbols.
+fn resolve_sink(def: &BacktestDefinition, symbol: &BacktestSymbol) -> Result<Box<TickSink + Send>, String> {
+    match def.data_dest {
+        DataDest::RedisChannel{ref host, ref channel} => {
+            Ok(Box::new(RedisSink::new(symbol.symbol.clone(), channel.clone(), host.as_str())))
+        },
+        DataDest::RedisBars{ref host, ref channel, bars} => Ok(Box::new(RedisBarSink::new(bars, channel.clone(), host.as_str())?)),
+        DataDest::Console => Ok(Box::new(ConsoleSink{})),
+        DataDest::Null => Ok(Box::new(NullSink{})),
+        DataDest::SimBroker{..} => {
+            Err(String::from("Batch runs can only be backtested against a SimBroker if they have a strategy."))
+        },
+    }
+}
+
+/// Sends ticks to their symbols' sinks until they run out or one of the definition's stop conditions is reached,
+/// adding to the batch's tick count as it goes.
+fn drive<I: Iterator<Item=(usize, Tick)>>(
+    ticks: I, def: &BacktestDefinition, sinks: &mut [Box<TickSink + Send>], status: &Mutex<BatchStatus>
+) -> (usize, Option<u64>) {
+    let mut tick_count = 0;
+    let mut last_timestamp = None;
+    for (index, t) in ticks {
+        sinks[index].tick(t);
+        tick_count += 1;
+        last_timestamp = Some(t.timestamp);
+
+        if tick_count % PROGRESS_INTERVAL == 0 {
+            status.lock().unwrap().ticks_processed += PROGRESS_INTERVAL;
+        }
+        if check_early_exit(&t, def, tick_count) {
+            break;
+        }
+    }
+
+    status.lock().unwrap().ticks_processed += tick_count % PROGRESS_INTERVAL;
+    (tick_count, last_timestamp)
+}
+
+/// Sends the ticks of a run without a strategy to the backtest's destination, reading historical data through
+/// the cache.
+fn run_ticks(
+    def: &BacktestDefinition, cache: &TickCache, status: &Mutex<BatchStatus>
+) -> Result<(usize, Option<u64>), String> {
+    let symbols = def.symbols();
+    let mut sinks = Vec::with_capacity(symbols.len());
+    let mut sources = Vec::with_capacity(symbols.len());
+    for symbol in &symbols {
+        sinks.push(resolve_sink(def, symbol)?);
+        // endless data such as random ticks can't be cached, so it's generated by each run instead
+        let ticks: Box<Iterator<Item=Tick> + Send> = match symbol.data_source {
+            ref source if !source.is_finite() => raw_ticks(def, symbol)?,
+            _ => {
+                let ticks = cache.get(def, symbol)?;
+                Box::new((0..ticks.len()).map(move |i| ticks[i]))
+            },
+        };
+        sources.push(ticks);
+    }
+
+    Ok(drive(MergedTicks::new(sources), def, &mut sinks, status))
+}
+
+/// Backtests the strategy of a run against an in-process SimBroker and stores the run, returning the ID that it
+/// was stored under.  Failing to store the run is logged but doesn't fail it.
+fn run_strategy(
+    index: usize, run_def: &RunDefinition, status: &Mutex<BatchStatus>, cs: &mut CommandServer
+) -> Result<(usize, Option<u64>, PerformanceReport, Option<Uuid>), String> {
+    let backtest = run_def.to_sim_backtest()?;
+    let (res, stored) = run_and_store(run_def, &backtest)?;
+
+    let uuid_string = {
+        let mut status = status.lock().unwrap();
+        status.ticks_processed += res.tick_count;
+        status.uuid.hyphenated().to_string()
+    };
+    let run_id = match stored {
+        Ok(id) => Some(id),
+        Err(err) => {
+            cs.warning(Some(&uuid_string), &format!("Unable to store batch run {}: {}", index, err));
+            None
+        },
+    };
+    let last_timestamp = if res.tick_count > 0 { Some(res.last_timestamp) } else { None };
+    Ok((res.tick_count, last_timestamp, res.report, run_id))
+}
+
+/// Executes a single run of a batch.
+fn run_one(
+    index: usize, def: &BatchDefinition, cache: &TickCache, status: &Mutex<BatchStatus>, cs: &mut CommandServer
+) -> BatchRunResult {
+    let start = Instant::now();
+    let res = validate_definition(def).and_then(|_| match def.to_run_definition() {
+        Some(run_def) => run_strategy(index, &run_def, status, cs)
+            .map(|(n, last, report, run_id)| (n, last, Some(report), run_id)),
+        None => run_ticks(&def.backtest, cache, status).map(|(n, last)| (n, last, None, None)),
+    });
+
+    let elapsed = start.elapsed();
+    let elapsed_ms = elapsed.as_secs() * 1000 + elapsed.subsec_nanos() as u64 / 1_000_000;
+    match res {
+        Ok((tick_count, last_timestamp, report, run_id)) => BatchRunResult {
+            index: index,
+            tick_count: tick_count,
+            last_timestamp: last_timestamp,
+            elapsed_ms: elapsed_ms,
+            report: report,
+            run_id: run_id,
+            error: None,
+        },
+        Err(err) => BatchRunResult {
+            index: index,
+            tick_count: 0,
+            last_timestamp: None,
+            elapsed_ms: elapsed_ms,
+            report: None,
+            run_id: None,
+            error: Some(err),
+        },
+    }
+}
+
+/// Returns the number of worker threads to use for a batch of `runs` backtests, which is one per CPU core unless a
+/// number of batch workers is configured or the batch has fewer runs than that.
+pub fn worker_count(runs: usize) -> usize {
+    let workers = match CONF.backtester_batch_workers {
+        0 => num_cpus::get(),
+        n => n,
+    };
+    workers.min(runs).max(1)
+}
+
+/// Starts running a batch of backtests on a pool of worker threads, returning its status which is updated as
+/// runs progress and finish.  The returned handles can be joined to wait for the batch to finish.
+pub fn start_batch(
+    uuid: Uuid, definitions: Vec<BatchDefinition>, cs: CommandServer
+) -> (Arc<Mutex<BatchStatus>>, Vec<thread::JoinHandle<()>>) {
+    let status = Arc::new(Mutex::new(BatchStatus {
+        uuid: uuid,
+        total_runs: definitions.len(),
+        completed_runs: 0,
+        failed_runs: 0,
+        ticks_processed: 0,
+        results: Vec::new(),
+    }));
+    let cache = Arc::new(TickCache::new());
+
+    let workers = worker_count(definitions.len());
+    let (tx, rx) = mpsc::channel();
+    for job in definitions.into_iter().enumerate() {
+        tx.send(job).unwrap();
+    }
+    drop(tx);
+    let rx = Arc::new(Mutex::new(rx));
+
+    let handles = (0..workers).map(|_| {
+        let rx = rx.clone();
+        let cache = cache.clone();
+        let status = status.clone();
+        let mut cs = cs.clone();
+        let uuid_string = uuid.hyphenated().to_string();
+
+        thread::spawn(move || loop {
+            // the lock is released as soon as the next job has been taken
+            let job = rx.lock().unwrap().recv();
+            let (index, def) = match job {
+                Ok(job) => job,
+                Err(_) => break,
+            };
+
+            let res = run_one(index, &def, &cache, &status, &mut cs);
+            let mut status = status.lock().unwrap();
+            status.completed_runs += 1;
+            match res.error {
+                Some(ref err) => {
+                    status.failed_runs += 1;
+                    cs.error(Some(&uuid_string), &format!("Batch run {} failed: {}", index, err));
+                },
+                None => cs.debug(Some(&uuid_string), &format!(
+                    "Batch run {} finished: {} ticks in {} ms", index, res.tick_count, res.elapsed_ms
+                )),
+            };
+            cs.notice(Some(&uuid_string), &format!(
+                "Backtest batch progress: {}/{} runs complete, {} failed, {} ticks processed",
+                status.completed_runs, status.total_runs, status.failed_runs, status.ticks_processed
+            ));
+            status.results.push(res);
+        })
+    }).collect();
+
+    (status, handles)
+}
+
+#[cfg(test)]
+fn test_definition(max_tick_n: Option<usize>, data_source: DataSource, data_dest: DataDest) -> BatchDefinition {
+    use simbroker::SimBrokerSettings;
+    use BacktestType;
+
+    BatchDefinition {
+        strategy: None,
+        strategy_settings: HashMap::new(),
+        decimal_precision: 0,
+        backtest: BacktestDefinition {
+            start_time: None,
+            max_timestamp: None,
+            max_tick_n: max_tick_n,
+            symbol: "TEST".to_string(),
+            backtest_type: BacktestType::Fast{delay_ms: 0},
+            data_source: data_source,
+            data_dest: data_dest,
+            broker_settings: SimBrokerSettings::default(),
+            additional_symbols: Vec::new(),
+        },
+    }
+}
+
+#[test]
+fn batch_definition_validation() {
+    use tickgrinder_util::trading::bar::{BarDefinition, BarType};
+    use tickgrinder_util::trading::synthetic::{SyntheticConfig, PriceModel, SpreadModel, ArrivalProcess};
+    use tickgrinder_util::trading::bootstrap::BootstrapConfig;
+
+    assert!(validate_definition(&test_definition(Some(10), DataSource::Random, DataDest::Null)).is_ok());
+    assert!(validate_definition(&test_definition(None, DataSource::Random, DataDest::Null)).is_err());
+    assert!(validate_definition(&test_definition(Some(10), DataSource::Flatfile, DataDest::SimBroker{uuid: Uuid::new_v4(), decimal_precision: 5})).is_err());
+    let redis_src = DataSource::RedisChannel{host: String::from("localhost"), channel: String::from("ticks")};
+    assert!(validate_definition(&test_definition(Some(10), redis_src, DataDest::Null)).is_err());
+    let synthetic = SyntheticConfig {
+        seed: 1,
+        start_time: 0,
+        tick_count: None,
+        initial_price: 1.,
+        pip_scale: 100_000.,
+        model: PriceModel::Gbm{drift: 0., volatility: 0.1},
+        spread: SpreadModel::Fixed{spread: 1},
+        arrivals: ArrivalProcess::Poisson{rate: 1.},
+    };
+    assert!(validate_definition(&test_definition(None, DataSource::Synthetic{config: synthetic.clone()}, DataDest::Null)).is_err());
+    let finite = SyntheticConfig{tick_count: Some(10), ..synthetic};
+    assert!(validate_definition(&test_definition(None, DataSource::Synthetic{config: finite}, DataDest::Null)).is_ok());
+    let bootstrap = |source| DataSource::Bootstrap {
+        source: Box::new(source),
+        history_start: None,
+        history_end: None,
+        config: BootstrapConfig{seed: 1, path: 0, block_size: 10, bucket_ms: 3_600_000, max_interval_ms: 3_600_000, tick_count: None},
+    };
+    assert!(validate_definition(&test_definition(None, bootstrap(DataSource::Flatfile), DataDest::Null)).is_ok());
+    assert!(validate_definition(&test_definition(Some(10), bootstrap(DataSource::Random), DataDest::Null)).is_err());
+
+    let mut multi = test_definition(Some(10), DataSource::Random, DataDest::Null);
+    multi.backtest.additional_symbols.push(BacktestSymbol{symbol: String::from("TEST2"), data_source: DataSource::Flatfile});
+    assert!(validate_definition(&multi).is_ok());
+    let bars = BarDefinition{bar_type: BarType::Tick{count: 10}, session: None};
+    multi.backtest.data_dest = DataDest::RedisBars{host: String::from("localhost"), channel: String::from("bars"), bars: bars};
+    assert!(validate_definition(&multi).is_err());
+    let max_workers = if CONF.backtester_batch_workers == 0 { num_cpus::get() } else { CONF.backtester_batch_workers };
+    assert!(worker_count(1) == 1 && worker_count(1000) >= 1 && worker_count(1000) <= max_workers.max(1));
+
+    // runs with a strategy get their own SimBroker, so the destination doesn't matter
+    let mut strategy_run = test_definition(Some(10), DataSource::Random, DataDest::SimBroker{uuid: Uuid::new_v4(), decimal_precision: 5});
+    strategy_run.strategy = Some(String::from("sma_cross"));
+    assert!(validate_definition(&strategy_run).is_ok());
+    strategy_run.backtest.max_tick_n = None;
+    assert!(validate_definition(&strategy_run).is_err());
+}
+
+#[test]
+fn tick_cache_bounds_loads() {
+    use tickgrinder_util::trading::synthetic::{SyntheticConfig, PriceModel, SpreadModel, ArrivalProcess};
+
+    let synthetic = DataSource::Synthetic{config: SyntheticConfig {
+        seed: 1,
+        start_time: 0,
+        tick_count: Some(1000),
+        initial_price: 1.,
+        pip_scale: 100_000.,
+        model: PriceModel::Gbm{drift: 0., volatility: 0.1},
+        spread: SpreadModel::Fixed{spread: 1},
+        arrivals: ArrivalProcess::Poisson{rate: 1.},
+    }};
+    let cache = TickCache::new();
+    let short = test_definition(Some(10), synthetic.clone(), DataDest::Null).backtest;
+    let long = test_definition(Some(20), synthetic, DataDest::Null).backtest;
+    let short_symbol = &short.symbols()[0];
+
+    assert_eq!(cache.get(&short, short_symbol).unwrap().len(), 10);
+    assert_eq!(cache.get(&short, short_symbol).unwrap().len(), 10);
+    // runs with other limits need other ticks
+    assert_eq!(cache.get(&long, &long.symbols()[0]).unwrap().len(), 20);
+    assert_eq!(cache.slots.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn batch_runs_in_parallel() {
+    let definitions: Vec<BatchDefinition> = (1..9)
+        .map(|n| test_definition(Some(n * 100), DataSource::Random, DataDest::Null))
+        .chain(Some(test_definition(None, DataSource::Random, DataDest::Null)))
+        .collect();
+
+    let (status, handles) = start_batch(Uuid::new_v4(), definitions, CommandServer::new(Uuid::new_v4(), "Batch Test"));
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let status = status.lock().unwrap();
+    assert!(status.is_finished());
+    assert_eq!(status.failed_runs, 1);
+    assert_eq!(status.ticks_processed, (1..9).map(|n| n * 100).sum::<usize>());
+    let mut results = status.results.clone();
+    results.sort_by_key(|res| res.index);
+    assert_eq!(results[3].tick_count, 400);
+    assert!(results[8].error.is_some());
+}
+
+#[test]
+fn batch_merges_symbols() {
+    let mut def = test_definition(Some(500), DataSource::Random, DataDest::Null);
+    def.backtest.additional_symbols.push(BacktestSymbol{symbol: String::from("TEST2"), data_source: DataSource::Random});
+
+    let (status, handles) = start_batch(Uuid::new_v4(), vec![def], CommandServer::new(Uuid::new_v4(), "Batch Test"));
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let status = status.lock().unwrap();
+    assert_eq!(status.failed_runs, 0);
+    // both random sources count up from the same timestamp so the ticks alternate between them
+    assert_eq!(status.results[0].tick_count, 500);
+    assert_eq!(status.results[0].last_timestamp, Some(249));
+}
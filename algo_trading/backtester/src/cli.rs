@@ -0,0 +1,394 @@
+//! Runs a single backtest from the command line without the rest of the platform.  A strategy from the `private`
+//! crate is run against an in-process SimBroker and the results are written to an output directory:
+//!
+//! ```sh
+//! ./backtester run backtest.toml output/
+//! ```
+//!
+//! The definition file can be TOML or JSON (chosen by extension) and contains the strategy's name, its settings,
+//! and a `BacktestDefinition`.  The backtest's data source determines what data the SimBroker is driven by;
+//! its destination and type are ignored since ticks are always processed as fast as possible.
+//!
+//! Completed runs are also stored in Postgres.  Stored runs can be listed, optionally filtered by a search string
+//! matching their ID, strategy, or git revision, and two runs can be compared side by side:
+//!
+//! ```sh
+//! ./backtester runs [search]
+//! ./backtester diff [run id] [run id]
+//! ```
+//!
+//! The data of a definition's symbols can be checked for problems such as out-of-order or crossed ticks, spikes,
+//! and gaps before it's used, printing up to `max issues` (default 20) of the problems found for each symbol:
+//!
+//! ```sh
+//! ./backtester check backtest.toml [max issues]
+//! ```
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::Path;
+
+use toml;
+use serde_json;
+
+use uuid::Uuid;
+
+use tickgrinder_util::transport::command_server::set_offline_mode;
+use tickgrinder_util::transport::tickstream::TickGenerators;
+use tickgrinder_util::transport::tickstream::maps::{QualityConfig, check_source};
+use tickgrinder_util::trading::performance::{Trade, equity_curve};
+use simbroker::{SimBacktestDefinition, SimBacktestResult};
+
+use backtest::{BacktestDefinition, BacktestSymbol};
+use runs::{RunDiff, RUN_LIST_LIMIT, run_and_store, list_runs, load_run};
+use DataSource;
+
+/// The backtest ran and its results were written
+pub const EXIT_SUCCESS: i32 = 0;
+/// The command line arguments were invalid
+pub const EXIT_USAGE: i32 = 1;
+/// The definition file couldn't be read or is invalid
+pub const EXIT_BAD_DEFINITION: i32 = 2;
+/// The strategy couldn't be created or the backtest failed
+pub const EXIT_BACKTEST_FAILED: i32 = 3;
+/// The results couldn't be written to the output directory
+pub const EXIT_OUTPUT_FAILED: i32 = 4;
+/// Stored runs couldn't be retrieved
+pub const EXIT_STORE_FAILED: i32 = 5;
+/// Data quality problems were found in the data of a definition's symbols
+pub const EXIT_DATA_ISSUES: i32 = 6;
+
+const USAGE: &'static str = "Usage: ./backtester run [definition.toml|definition.json] [output directory]";
+const DIFF_USAGE: &'static str = "Usage: ./backtester diff [run id] [run id]";
+const CHECK_USAGE: &'static str = "Usage: ./backtester check [definition.toml|definition.json] [max issues]";
+/// Number of problems printed for each symbol by `check` if no other number is given
+const DEFAULT_MAX_ISSUES: usize = 20;
+
+/// The contents of a definition file.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RunDefinition {
+    /// The name of the strategy as passed to `private::strategies::get_strategy()`
+    pub strategy: String,
+    #[serde(default)]
+    pub strategy_settings: HashMap<String, String>,
+    /// Number of decimal places in the prices of the backtest's symbols
+    #[serde(default)]
+    pub decimal_precision: usize,
+    pub backtest: BacktestDefinition,
+}
+
+impl RunDefinition {
+    /// Reads a definition from a TOML file, or a JSON file if the file's extension is `.json`.
+    pub fn load(path: &Path) -> Result<RunDefinition, String> {
+        let mut contents = String::new();
+        File::open(path).and_then(|mut f| f.read_to_string(&mut contents))
+            .map_err(|err| format!("Unable to read {:?}: {}", path, err))?;
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => serde_json::from_str(&contents).map_err(|err| format!("Unable to parse {:?}: {}", path, err)),
+            _ => toml::from_str(&contents).map_err(|err| format!("Unable to parse {:?}: {}", path, err)),
+        }
+    }
+
+    /// Converts the definition into an in-process SimBroker backtest that gets its data from the data sources of
+    /// the backtest's symbols.
+    pub fn to_sim_backtest(&self) -> Result<SimBacktestDefinition, String> {
+        let def = &self.backtest;
+        let mut broker_settings = def.broker_settings.clone();
+        let mut tickstreams = Vec::new();
+        for s in def.symbols() {
+            let generator = self.tick_generator(&s)?;
+            // the SimBroker merges the ticks of all of its tickstreams by timestamp
+            tickstreams.push((s.symbol, generator, broker_settings.fx, self.decimal_precision));
+        }
+        broker_settings.tickstreams = serde_json::to_string(&tickstreams).map_err(|err| format!("{:?}", err))?;
+
+        Ok(SimBacktestDefinition {
+            broker_settings: broker_settings,
+            min_timestamp: None,
+            max_timestamp: def.max_timestamp,
+            max_tick_n: def.max_tick_n,
+            close_open_positions: false,
+        })
+    }
+
+    /// Returns the generator that reads the ticks of one of the backtest's symbols.
+    fn tick_generator(&self, s: &BacktestSymbol) -> Result<TickGenerators, String> {
+        let def = &self.backtest;
+        if !s.data_source.is_finite() && def.max_tick_n.is_none() && def.max_timestamp.is_none() {
+            return Err(String::from("Backtests over endless data such as random ticks need a tick or timestamp limit."));
+        }
+
+        source_generator(&s.symbol, &s.data_source, def.start_time)
+    }
+}
+
+/// Converts a data source into the generator that reads it.
+fn source_generator(symbol: &str, source: &DataSource, start_time: Option<u64>) -> Result<TickGenerators, String> {
+    let symbol = symbol.to_string();
+    Ok(match *source {
+        DataSource::Flatfile => TickGenerators::FlatfileReader{symbol: symbol, start_time: start_time},
+        DataSource::Binary => TickGenerators::BinaryReader{symbol: symbol, start_time: start_time},
+        DataSource::Csv{ref schema} => {
+            TickGenerators::CsvReader{symbol: symbol, start_time: start_time, schema: schema.clone()}
+        },
+        DataSource::Parquet => TickGenerators::ParquetReader{symbol: symbol, start_time: start_time, end_time: None},
+        DataSource::Postgres => TickGenerators::PostgresReader{symbol: symbol, start_time: start_time},
+        DataSource::Random => TickGenerators::RandomReader,
+        DataSource::Synthetic{ref config} => {
+            TickGenerators::SyntheticReader{config: config.clone(), start_time: start_time}
+        },
+        DataSource::Bootstrap{ref source, history_start, history_end, ref config} => {
+            if !source.is_finite() {
+                return Err(String::from("Only data sources that end can be bootstrapped."));
+            }
+            TickGenerators::BootstrapReader{
+                source: Box::new(source_generator(&symbol, source, history_start)?),
+                history_start: history_start,
+                history_end: history_end,
+                config: config.clone(),
+                start_time: start_time,
+            }
+        },
+        DataSource::Faulty{ref source, ref faults} => {
+            faults.validate()?;
+            let source = Box::new(source_generator(&symbol, source, start_time)?);
+            TickGenerators::FaultyReader{source: source, faults: faults.clone()}
+        },
+        DataSource::RedisChannel{..} => return Err(String::from("Redis data sources can't be used from the command line.")),
+    })
+}
+
+/// Writes `report.json`, `trades.csv`, and `equity.csv` into the output directory.
+fn write_results(dir: &Path, res: &SimBacktestResult) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|err| format!("Unable to create {:?}: {}", dir, err))?;
+    let write_file = |name: &str, contents: &str| {
+        let path = dir.join(name);
+        File::create(&path).and_then(|mut f| f.write_all(contents.as_bytes()))
+            .map_err(|err| format!("Unable to write {:?}: {}", path, err))
+    };
+
+    let report = serde_json::to_string_pretty(&res.report).map_err(|err| format!("{:?}", err))?;
+    write_file("report.json", &report)?;
+    write_file("trades.csv", &trades_csv(&res.trades))?;
+
+    let mut equity = String::from("timestamp,balance\n");
+    for (timestamp, balance) in equity_curve(&res.trades, res.report.starting_balance) {
+        equity.push_str(&format!("{},{}\n", timestamp, balance));
+    }
+    write_file("equity.csv", &equity)
+}
+
+fn trades_csv(trades: &[Trade]) -> String {
+    let mut csv = String::from("symbol_id,long,size,entry_price,exit_price,entry_time,exit_time,pnl\n");
+    for t in trades {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{}\n",
+            t.symbol_id, t.long, t.size, t.entry_price, t.exit_price, t.entry_time, t.exit_time, t.pnl()
+        ));
+    }
+
+    csv
+}
+
+/// Runs the `run` subcommand with the arguments following it, returning the process's exit code.
+pub fn run(args: &[String]) -> i32 {
+    let (def_path, out_dir) = match *args {
+        [ref def_path, ref out_dir] => (Path::new(def_path), Path::new(out_dir)),
+        _ => {
+            println!("{}", USAGE);
+            return EXIT_USAGE;
+        },
+    };
+
+    let def = match RunDefinition::load(def_path) {
+        Ok(def) => def,
+        Err(err) => {
+            println!("{}", err);
+            return EXIT_BAD_DEFINITION;
+        },
+    };
+    let backtest = match def.to_sim_backtest() {
+        Ok(backtest) => backtest,
+        Err(err) => {
+            println!("Invalid backtest definition: {}", err);
+            return EXIT_BAD_DEFINITION;
+        },
+    };
+
+    set_offline_mode();
+    let (res, stored) = match run_and_store(&def, &backtest) {
+        Ok(outcome) => outcome,
+        Err(err) => {
+            println!("Backtest failed: {}", err);
+            return EXIT_BACKTEST_FAILED;
+        },
+    };
+
+    if let Err(err) = write_results(out_dir, &res) {
+        println!("{}", err);
+        return EXIT_OUTPUT_FAILED;
+    }
+    println!(
+        "Processed {} ticks and made {} trades; total return: {:.4}, max drawdown: {:.4}, Sharpe: {:.4}",
+        res.tick_count, res.report.trade_count, res.report.total_return, res.report.max_drawdown, res.report.sharpe
+    );
+
+    // the results have already been written, so failing to store them isn't fatal
+    match stored {
+        Ok(id) => println!("Stored run {}", id),
+        Err(err) => println!("Unable to store the run: {}", err),
+    }
+
+    EXIT_SUCCESS
+}
+
+/// Runs the `runs` subcommand, printing the stored runs that match the optional search string.
+pub fn list(args: &[String]) -> i32 {
+    let search = args.get(0).map(|s| s.as_str()).unwrap_or("");
+    let runs = match list_runs(search, RUN_LIST_LIMIT) {
+        Ok(runs) => runs,
+        Err(err) => {
+            println!("Unable to list runs: {}", err);
+            return EXIT_STORE_FAILED;
+        },
+    };
+
+    for run in runs {
+        println!(
+            "{}  {}  {:<20} {:<24} return: {:.4}, max drawdown: {:.4}, Sharpe: {:.4}, trades: {}",
+            run.id, run.git_revision, run.strategy, run.symbols.join(","),
+            run.total_return, run.max_drawdown, run.sharpe, run.trade_count
+        );
+    }
+
+    EXIT_SUCCESS
+}
+
+/// Runs the `diff` subcommand, printing the parameters and metrics of two stored runs side by side.
+pub fn diff(args: &[String]) -> i32 {
+    let ids: Result<Vec<Uuid>, _> = args.iter().map(|arg| Uuid::parse_str(arg)).collect();
+    let (a, b) = match ids.as_ref().map(|ids| ids.as_slice()) {
+        Ok(&[a, b]) => (a, b),
+        _ => {
+            println!("{}", DIFF_USAGE);
+            return EXIT_USAGE;
+        },
+    };
+
+    match load_run(a).and_then(|a| load_run(b).map(|b| RunDiff::new(&a, &b))) {
+        Ok(diff) => {
+            print!("{}", diff.to_table());
+            EXIT_SUCCESS
+        },
+        Err(err) => {
+            println!("Unable to load runs: {}", err);
+            EXIT_STORE_FAILED
+        },
+    }
+}
+
+/// Runs the `check` subcommand, printing a data quality report for each of the definition's symbols.
+pub fn check(args: &[String]) -> i32 {
+    let (def_path, max_issues) = match *args {
+        [ref def_path] => (Path::new(def_path), Ok(DEFAULT_MAX_ISSUES)),
+        [ref def_path, ref max_issues] => (Path::new(def_path), max_issues.parse::<usize>()),
+        _ => {
+            println!("{}", CHECK_USAGE);
+            return EXIT_USAGE;
+        },
+    };
+    let max_issues = match max_issues {
+        Ok(max_issues) => max_issues,
+        Err(_) => {
+            println!("{}", CHECK_USAGE);
+            return EXIT_USAGE;
+        },
+    };
+
+    let def = match RunDefinition::load(def_path) {
+        Ok(def) => def,
+        Err(err) => {
+            println!("{}", err);
+            return EXIT_BAD_DEFINITION;
+        },
+    };
+
+    let mut exit_code = EXIT_SUCCESS;
+    for s in def.backtest.symbols() {
+        let gen = match def.tick_generator(&s) {
+            Ok(gen) => gen,
+            Err(err) => {
+                println!("Invalid backtest definition: {}", err);
+                return EXIT_BAD_DEFINITION;
+            },
+        };
+
+        match check_source(&gen, QualityConfig::default(), max_issues) {
+            Ok(report) => {
+                println!("{}:\n{}", s.symbol, report);
+                if !report.is_clean() && exit_code == EXIT_SUCCESS {
+                    exit_code = EXIT_DATA_ISSUES;
+                }
+            },
+            Err(err) => {
+                println!("Unable to check the data for {}: {}", s.symbol, err);
+                return EXIT_BAD_DEFINITION;
+            },
+        }
+    }
+
+    exit_code
+}
+
+#[test]
+fn cli_definition_parsing() {
+    use std::env;
+
+    let toml_def = r#"
+        strategy = "sma_cross"
+        decimal_precision = 5
+
+        [strategy_settings]
+        symbol = "EURUSD"
+        period = "20"
+
+        [backtest]
+        symbol = "EURUSD"
+        max_tick_n = 1000
+        backtest_type = { Fast = { delay_ms = 0 } }
+        data_source = "Flatfile"
+        data_dest = "Null"
+
+        [backtest.broker_settings]
+        starting_balance = 100000
+
+        [[backtest.additional_symbols]]
+        symbol = "GBPUSD"
+        data_source = "Postgres"
+    "#;
+    let mut path = env::temp_dir();
+    path.push(format!("backtester_cli_test_{}.toml", Uuid::new_v4().hyphenated()));
+    File::create(&path).unwrap().write_all(toml_def.as_bytes()).unwrap();
+
+    let def = RunDefinition::load(&path);
+    fs::remove_file(&path).unwrap();
+    let def = def.unwrap();
+    assert_eq!(def.strategy_settings["period"], "20");
+    let backtest = def.to_sim_backtest().unwrap();
+    assert_eq!(backtest.max_tick_n, Some(1000));
+    assert_eq!(backtest.broker_settings.starting_balance, 100000);
+    let tickstreams: Vec<(String, TickGenerators, bool, usize)> = serde_json::from_str(&backtest.broker_settings.tickstreams).unwrap();
+    assert_eq!(tickstreams[0].0, "EURUSD");
+    assert_eq!(tickstreams[0].3, 5);
+    assert_eq!(tickstreams[1].0, "GBPUSD");
+
+    let mut random = def.clone();
+    random.backtest.data_source = DataSource::Random;
+    random.backtest.max_tick_n = None;
+    assert!(random.to_sim_backtest().is_err());
+    assert_eq!(run(&[]), EXIT_USAGE);
+    assert_eq!(diff(&[String::from("not a uuid"), String::from("x")]), EXIT_USAGE);
+    assert_eq!(check(&[]), EXIT_USAGE);
+}
@@ -22,38 +22,67 @@ extern crate test;
 #[macro_use]
 extern crate from_hashmap;
 extern crate simbroker;
+extern crate private;
+extern crate toml;
+extern crate num_cpus;
 
 mod backtest;
+mod batch;
+mod cli;
+mod runs;
 
 use std::sync::{Arc, Mutex, mpsc};
+use std::sync::atomic::Ordering;
 use std::thread;
+use std::time::Instant;
 use std::env;
+use std::process;
 use std::collections::HashMap;
 use std::str::FromStr;
 
 use uuid::Uuid;
 use futures::Future;
 use futures::stream::{Stream, BoxStream};
+use futures::sync::mpsc::unbounded;
 use serde_json::to_string;
 
 use tickgrinder_util::transport::command_server::CommandServer;
 use tickgrinder_util::transport::redis::{sub_multiple, get_client};
 use tickgrinder_util::transport::commands::*;
 use tickgrinder_util::transport::tickstream::*;
+use tickgrinder_util::transport::csv_schema::CsvSchema;
 use tickgrinder_util::trading::tick::Tick;
+use tickgrinder_util::trading::synthetic::SyntheticConfig;
+use tickgrinder_util::trading::bootstrap::BootstrapConfig;
+use tickgrinder_util::trading::bar::BarDefinition;
 use tickgrinder_util::instance::PlatformInstance;
 use tickgrinder_util::conf::CONF;
 use backtest::*;
+use batch::*;
+use runs::*;
 use simbroker::*;
 
 lazy_static!{
     static ref NO_BACKTEST: String = String::from("No backtest with that UUID!");
 }
 
+/// How many finished batches are kept around for their status to be retrieved.  The oldest ones are forgotten
+/// when new batches are started.
+const MAX_FINISHED_BATCHES: usize = 50;
+
 /// Starts the backtester module, initializing its interface to the rest of the platform
 fn main() {
     let args: Vec<String> = env::args().collect();
-    let uuid_str = args.get(1).expect("Usage: ./tick_processor [uuid]");
+    // run a single backtest or inspect stored runs from the command line without connecting to the rest of the platform
+    match args.get(1).map(|arg| arg.as_str()) {
+        Some("run") => process::exit(cli::run(&args[2..])),
+        Some("runs") => process::exit(cli::list(&args[2..])),
+        Some("diff") => process::exit(cli::diff(&args[2..])),
+        Some("check") => process::exit(cli::check(&args[2..])),
+        _ => (),
+    }
+
+    let uuid_str = args.get(1).expect("Usage: ./backtester [uuid] or ./backtester [run|runs|diff|check] [args...]");
     let uuid = Uuid::parse_str(uuid_str)
         .expect("Unable to parse Uuid from supplied argument");
 
@@ -73,9 +102,44 @@ pub enum BacktestType {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum DataSource {
     Flatfile,
+    /// Memory-mapped files in the binary tick format
+    Binary,
+    /// Apache Parquet files
+    Parquet,
+    /// CSV files in a third-party format described by the schema
+    Csv{schema: CsvSchema},
     RedisChannel{host: String, channel: String},
     Postgres,
     Random,
+    /// Seeded ticks from a stochastic price model
+    Synthetic{config: SyntheticConfig},
+    /// An alternative history bootstrapped from the ticks of `source` between `history_start` and `history_end`
+    Bootstrap{source: Box<DataSource>, history_start: Option<u64>, history_end: Option<u64>, config: BootstrapConfig},
+    /// The ticks of `source` with feed faults such as lost ticks, latency, and bad prints injected into them
+    Faulty{source: Box<DataSource>, faults: FaultConfig},
+}
+
+impl DataSource {
+    /// Returns whether the source's data ends, so that it can be read completely.
+    pub fn is_finite(&self) -> bool {
+        match *self {
+            DataSource::RedisChannel{..} | DataSource::Random => false,
+            DataSource::Synthetic{ref config} => config.tick_count.is_some(),
+            DataSource::Bootstrap{ref source, ..} | DataSource::Faulty{ref source, ..} => source.is_finite(),
+            _ => true,
+        }
+    }
+
+    /// Returns whether backtests reading from the source can be sought to a different timestamp.
+    pub fn is_seekable(&self) -> bool {
+        match *self {
+            DataSource::Flatfile | DataSource::Binary | DataSource::Parquet | DataSource::Csv{..} |
+                DataSource::Synthetic{..} | DataSource::Bootstrap{..} => true,
+            // the injected faults depend on the position in the stream, so it always has to be read from the start
+            DataSource::Faulty{..} => false,
+            DataSource::RedisChannel{..} | DataSource::Postgres | DataSource::Random => false,
+        }
+    }
 }
 
 /// Where to send the backtest's generated data
@@ -84,15 +148,28 @@ pub enum DataDest {
     RedisChannel{host: String, channel: String},
     Console,
     Null,
-    SimBroker{uuid: Uuid}, // Requires that a SimBroker is running on the Backtester in order to work
+    /// Aggregates the ticks into bars and publishes them on a Redis channel
+    RedisBars{host: String, channel: String, bars: BarDefinition},
+    /// Registers a tickstream for every symbol with the SimBroker, which must be running on the Backtester.  The
+    /// SimBroker's simulation loop isn't started; that's left to whoever drives the SimBroker.
+    SimBroker{
+        uuid: Uuid,
+        /// Decimal precision of the symbols' prices
+        #[serde(default = "default_decimal_precision")]
+        decimal_precision: usize,
+    },
 }
 
+fn default_decimal_precision() -> usize { 5 }
+
 #[derive(Clone)]
 struct Backtester {
     pub uuid: Uuid,
     pub cs: CommandServer,
     pub running_backtests: Arc<Mutex<HashMap<Uuid, BacktestHandle>>>,
     pub simbrokers: Arc<Mutex<HashMap<Uuid, SimBrokerClient>>>,
+    /// The status of every batch along with when it was started
+    pub batches: Arc<Mutex<HashMap<Uuid, (Instant, Arc<Mutex<BatchStatus>>)>>>,
 }
 
 impl PlatformInstance for Backtester {
@@ -117,6 +194,44 @@ impl PlatformInstance for Backtester {
                     })
                 }
             },
+            Command::StartBacktestBatch{definitions} => {
+                Some(match serde_json::from_str::<Vec<BatchDefinition>>(&definitions) {
+                    Ok(definitions) => match self.start_backtest_batch(definitions) {
+                        Ok(uuid) => Response::Info{info: uuid.hyphenated().to_string()},
+                        Err(err) => Response::Error{status: err},
+                    },
+                    Err(err) => Response::Error{status: format!("Can't parse backtest definitions from String: {}", err)},
+                })
+            },
+            Command::GetBacktestBatch{uuid} => {
+                let batches = self.batches.lock().unwrap();
+                Some(match batches.get(&uuid) {
+                    Some(&(_, ref status)) => match to_string(&*status.lock().unwrap()) {
+                        Ok(msg) => Response::Info{info: msg},
+                        Err(e) => Response::Error{status: format!("Unable to convert batch status into String: {:?}", e)},
+                    },
+                    None => Response::Error{status: String::from("No backtest batch with that UUID!")},
+                })
+            },
+            Command::ListBacktestRuns{query} => {
+                Some(match list_runs(&query, RUN_LIST_LIMIT).and_then(|runs| to_string(&runs).map_err(|err| format!("{:?}", err))) {
+                    Ok(msg) => Response::Info{info: msg},
+                    Err(err) => Response::Error{status: format!("Unable to list backtest runs: {}", err)},
+                })
+            },
+            Command::GetBacktestRun{id} => {
+                Some(match load_run(id).and_then(|run| to_string(&run).map_err(|err| format!("{:?}", err))) {
+                    Ok(msg) => Response::Info{info: msg},
+                    Err(err) => Response::Error{status: err},
+                })
+            },
+            Command::DiffBacktestRuns{a, b} => {
+                let diff = load_run(a).and_then(|a| load_run(b).map(|b| RunDiff::new(&a, &b)));
+                Some(match diff.and_then(|diff| to_string(&diff).map_err(|err| format!("{:?}", err))) {
+                    Ok(msg) => Response::Info{info: msg},
+                    Err(err) => Response::Error{status: err},
+                })
+            },
             Command::Kill => {
                 thread::spawn(|| {
                     thread::sleep(std::time::Duration::from_secs(3));
@@ -147,6 +262,37 @@ impl PlatformInstance for Backtester {
                     Err(()) => Response::Error{status: NO_BACKTEST.clone()},
                 })
             },
+            Command::StepBacktest{uuid, ticks} => {
+                let cmd = if ticks == 1 { TickstreamCommand::Step } else { TickstreamCommand::StepN(ticks) };
+                Some(match self.send_backtest_cmd(&uuid, cmd) {
+                    Ok(()) => Response::Ok,
+                    Err(()) => Response::Error{status: NO_BACKTEST.clone()},
+                })
+            },
+            Command::SeekBacktest{uuid, timestamp} => {
+                let seekable = self.running_backtests.lock().unwrap().get(&uuid).map(|handle| handle.seekable);
+                if seekable == Some(false) {
+                    return Some(Response::Error{
+                        status: String::from("That backtest's tickstream can't be sought; only single-symbol backtests of file or generated data can."),
+                    });
+                }
+
+                Some(match self.send_backtest_cmd(&uuid, TickstreamCommand::Seek(timestamp)) {
+                    Ok(()) => {
+                        if let Some(handle) = self.running_backtests.lock().unwrap().get(&uuid) {
+                            handle.sought.store(true, Ordering::SeqCst);
+                        }
+                        Response::Ok
+                    },
+                    Err(()) => Response::Error{status: NO_BACKTEST.clone()},
+                })
+            },
+            Command::SetBacktestSpeed{uuid, multiplier} => {
+                Some(match self.set_backtest_speed(&uuid, multiplier) {
+                    Ok(()) => Response::Ok,
+                    Err(err) => Response::Error{status: err},
+                })
+            },
             Command::ListBacktests => {
                 let backtests = self.running_backtests.lock().unwrap();
                 let mut message_vec = Vec::new();
@@ -186,6 +332,7 @@ impl Backtester {
             cs: CommandServer::new(uuid, "Backtester"),
             running_backtests: Arc::new(Mutex::new(HashMap::new())),
             simbrokers: Arc::new(Mutex::new(HashMap::new())),
+            batches: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
@@ -205,84 +352,149 @@ impl Backtester {
     {
         let msg = format!("Starting backtest with definition: {:?}", definition);
         self.cs.notice(None, &msg);
-        // Create the TickGenerator that provides the backtester with data
-        let mut src: Box<TickGenerator> = resolve_data_source(
-            &definition.data_source, definition.symbol.clone(), definition.start_time
-        );
+        let symbols = definition.symbols();
+        match definition.data_dest {
+            DataDest::RedisBars{..} => if symbols.len() > 1 {
+                return Err(String::from("Bars can only be published for single-symbol backtests."));
+            },
+            DataDest::SimBroker{uuid, ..} => if !self.simbrokers.lock().unwrap().contains_key(&uuid) {
+                return Err("No SimBroker running with that Uuid!".to_string())
+            },
+            _ => (),
+        }
 
-        // create channel for communicating messages to the running backtest sent externally
-        let (external_handle_tx, handle_rx) = mpsc::sync_channel::<TickstreamCommand>(5);
+        // create channel for communicating messages to the running backtest sent externally.  It's unbounded since
+        // the tickstream only reads its commands in between ticks.
+        let (external_handle_tx, handle_rx) = mpsc::channel::<TickstreamCommand>();
         // create channel for communicating messages to the running backtest internally
         let internal_handle_tx = external_handle_tx.clone();
 
         // modify the source tickstream to add delay between the ticks or add some other kind of
         // advanced functionality to the way they're outputted
-        let tickstream: Result<BoxStream<Tick, ()>, String> = match definition.backtest_type {
-            BacktestType::Fast{delay_ms} => src.get(
-                Box::new(FastMap{delay_ms: delay_ms}), handle_rx
-            ),
-            BacktestType::Live => src.get(Box::new(LiveMap::new()), handle_rx),
+        let mut speed = None;
+        let map: Box<TickMap + Send> = match definition.backtest_type {
+            BacktestType::Fast{delay_ms} => Box::new(FastMap{delay_ms: delay_ms}),
+            BacktestType::Live => {
+                let map = LiveMap::new();
+                speed = Some(map.speed_handle());
+                Box::new(map)
+            },
+        };
+
+        // Create the tickstream that provides the backtester with data.  The ticks of multi-symbol backtests are
+        // merged into one stream, and every tick is paired with the index of its symbol.
+        let tickstream: Result<BoxStream<(usize, Tick), ()>, String> = if symbols.len() == 1 {
+            let mut src: Box<TickGenerator> = resolve_data_source(
+                &definition.data_source, definition.symbol.clone(), definition.start_time
+            );
+            src.get(map, handle_rx).map(|stream| stream.map(|t| (0, t)).boxed())
+        } else {
+            let generators = symbols.iter()
+                .map(|s| resolve_data_source(&s.data_source, s.symbol.clone(), definition.start_time))
+                .collect();
+            MergedReader::new(generators).get(map, handle_rx)
         };
 
         if tickstream.is_err() {
             return Err( format!("Error creating tickstream: {}", tickstream.err().unwrap()) )
         }
 
-        // create a TickSink that receives the output of the backtest
-        let dst_opt: Result<Box<TickSink + Send>, Uuid> = match definition.data_dest {
+        // create a TickSink for each symbol that receives the output of the backtest
+        let mut sim_tickstreams = Vec::new();
+        let mut dsts: Vec<Box<TickSink + Send>> = match definition.data_dest {
             DataDest::RedisChannel{ref host, ref channel} => {
-                Ok(Box::new(RedisSink::new(definition.symbol.clone(), channel.clone(), host.as_str())))
+                symbols.iter().map(|s| {
+                    Box::new(RedisSink::new(s.symbol.clone(), channel.clone(), host.as_str())) as Box<TickSink + Send>
+                }).collect()
+            },
+            DataDest::RedisBars{ref host, ref channel, bars} => {
+                vec![Box::new(RedisBarSink::new(bars, channel.clone(), host.as_str())?)]
+            },
+            DataDest::Console => symbols.iter().map(|_| Box::new(ConsoleSink{}) as Box<TickSink + Send>).collect(),
+            DataDest::Null => symbols.iter().map(|_| Box::new(NullSink{}) as Box<TickSink + Send>).collect(),
+            DataDest::SimBroker{..} => {
+                // Split the ticks back up into one stream per symbol for the SimBroker.  The channels are unbounded
+                // since the SimBroker waits for the next tick of every symbol, so a bounded one would block the rest.
+                symbols.iter().map(|s| {
+                    let (tx, rx) = unbounded::<Tick>();
+                    sim_tickstreams.push((s.symbol.clone(), rx));
+                    Box::new(StreamSink::new(s.symbol.clone(), tx)) as Box<TickSink + Send>
+                }).collect()
             },
-            DataDest::Console => Ok(Box::new(ConsoleSink{})),
-            DataDest::Null => Ok(Box::new(NullSink{})),
-            DataDest::SimBroker{uuid} => Err(uuid),
         };
 
+        let seekable = symbols.len() == 1 && definition.data_source.is_seekable();
         let _definition = definition.clone();
+        let mut tracker = ProgressTracker::new(definition.clone());
+        let progress = tracker.progress();
+        let sought = tracker.sought();
         let mut i = 0;
         let uuid = Uuid::new_v4();
 
         // initiate tick flow
         let mut csc = self.cs.clone();
-        if dst_opt.is_ok() {
-            let mut dst = dst_opt.unwrap();
-            thread::spawn(move || {
-                for t_res in tickstream.unwrap().wait() {
-                    match t_res {
-                        Ok(t) => {
-                            i += 1;
-
-                            // send the tick to the sink
-                            dst.tick(t);
-
-                            if check_early_exit(&t, &_definition, i) {
-                                let msg = "Backtest early exit condition true; exiting backtest.";
-                                csc.notice(None, msg);
-                                return Err(())
-                            }
-                        },
-                        Err(_) => {
-                            csc.notice(None, "Stopping backtest because tickstream has ended");
-                            internal_handle_tx.send(TickstreamCommand::Stop)
-                                .expect("Sending through the internal handle failed; tickstream dropped?");
+        let run_simbrokers = self.simbrokers.clone();
+        thread::spawn(move || {
+            let mut last_timestamp = 0;
+            for t_res in tickstream.unwrap().wait() {
+                match t_res {
+                    Ok((index, t)) => {
+                        i += 1;
+                        last_timestamp = t.timestamp;
+
+                        // send the tick to its symbol's sink
+                        dsts[index].tick(t);
+                        tracker.tick(&t);
+
+                        if check_early_exit(&t, &_definition, i) {
+                            let msg = "Backtest early exit condition true; exiting backtest.";
+                            csc.notice(None, msg);
+                            break;
                         }
-                    };
-                }
-                Ok(())
-            });
-        } else {
-            let mut simbrokers = self.simbrokers.lock().unwrap();
-            let simbroker_opt = simbrokers.get_mut(&dst_opt.err().unwrap());
-            if simbroker_opt.is_none() {
-                return Err("No SimBroker running with that Uuid!".to_string())
+                    },
+                    Err(_) => {
+                        csc.notice(None, "Stopping backtest because tickstream has ended");
+                        internal_handle_tx.send(TickstreamCommand::Stop)
+                            .expect("Sending through the internal handle failed; tickstream dropped?");
+                    }
+                };
+            }
+
+            // the backtest is over, whether its ticks ran out or it was stopped, so it's stored like every other run.
+            // Dropping the sinks first ends the SimBroker's tickstreams, which releases it if it's still waiting for
+            // the first tick of one of them.
+            drop(dsts);
+            let stored = match _definition.data_dest {
+                DataDest::SimBroker{uuid, ..} => {
+                    let mut simbrokers = run_simbrokers.lock().unwrap();
+                    store_tickstream_run(&_definition, simbrokers.get_mut(&uuid), i, last_timestamp)
+                },
+                _ => store_tickstream_run(&_definition, None, i, last_timestamp),
+            };
+            match stored {
+                Ok(run_id) => csc.notice(None, &format!("Stored backtest run {}", run_id)),
+                Err(err) => csc.error(None, &format!("Unable to store backtest run: {}", err)),
             }
+        });
 
-            let simbroker = simbroker_opt.unwrap();
-            // plug the tickstream into the matching SimBroker
-            // TODO TODO TODO: Implement proper values here
-            // BIG TODO; a large part of the backtester logic will have to be re-thought
-            // simbroker.register_tickstream(definition.symbol.clone(), tickstream.unwrap(), true, 6).unwrap();
-            // simbroker.init_sim_loop().expect("Unable to start SimBroker sim loop");
+        // plug the tickstreams into the matching SimBroker.  Registering blocks until the first tick of the symbol
+        // arrives, so it's done off of the command thread.
+        if let DataDest::SimBroker{uuid, decimal_precision} = definition.data_dest {
+            let simbrokers = self.simbrokers.clone();
+            let mut csc = self.cs.clone();
+            thread::spawn(move || {
+                let mut simbrokers = simbrokers.lock().unwrap();
+                let simbroker = match simbrokers.get_mut(&uuid) {
+                    Some(simbroker) => simbroker,
+                    None => return csc.error(None, "SimBroker was removed before its tickstreams could be registered"),
+                };
+                let is_fx = simbroker.get_settings().fx;
+                for (symbol, rx) in sim_tickstreams {
+                    if let Err(err) = simbroker.register_tickstream(symbol.clone(), rx.boxed(), is_fx, decimal_precision) {
+                        csc.error(None, &format!("Unable to register tickstream for {} with SimBroker: {:?}", symbol, err));
+                    }
+                }
+            });
         }
 
         let handle = BacktestHandle {
@@ -290,7 +502,11 @@ impl Backtester {
             backtest_type: definition.backtest_type,
             data_source: definition.data_source,
             endpoint: definition.data_dest,
-            handle: external_handle_tx
+            handle: external_handle_tx,
+            progress: progress,
+            sought: sought,
+            seekable: seekable,
+            speed: speed,
         };
 
         // register the backtest's existence
@@ -300,6 +516,45 @@ impl Backtester {
         Ok(uuid)
     }
 
+    /// Validates a batch of backtest definitions and starts running them in parallel, returning the UUID that
+    /// the batch's status can be retrieved with.
+    fn start_backtest_batch(&mut self, definitions: Vec<BatchDefinition>) -> Result<Uuid, String> {
+        if definitions.is_empty() {
+            return Err(String::from("The batch doesn't contain any backtests."));
+        }
+        for (i, def) in definitions.iter().enumerate() {
+            validate_definition(def).map_err(|err| format!("Invalid definition at index {}: {}", i, err))?;
+        }
+
+        let uuid = Uuid::new_v4();
+        self.cs.notice(None, &format!(
+            "Starting batch {} of {} backtests on {} workers", uuid, definitions.len(), worker_count(definitions.len())
+        ));
+        let (status, _) = start_batch(uuid, definitions, self.cs.clone());
+        let mut batches = self.batches.lock().unwrap();
+        evict_finished_batches(&mut batches, MAX_FINISHED_BATCHES);
+        batches.insert(uuid, (Instant::now(), status));
+
+        Ok(uuid)
+    }
+
+    /// Changes the playback speed of a `Live` backtest.
+    pub fn set_backtest_speed(&mut self, uuid: &Uuid, multiplier: f64) -> Result<(), String> {
+        if !(multiplier > 0.) {
+            return Err(String::from("The speed multiplier must be greater than 0."));
+        }
+
+        let handles = self.running_backtests.lock().unwrap();
+        match handles.get(uuid) {
+            Some(&BacktestHandle{speed: Some(ref speed), ..}) => {
+                *speed.lock().unwrap() = multiplier;
+                Ok(())
+            },
+            Some(_) => Err(String::from("Only Live backtests have an adjustable speed.")),
+            None => Err(NO_BACKTEST.clone()),
+        }
+    }
+
     /// Removes a stopped backtest from the internal running backtest list
     pub fn remove_backtest(&mut self, uuid: &Uuid) {
         let mut handles = self.running_backtests.lock().unwrap();
@@ -314,14 +569,31 @@ impl Backtester {
         if handle.is_none() {
             return Err(());
         }
-        let sender: &mpsc::SyncSender<TickstreamCommand> = &handle.unwrap().handle;
-        sender.send(cmd)
-            .expect("The receiver corresponding to the sender in the backtest handle seems to have been dropped.");
+        let sender: &mpsc::Sender<TickstreamCommand> = &handle.unwrap().handle;
+        // the receiver is dropped once the tickstream has ended, after which commands have no effect
+        let _ = sender.send(cmd);
 
         Ok(())
     }
 }
 
+/// Forgets the oldest finished batches until at most `max_finished` of them are left.  Running batches are kept.
+fn evict_finished_batches(batches: &mut HashMap<Uuid, (Instant, Arc<Mutex<BatchStatus>>)>, max_finished: usize) {
+    let mut finished: Vec<(Instant, Uuid)> = batches.iter()
+        .filter(|&(_, &(_, ref status))| status.lock().unwrap().is_finished())
+        .map(|(uuid, &(started, _))| (started, *uuid))
+        .collect();
+    if finished.len() <= max_finished {
+        return;
+    }
+
+    finished.sort();
+    let excess = finished.len() - max_finished;
+    for &(_, uuid) in finished.iter().take(excess) {
+        batches.remove(&uuid);
+    }
+}
+
 /// Creates a `TickGenerator` from a `DataSource` and symbol String
 pub fn resolve_data_source(data_source: &DataSource, symbol: String, start_time: Option<u64>) -> Box<TickGenerator> {
     match *data_source {
@@ -331,6 +603,26 @@ pub fn resolve_data_source(data_source: &DataSource, symbol: String, start_time:
                 start_time: start_time,
             }) as Box<TickGenerator>
         },
+        DataSource::Binary => {
+            Box::new(BinaryReader{
+                symbol: symbol.clone(),
+                start_time: start_time,
+            }) as Box<TickGenerator>
+        },
+        DataSource::Csv{ref schema} => {
+            Box::new(CsvReader{
+                symbol: symbol.clone(),
+                start_time: start_time,
+                schema: schema.clone(),
+            }) as Box<TickGenerator>
+        },
+        DataSource::Parquet => {
+            Box::new(ParquetReader{
+                symbol: symbol.clone(),
+                start_time: start_time,
+                end_time: None,
+            }) as Box<TickGenerator>
+        },
         DataSource::RedisChannel{ref host, ref channel} => {
             Box::new(
                 RedisReader::new(symbol.clone(), host.clone(), channel.clone())
@@ -339,6 +631,27 @@ pub fn resolve_data_source(data_source: &DataSource, symbol: String, start_time:
         DataSource::Random => {
             Box::new(RandomReader {}) as Box<TickGenerator>
         },
+        DataSource::Synthetic{ref config} => {
+            Box::new(SyntheticReader {
+                config: config.clone(),
+                start_time: start_time,
+            }) as Box<TickGenerator>
+        },
+        DataSource::Bootstrap{ref source, history_start, history_end, ref config} => {
+            Box::new(BootstrapReader {
+                source: resolve_data_source(source, symbol.clone(), history_start),
+                history_start: history_start,
+                history_end: history_end,
+                config: config.clone(),
+                start_time: start_time,
+            }) as Box<TickGenerator>
+        },
+        DataSource::Faulty{ref source, ref faults} => {
+            Box::new(FaultyReader {
+                source: resolve_data_source(source, symbol.clone(), start_time),
+                faults: faults.clone(),
+            }) as Box<TickGenerator>
+        },
         DataSource::Postgres => {
             Box::new(PostgresReader {symbol: symbol, start_time: start_time} )
         },
@@ -357,6 +670,31 @@ fn check_early_exit (
     false
 }
 
+#[test]
+fn finished_batch_eviction() {
+    let status = |total_runs, completed_runs| Arc::new(Mutex::new(BatchStatus {
+        uuid: Uuid::new_v4(),
+        total_runs: total_runs,
+        completed_runs: completed_runs,
+        failed_runs: 0,
+        ticks_processed: 0,
+        results: Vec::new(),
+    }));
+    let mut batches = HashMap::new();
+    let running = Uuid::new_v4();
+    batches.insert(running, (Instant::now(), status(2, 1)));
+    let finished: Vec<Uuid> = (0..3).map(|_| {
+        let uuid = Uuid::new_v4();
+        batches.insert(uuid, (Instant::now(), status(1, 1)));
+        thread::sleep(std::time::Duration::from_millis(2));
+        uuid
+    }).collect();
+
+    evict_finished_batches(&mut batches, 1);
+    assert_eq!(batches.len(), 2);
+    assert!(batches.contains_key(&running) && batches.contains_key(&finished[2]));
+}
+
 #[test]
 fn backtest_n_early_exit() {
     let rx = tickgrinder_util::transport::redis::sub_channel(CONF.redis_host, "test1_ii");
@@ -374,6 +712,7 @@ fn backtest_n_early_exit() {
             channel: "test1_ii".to_string()
         },
         broker_settings: SimBrokerSettings::default(),
+        additional_symbols: Vec::new(),
     };
 
     let uuid = bt.start_backtest(definition).unwrap();
@@ -400,6 +739,7 @@ fn backtest_timestamp_early_exit() {
             channel: "test2_ii".to_string()
         },
         broker_settings: SimBrokerSettings::default(),
+        additional_symbols: Vec::new(),
     };
 
     let uuid = bt.start_backtest(definition)
@@ -0,0 +1,374 @@
+//! Stores the results of completed backtests in Postgres so that they can be listed, searched, and compared
+//! after the backtester that ran them has exited.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering, ATOMIC_BOOL_INIT};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use postgres::Connection;
+use serde_json::{self, Value};
+use uuid::Uuid;
+
+use tickgrinder_util::conf::CONF;
+use tickgrinder_util::transport::postgres::{
+    get_client, init_backtest_run_table, store_backtest_run, search_backtest_runs, get_backtest_run
+};
+use tickgrinder_util::trading::performance::{Trade, PerformanceReport, equity_curve};
+use simbroker::{SimBrokerSettings, SimBacktestDefinition, SimBacktestResult, SimBrokerClient, broker_trades};
+use private::strategies::get_strategy;
+
+use backtest::BacktestDefinition;
+use cli::RunDefinition;
+
+/// The maximum number of stored runs returned when listing them
+pub const RUN_LIST_LIMIT: usize = 100;
+
+/// Set once the table that runs are stored in has been set up
+static RUN_TABLE_READY: AtomicBool = ATOMIC_BOOL_INIT;
+
+/// Everything that's stored about a completed backtest.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BacktestRun {
+    pub id: Uuid,
+    /// Milliseconds since the epoch at which the run finished
+    pub finished_at: u64,
+    /// Revision of the code that the run was made with as reported by `git describe`
+    pub git_revision: String,
+    pub strategy: String,
+    pub strategy_settings: HashMap<String, String>,
+    pub definition: BacktestDefinition,
+    /// The settings of the SimBroker that the strategy was run against, including its tickstreams
+    pub broker_settings: SimBrokerSettings,
+    pub report: PerformanceReport,
+    pub tick_count: usize,
+    pub last_timestamp: u64,
+    pub trades: Vec<Trade>,
+    pub equity_curve: Vec<(u64, f64)>,
+}
+
+/// A short description of a stored run that is returned when listing runs.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BacktestRunSummary {
+    pub id: Uuid,
+    pub finished_at: u64,
+    pub git_revision: String,
+    pub strategy: String,
+    pub symbols: Vec<String>,
+    pub total_return: f64,
+    pub max_drawdown: f64,
+    pub sharpe: f64,
+    pub trade_count: usize,
+}
+
+impl BacktestRun {
+    /// Creates a record of a backtest that just finished.
+    pub fn new(
+        strategy: String, strategy_settings: HashMap<String, String>, definition: BacktestDefinition,
+        broker_settings: SimBrokerSettings, res: SimBacktestResult
+    ) -> BacktestRun {
+        let finished_at = SystemTime::now().duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() * 1000 + d.subsec_nanos() as u64 / 1_000_000)
+            .unwrap_or(0);
+
+        BacktestRun {
+            id: Uuid::new_v4(),
+            finished_at: finished_at,
+            git_revision: git_revision(),
+            strategy: strategy,
+            strategy_settings: strategy_settings,
+            definition: definition,
+            broker_settings: broker_settings,
+            equity_curve: equity_curve(&res.trades, res.report.starting_balance),
+            report: res.report,
+            tick_count: res.tick_count,
+            last_timestamp: res.last_timestamp,
+            trades: res.trades,
+        }
+    }
+
+    pub fn summary(&self) -> BacktestRunSummary {
+        BacktestRunSummary {
+            id: self.id,
+            finished_at: self.finished_at,
+            git_revision: self.git_revision.clone(),
+            strategy: self.strategy.clone(),
+            symbols: self.definition.symbols().into_iter().map(|s| s.symbol).collect(),
+            total_return: self.report.total_return,
+            max_drawdown: self.report.max_drawdown,
+            sharpe: self.report.sharpe,
+            trade_count: self.report.trade_count,
+        }
+    }
+
+    /// Returns all of the settings that determined the outcome of the run, keyed by dotted paths such as
+    /// `backtest.max_tick_n` or `strategy.period`.
+    pub fn parameters(&self) -> BTreeMap<String, String> {
+        let mut params = BTreeMap::new();
+        params.insert(String::from("strategy"), self.strategy.clone());
+        params.insert(String::from("git_revision"), self.git_revision.clone());
+        for (k, v) in &self.strategy_settings {
+            params.insert(format!("strategy.{}", k), v.clone());
+        }
+        // the broker settings are compared separately since those that were actually used can differ
+        let mut definition = serde_json::to_value(&self.definition).unwrap_or(Value::Null);
+        if let Value::Object(ref mut map) = definition {
+            map.remove("broker_settings");
+        }
+        flatten("backtest", &definition, &mut params);
+        flatten("broker", &serde_json::to_value(&self.broker_settings).unwrap_or(Value::Null), &mut params);
+
+        params
+    }
+
+    /// Returns all of the numeric results of the run by name.
+    pub fn metrics(&self) -> BTreeMap<String, f64> {
+        let mut metrics = BTreeMap::new();
+        if let Ok(Value::Object(report)) = serde_json::to_value(&self.report) {
+            for (k, v) in report {
+                if let Some(n) = v.as_f64() {
+                    metrics.insert(k, n);
+                }
+            }
+        }
+        metrics.insert(String::from("tick_count"), self.tick_count as f64);
+
+        metrics
+    }
+}
+
+/// Flattens a JSON value into `params`, naming nested values by their dotted path.
+fn flatten(path: &str, value: &Value, params: &mut BTreeMap<String, String>) {
+    match *value {
+        Value::Object(ref map) => for (k, v) in map {
+            flatten(&format!("{}.{}", path, k), v, params);
+        },
+        Value::String(ref s) => { params.insert(path.to_string(), s.clone()); },
+        ref other => { params.insert(path.to_string(), other.to_string()); },
+    }
+}
+
+/// Returns the revision of the code being run.  It can be set at compile time with the `GIT_REVISION` environment
+/// variable; otherwise `git` is asked for the revision of the current directory.
+pub fn git_revision() -> String {
+    if let Some(rev) = option_env!("GIT_REVISION") {
+        return rev.to_string();
+    }
+
+    match process::Command::new("git").args(&["describe", "--always", "--dirty"]).output() {
+        Ok(ref output) if output.status.success() => String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        _ => String::from("unknown"),
+    }
+}
+
+/// Returns a connection to the database with the run table set up, creating the table the first time.
+fn run_client() -> Result<Connection, String> {
+    let client = get_client().map_err(|err| format!("{:?}", err))?;
+    if !RUN_TABLE_READY.load(Ordering::SeqCst) {
+        init_backtest_run_table(&client, CONF.postgres_user)?;
+        RUN_TABLE_READY.store(true, Ordering::SeqCst);
+    }
+
+    Ok(client)
+}
+
+/// Backtests the strategy of a definition against `backtest`, which is the definition converted with
+/// `to_sim_backtest()`, and stores the finished run.  This is the path that every strategy backtest finishes on,
+/// whether it was started from the command line or as part of a batch.  Storing happens after the backtest is
+/// over, so failing to store the run doesn't fail the backtest; the error is returned in place of the run's ID.
+pub fn run_and_store(
+    def: &RunDefinition, backtest: &SimBacktestDefinition
+) -> Result<(SimBacktestResult, Result<Uuid, String>), String> {
+    let strategy = get_strategy::<SimBrokerClient>(&def.strategy, def.strategy_settings.clone())?;
+    let res = backtest.run(strategy)?;
+
+    let run = BacktestRun::new(
+        def.strategy.clone(), def.strategy_settings.clone(), def.backtest.clone(), backtest.broker_settings.clone(),
+        res.clone()
+    );
+    let stored = store_run(&run).map(|_| run.id);
+
+    Ok((res, stored))
+}
+
+/// Stores a backtest started with a `StartBacktest` command once its tickstream has ended.  The Backtester doesn't
+/// run a strategy for these, so the strategy is left empty.  If the ticks were sent to a SimBroker, the run holds
+/// the trades made on it by the time the tickstream ended; other destinations have no trades.
+pub fn store_tickstream_run(
+    definition: &BacktestDefinition, broker: Option<&mut SimBrokerClient>, tick_count: usize, last_timestamp: u64
+) -> Result<Uuid, String> {
+    let (broker_settings, trades) = match broker {
+        Some(broker) => (broker.get_settings(), broker_trades(broker, false, last_timestamp)?),
+        None => (definition.broker_settings.clone(), Vec::new()),
+    };
+    let res = SimBacktestResult {
+        report: PerformanceReport::from_trades(&trades, broker_settings.starting_balance as f64),
+        trades: trades,
+        tick_count: tick_count,
+        last_timestamp: last_timestamp,
+    };
+
+    let run = BacktestRun::new(String::new(), HashMap::new(), definition.clone(), broker_settings, res);
+    store_run(&run).map(|_| run.id)
+}
+
+/// Stores a completed run in the database.
+pub fn store_run(run: &BacktestRun) -> Result<(), String> {
+    let client = run_client()?;
+
+    let summary = serde_json::to_string(&run.summary()).map_err(|err| format!("{:?}", err))?;
+    let run_json = serde_json::to_string(run).map_err(|err| format!("{:?}", err))?;
+    store_backtest_run(
+        &run.id.hyphenated().to_string(), run.finished_at, &run.strategy, &run.git_revision, &summary, &run_json, &client
+    )
+}
+
+/// Returns the summaries of the most recent stored runs that match the search, newest first.  An empty search
+/// matches every run.
+pub fn list_runs(search: &str, limit: usize) -> Result<Vec<BacktestRunSummary>, String> {
+    let client = run_client()?;
+
+    search_backtest_runs(search, limit, &client)?.iter()
+        .map(|summary| serde_json::from_str(summary).map_err(|err| format!("{:?}", err)))
+        .collect()
+}
+
+/// Loads the full record of a stored run.
+pub fn load_run(id: Uuid) -> Result<BacktestRun, String> {
+    let client = run_client()?;
+
+    match get_backtest_run(&id.hyphenated().to_string(), &client)? {
+        Some(run) => serde_json::from_str(&run).map_err(|err| format!("{:?}", err)),
+        None => Err(format!("No backtest run with ID {}", id)),
+    }
+}
+
+/// The value of one parameter in two runs.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ParameterDiff {
+    pub name: String,
+    /// The parameter's value in the first run or None if it wasn't set
+    pub a: Option<String>,
+    /// The parameter's value in the second run or None if it wasn't set
+    pub b: Option<String>,
+    pub changed: bool,
+}
+
+/// The value of one metric in two runs.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MetricDiff {
+    pub name: String,
+    pub a: f64,
+    pub b: f64,
+    /// `b - a`
+    pub delta: f64,
+}
+
+/// A side by side comparison of two runs.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RunDiff {
+    pub a: BacktestRunSummary,
+    pub b: BacktestRunSummary,
+    pub parameters: Vec<ParameterDiff>,
+    pub metrics: Vec<MetricDiff>,
+}
+
+impl RunDiff {
+    pub fn new(a: &BacktestRun, b: &BacktestRun) -> RunDiff {
+        let (a_params, b_params) = (a.parameters(), b.parameters());
+        let names: BTreeSet<&String> = a_params.keys().chain(b_params.keys()).collect();
+        let parameters = names.into_iter().map(|name| {
+            let (a_val, b_val) = (a_params.get(name).cloned(), b_params.get(name).cloned());
+            ParameterDiff {
+                name: name.clone(),
+                changed: a_val != b_val,
+                a: a_val,
+                b: b_val,
+            }
+        }).collect();
+
+        let b_metrics = b.metrics();
+        let metrics = a.metrics().into_iter().filter_map(|(name, a_val)| {
+            b_metrics.get(&name).map(|&b_val| MetricDiff {
+                name: name,
+                a: a_val,
+                b: b_val,
+                delta: b_val - a_val,
+            })
+        }).collect();
+
+        RunDiff {
+            a: a.summary(),
+            b: b.summary(),
+            parameters: parameters,
+            metrics: metrics,
+        }
+    }
+
+    /// Renders the diff as a table.  Changed parameters are marked with a `*`.
+    pub fn to_table(&self) -> String {
+        let mut table = format!("{:<40} {:<38} {:<38}\n", "", self.a.id, self.b.id);
+        for p in &self.parameters {
+            table.push_str(&format!(
+                "{} {:<38} {:<38} {:<38}\n",
+                if p.changed { "*" } else { " " },
+                p.name,
+                p.a.as_ref().map(|s| s.as_str()).unwrap_or("-"),
+                p.b.as_ref().map(|s| s.as_str()).unwrap_or("-")
+            ));
+        }
+        table.push('\n');
+        for m in &self.metrics {
+            table.push_str(&format!("  {:<38} {:<38.6} {:<38.6} {:+.6}\n", m.name, m.a, m.b, m.delta));
+        }
+
+        table
+    }
+}
+
+#[cfg(test)]
+fn test_run(period: &str, trades: Vec<Trade>) -> BacktestRun {
+    use {BacktestType, DataSource, DataDest};
+
+    let definition = BacktestDefinition {
+        start_time: None,
+        max_timestamp: None,
+        max_tick_n: Some(1000),
+        symbol: "TEST".to_string(),
+        backtest_type: BacktestType::Fast{delay_ms: 0},
+        data_source: DataSource::Flatfile,
+        data_dest: DataDest::Null,
+        broker_settings: SimBrokerSettings::default(),
+        additional_symbols: Vec::new(),
+    };
+    let mut strategy_settings = HashMap::new();
+    strategy_settings.insert(String::from("period"), String::from(period));
+    let res = SimBacktestResult {
+        report: PerformanceReport::from_trades(&trades, 1000.),
+        trades: trades,
+        tick_count: 1000,
+        last_timestamp: 999,
+    };
+
+    BacktestRun::new(String::from("sma_cross"), strategy_settings, definition.clone(), definition.broker_settings, res)
+}
+
+#[test]
+fn run_diffing() {
+    let trade = Trade {symbol_id: 0, long: true, size: 1, entry_price: 100, exit_price: 150, entry_time: 10, exit_time: 20};
+    let a = test_run("20", Vec::new());
+    let b = test_run("50", vec![trade]);
+    // the starting balance when the trade was opened followed by the balance after it was closed
+    assert_eq!(b.equity_curve, vec![(10, 1000.), (20, 1050.)]);
+    assert_eq!(a.equity_curve, vec![(0, 1000.)]);
+    assert_eq!(b.summary().symbols, vec![String::from("TEST")]);
+
+    let diff = RunDiff::new(&a, &b);
+    let changed: Vec<&str> = diff.parameters.iter().filter(|p| p.changed).map(|p| p.name.as_str()).collect();
+    assert_eq!(changed, vec!["strategy.period"]);
+    assert!(diff.parameters.iter().any(|p| p.name == "backtest.max_tick_n" && p.a == Some(String::from("1000"))));
+
+    let trade_count = diff.metrics.iter().find(|m| m.name == "trade_count").unwrap();
+    assert_eq!((trade_count.a, trade_count.b, trade_count.delta), (0., 1., 1.));
+    assert!(diff.to_table().contains("* strategy.period"));
+}
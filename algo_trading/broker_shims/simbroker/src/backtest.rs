@@ -0,0 +1,201 @@
+//! Runs backtests entirely in-process by driving the simulation loop of a `SimBroker` directly from the thread
+//! that the strategy is running on.  This is much faster than routing data through the rest of the platform
+//! and is used for things like optimization where many backtests need to be run one after another.
+
+use serde_json;
+
+use tickgrinder_util::strategies::{StrategyManager, ManagedStrategy, StrategyAction};
+use tickgrinder_util::trading::objects::Position;
+use tickgrinder_util::trading::performance::{Trade, PerformanceReport, sort_trades};
+use tickgrinder_util::transport::tickstream::TickGenerators;
+
+use super::*;
+
+/// Size of the buffer passed to the `SimBroker` to be filled with messages for the client each tick.
+const OUTPUT_BUFFER_SIZE: usize = 420;
+
+/// Contains everything necessary to run an in-process backtest other than the strategy itself.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SimBacktestDefinition {
+    /// Settings for the `SimBroker`.  Its `tickstreams` determine what data the backtest is run on.
+    pub broker_settings: SimBrokerSettings,
+    /// Ticks with timestamps before this aren't sent to the strategy or None.  Tickstreams that can be started at
+    /// a point in time skip these ticks without reading them at all.
+    #[serde(default)]
+    pub min_timestamp: Option<u64>,
+    /// Stop the backtest once a tick with a timestamp past this is received or None
+    pub max_timestamp: Option<u64>,
+    /// Stop the backtest after `max_tick_n` ticks have been sent to the strategy or None
+    pub max_tick_n: Option<usize>,
+    /// Close the positions that are still open at the end of the backtest at the last price of their symbol so
+    /// that they're included in the trades.  If false, they are left out.
+    #[serde(default)]
+    pub close_open_positions: bool,
+}
+
+/// The outcome of an in-process backtest.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SimBacktestResult {
+    /// Every position that was fully closed during the backtest, sorted by exit time.  Positions that are still
+    /// open at the end of the backtest are only included if `close_open_positions` is set.
+    pub trades: Vec<Trade>,
+    pub report: PerformanceReport,
+    /// The number of ticks that were sent to the strategy
+    pub tick_count: usize,
+    /// The timestamp of the last tick that was sent to the strategy
+    pub last_timestamp: u64,
+}
+
+impl SimBacktestDefinition {
+    /// Runs the backtest to completion on the current thread, returning the trades made by the strategy and
+    /// statistics about its performance.
+    pub fn run(&self, strategy: Box<ManagedStrategy<SimBrokerClient, ()>>) -> Result<SimBacktestResult, String> {
+        let mut broker_settings = self.broker_settings.clone();
+        if let Some(min_timestamp) = self.min_timestamp {
+            broker_settings.tickstreams = start_tickstreams_at(&broker_settings.tickstreams, min_timestamp)?;
+        }
+        let client = SimBrokerClient::from_settings(broker_settings)
+            .map_err(|err| format!("Unable to initialize SimBroker: {:?}", err))?;
+        let mut manager: StrategyManager<SimBrokerClient, ()> = StrategyManager::new(strategy, client, Vec::new());
+        manager.init();
+        manager.helper.broker.init_sim_loop()
+            .map_err(|err| format!("Unable to initialize sim loop: {:?}", err))?;
+
+        let (tick_count, last_timestamp) = self.drive(&mut manager);
+        let trades = broker_trades(&mut manager.helper.broker, self.close_open_positions, last_timestamp)?;
+
+        let report = PerformanceReport::from_trades(&trades, self.broker_settings.starting_balance as f64);
+        Ok(SimBacktestResult {
+            trades: trades,
+            report: report,
+            tick_count: tick_count,
+            last_timestamp: last_timestamp,
+        })
+    }
+
+    /// Drives the simulation loop, routing all ticks and pushstream messages into the strategy and its actions back
+    /// into the broker until the data runs out or one of the stop conditions is reached.  Returns the number of
+    /// ticks processed and the timestamp of the last one.
+    fn drive(&self, manager: &mut StrategyManager<SimBrokerClient, ()>) -> (usize, u64) {
+        let mut buffer = Vec::with_capacity(OUTPUT_BUFFER_SIZE);
+        buffer.resize(OUTPUT_BUFFER_SIZE, TickOutput::Tick(0, Tick::null()));
+        let mut tick_count = 0;
+        let mut last_timestamp = 0;
+        // how many actions were sent to the broker in response to the last batch of messages
+        let mut action_count = 0;
+
+        loop {
+            // actions sent during the last iteration are only added to the queue during the next call to `tick_sim_loop()`
+            if action_count == 0 && manager.helper.broker.sim_finished() {
+                break;
+            }
+
+            let msg_count = manager.helper.broker.tick_sim_loop(action_count, &mut buffer);
+            action_count = 0;
+
+            for i in 0..msg_count {
+                let strategy_action = match buffer[i] {
+                    TickOutput::Tick(_, tick) if self.min_timestamp.map(|min| tick.timestamp < min).unwrap_or(false) => None,
+                    TickOutput::Tick(ix, tick) => {
+                        tick_count += 1;
+                        last_timestamp = tick.timestamp;
+                        manager.broker_tick(ix, tick)
+                    },
+                    TickOutput::Pushstream(timestamp, ref res) => manager.pushstream_tick(res.clone(), timestamp),
+                };
+
+                // only broker actions have any meaning when running outside of the platform
+                if let Some(StrategyAction::BrokerAction(broker_action)) = strategy_action {
+                    let _ = manager.helper.broker.execute(broker_action);
+                    action_count += 1;
+                }
+            }
+
+            let past_max_time = self.max_timestamp.map(|max| last_timestamp > max).unwrap_or(false);
+            let past_max_ticks = self.max_tick_n.map(|max| tick_count >= max).unwrap_or(false);
+            if past_max_time || past_max_ticks {
+                break;
+            }
+        }
+
+        (tick_count, last_timestamp)
+    }
+}
+
+/// Makes all tickstreams in the JSON-encoded list of `SimBrokerSettings` skip the ticks before `timestamp`.
+/// Tickstreams that can't be started at a point in time are left as they are.
+fn start_tickstreams_at(tickstreams: &str, timestamp: u64) -> Result<String, String> {
+    let mut tickstreams: Vec<(String, TickGenerators, bool, usize)> = serde_json::from_str(tickstreams)
+        .map_err(|err| format!("Unable to deserialize the input tickstreams: {:?}", err))?;
+    for &mut (_, ref mut gen, _, _) in tickstreams.iter_mut() {
+        gen.start_at(timestamp);
+    }
+
+    serde_json::to_string(&tickstreams).map_err(|err| format!("{:?}", err))
+}
+
+/// Collects the closed positions out of all of the broker's simulated accounts, sorted by exit time.  If
+/// `close_open_positions` is set, positions that are still open are closed at the last price of their symbol at
+/// `timestamp`; otherwise they're left out.
+pub fn broker_trades(
+    broker: &mut SimBrokerClient, close_open_positions: bool, timestamp: u64
+) -> Result<Vec<Trade>, String> {
+    let mut trades = Vec::new();
+    for account_uuid in broker.list_accounts() {
+        let ledger = broker.get_ledger_clone(account_uuid).map_err(|err| format!("{:?}", err))?;
+        trades.extend(ledger.closed_positions.values().filter_map(Trade::from_position));
+        if close_open_positions {
+            for pos in ledger.open_positions.values() {
+                let price = broker.get_price(pos.symbol_id);
+                trades.extend(price.and_then(|price| close_at(pos, price, timestamp)));
+            }
+        }
+    }
+    sort_trades(&mut trades);
+
+    Ok(trades)
+}
+
+/// Returns the trade made by closing an open position at the current `(bid, ask)` price of its symbol.  Returns
+/// `None` if the position was never opened.
+fn close_at(pos: &Position, (bid, ask): (usize, usize), timestamp: u64) -> Option<Trade> {
+    let mut pos = pos.clone();
+    pos.exit_price = Some(if pos.long { bid } else { ask });
+    pos.exit_time = Some(timestamp);
+    Trade::from_position(&pos)
+}
+
+#[test]
+fn backtest_tickstreams_start_at_min_timestamp() {
+    let tickstreams = vec![
+        ("EURUSD", TickGenerators::BinaryReader{symbol: String::from("EURUSD"), start_time: None}, true, 5),
+        ("TEST", TickGenerators::RandomReader, false, 0),
+    ];
+    let json = serde_json::to_string(&tickstreams).unwrap();
+    let started = start_tickstreams_at(&json, 100).unwrap();
+    let parsed: Vec<(String, TickGenerators, bool, usize)> = serde_json::from_str(&started).unwrap();
+    match parsed[0].1 {
+        TickGenerators::BinaryReader{start_time: Some(100), ..} => (),
+        _ => panic!("The binary reader wasn't started at the minimum timestamp"),
+    }
+    match parsed[1].1 {
+        TickGenerators::RandomReader => (),
+        _ => panic!("The random reader was changed"),
+    }
+}
+
+#[test]
+fn open_positions_closed_at_last_price() {
+    let mut pos = Position {
+        creation_time: 1, symbol_id: 0, size: 2, price: Some(10), long: true, stop: None, take_profit: None,
+        execution_time: Some(2), execution_price: Some(10), exit_price: None, exit_time: None,
+    };
+    let trade = close_at(&pos, (14, 15), 9).unwrap();
+    assert_eq!((trade.exit_price, trade.exit_time), (14, 9));
+    assert_eq!(trade.pnl(), 8.);
+
+    pos.long = false;
+    assert_eq!(close_at(&pos, (14, 15), 9).unwrap().exit_price, 15);
+    pos.execution_price = None;
+    assert!(close_at(&pos, (14, 15), 9).is_none());
+}
@@ -27,35 +27,7 @@ impl Broker for SimBrokerClient {
         // this currently panics if you give it bad values...
         // TODO: convert FromHashmap to return a Result<SimbrokerSettings>
         let broker_settings = SimBrokerSettings::from_hashmap(settings);
-        let cs = CommandServer::new(Uuid::new_v4(), "Simbroker");
-        // the channel to communicate commands to the consumed broker.
-        // has a buffer size of 32 to avoid blocking during the send cycle
-        let (tx, rx) = mpsc::sync_channel(32);
-        let mut sim = match SimBroker::new(broker_settings, cs, rx) {
-            Ok(sim) => sim,
-            Err(err) => {
-                c.complete(Err(err));
-                return o;
-            },
-        };
-
-        let push_stream_recv = sim.push_stream_recv.take().expect("No push stream to take from the sim!");
-        let mut tick_hm = HashMap::new();
-        // take the tick receivers from each of the symbols and put them in the `HashMap`
-        for sym in sim.symbols.iter_mut() {
-            let recv = sym.client_receiver.take().unwrap();
-            tick_hm.insert(sym.name.clone(), (recv, Arc::new(AtomicBool::new(false)),));
-        }
-
-        let mut client = SimBrokerClient {
-            simbroker: sim,
-            inner_tx: tx,
-            push_stream_recv: Some((push_stream_recv, Arc::new(AtomicBool::new(false)),)),
-            tick_recvs: tick_hm,
-            in_loop: false,
-        };
-
-        c.complete(Ok(client));
+        c.complete(SimBrokerClient::from_settings(broker_settings));
 
         o
     }
@@ -138,6 +110,31 @@ impl Broker for SimBrokerClient {
 }
 
 impl SimBrokerClient {
+    /// Creates a new client and the `SimBroker` that it wraps out of a `SimBrokerSettings` object.
+    pub fn from_settings(settings: SimBrokerSettings) -> Result<SimBrokerClient, BrokerError> {
+        let cs = CommandServer::new(Uuid::new_v4(), "Simbroker");
+        // the channel to communicate commands to the consumed broker.
+        // has a buffer size of 32 to avoid blocking during the send cycle
+        let (tx, rx) = mpsc::sync_channel(32);
+        let mut sim = SimBroker::new(settings, cs, rx)?;
+
+        let push_stream_recv = sim.push_stream_recv.take().expect("No push stream to take from the sim!");
+        let mut tick_hm = HashMap::new();
+        // take the tick receivers from each of the symbols and put them in the `HashMap`
+        for sym in sim.symbols.iter_mut() {
+            let recv = sym.client_receiver.take().unwrap();
+            tick_hm.insert(sym.name.clone(), (recv, Arc::new(AtomicBool::new(false)),));
+        }
+
+        Ok(SimBrokerClient {
+            simbroker: sim,
+            inner_tx: tx,
+            push_stream_recv: Some((push_stream_recv, Arc::new(AtomicBool::new(false)),)),
+            tick_recvs: tick_hm,
+            in_loop: false,
+        })
+    }
+
     /// Initializes the inner `SimBroker` and starts its simulation loop.  This essentially "turns on" the
     /// `SimBroker`.  After this is called, it's impossible to do things like add new symbols.
     pub fn init_sim_loop(&mut self) -> BrokerResult {
@@ -220,11 +217,56 @@ impl SimBrokerClient {
         Ok(BrokerMessage::Success)
     }
 
+    /// Adds a tickstream for a new symbol to the inner `SimBroker`, blocking until its first tick is received.
+    /// Tickstreams can only be added before the simulation loop is started.
+    pub fn register_tickstream(
+        &mut self, name: String, tickstream: BoxStream<Tick, ()>, is_fx: bool, decimal_precision: usize
+    ) -> BrokerResult {
+        if self.in_loop {
+            return Err(BrokerError::Message{
+                message: String::from("Tickstreams can't be added after the simulation loop has been started."),
+            });
+        }
+
+        let res = self.simbroker.register_tickstream(name.clone(), tickstream, is_fx, decimal_precision)?;
+        // hand out the symbol's ticks to clients the same way as those of the initial tickstreams
+        let ix = self.simbroker.symbols.get_index(&name).unwrap();
+        let recv = self.simbroker.symbols[ix].client_receiver.take().unwrap();
+        self.tick_recvs.insert(name, (recv, Arc::new(AtomicBool::new(false)),));
+
+        Ok(res)
+    }
+
     /// Ticks the inner `SimBroker`'s event loop.
     pub fn tick_sim_loop(&mut self, num_last_actions: usize, buffer: &mut Vec<TickOutput>) -> usize {
         self.simbroker.tick_sim_loop(num_last_actions, buffer)
     }
 
+    /// Returns `true` if the simulation has processed every tick and event in its queue.
+    pub fn sim_finished(&self) -> bool {
+        self.simbroker.sim_finished()
+    }
+
+    /// Returns the UUIDs of all accounts simulated by the inner `SimBroker`.
+    pub fn list_accounts(&self) -> Vec<Uuid> {
+        self.simbroker.accounts.iter().map(|(uuid, _)| *uuid).collect()
+    }
+
+    /// Calls same function on inner `SimBroker`
+    pub fn get_ledger_clone(&mut self, account_uuid: Uuid) -> Result<Ledger, BrokerError> {
+        self.simbroker.get_ledger_clone(account_uuid)
+    }
+
+    /// Calls same function on inner `SimBroker`
+    pub fn get_price(&self, ix: usize) -> Option<(usize, usize)> {
+        self.simbroker.get_price(ix)
+    }
+
+    /// Returns a copy of the inner `SimBroker`'s settings.
+    pub fn get_settings(&self) -> SimBrokerSettings {
+        self.simbroker.settings.clone()
+    }
+
     /// Calls same function on inner `SimBroker`
     pub fn oneshot_price_set(
         &mut self, name: String, price: (usize, usize), is_fx: bool, decimal_precision: usize,
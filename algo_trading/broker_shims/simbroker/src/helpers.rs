@@ -23,6 +23,7 @@ pub trait FromHashmap<T> : Default {
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 // procedural macro is defined in the `from_hashmap` crate found in the util directory's root.
 #[derive(FromHashmap)]
+#[serde(default)]
 pub struct SimBrokerSettings {
     pub starting_balance: usize,
     /// How many nanoseconds ahead the broker is to the client
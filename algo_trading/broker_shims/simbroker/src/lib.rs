@@ -47,6 +47,8 @@ mod helpers;
 pub use self::helpers::*;
 mod client;
 pub use self::client::*;
+mod backtest;
+pub use self::backtest::*;
 mod superlog;
 use superlog::SuperLogger;
 
@@ -279,6 +281,12 @@ impl SimBroker {
         client_event_count
     }
 
+    /// Returns `true` if there are no more ticks or events left to process in the simulation queue.  Actions
+    /// submitted by the client that haven't yet been passed in to `tick_sim_loop()` aren't counted.
+    pub fn sim_finished(&self) -> bool {
+        self.pq.q.is_empty()
+    }
+
     /// Immediately sends a message over the broker's push channel.  Should only be called from within
     /// the SimBroker's internal event handling loop since it immediately sends the message.
     fn push_msg(&mut self, _: BrokerResult) {
@@ -904,7 +912,7 @@ impl SimBroker {
     /// Returns the current price for a given symbol or None if the SimBroker
     /// doensn't have a price.
     pub fn get_price(&self, ix: usize) -> Option<(usize, usize)> {
-        if !self.symbols.len() > ix {
+        if ix < self.symbols.len() {
             return Some(self.symbols[ix].price)
         }
 
@@ -263,6 +263,27 @@ pub const RUNTIME_SETTINGS: SettingsPage = SettingsPage {
             setting_type: SettingType::Boolean,
             comment: Some("If true, entire PostgreSQL database will be wiped every time a Tick Processor is spawned."),
         },
+        SettingRow {
+            id: "tick_parser_batch_size",
+            name: "Tick Parser Batch Size",
+            default: Some("500"),
+            setting_type: SettingType::Usize,
+            comment: Some("How many ticks the Tick Parser buffers before writing them to the database in a single query."),
+        },
+        SettingRow {
+            id: "tick_parser_flush_interval",
+            name: "Tick Parser Flush Interval",
+            default: Some("1000"),
+            setting_type: SettingType::Usize,
+            comment: Some("How often in ms the Tick Parser writes buffered ticks to the database if its buffer isn't full."),
+        },
+        SettingRow {
+            id: "backtester_batch_workers",
+            name: "Backtester Batch Workers",
+            default: Some("0"),
+            setting_type: SettingType::Usize,
+            comment: Some("How many backtests of a batch the Backtester runs at the same time.  0 runs one per CPU core."),
+        },
     ],
     comment: None,
 };
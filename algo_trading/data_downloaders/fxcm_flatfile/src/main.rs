@@ -3,7 +3,6 @@
 #![feature(rustc_attrs, conservative_impl_trait, associated_consts, custom_derive, slice_patterns)]
 
 extern crate uuid;
-extern crate libflate;
 extern crate hyper;
 extern crate chrono;
 extern crate tickgrinder_util;
@@ -20,14 +19,13 @@ use std::thread;
 use std::sync::{Arc, Mutex};
 use std::collections::HashMap;
 use std::path::Path;
-use std::io::{Read, Write};
+use std::io;
 use std::fmt::Debug;
 use std::fs::File;
 
 use uuid::Uuid;
 use chrono::{Datelike, NaiveDate, NaiveDateTime};
 use hyper::client::Client;
-use libflate::gzip::Decoder;
 use tempdir::TempDir;
 
 use tickgrinder_util::instance::PlatformInstance;
@@ -167,7 +165,7 @@ impl Downloader {
             let dst_dir = TempDir::new(&symbol).expect("Unable to create temporary directory");
             loop {
                 let download_url = get_data_url(&symbol, year, week);
-                let dst_path = &dst_dir.path().join(&format!("{}_{}.csv", year, week));
+                let dst_path = &dst_dir.path().join(&format!("{}_{}.csv.gz", year, week));
 
                 match download_chunk(&*clone.http_client, &download_url, dst_path) {
                     Ok(true) => {
@@ -184,9 +182,9 @@ impl Downloader {
                         // entry.cur_week = week;
                     },
                     Ok(false) => { // download is complete
-                        // transfer the data from the temporary .csv files into the `HistTickDst`
+                        // transfer the data from the temporary .csv.gz files into the `HistTickDst`, decompressing it
                         loop {
-                            let filename = String::from(dst_dir.path().join(&format!("{}_{}.csv", start_year, start_week))
+                            let filename = String::from(dst_dir.path().join(&format!("{}_{}.csv.gz", start_year, start_week))
                                 .to_str().expect("Unable to convert path to `str`"));
                             transfer_data(HistTickDst::Flatfile{filename: filename}, dst.clone(), clone.cs.clone());
 
@@ -221,35 +219,20 @@ impl Downloader {
     }
 }
 
-/// Downloads a file using HTTP, decompresses it using GZIP, and saves it to the supplied path.  The return value of the boolean
-/// is true if the download was successful and false if it was a 404 error.
+/// Downloads a gzipped file using HTTP and saves it to the supplied path without decompressing it.  The return value of the
+/// boolean is true if the download was successful and false if it was a 404 error.
 fn download_chunk(http_client: &Client, url: &str, dst: &Path) -> Result<bool, String> {
     // make the HTTP request and make sure it was successful
-    let res = http_client.get(url).send().expect(&format!("Error while sending HTTP request to {}", url));
+    let mut res = http_client.get(url).send().expect(&format!("Error while sending HTTP request to {}", url));
     if res.status == hyper::NotFound {
         return Ok(false);
     } else if res.status != hyper::Ok {
         return Err(format!("Unexpected response type from HTTP request: {:?}", res.status));
     }
 
-    // create a new Gzip decoder to decompress the data from the HTTP response
-    let mut decoder = try!(Decoder::new(res).map_err(debug));
-    // allocate a 1MB buffer for the data from the unzipped data
-    let mut buf = Box::new([0u8; 1024 * 1024]);
-    // create the output file
+    // create the output file and copy the compressed response into it
     let mut dst_file = File::create(dst).map_err(debug)?;
-
-    // keep reading chunks of data out of the decoder until it's empty and writing them to file
-    loop {
-        let bytes_read = decoder.read(buf.as_mut()).map_err(debug)?;
-        // we're done if we read 0 bytes
-        if bytes_read == 0 {
-            break;
-        }
-
-        // write the read bytes into the destination file
-        dst_file.write(&buf.as_ref()[0..bytes_read]).map_err(debug)?;
-    }
+    io::copy(&mut res, &mut dst_file).map_err(debug)?;
 
     dst_file.sync_all().map_err(|_| format!("Unable to sync file to disc: {:?}", dst_file))?;
     Ok(true)
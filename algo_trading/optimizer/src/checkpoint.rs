@@ -0,0 +1,75 @@
+//! Saves the progress of optimizations to disk so that interrupted runs can be resumed.
+
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use serde_json;
+use uuid::Uuid;
+
+use leaderboard::Leaderboard;
+use search::{OptimizationDefinition, Evaluation};
+use genetic::Population;
+
+/// Everything needed to resume an optimization.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Checkpoint {
+    pub id: Uuid,
+    pub definition: OptimizationDefinition,
+    pub leaderboard: Leaderboard,
+    /// Every point evaluated so far, in order
+    pub history: Vec<Evaluation>,
+    /// The last evaluated population if this is a genetic optimization
+    pub population: Option<Population>,
+    /// `true` if the optimization ran to completion
+    pub finished: bool,
+}
+
+/// Returns the path of the checkpoint file for the optimization with the given ID.
+pub fn checkpoint_path(dir: &Path, id: Uuid) -> PathBuf {
+    let mut path = dir.to_path_buf();
+    path.push(id.hyphenated().to_string());
+    path.set_extension("json");
+    path
+}
+
+impl Checkpoint {
+    /// Writes the checkpoint into the directory, replacing any earlier checkpoint of the same optimization.  The
+    /// file is written to a temporary location first so that an interruption never leaves a partial checkpoint.
+    pub fn save(&self, dir: &Path) -> Result<(), String> {
+        try!(fs::create_dir_all(dir).map_err(|err| format!("Unable to create checkpoint directory: {:?}", err)));
+        let json = try!(serde_json::to_string(self).map_err(|err| format!("{:?}", err)));
+
+        let path = checkpoint_path(dir, self.id);
+        let tmp_path = path.with_extension("json.tmp");
+        let mut file = try!(File::create(&tmp_path).map_err(|err| format!("Unable to create checkpoint file: {:?}", err)));
+        try!(file.write_all(json.as_bytes()).map_err(|err| format!("Unable to write checkpoint: {:?}", err)));
+        fs::rename(&tmp_path, &path).map_err(|err| format!("Unable to move checkpoint into place: {:?}", err))
+    }
+
+    pub fn load(dir: &Path, id: Uuid) -> Result<Checkpoint, String> {
+        let path = checkpoint_path(dir, id);
+        let mut file = try!(File::open(&path).map_err(|err| format!("Unable to open checkpoint {:?}: {:?}", path, err)));
+        let mut json = String::new();
+        try!(file.read_to_string(&mut json).map_err(|err| format!("Unable to read checkpoint: {:?}", err)));
+        serde_json::from_str(&json).map_err(|err| format!("Unable to parse checkpoint: {:?}", err))
+    }
+}
+
+#[test]
+fn checkpoint_round_trip() {
+    use std::env;
+    use uuid::Uuid;
+    use search::{Search, SearchMethod, TestEvaluator, test_definition};
+
+    let mut dir = env::temp_dir();
+    dir.push("optimizer_checkpoint_test");
+    let def = test_definition(SearchMethod::Grid);
+    let mut search = Search::new(Uuid::new_v4(), def, TestEvaluator {evaluations: 0});
+    search.evaluate(&[3, 1]);
+    let checkpoint = search.checkpoint(false);
+
+    checkpoint.save(&dir).unwrap();
+    assert_eq!(Checkpoint::load(&dir, checkpoint.id).unwrap(), checkpoint);
+    assert!(Checkpoint::load(&dir, Uuid::new_v4()).is_err());
+}
@@ -0,0 +1,246 @@
+//! A genetic algorithm that evolves a population of parameter sets.  Each generation, parents are chosen by
+//! tournament selection, recombined with uniform crossover, and mutated to create the next generation.  The best
+//! individuals of each generation are carried over unchanged.
+
+use std::cmp::{self, Ordering};
+
+use rand::Rng;
+
+use params::*;
+use search::{Search, Evaluator, Evaluation, step_rng, compare_scores};
+
+/// Settings for a genetic optimization.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct GeneticSettings {
+    pub population_size: usize,
+    /// The maximum number of generations to evaluate, including the initial random one
+    pub generations: usize,
+    /// How many randomly chosen individuals compete to become each parent
+    pub tournament_size: usize,
+    /// Probability that a child is created by crossing over two parents rather than copying one
+    pub crossover_rate: f64,
+    /// Probability that each parameter of a child is mutated
+    pub mutation_rate: f64,
+    /// How many of the best individuals are copied unchanged into the next generation
+    pub elitism: usize,
+    pub seed: u64,
+    /// Stop early if the best score hasn't improved for this many generations
+    pub stagnation_limit: Option<usize>,
+}
+
+impl GeneticSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.population_size == 0 || self.generations == 0 || self.tournament_size == 0 {
+            return Err(String::from("The population size, generation count, and tournament size must be nonzero."));
+        }
+        if self.elitism > self.population_size {
+            return Err(String::from("Elitism can't be larger than the population size."));
+        }
+        if self.crossover_rate < 0. || self.crossover_rate > 1. || self.mutation_rate < 0. || self.mutation_rate > 1. {
+            return Err(String::from("Crossover and mutation rates must be between 0 and 1."));
+        }
+
+        Ok(())
+    }
+}
+
+/// One evaluated generation of a genetic optimization.  This is saved after every generation so that an
+/// interrupted optimization can be resumed.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Population {
+    /// The index of the generation, starting at 0
+    pub generation: usize,
+    /// The individuals of the generation sorted from best to worst
+    pub individuals: Vec<Evaluation>,
+    /// The best score of any generation so far
+    pub best_score: Option<f64>,
+    /// How many generations in a row have failed to improve on `best_score`
+    pub stagnant_generations: usize,
+}
+
+/// Runs the genetic algorithm until the maximum number of generations is reached or it stagnates.  If the search
+/// already contains a population, evolution continues from it.
+pub fn run<E: Evaluator>(search: &mut Search<E>, settings: &GeneticSettings) -> Result<(), String> {
+    try!(settings.validate());
+    let space = search.def.params.clone();
+
+    let mut pop = match search.population.take() {
+        Some(pop) => pop,
+        None => {
+            let mut rng = step_rng(settings.seed, 0);
+            let genes = (0..settings.population_size).map(|_| random_point(&space, &mut rng)).collect();
+            evaluate_generation(search, genes, 0, None, 0)
+        },
+    };
+
+    loop {
+        search.population = Some(pop.clone());
+        try!(search.save_checkpoint(false));
+
+        if pop.generation + 1 >= settings.generations {
+            break;
+        }
+        if settings.stagnation_limit.map(|limit| pop.stagnant_generations >= limit).unwrap_or(false) {
+            break;
+        }
+
+        let genes = breed(&space, settings, &pop);
+        pop = evaluate_generation(search, genes, pop.generation + 1, pop.best_score, pop.stagnant_generations);
+    }
+
+    Ok(())
+}
+
+/// Evaluates every individual of a generation and determines whether it improved on the best score so far.
+fn evaluate_generation<E: Evaluator>(
+    search: &mut Search<E>, genes: Vec<Vec<usize>>, generation: usize, best_score: Option<f64>, stagnant: usize
+) -> Population {
+    let mut individuals: Vec<Evaluation> = genes.into_iter().map(|point| {
+        let score = search.evaluate(&point);
+        Evaluation {point: point, score: score}
+    }).collect();
+    individuals.sort_by(|a, b| compare_scores(a.score, b.score));
+
+    let gen_best = individuals.first().and_then(|i| i.score);
+    let improved = compare_scores(gen_best, best_score) == Ordering::Less;
+
+    Population {
+        generation: generation,
+        individuals: individuals,
+        best_score: if improved { gen_best } else { best_score },
+        stagnant_generations: if improved { 0 } else { stagnant + 1 },
+    }
+}
+
+/// Creates the genes of the generation following `pop`.
+pub fn breed(space: &ParamSpace, settings: &GeneticSettings, pop: &Population) -> Vec<Vec<usize>> {
+    let mut rng = step_rng(settings.seed, pop.generation + 1);
+    let mut next: Vec<Vec<usize>> = pop.individuals.iter().take(settings.elitism).map(|i| i.point.clone()).collect();
+
+    while next.len() < settings.population_size {
+        let parent = tournament(&pop.individuals, settings.tournament_size, &mut rng);
+        let mut child = if rng.gen::<f64>() < settings.crossover_rate {
+            let other = tournament(&pop.individuals, settings.tournament_size, &mut rng);
+            crossover(&parent.point, &other.point, &mut rng)
+        } else {
+            parent.point.clone()
+        };
+        mutate(&mut child, space, settings.mutation_rate, &mut rng);
+        next.push(child);
+    }
+
+    next
+}
+
+/// Picks `size` individuals at random and returns the best of them.  Individuals must be sorted best-first.
+fn tournament<'a, R: Rng>(individuals: &'a [Evaluation], size: usize, rng: &mut R) -> &'a Evaluation {
+    let mut winner = rng.gen_range(0, individuals.len());
+    for _ in 1..size {
+        winner = cmp::min(winner, rng.gen_range(0, individuals.len()));
+    }
+
+    &individuals[winner]
+}
+
+/// Uniform crossover; each parameter is taken from either parent with equal probability.
+fn crossover<R: Rng>(a: &[usize], b: &[usize], rng: &mut R) -> Vec<usize> {
+    a.iter().zip(b.iter()).map(|(&x, &y)| if rng.gen() { x } else { y }).collect()
+}
+
+/// Mutates each parameter with probability `rate`.  Numeric parameters are shifted by up to a tenth of their
+/// range in either direction while categorical ones are replaced with a random choice.
+fn mutate<R: Rng>(genes: &mut [usize], space: &ParamSpace, rate: f64, rng: &mut R) {
+    for (gene, range) in genes.iter_mut().zip(space.values()) {
+        if rng.gen::<f64>() >= rate {
+            continue;
+        }
+
+        let len = range.len();
+        match *range {
+            ParamRange::Range{..} => {
+                let max_shift = cmp::max(1, len / 10) as isize;
+                let shifted = *gene as isize + rng.gen_range(-max_shift, max_shift + 1);
+                *gene = cmp::max(0, cmp::min(len as isize - 1, shifted)) as usize;
+            },
+            ParamRange::Choice{..} => *gene = rng.gen_range(0, len),
+        }
+    }
+}
+
+#[cfg(test)]
+use uuid::Uuid;
+#[cfg(test)]
+use search::{SearchMethod, TestEvaluator, test_definition};
+
+#[cfg(test)]
+fn test_settings(generations: usize, stagnation_limit: Option<usize>) -> GeneticSettings {
+    GeneticSettings {
+        population_size: 6,
+        generations: generations,
+        tournament_size: 2,
+        crossover_rate: 0.7,
+        mutation_rate: 0.3,
+        elitism: 1,
+        seed: 1337,
+        stagnation_limit: stagnation_limit,
+    }
+}
+
+#[test]
+fn genetic_is_reproducible_and_elitist() {
+    let def = test_definition(SearchMethod::Genetic(test_settings(8, None)));
+    let mut a = Search::new(Uuid::new_v4(), def.clone(), TestEvaluator {evaluations: 0});
+    let mut b = Search::new(Uuid::new_v4(), def, TestEvaluator {evaluations: 0});
+    let lb_a = a.run().unwrap();
+    let lb_b = b.run().unwrap();
+    assert_eq!(a.history, b.history);
+    assert_eq!(lb_a.results, lb_b.results);
+
+    let pop = a.population.unwrap();
+    assert_eq!(pop.generation, 7);
+    assert_eq!(pop.individuals.len(), 6);
+    // the best individual is never lost thanks to elitism
+    assert_eq!(pop.individuals[0].score, pop.best_score);
+    assert_eq!(pop.best_score, Some(lb_a.best().unwrap().score));
+}
+
+#[test]
+fn genetic_stops_on_stagnation() {
+    let def = test_definition(SearchMethod::Genetic(test_settings(100, Some(2))));
+    let mut search = Search::new(Uuid::new_v4(), def, TestEvaluator {evaluations: 0});
+    search.run().unwrap();
+    let pop = search.population.unwrap();
+    assert!(pop.generation < 99);
+    assert_eq!(pop.stagnant_generations, 2);
+}
+
+#[test]
+fn genetic_resumes_from_population() {
+    let def = test_definition(SearchMethod::Genetic(test_settings(6, None)));
+    let mut uninterrupted = Search::new(Uuid::new_v4(), def.clone(), TestEvaluator {evaluations: 0});
+    uninterrupted.run().unwrap();
+
+    // stop after three generations, then resume with the full generation count
+    let mut short_def = def.clone();
+    short_def.search = SearchMethod::Genetic(test_settings(3, None));
+    let mut interrupted = Search::new(Uuid::new_v4(), short_def, TestEvaluator {evaluations: 0});
+    interrupted.run().unwrap();
+    let mut checkpoint = interrupted.checkpoint(false);
+    checkpoint.definition = def;
+
+    let mut resumed = Search::resume(checkpoint, TestEvaluator {evaluations: 0});
+    resumed.run().unwrap();
+    assert_eq!(resumed.population, uninterrupted.population);
+    assert_eq!(resumed.history, uninterrupted.history);
+}
+
+#[test]
+fn mutation_stays_in_range() {
+    let def = test_definition(SearchMethod::Grid);
+    let mut rng = step_rng(5, 0);
+    for _ in 0..100 {
+        let mut genes = vec![10, 1];
+        mutate(&mut genes, &def.params, 1., &mut rng);
+        assert!(genes[0] <= 10 && genes[1] <= 1);
+    }
+}
@@ -0,0 +1,132 @@
+//! Keeps track of the best-scoring parameter sets found during an optimization.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json;
+use uuid::Uuid;
+
+use tickgrinder_util::transport::commands::SrcDocument;
+use tickgrinder_util::trading::performance::{PerformanceReport, Objective};
+
+use params::ParamSet;
+
+/// The result of backtesting the strategy with one set of parameters.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RunResult {
+    pub params: ParamSet,
+    /// The value of the optimization's objective for this run
+    pub score: f64,
+    pub report: PerformanceReport,
+}
+
+/// The best results of an optimization, sorted from best to worst.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Leaderboard {
+    pub id: Uuid,
+    pub strategy: String,
+    pub objective: Objective,
+    /// The maximum number of results to keep
+    pub size: usize,
+    /// How many backtests were run in total
+    pub runs: usize,
+    /// How many of the backtests failed to complete
+    pub failed_runs: usize,
+    /// How many of the backtests made too few trades to be scored
+    #[serde(default)]
+    pub unscored_runs: usize,
+    pub results: Vec<RunResult>,
+}
+
+impl Leaderboard {
+    pub fn new(id: Uuid, strategy: String, objective: Objective, size: usize) -> Leaderboard {
+        Leaderboard {
+            id: id,
+            strategy: strategy,
+            objective: objective,
+            size: size,
+            runs: 0,
+            failed_runs: 0,
+            unscored_runs: 0,
+            results: Vec::new(),
+        }
+    }
+
+    /// Records a successful run, keeping it only if it is among the best `size` results.  Results with equal
+    /// scores are kept in the order they were inserted.
+    pub fn insert(&mut self, res: RunResult) {
+        self.runs += 1;
+        let ix = self.results.iter().position(|other| res.score > other.score).unwrap_or(self.results.len());
+        if ix < self.size {
+            self.results.insert(ix, res);
+            self.results.truncate(self.size);
+        }
+    }
+
+    /// Records a run that didn't complete.
+    pub fn insert_failure(&mut self) {
+        self.runs += 1;
+        self.failed_runs += 1;
+    }
+
+    /// Records a run that completed but can't be ranked against the others.
+    pub fn insert_unscored(&mut self) {
+        self.runs += 1;
+        self.unscored_runs += 1;
+    }
+
+    pub fn best(&self) -> Option<&RunResult> {
+        self.results.first()
+    }
+
+    /// Converts the leaderboard into a JSON-encoded `SrcDocument` that can be inserted into the document store.
+    pub fn to_document(&self) -> Result<String, String> {
+        let body = serde_json::to_string_pretty(self).map_err(|err| format!("{:?}", err))?;
+        let title = format!("Optimization Leaderboard: {} ({})", self.strategy, self.id.hyphenated());
+        let tags = vec![String::from("optimization"), String::from("leaderboard"), self.strategy.clone()];
+        make_document(self.id, title, body, tags)
+    }
+}
+
+/// Creates a JSON-encoded `SrcDocument` with the current time as its creation and modification dates.
+pub fn make_document(id: Uuid, title: String, body: String, tags: Vec<String>) -> Result<String, String> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_err(|err| format!("{:?}", err))?;
+    let timestamp = (now.as_secs() * 1000 + now.subsec_nanos() as u64 / 1_000_000).to_string();
+
+    let doc = SrcDocument {
+        title: title,
+        body: body,
+        tags: tags,
+        creation_date: timestamp.clone(),
+        modification_date: timestamp,
+        id: id,
+    };
+    serde_json::to_string(&doc).map_err(|err| format!("{:?}", err))
+}
+
+#[cfg(test)]
+fn test_result(score: f64) -> RunResult {
+    let mut params = ParamSet::new();
+    params.insert(String::from("period"), score.to_string());
+    let mut report = PerformanceReport::from_trades(&[], 1000.);
+    report.sharpe = score;
+    RunResult {params: params, score: score, report: report}
+}
+
+#[test]
+fn leaderboard_ordering() {
+    let mut lb = Leaderboard::new(Uuid::new_v4(), String::from("test"), Objective::Sharpe, 3);
+    for &score in &[1., 5., 3., 4., 2.] {
+        lb.insert(test_result(score));
+    }
+    lb.insert_failure();
+
+    let scores: Vec<f64> = lb.results.iter().map(|r| r.score).collect();
+    assert_eq!(scores, vec![5., 4., 3.]);
+    assert_eq!(lb.runs, 6);
+    assert_eq!(lb.failed_runs, 1);
+
+    let doc: SrcDocument = serde_json::from_str(&lb.to_document().unwrap()).unwrap();
+    assert_eq!(doc.id, lb.id);
+    let stored: Leaderboard = serde_json::from_str(&doc.body).unwrap();
+    assert_eq!(stored, lb);
+}
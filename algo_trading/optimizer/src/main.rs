@@ -13,12 +13,24 @@ extern crate serde_json;
 #[macro_use]
 extern crate serde_derive;
 extern crate fxcm;
+extern crate rand;
 
 extern crate tickgrinder_util;
+extern crate simbroker;
+extern crate private;
+
+mod params;
+mod leaderboard;
+mod search;
+mod genetic;
+mod tpe;
+mod checkpoint;
+mod walk_forward;
 
 use std::thread;
 use std::time::Duration;
 use std::env;
+use std::path::PathBuf;
 
 use uuid::Uuid;
 use futures::stream::Stream;
@@ -27,31 +39,32 @@ use tickgrinder_util::transport::commands::*;
 use tickgrinder_util::transport::redis::*;
 use tickgrinder_util::transport::command_server::CommandServer;
 use tickgrinder_util::conf::CONF;
+use tickgrinder_util::trading::performance::PerformanceReport;
+use tickgrinder_util::trading::monte_carlo::{MonteCarloDefinition, MonteCarloReport};
+use params::ParamSet;
+use leaderboard::make_document;
+use search::{OptimizationDefinition, SearchMethod, Search, SimEvaluator};
+use checkpoint::Checkpoint;
+use walk_forward::{WalkForwardDefinition, WalkForward, WindowResult};
 
 struct Optimizer {
     cs: CommandServer,
     uuid: Uuid,
+    /// The strategy that the optimizer was spawned with, used for definitions that don't specify one
+    strategy: Option<String>,
 }
 
 impl Optimizer {
-    pub fn new(uuid: Uuid) -> Optimizer {
+    pub fn new(uuid: Uuid, strategy: Option<String>) -> Optimizer {
         let cs = CommandServer::new(uuid, "Optimizer");
         Optimizer {
             cs: cs,
             uuid: uuid,
+            strategy: strategy,
         }
     }
 
     pub fn init(mut self) {
-        // initialize the strategy
-        // let query_server = QueryServer::new(CONF.conn_senders);
-        // let broker = FXCMNative::init(HashMap::new()).wait();
-        // let cs = self.cs.clone();
-        // thread::spawn(move || {
-        //     let mut strat = strat::new(cs, query_server, &mut broker);
-        //     strat.init();
-        // });
-
         let rx = sub_multiple(CONF.redis_host, &[self.uuid.hyphenated().to_string().as_str(), CONF.redis_control_channel]);
         let client = get_client(CONF.redis_host);
 
@@ -87,22 +100,240 @@ impl Optimizer {
                 });
                 Response::Info{ info: "Optimizer ending life in 3 seconds...".to_string() }
             },
+            Command::StartOptimization{ref definition} => {
+                match self.start_optimization(definition) {
+                    Ok(id) => Response::Info{ info: id.hyphenated().to_string() },
+                    Err(err) => Response::Error{ status: err },
+                }
+            },
+            Command::ResumeOptimization{id} => {
+                match self.resume_optimization(id) {
+                    Ok(()) => Response::Ok,
+                    Err(err) => Response::Error{ status: err },
+                }
+            },
+            Command::StartWalkForward{ref definition} => {
+                match self.start_walk_forward(definition) {
+                    Ok(id) => Response::Info{ info: id.hyphenated().to_string() },
+                    Err(err) => Response::Error{ status: err },
+                }
+            },
+            Command::RunMonteCarlo{ref definition} => {
+                match self.run_monte_carlo(definition) {
+                    Ok(id) => Response::Info{ info: id.hyphenated().to_string() },
+                    Err(err) => Response::Error{ status: err },
+                }
+            },
             _ => Response::Error{status: "Optimizer doesn't recognize that command.".to_string() }
         }
     }
+
+    /// Parses the optimization definition and starts running it in a new thread, returning the ID of the
+    /// optimization.  Once the optimization is finished, its leaderboard is inserted into the document store.
+    fn start_optimization(&mut self, definition: &str) -> Result<Uuid, String> {
+        let mut def: OptimizationDefinition = try!(serde_json::from_str(definition)
+            .map_err(|err| format!("Unable to parse optimization definition: {:?}", err)));
+        try!(self.prepare_definition(&mut def));
+
+        let id = Uuid::new_v4();
+        let cs = self.cs.clone();
+        thread::spawn(move || {
+            let evaluator = SimEvaluator::new(&def);
+            run_optimization(Search::new(id, def, evaluator), cs);
+        });
+
+        Ok(id)
+    }
+
+    /// Fills in the default strategy of an optimization definition and makes sure that it is valid.
+    fn prepare_definition(&self, def: &mut OptimizationDefinition) -> Result<(), String> {
+        if def.strategy.is_empty() {
+            def.strategy = try!(self.strategy.clone()
+                .ok_or(String::from("No strategy was supplied in the definition or when spawning the optimizer.")));
+        }
+        try!(params::validate_space(&def.params));
+        match def.search {
+            SearchMethod::Genetic(ref settings) => settings.validate(),
+            SearchMethod::Tpe(ref settings) => settings.validate(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Parses the walk-forward definition and starts running it in a new thread, returning the ID of the
+    /// analysis.  Once it is finished, its report is inserted into the document store.
+    fn start_walk_forward(&mut self, definition: &str) -> Result<Uuid, String> {
+        let mut def: WalkForwardDefinition = try!(serde_json::from_str(definition)
+            .map_err(|err| format!("Unable to parse walk-forward definition: {:?}", err)));
+        try!(self.prepare_definition(&mut def.optimization));
+        try!(def.windows());
+
+        let id = Uuid::new_v4();
+        let cs = self.cs.clone();
+        thread::spawn(move || {
+            let evaluator = SimEvaluator::new(&def.optimization);
+            run_walk_forward(WalkForward::new(id, def, evaluator), cs);
+        });
+
+        Ok(id)
+    }
+
+    /// Parses the Monte Carlo definition and runs the analysis in a new thread, returning the ID of the document
+    /// that its report will be inserted into the document store as.
+    fn run_monte_carlo(&mut self, definition: &str) -> Result<Uuid, String> {
+        let def: MonteCarloDefinition = try!(serde_json::from_str(definition)
+            .map_err(|err| format!("Unable to parse Monte Carlo definition: {:?}", err)));
+        try!(def.settings.validate());
+
+        let id = Uuid::new_v4();
+        let mut cs = self.cs.clone();
+        thread::spawn(move || {
+            let id_string = id.hyphenated().to_string();
+            let doc_res = def.run().and_then(|report| {
+                cs.notice(Some(&id_string), &format!(
+                    "Monte Carlo analysis finished; final equity interval: ({}, {}), risk of ruin: {}",
+                    report.final_equity.lower, report.final_equity.upper, report.risk_of_ruin
+                ));
+                monte_carlo_document(id, &report)
+            });
+
+            match doc_res {
+                Ok(doc) => {
+                    let _ = cs.execute(Command::InsertIntoDocumentStore{doc: doc}, CONF.redis_control_channel.to_string());
+                },
+                Err(err) => cs.error(Some(&id_string), &format!("Monte Carlo analysis failed: {}", err)),
+            }
+        });
+
+        Ok(id)
+    }
+
+    /// Continues an interrupted optimization from its last checkpoint in a new thread.
+    fn resume_optimization(&mut self, id: Uuid) -> Result<(), String> {
+        let checkpoint = try!(Checkpoint::load(&checkpoint_dir(), id));
+        if checkpoint.finished {
+            return Err(String::from("That optimization has already finished."));
+        }
+
+        let cs = self.cs.clone();
+        thread::spawn(move || {
+            let evaluator = SimEvaluator::new(&checkpoint.definition);
+            run_optimization(Search::resume(checkpoint, evaluator), cs);
+        });
+
+        Ok(())
+    }
+}
+
+/// Returns the directory that optimization checkpoints are saved in.
+fn checkpoint_dir() -> PathBuf {
+    let mut dir = PathBuf::from(CONF.data_dir);
+    dir.push("optimizations");
+    dir
+}
+
+/// Runs an optimization to completion, logging its progress, saving checkpoints, and finally inserting its
+/// leaderboard into the document store.
+fn run_optimization(mut search: Search<SimEvaluator>, mut cs: CommandServer) {
+    let id_string = search.id.hyphenated().to_string();
+    cs.notice(Some(&id_string), &format!(
+        "Starting optimization of {}; {} backtests have already been completed.", search.def.strategy, search.history.len()
+    ));
+
+    search.save_checkpoints(checkpoint_dir());
+    let mut progress_cs = cs.clone();
+    let progress_id = id_string.clone();
+    let mut completed = search.history.len();
+    search.on_progress(Box::new(move |params: &ParamSet, res: &Result<PerformanceReport, String>| {
+        completed += 1;
+        match *res {
+            Ok(_) => progress_cs.debug(Some(&progress_id), &format!("Finished backtest {}: {:?}", completed, params)),
+            Err(ref err) => progress_cs.error(Some(&progress_id), &format!("Backtest with params {:?} failed: {}", params, err)),
+        };
+    }));
+
+    let leaderboard = match search.run() {
+        Ok(lb) => lb,
+        Err(err) => {
+            cs.error(Some(&id_string), &format!("Optimization failed: {}", err));
+            return;
+        },
+    };
+    cs.notice(Some(&id_string), &format!("Optimization finished; best result: {:?}", leaderboard.best()));
+
+    match leaderboard.to_document() {
+        Ok(doc) => {
+            let _ = cs.execute(Command::InsertIntoDocumentStore{doc: doc}, CONF.redis_control_channel.to_string());
+        },
+        Err(err) => cs.error(Some(&id_string), &format!("Unable to store the leaderboard: {}", err)),
+    }
+}
+
+/// Converts a Monte Carlo report into a JSON-encoded `SrcDocument`.
+fn monte_carlo_document(id: Uuid, report: &MonteCarloReport) -> Result<String, String> {
+    let body = try!(serde_json::to_string_pretty(report).map_err(|err| format!("{:?}", err)));
+    let title = format!("Monte Carlo Analysis ({})", id.hyphenated());
+    make_document(id, title, body, vec![String::from("monte_carlo")])
+}
+
+/// Runs a walk-forward analysis to completion, logging the result of each window and finally inserting the
+/// report into the document store.
+fn run_walk_forward(mut wf: WalkForward<SimEvaluator>, mut cs: CommandServer) {
+    let id_string = wf.id.hyphenated().to_string();
+    let window_count = wf.def.windows().map(|windows| windows.len()).unwrap_or(0);
+    cs.notice(Some(&id_string), &format!(
+        "Starting walk-forward analysis of {} over {} windows.", wf.def.optimization.strategy, window_count
+    ));
+
+    let mut progress_cs = cs.clone();
+    let progress_id = id_string.clone();
+    wf.on_progress(Box::new(move |i: usize, res: &WindowResult| {
+        match res.error {
+            None => progress_cs.debug(Some(&progress_id), &format!(
+                "Finished window {}: params {:?}, out-of-sample {:?}", i, res.params, res.out_of_sample
+            )),
+            Some(ref err) => progress_cs.error(Some(&progress_id), &format!("Window {} failed: {}", i, err)),
+        };
+    }));
+
+    let report = match wf.run() {
+        Ok(report) => report,
+        Err(err) => {
+            cs.error(Some(&id_string), &format!("Walk-forward analysis failed: {}", err));
+            return;
+        },
+    };
+    cs.notice(Some(&id_string), &format!(
+        "Walk-forward analysis finished; out-of-sample return: {}, efficiency: {:?}",
+        report.out_of_sample.total_return, report.efficiency
+    ));
+
+    match report.to_document() {
+        Ok(doc) => {
+            let _ = cs.execute(Command::InsertIntoDocumentStore{doc: doc}, CONF.redis_control_channel.to_string());
+        },
+        Err(err) => cs.error(Some(&id_string), &format!("Unable to store the walk-forward report: {}", err)),
+    }
 }
 
 fn main() {
     let args = env::args().collect::<Vec<String>>();
     let uuid: Uuid;
 
+    let strategy: Option<String>;
+
     match *args.as_slice() {
         [_, ref uuid_str] => {
             uuid = Uuid::parse_str(uuid_str.as_str())
                 .expect("Unable to parse Uuid from supplied argument");
+            strategy = None;
+        },
+        [_, ref uuid_str, ref strategy_name] => {
+            uuid = Uuid::parse_str(uuid_str.as_str())
+                .expect("Unable to parse Uuid from supplied argument");
+            strategy = Some(strategy_name.clone());
         },
-        _ => panic!("Wrong number of arguments provided!  Usage: ./tick_processor [uuid] [symbol]"),
+        _ => panic!("Wrong number of arguments provided!  Usage: ./optimizer [uuid] [strategy]"),
     }
 
-    Optimizer::new(uuid).init()
+    Optimizer::new(uuid, strategy).init()
 }
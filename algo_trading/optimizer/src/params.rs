@@ -0,0 +1,202 @@
+//! Definitions of the parameter spaces that optimizations search over.
+//!
+//! Every parameter has a finite, ordered list of values that it can take.  Points in the parameter space are
+//! represented as the index of each parameter's value, in the iteration order of the `ParamSpace`, which makes
+//! it easy to enumerate, sample, and recombine them.
+
+use std::collections::{BTreeMap, HashMap};
+
+use rand::Rng;
+
+/// The values that a single parameter can take.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ParamRange {
+    /// Every value from `min` to `max` (inclusive) in increments of `step`
+    Range{min: f64, max: f64, step: f64},
+    /// A categorical parameter that can be any of the listed values
+    Choice{values: Vec<String>},
+}
+
+impl ParamRange {
+    /// Returns the number of distinct values that the parameter can take.
+    pub fn len(&self) -> usize {
+        match *self {
+            // the small epsilon keeps floating point error from dropping the last value
+            ParamRange::Range{min, max, step} => ((max - min) / step + 1e-9).floor() as usize + 1,
+            ParamRange::Choice{ref values} => values.len(),
+        }
+    }
+
+    /// Returns the value at index `i` formatted the way it is passed to the strategy.
+    pub fn value(&self, i: usize) -> String {
+        match *self {
+            ParamRange::Range{min, step, ..} => {
+                // round off floating point error so that values like 0.30000000000000004 don't appear
+                let val = ((min + step * i as f64) * 1e9).round() / 1e9;
+                val.to_string()
+            },
+            ParamRange::Choice{ref values} => values[i].clone(),
+        }
+    }
+
+    /// Makes sure that the range contains at least one value.
+    pub fn validate(&self, name: &str) -> Result<(), String> {
+        match *self {
+            ParamRange::Range{min, max, step} => {
+                if step <= 0. || !step.is_finite() {
+                    return Err(format!("The step of parameter \"{}\" must be positive.", name));
+                }
+                if max < min {
+                    return Err(format!("The max of parameter \"{}\" is less than its min.", name));
+                }
+            },
+            ParamRange::Choice{ref values} => {
+                if values.is_empty() {
+                    return Err(format!("Parameter \"{}\" has no values to choose from.", name));
+                }
+            },
+        }
+
+        Ok(())
+    }
+}
+
+/// Maps parameter names to the values they can take.  A `BTreeMap` is used so that parameters are always in
+/// the same order, which keeps seeded searches reproducible.
+pub type ParamSpace = BTreeMap<String, ParamRange>;
+
+/// A set of concrete parameter values that a strategy is run with.
+pub type ParamSet = BTreeMap<String, String>;
+
+/// Returns an error if any of the parameters in the space can't take any values.
+pub fn validate_space(space: &ParamSpace) -> Result<(), String> {
+    if space.is_empty() {
+        return Err(String::from("The parameter space doesn't contain any parameters."));
+    }
+
+    for (name, range) in space {
+        try!(range.validate(name));
+    }
+
+    Ok(())
+}
+
+/// Returns the total number of points in the parameter space.
+pub fn space_size(space: &ParamSpace) -> usize {
+    space.values().fold(1usize, |acc, range| acc.saturating_mul(range.len()))
+}
+
+/// Iterates over every point in a parameter space, varying the last parameter fastest.  Points are generated as
+/// they're needed so that the whole grid never has to be held in memory.
+pub struct GridPoints {
+    /// The number of values of each parameter
+    lens: Vec<usize>,
+    next_point: Option<Vec<usize>>,
+}
+
+impl Iterator for GridPoints {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Vec<usize>> {
+        let point = match self.next_point.take() {
+            Some(point) => point,
+            None => return None,
+        };
+
+        // count up like an odometer, carrying into the earlier parameters
+        let mut following = point.clone();
+        let mut i = following.len();
+        while i > 0 {
+            i -= 1;
+            following[i] += 1;
+            if following[i] < self.lens[i] {
+                self.next_point = Some(following);
+                break;
+            }
+            following[i] = 0;
+        }
+
+        Some(point)
+    }
+}
+
+/// Returns an iterator over every point in the parameter space, varying the last parameter fastest.
+pub fn grid_points(space: &ParamSpace) -> GridPoints {
+    let lens: Vec<usize> = space.values().map(|range| range.len()).collect();
+    let first = if lens.iter().any(|&len| len == 0) { None } else { Some(vec![0; lens.len()]) };
+
+    GridPoints {
+        lens: lens,
+        next_point: first,
+    }
+}
+
+/// Returns a point chosen uniformly at random from the parameter space.
+pub fn random_point<R: Rng>(space: &ParamSpace, rng: &mut R) -> Vec<usize> {
+    space.values().map(|range| rng.gen_range(0, range.len())).collect()
+}
+
+/// Converts a point into the parameter values that it represents.
+pub fn to_params(space: &ParamSpace, point: &[usize]) -> ParamSet {
+    space.iter().zip(point.iter()).map(|((name, range), &i)| (name.clone(), range.value(i))).collect()
+}
+
+/// Combines the optimized parameters with the fixed parameters into the settings passed to the strategy.
+/// Optimized parameters take precedence.
+pub fn strategy_settings(fixed: &HashMap<String, String>, params: &ParamSet) -> HashMap<String, String> {
+    let mut settings = fixed.clone();
+    for (k, v) in params {
+        settings.insert(k.clone(), v.clone());
+    }
+
+    settings
+}
+
+#[cfg(test)]
+fn test_space() -> ParamSpace {
+    let mut space = ParamSpace::new();
+    space.insert(String::from("period"), ParamRange::Range{min: 10., max: 30., step: 10.});
+    space.insert(String::from("threshold"), ParamRange::Range{min: 0.1, max: 0.3, step: 0.1});
+    space.insert(String::from("type"), ParamRange::Choice{values: vec![String::from("ema"), String::from("sma")]});
+    space
+}
+
+#[test]
+fn range_values() {
+    let range = ParamRange::Range{min: 0.1, max: 0.3, step: 0.1};
+    assert_eq!(range.len(), 3);
+    assert_eq!((0..3).map(|i| range.value(i)).collect::<Vec<_>>(), vec!["0.1", "0.2", "0.3"]);
+
+    let int_range = ParamRange::Range{min: 5., max: 12., step: 5.};
+    assert_eq!(int_range.len(), 2);
+    assert_eq!(int_range.value(1), "10");
+
+    assert!(ParamRange::Range{min: 1., max: 0., step: 1.}.validate("x").is_err());
+    assert!(ParamRange::Range{min: 0., max: 1., step: 0.}.validate("x").is_err());
+    assert!(ParamRange::Choice{values: Vec::new()}.validate("x").is_err());
+}
+
+#[test]
+fn grid_enumeration() {
+    let space = test_space();
+    let points: Vec<Vec<usize>> = grid_points(&space).collect();
+    assert_eq!(points.len(), 18);
+    assert_eq!(space_size(&space), 18);
+    assert_eq!(points[0], vec![0, 0, 0]);
+    assert_eq!(points[1], vec![0, 0, 1]);
+    assert_eq!(points[17], vec![2, 2, 1]);
+
+    let params = to_params(&space, &points[17]);
+    assert_eq!(params["period"], "30");
+    assert_eq!(params["threshold"], "0.3");
+    assert_eq!(params["type"], "sma");
+
+    // points of huge spaces are generated as they're needed
+    let mut huge = ParamSpace::new();
+    for name in &["a", "b", "c", "d", "e", "f", "g"] {
+        huge.insert(String::from(*name), ParamRange::Range{min: 0., max: 999., step: 1.});
+    }
+    let first: Vec<Vec<usize>> = grid_points(&huge).take(1001).collect();
+    assert_eq!(first[999], vec![0, 0, 0, 0, 0, 0, 999]);
+    assert_eq!(first[1000], vec![0, 0, 0, 0, 0, 1, 0]);
+}
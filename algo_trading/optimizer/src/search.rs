@@ -0,0 +1,425 @@
+//! Searches the parameter space of a strategy for the parameters that maximize an objective by running a
+//! SimBroker-backed backtest for each set of parameters.
+
+use std::collections::{HashMap, HashSet};
+use std::cmp::Ordering;
+use std::path::PathBuf;
+use std::thread;
+use std::f64;
+
+use rand::{SeedableRng, Isaac64Rng};
+use uuid::Uuid;
+
+use simbroker::{SimBacktestDefinition, SimBacktestResult, SimBrokerClient};
+use private::strategies::get_strategy;
+use tickgrinder_util::trading::performance::{PerformanceReport, Objective};
+
+use params::*;
+use leaderboard::{Leaderboard, RunResult};
+use checkpoint::Checkpoint;
+use genetic::{self, GeneticSettings, Population};
+use tpe::{self, TpeSettings};
+
+/// How the points in the parameter space that are backtested are chosen.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum SearchMethod {
+    /// Backtest every point in the parameter space
+    Grid,
+    /// Backtest `iterations` distinct points chosen at random
+    Random{iterations: usize, seed: u64},
+    /// Evolve a population of parameter sets with a genetic algorithm
+    Genetic(GeneticSettings),
+    /// Choose each point to backtest based on the results so far using a Tree-structured Parzen Estimator
+    Tpe(TpeSettings),
+}
+
+fn default_leaderboard_size() -> usize { 25 }
+fn default_min_trades() -> usize { 30 }
+
+/// Contains everything necessary to run an optimization.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct OptimizationDefinition {
+    /// The name of the strategy as passed to `private::strategies::get_strategy()`.  If empty, the strategy that
+    /// the optimizer was spawned with is used.
+    #[serde(default)]
+    pub strategy: String,
+    pub params: ParamSpace,
+    /// Settings passed to the strategy in every run that aren't optimized
+    #[serde(default)]
+    pub fixed_params: HashMap<String, String>,
+    pub backtest: SimBacktestDefinition,
+    pub search: SearchMethod,
+    pub objective: Objective,
+    /// How many of the best results to keep
+    #[serde(default = "default_leaderboard_size")]
+    pub leaderboard_size: usize,
+    /// Runs that make fewer trades than this aren't scored.  Ratios like the profit factor are meaningless for a
+    /// handful of trades and a single winning trade would otherwise top the leaderboard.
+    #[serde(default = "default_min_trades")]
+    pub min_trades: usize,
+}
+
+/// Something that can determine how well a strategy performs with a set of parameters.
+pub trait Evaluator {
+    fn evaluate(&mut self, params: &ParamSet) -> Result<PerformanceReport, String>;
+}
+
+/// Evaluates parameters by running the strategy in an in-process backtest against a `SimBroker`.
+pub struct SimEvaluator {
+    pub strategy: String,
+    pub fixed_params: HashMap<String, String>,
+    pub backtest: SimBacktestDefinition,
+}
+
+impl SimEvaluator {
+    pub fn new(def: &OptimizationDefinition) -> SimEvaluator {
+        SimEvaluator {
+            strategy: def.strategy.clone(),
+            fixed_params: def.fixed_params.clone(),
+            backtest: def.backtest.clone(),
+        }
+    }
+
+    /// Runs a backtest with the given parameters, returning its full result.
+    pub fn backtest(&self, params: &ParamSet) -> Result<SimBacktestResult, String> {
+        let name = self.strategy.clone();
+        let settings = strategy_settings(&self.fixed_params, params);
+        let backtest = self.backtest.clone();
+
+        // run the backtest in its own thread so that a panicking strategy only fails this run
+        let handle = thread::spawn(move || {
+            let strategy = try!(get_strategy::<SimBrokerClient>(&name, settings));
+            backtest.run(strategy)
+        });
+        match handle.join() {
+            Ok(res) => res,
+            Err(_) => Err(String::from("The backtest panicked.")),
+        }
+    }
+}
+
+impl Evaluator for SimEvaluator {
+    fn evaluate(&mut self, params: &ParamSet) -> Result<PerformanceReport, String> {
+        self.backtest(params).map(|res| res.report)
+    }
+}
+
+/// Returns the points that a grid or random search should evaluate, in order.  Other methods choose points as
+/// they go based on earlier results, so nothing is returned for them.
+pub fn search_points(space: &ParamSpace, method: &SearchMethod) -> Box<Iterator<Item=Vec<usize>>> {
+    match *method {
+        SearchMethod::Grid => Box::new(grid_points(space)),
+        SearchMethod::Random{iterations, seed} => {
+            let mut rng = step_rng(seed, 0);
+            // never try to sample more distinct points than there are
+            let n = if iterations < space_size(space) { iterations } else { space_size(space) };
+            let mut seen = HashSet::new();
+            let mut points = Vec::with_capacity(n);
+            while points.len() < n {
+                let point = random_point(space, &mut rng);
+                if seen.insert(point.clone()) {
+                    points.push(point);
+                }
+            }
+
+            Box::new(points.into_iter())
+        },
+        _ => Box::new(Vec::new().into_iter()),
+    }
+}
+
+/// A point in the parameter space along with its score, or `None` if its backtest failed or made too few trades to
+/// be scored.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Evaluation {
+    pub point: Vec<usize>,
+    pub score: Option<f64>,
+}
+
+/// Returns a PRNG for one step of a seeded search.  Deriving a separate generator for each step (generation or
+/// iteration) means that a resumed search makes exactly the same choices as one that was never interrupted, even
+/// after an upgrade of `rand`.
+pub fn step_rng(seed: u64, step: usize) -> Isaac64Rng {
+    Isaac64Rng::from_seed(&[seed, step as u64][..])
+}
+
+/// Orders scores from best to worst, with failed runs last.
+pub fn compare_scores(a: Option<f64>, b: Option<f64>) -> Ordering {
+    let a = a.unwrap_or(f64::NEG_INFINITY);
+    let b = b.unwrap_or(f64::NEG_INFINITY);
+    b.partial_cmp(&a).unwrap_or(Ordering::Equal)
+}
+
+/// The state of a running optimization.  Holds the evaluator and keeps track of every point that has been
+/// evaluated so that no set of parameters is ever backtested twice.
+pub struct Search<E> {
+    pub id: Uuid,
+    pub def: OptimizationDefinition,
+    pub evaluator: E,
+    pub leaderboard: Leaderboard,
+    /// Every point that has been evaluated, in the order they were evaluated
+    pub history: Vec<Evaluation>,
+    /// The last population of a genetic optimization
+    pub population: Option<Population>,
+    cache: HashMap<Vec<usize>, Option<f64>>,
+    progress: Box<FnMut(&ParamSet, &Result<PerformanceReport, String>)>,
+    /// Directory that checkpoints are saved to, or `None` to not save them
+    checkpoint_dir: Option<PathBuf>,
+}
+
+impl<E: Evaluator> Search<E> {
+    pub fn new(id: Uuid, def: OptimizationDefinition, evaluator: E) -> Search<E> {
+        let leaderboard = Leaderboard::new(id, def.strategy.clone(), def.objective, def.leaderboard_size);
+
+        Search {
+            id: id,
+            def: def,
+            evaluator: evaluator,
+            leaderboard: leaderboard,
+            history: Vec::new(),
+            population: None,
+            cache: HashMap::new(),
+            progress: Box::new(|_, _| ()),
+            checkpoint_dir: None,
+        }
+    }
+
+    /// Restores a search from a checkpoint so that it continues where it left off.
+    pub fn resume(checkpoint: Checkpoint, evaluator: E) -> Search<E> {
+        let mut search = Search::new(checkpoint.id, checkpoint.definition, evaluator);
+        search.leaderboard = checkpoint.leaderboard;
+        search.population = checkpoint.population;
+        for eval in checkpoint.history {
+            search.cache.insert(eval.point.clone(), eval.score);
+            search.history.push(eval);
+        }
+
+        search
+    }
+
+    /// Sets a function to be called with the results of every backtest that is run.
+    pub fn on_progress(&mut self, progress: Box<FnMut(&ParamSet, &Result<PerformanceReport, String>)>) {
+        self.progress = progress;
+    }
+
+    /// Causes a checkpoint to be written into the given directory every time progress is made.
+    pub fn save_checkpoints(&mut self, dir: PathBuf) {
+        self.checkpoint_dir = Some(dir);
+    }
+
+    pub fn is_evaluated(&self, point: &[usize]) -> bool {
+        self.cache.contains_key(point)
+    }
+
+    /// Returns the score of a point, backtesting it if it hasn't already been evaluated.
+    pub fn evaluate(&mut self, point: &[usize]) -> Option<f64> {
+        if let Some(&score) = self.cache.get(point) {
+            return score;
+        }
+
+        let params = to_params(&self.def.params, point);
+        let res = self.evaluator.evaluate(&params);
+        (self.progress)(&params, &res);
+
+        let score = match res {
+            Ok(ref report) if report.trade_count < self.def.min_trades => {
+                self.leaderboard.insert_unscored();
+                None
+            },
+            Ok(report) => {
+                let score = self.def.objective.score(&report);
+                self.leaderboard.insert(RunResult {params: params, score: score, report: report});
+                Some(score)
+            },
+            Err(_) => {
+                self.leaderboard.insert_failure();
+                None
+            },
+        };
+
+        self.cache.insert(point.to_vec(), score);
+        self.history.push(Evaluation {point: point.to_vec(), score: score});
+        score
+    }
+
+    /// Returns a snapshot of the search that can be used to resume it.
+    pub fn checkpoint(&self, finished: bool) -> Checkpoint {
+        Checkpoint {
+            id: self.id,
+            definition: self.def.clone(),
+            leaderboard: self.leaderboard.clone(),
+            history: self.history.clone(),
+            population: self.population.clone(),
+            finished: finished,
+        }
+    }
+
+    /// Saves a checkpoint if a checkpoint directory has been set.
+    pub fn save_checkpoint(&self, finished: bool) -> Result<(), String> {
+        match self.checkpoint_dir {
+            Some(ref dir) => self.checkpoint(finished).save(dir),
+            None => Ok(()),
+        }
+    }
+
+    /// Runs the search as specified by the definition to completion, returning the leaderboard of the best results.
+    pub fn run(&mut self) -> Result<Leaderboard, String> {
+        try!(validate_space(&self.def.params));
+
+        match self.def.search.clone() {
+            SearchMethod::Genetic(settings) => try!(genetic::run(self, &settings)),
+            SearchMethod::Tpe(settings) => try!(tpe::run(self, &settings)),
+            ref method => {
+                for point in search_points(&self.def.params, method) {
+                    // points evaluated before the search was resumed come straight out of the cache
+                    if !self.is_evaluated(&point) {
+                        self.evaluate(&point);
+                        try!(self.save_checkpoint(false));
+                    }
+                }
+            },
+        }
+
+        try!(self.save_checkpoint(true));
+        Ok(self.leaderboard.clone())
+    }
+}
+
+/// Scores parameters using a simple function of their values instead of running backtests.
+#[cfg(test)]
+pub struct TestEvaluator {
+    pub evaluations: usize,
+}
+
+#[cfg(test)]
+impl Evaluator for TestEvaluator {
+    /// The Sharpe ratio is highest when `a` is 3 and `b` is "y".  Fails whenever `a` is 0.
+    fn evaluate(&mut self, params: &ParamSet) -> Result<PerformanceReport, String> {
+        self.evaluations += 1;
+        let a: f64 = params["a"].parse().unwrap();
+        if a == 0. {
+            return Err(String::from("a is 0"));
+        }
+
+        let mut report = PerformanceReport::from_trades(&[], 1000.);
+        report.sharpe = -(a - 3.).powi(2) + if params["b"] == "y" { 1. } else { 0. };
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+pub fn test_definition(search: SearchMethod) -> OptimizationDefinition {
+    use std::default::Default;
+
+    let mut space = ParamSpace::new();
+    space.insert(String::from("a"), ParamRange::Range{min: 0., max: 10., step: 1.});
+    space.insert(String::from("b"), ParamRange::Choice{values: vec![String::from("x"), String::from("y")]});
+
+    OptimizationDefinition {
+        strategy: String::from("test"),
+        params: space,
+        fixed_params: HashMap::new(),
+        backtest: SimBacktestDefinition {
+            broker_settings: Default::default(),
+            min_timestamp: None,
+            max_timestamp: None,
+            max_tick_n: None,
+            close_open_positions: false,
+        },
+        search: search,
+        objective: Objective::Sharpe,
+        leaderboard_size: 5,
+        min_trades: 0,
+    }
+}
+
+#[test]
+fn grid_search_finds_optimum() {
+    let def = test_definition(SearchMethod::Grid);
+    let mut search = Search::new(Uuid::new_v4(), def, TestEvaluator {evaluations: 0});
+    let lb = search.run().unwrap();
+
+    assert_eq!(search.evaluator.evaluations, 22);
+    assert_eq!(lb.runs, 22);
+    assert_eq!(lb.failed_runs, 2);
+    assert_eq!(lb.results.len(), 5);
+    let best = lb.best().unwrap();
+    assert_eq!(best.params["a"], "3");
+    assert_eq!(best.params["b"], "y");
+    assert_eq!(best.score, 1.);
+}
+
+#[test]
+fn runs_with_few_trades_are_not_scored() {
+    use tickgrinder_util::trading::performance::Trade;
+
+    /// Makes the number of trades given by the `trades` parameter, winning all but every fifth one.
+    struct TradeEvaluator;
+
+    impl Evaluator for TradeEvaluator {
+        fn evaluate(&mut self, params: &ParamSet) -> Result<PerformanceReport, String> {
+            let n: u64 = params["trades"].parse().unwrap();
+            let trades: Vec<Trade> = (0..n).map(|i| Trade {
+                symbol_id: 0,
+                long: true,
+                size: 1,
+                entry_price: 100,
+                exit_price: if i % 5 == 4 { 95 } else { 110 },
+                entry_time: 2 * i,
+                exit_time: 2 * i + 1,
+            }).collect();
+            Ok(PerformanceReport::from_trades(&trades, 1000.))
+        }
+    }
+
+    let mut def = test_definition(SearchMethod::Grid);
+    def.params = ParamSpace::new();
+    def.params.insert(String::from("trades"), ParamRange::Choice{values: vec![String::from("1"), String::from("50")]});
+    def.objective = Objective::ProfitFactor;
+    def.min_trades = 30;
+    let lb = Search::new(Uuid::new_v4(), def, TradeEvaluator).run().unwrap();
+
+    // the single winning trade has an unbounded profit factor but doesn't count
+    assert_eq!(lb.runs, 2);
+    assert_eq!(lb.unscored_runs, 1);
+    assert_eq!(lb.results.len(), 1);
+    assert_eq!(lb.best().unwrap().params["trades"], "50");
+    assert_eq!(lb.best().unwrap().score, 8.);
+}
+
+#[test]
+fn random_search_is_seeded() {
+    let def = test_definition(SearchMethod::Random{iterations: 8, seed: 42});
+    let a: Vec<Vec<usize>> = search_points(&def.params, &def.search).collect();
+    let b: Vec<Vec<usize>> = search_points(&def.params, &def.search).collect();
+    assert_eq!(a, b);
+    assert_eq!(a.len(), 8);
+    let distinct: HashSet<_> = a.iter().collect();
+    assert_eq!(distinct.len(), 8);
+
+    // asking for more points than exist returns each point once
+    let all = search_points(&def.params, &SearchMethod::Random{iterations: 100, seed: 1});
+    assert_eq!(all.count(), 22);
+}
+
+#[test]
+fn resumed_search_skips_evaluated_points() {
+    let def = test_definition(SearchMethod::Random{iterations: 10, seed: 7});
+    let mut search = Search::new(Uuid::new_v4(), def.clone(), TestEvaluator {evaluations: 0});
+    let points: Vec<Vec<usize>> = search_points(&def.params, &def.search).collect();
+    for point in &points[0..4] {
+        search.evaluate(point);
+    }
+    // evaluating the same point again doesn't run another backtest
+    search.evaluate(&points[0]);
+    assert_eq!(search.evaluator.evaluations, 4);
+
+    let mut resumed = Search::resume(search.checkpoint(false), TestEvaluator {evaluations: 0});
+    let lb = resumed.run().unwrap();
+    assert_eq!(resumed.evaluator.evaluations, 6);
+    assert_eq!(resumed.history.len(), 10);
+    assert_eq!(lb.runs, 10);
+
+    let mut uninterrupted = Search::new(Uuid::new_v4(), def, TestEvaluator {evaluations: 0});
+    assert_eq!(uninterrupted.run().unwrap().results, lb.results);
+}
@@ -0,0 +1,284 @@
+//! A sequential optimizer based on the Tree-structured Parzen Estimator.  After a number of random startup
+//! iterations, the evaluated points are split into a "good" group containing the best `gamma` fraction and a "bad"
+//! group containing the rest.  For each parameter, a density is estimated over its values for both groups and the
+//! candidate that maximizes the ratio of the good density to the bad density is evaluated next.
+
+use std::cmp;
+
+use rand::Rng;
+
+use params::*;
+use search::{Search, Evaluator, Evaluation, step_rng, compare_scores};
+
+/// How many random points are tried when looking for one that hasn't been evaluated yet.
+const MAX_SAMPLE_ATTEMPTS: usize = 1000;
+
+/// Settings for a TPE optimization.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TpeSettings {
+    /// The maximum number of points to evaluate
+    pub iterations: usize,
+    /// How many points are chosen at random before the estimator is used
+    pub startup_iterations: usize,
+    /// How many candidates are sampled from the good densities each iteration
+    pub candidates: usize,
+    /// The fraction of evaluated points that are considered good
+    pub gamma: f64,
+    pub seed: u64,
+    /// Stop early if the best score hasn't improved for this many iterations after the startup iterations
+    pub stagnation_limit: Option<usize>,
+}
+
+impl TpeSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.iterations == 0 || self.candidates == 0 {
+            return Err(String::from("The iteration and candidate counts must be nonzero."));
+        }
+        if self.gamma <= 0. || self.gamma >= 1. {
+            return Err(String::from("Gamma must be between 0 and 1."));
+        }
+
+        Ok(())
+    }
+}
+
+/// Runs the optimizer until the iteration limit is reached, every point has been evaluated, or it stagnates.
+/// Since every iteration evaluates exactly one new point, a resumed search picks up at the iteration following
+/// the last evaluated point.
+pub fn run<E: Evaluator>(search: &mut Search<E>, settings: &TpeSettings) -> Result<(), String> {
+    try!(settings.validate());
+    let space = search.def.params.clone();
+    let total = cmp::min(settings.iterations, space_size(&space));
+
+    while search.history.len() < total {
+        let iteration = search.history.len();
+        if iteration >= settings.startup_iterations && is_stagnant(&search.history, settings.stagnation_limit) {
+            break;
+        }
+
+        let mut rng = step_rng(settings.seed, iteration);
+        let point = if iteration < settings.startup_iterations {
+            random_unevaluated(&space, search, &mut rng)
+        } else {
+            suggest(&space, search, settings, &mut rng)
+        };
+
+        match point {
+            Some(point) => { search.evaluate(&point); },
+            None => break,
+        }
+        try!(search.save_checkpoint(false));
+    }
+
+    Ok(())
+}
+
+/// Returns `true` if the best score in the history was found more than `limit` evaluations ago.
+fn is_stagnant(history: &[Evaluation], limit: Option<usize>) -> bool {
+    let limit = match limit {
+        Some(limit) => limit,
+        None => return false,
+    };
+
+    let mut best_ix = 0;
+    for (i, eval) in history.iter().enumerate() {
+        if compare_scores(eval.score, history[best_ix].score) == cmp::Ordering::Less {
+            best_ix = i;
+        }
+    }
+
+    !history.is_empty() && history.len() - 1 - best_ix >= limit
+}
+
+/// Returns a random point that hasn't been evaluated yet, or `None` if one can't be found.
+fn random_unevaluated<E: Evaluator, R: Rng>(space: &ParamSpace, search: &Search<E>, rng: &mut R) -> Option<Vec<usize>> {
+    for _ in 0..MAX_SAMPLE_ATTEMPTS {
+        let point = random_point(space, rng);
+        if !search.is_evaluated(&point) {
+            return Some(point);
+        }
+    }
+
+    None
+}
+
+/// Returns the unevaluated candidate with the highest ratio of good to bad density.
+fn suggest<E: Evaluator, R: Rng>(
+    space: &ParamSpace, search: &Search<E>, settings: &TpeSettings, rng: &mut R
+) -> Option<Vec<usize>> {
+    let mut sorted: Vec<&Evaluation> = search.history.iter().collect();
+    sorted.sort_by(|a, b| compare_scores(a.score, b.score));
+    let n_good = cmp::min(sorted.len(), cmp::max(1, (settings.gamma * sorted.len() as f64).ceil() as usize));
+    let (good, bad) = sorted.split_at(n_good);
+
+    let densities: Vec<(Vec<f64>, Vec<f64>)> = space.values().enumerate()
+        .map(|(dim, range)| (density(range, good, dim), density(range, bad, dim)))
+        .collect();
+
+    let mut best: Option<(f64, Vec<usize>)> = None;
+    for _ in 0..settings.candidates {
+        let candidate: Vec<usize> = densities.iter().map(|&(ref l, _)| sample(l, rng)).collect();
+        if search.is_evaluated(&candidate) {
+            continue;
+        }
+
+        let ratio: f64 = candidate.iter().zip(densities.iter()).map(|(&x, &(ref l, ref g))| (l[x] / g[x]).ln()).sum();
+        if best.as_ref().map(|&(best_ratio, _)| ratio > best_ratio).unwrap_or(true) {
+            best = Some((ratio, candidate));
+        }
+    }
+
+    // if all of the candidates have already been evaluated, fall back to exploring randomly
+    match best {
+        Some((_, point)) => Some(point),
+        None => random_unevaluated(space, search, rng),
+    }
+}
+
+/// Estimates the density of the values of parameter `dim` among the evaluations.  Categorical values are
+/// counted while numeric values are smoothed with a Gaussian kernel, and a uniform prior with the weight of one
+/// observation ensures that no value has zero density.
+fn density(range: &ParamRange, evals: &[&Evaluation], dim: usize) -> Vec<f64> {
+    let len = range.len();
+    let mut weights = vec![1. / len as f64; len];
+
+    match *range {
+        ParamRange::Range{..} => {
+            let bandwidth = (len as f64 / 10.).max(1.);
+            for eval in evals {
+                let center = eval.point[dim] as f64;
+                let kernel: Vec<f64> = (0..len).map(|x| (-0.5 * ((x as f64 - center) / bandwidth).powi(2)).exp()).collect();
+                let total: f64 = kernel.iter().sum();
+                for (w, k) in weights.iter_mut().zip(kernel.iter()) {
+                    *w += k / total;
+                }
+            }
+        },
+        ParamRange::Choice{..} => {
+            for eval in evals {
+                weights[eval.point[dim]] += 1.;
+            }
+        },
+    }
+
+    let total: f64 = weights.iter().sum();
+    weights.iter().map(|w| w / total).collect()
+}
+
+/// Samples an index from a discrete probability distribution.
+fn sample<R: Rng>(probabilities: &[f64], rng: &mut R) -> usize {
+    let mut remaining = rng.gen::<f64>();
+    for (i, p) in probabilities.iter().enumerate() {
+        if remaining < *p {
+            return i;
+        }
+        remaining -= *p;
+    }
+
+    probabilities.len() - 1
+}
+
+#[cfg(test)]
+use uuid::Uuid;
+#[cfg(test)]
+use search::{SearchMethod, TestEvaluator, test_definition};
+#[cfg(test)]
+use tickgrinder_util::trading::performance::PerformanceReport;
+
+#[cfg(test)]
+fn test_settings(iterations: usize, stagnation_limit: Option<usize>) -> TpeSettings {
+    TpeSettings {
+        iterations: iterations,
+        startup_iterations: 4,
+        candidates: 24,
+        gamma: 0.25,
+        seed: 99,
+        stagnation_limit: stagnation_limit,
+    }
+}
+
+#[test]
+fn density_favors_observations() {
+    let range = ParamRange::Range{min: 0., max: 20., step: 1.};
+    let evals = vec![Evaluation {point: vec![5], score: Some(1.)}];
+    let refs: Vec<&Evaluation> = evals.iter().collect();
+    let d = density(&range, &refs, 0);
+    assert!((d.iter().sum::<f64>() - 1.).abs() < 1e-9);
+    assert!(d[5] > d[4] && d[4] > d[3] && d[5] > d[15]);
+    assert!(d[20] > 0.);
+}
+
+#[test]
+fn tpe_is_reproducible() {
+    let def = test_definition(SearchMethod::Tpe(test_settings(12, None)));
+    let mut a = Search::new(Uuid::new_v4(), def.clone(), TestEvaluator {evaluations: 0});
+    let mut b = Search::new(Uuid::new_v4(), def, TestEvaluator {evaluations: 0});
+    a.run().unwrap();
+    b.run().unwrap();
+    assert_eq!(a.history, b.history);
+    assert_eq!(a.evaluator.evaluations, 12);
+}
+
+/// Gives every set of parameters the same score so that the search never improves.
+#[cfg(test)]
+struct FlatEvaluator {
+    evaluations: usize,
+}
+
+#[cfg(test)]
+impl Evaluator for FlatEvaluator {
+    fn evaluate(&mut self, _: &ParamSet) -> Result<PerformanceReport, String> {
+        self.evaluations += 1;
+        let mut report = PerformanceReport::from_trades(&[], 1000.);
+        report.sharpe = 1.;
+        Ok(report)
+    }
+}
+
+#[test]
+fn tpe_resumes() {
+    let def = test_definition(SearchMethod::Tpe(test_settings(16, None)));
+    let mut uninterrupted = Search::new(Uuid::new_v4(), def.clone(), TestEvaluator {evaluations: 0});
+    uninterrupted.run().unwrap();
+    assert_eq!(uninterrupted.history.len(), 16);
+
+    // stop a search partway through, past the startup iterations, and resume it with the full iteration count
+    let mut interrupted_def = def.clone();
+    interrupted_def.search = SearchMethod::Tpe(test_settings(7, None));
+    let mut interrupted = Search::new(Uuid::new_v4(), interrupted_def, TestEvaluator {evaluations: 0});
+    interrupted.run().unwrap();
+    let mut checkpoint = interrupted.checkpoint(false);
+    let saved = checkpoint.history.clone();
+    assert_eq!(saved.len(), 7);
+    checkpoint.definition = def;
+
+    let mut resumed = Search::resume(checkpoint, TestEvaluator {evaluations: 0});
+    resumed.run().unwrap();
+    // the saved trials are kept as they were instead of being run again
+    assert_eq!(&resumed.history[..7], &saved[..]);
+    assert_eq!(resumed.evaluator.evaluations, 9);
+    assert_eq!(resumed.leaderboard.runs, 16);
+    // and the search continues exactly as if it had never been interrupted
+    assert_eq!(resumed.history, uninterrupted.history);
+    assert_eq!(resumed.leaderboard.results, uninterrupted.leaderboard.results);
+}
+
+#[test]
+fn tpe_stops_when_stagnant() {
+    // without a stagnation limit, the search runs until every point in the space has been evaluated
+    let def = test_definition(SearchMethod::Tpe(test_settings(30, None)));
+    let mut exhaustive = Search::new(Uuid::new_v4(), def.clone(), FlatEvaluator {evaluations: 0});
+    exhaustive.run().unwrap();
+    assert_eq!(exhaustive.evaluator.evaluations, 22);
+
+    // the first score is never beaten, so the search stops as soon as the startup iterations are done
+    let mut stagnant_def = def;
+    stagnant_def.search = SearchMethod::Tpe(test_settings(30, Some(3)));
+    let mut stagnant = Search::new(Uuid::new_v4(), stagnant_def, FlatEvaluator {evaluations: 0});
+    stagnant.run().unwrap();
+    assert_eq!(stagnant.evaluator.evaluations, 4);
+    assert_eq!(stagnant.history.len(), 4);
+
+    assert!(!is_stagnant(&stagnant.history[..3], Some(3)));
+    assert!(is_stagnant(&stagnant.history, Some(3)));
+}
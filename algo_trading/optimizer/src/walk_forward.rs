@@ -0,0 +1,357 @@
+//! Walk-forward analysis guards against overfitting by only ever judging parameters on data that they weren't
+//! optimized on.  The data range is split into a series of windows, each made up of an in-sample period that the
+//! strategy is optimized over followed by an out-of-sample period that the best parameters are then backtested
+//! on.  The out-of-sample results of all windows are stitched together into a single report.
+
+use serde_json;
+use uuid::Uuid;
+
+use tickgrinder_util::trading::performance::{Trade, PerformanceReport, sort_trades, equity_curve};
+
+use params::ParamSet;
+use leaderboard::make_document;
+use search::{OptimizationDefinition, Search, Evaluator, SimEvaluator};
+
+/// How the in-sample period moves from one window to the next.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum WindowMode {
+    /// Every in-sample period has the same length and moves forward by the out-of-sample length each window
+    Rolling,
+    /// Every in-sample period starts at the beginning of the data and grows by the out-of-sample length each window
+    Anchored,
+}
+
+/// Contains everything necessary to run a walk-forward analysis.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WalkForwardDefinition {
+    /// The optimization that is run on each in-sample period.  The time limits of its backtest are ignored.
+    pub optimization: OptimizationDefinition,
+    /// Timestamp of the start of the data range
+    pub start_time: u64,
+    /// Timestamp of the end of the data range
+    pub end_time: u64,
+    /// Length of the (initial) in-sample period
+    pub in_sample_len: u64,
+    /// Length of each out-of-sample period
+    pub out_of_sample_len: u64,
+    pub mode: WindowMode,
+}
+
+/// One in-sample period and the out-of-sample period that follows it.  Periods include their start but not
+/// their end.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Window {
+    pub in_sample_start: u64,
+    pub in_sample_end: u64,
+    pub out_of_sample_start: u64,
+    pub out_of_sample_end: u64,
+}
+
+impl WalkForwardDefinition {
+    /// Splits the data range into windows.  The last out-of-sample period is cut short if it would extend past
+    /// the end of the data range.
+    pub fn windows(&self) -> Result<Vec<Window>, String> {
+        if self.in_sample_len == 0 || self.out_of_sample_len == 0 {
+            return Err(String::from("The in-sample and out-of-sample lengths must be nonzero."));
+        }
+        if self.start_time + self.in_sample_len >= self.end_time {
+            return Err(String::from("The data range is too short to contain a single window."));
+        }
+
+        let mut windows = Vec::new();
+        let mut in_sample_end = self.start_time + self.in_sample_len;
+        while in_sample_end < self.end_time {
+            let in_sample_start = match self.mode {
+                WindowMode::Rolling => in_sample_end - self.in_sample_len,
+                WindowMode::Anchored => self.start_time,
+            };
+            let out_of_sample_end = in_sample_end + self.out_of_sample_len;
+
+            windows.push(Window {
+                in_sample_start: in_sample_start,
+                in_sample_end: in_sample_end,
+                out_of_sample_start: in_sample_end,
+                out_of_sample_end: if out_of_sample_end < self.end_time { out_of_sample_end } else { self.end_time },
+            });
+            in_sample_end = out_of_sample_end;
+        }
+
+        Ok(windows)
+    }
+}
+
+/// An evaluator that can be limited to a period of time and can return the individual trades of a backtest.
+pub trait WindowedEvaluator: Evaluator + Sized {
+    /// Returns a copy of the evaluator that only backtests over the period from `start` to `end`.
+    fn restrict(&self, start: u64, end: u64) -> Self;
+
+    /// Backtests a set of parameters, returning the trades that were made.
+    fn trades(&mut self, params: &ParamSet) -> Result<Vec<Trade>, String>;
+}
+
+impl WindowedEvaluator for SimEvaluator {
+    fn restrict(&self, start: u64, end: u64) -> SimEvaluator {
+        let mut backtest = self.backtest.clone();
+        backtest.min_timestamp = Some(start);
+        // the backtest stops once it sees a tick past `max_timestamp`
+        backtest.max_timestamp = Some(end - 1);
+        // positions left open at the end of one window would otherwise never show up in any window's trades
+        backtest.close_open_positions = true;
+
+        SimEvaluator {
+            strategy: self.strategy.clone(),
+            fixed_params: self.fixed_params.clone(),
+            backtest: backtest,
+        }
+    }
+
+    fn trades(&mut self, params: &ParamSet) -> Result<Vec<Trade>, String> {
+        self.backtest(params).map(|res| res.trades)
+    }
+}
+
+/// The outcome of a single window of a walk-forward analysis.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WindowResult {
+    pub window: Window,
+    /// The best parameters found during the in-sample optimization
+    pub params: Option<ParamSet>,
+    /// The performance of the best parameters over the in-sample period
+    pub in_sample: Option<PerformanceReport>,
+    /// The performance of the best parameters over the out-of-sample period
+    pub out_of_sample: Option<PerformanceReport>,
+    /// The reason that the window couldn't be completed, if any
+    pub error: Option<String>,
+}
+
+/// The combined results of a walk-forward analysis.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WalkForwardReport {
+    pub id: Uuid,
+    pub strategy: String,
+    pub windows: Vec<WindowResult>,
+    /// The balance after every out-of-sample trade of every window as `(timestamp, balance)` pairs
+    pub equity_curve: Vec<(u64, f64)>,
+    /// The performance of all out-of-sample trades taken together
+    pub out_of_sample: PerformanceReport,
+    /// The out-of-sample return per unit of time divided by the in-sample return per unit of time over all
+    /// completed windows.  Values well below 1 are a sign that the optimization is overfitting the in-sample data.
+    /// `None` if no windows were completed or the in-sample return was 0.
+    pub efficiency: Option<f64>,
+}
+
+impl WalkForwardReport {
+    /// Converts the report into a JSON-encoded `SrcDocument` that can be inserted into the document store.
+    pub fn to_document(&self) -> Result<String, String> {
+        let body = serde_json::to_string_pretty(self).map_err(|err| format!("{:?}", err))?;
+        let title = format!("Walk-Forward Analysis: {} ({})", self.strategy, self.id.hyphenated());
+        let tags = vec![String::from("optimization"), String::from("walk_forward"), self.strategy.clone()];
+        make_document(self.id, title, body, tags)
+    }
+}
+
+/// Returns the walk-forward efficiency of the completed windows.
+fn efficiency(windows: &[WindowResult]) -> Option<f64> {
+    let mut in_sample_rate = 0.;
+    let mut out_of_sample_rate = 0.;
+    let mut completed = 0;
+    for res in windows {
+        if let (&Some(ref is), &Some(ref oos)) = (&res.in_sample, &res.out_of_sample) {
+            let w = res.window;
+            in_sample_rate += is.total_return / (w.in_sample_end - w.in_sample_start) as f64;
+            out_of_sample_rate += oos.total_return / (w.out_of_sample_end - w.out_of_sample_start) as f64;
+            completed += 1;
+        }
+    }
+
+    if completed == 0 || in_sample_rate == 0. {
+        None
+    } else {
+        Some(out_of_sample_rate / in_sample_rate)
+    }
+}
+
+/// A running walk-forward analysis.
+pub struct WalkForward<E> {
+    pub id: Uuid,
+    pub def: WalkForwardDefinition,
+    pub evaluator: E,
+    progress: Box<FnMut(usize, &WindowResult)>,
+}
+
+impl<E: WindowedEvaluator> WalkForward<E> {
+    pub fn new(id: Uuid, def: WalkForwardDefinition, evaluator: E) -> WalkForward<E> {
+        WalkForward {
+            id: id,
+            def: def,
+            evaluator: evaluator,
+            progress: Box::new(|_, _| ()),
+        }
+    }
+
+    /// Sets a function to be called with the index and result of each window once it is finished.
+    pub fn on_progress(&mut self, progress: Box<FnMut(usize, &WindowResult)>) {
+        self.progress = progress;
+    }
+
+    /// Optimizes and validates every window in order, returning the stitched out-of-sample results.
+    pub fn run(&mut self) -> Result<WalkForwardReport, String> {
+        let windows = try!(self.def.windows());
+        let starting_balance = self.def.optimization.backtest.broker_settings.starting_balance as f64;
+
+        let mut results = Vec::with_capacity(windows.len());
+        let mut trades = Vec::new();
+        for (i, window) in windows.into_iter().enumerate() {
+            let res = self.run_window(window, &mut trades);
+            (self.progress)(i, &res);
+            results.push(res);
+        }
+
+        sort_trades(&mut trades);
+        Ok(WalkForwardReport {
+            id: self.id,
+            strategy: self.def.optimization.strategy.clone(),
+            efficiency: efficiency(&results),
+            equity_curve: equity_curve(&trades, starting_balance),
+            out_of_sample: PerformanceReport::from_trades(&trades, starting_balance),
+            windows: results,
+        })
+    }
+
+    /// Optimizes over the in-sample period of a window and backtests the best parameters over its out-of-sample
+    /// period, adding the out-of-sample trades to `trades`.
+    fn run_window(&mut self, window: Window, trades: &mut Vec<Trade>) -> WindowResult {
+        let mut res = WindowResult {window: window, params: None, in_sample: None, out_of_sample: None, error: None};
+
+        let mut opt_def = self.def.optimization.clone();
+        opt_def.backtest.min_timestamp = Some(window.in_sample_start);
+        opt_def.backtest.max_timestamp = Some(window.in_sample_end - 1);
+        opt_def.backtest.close_open_positions = true;
+        let evaluator = self.evaluator.restrict(window.in_sample_start, window.in_sample_end);
+        let mut search = Search::new(Uuid::new_v4(), opt_def, evaluator);
+
+        let best = match search.run() {
+            Ok(ref lb) if lb.best().is_some() => lb.best().unwrap().clone(),
+            Ok(_) => {
+                res.error = Some(String::from("None of the in-sample backtests succeeded."));
+                return res;
+            },
+            Err(err) => {
+                res.error = Some(format!("In-sample optimization failed: {}", err));
+                return res;
+            },
+        };
+        res.params = Some(best.params.clone());
+        res.in_sample = Some(best.report);
+
+        let starting_balance = self.def.optimization.backtest.broker_settings.starting_balance as f64;
+        let mut oos_evaluator = self.evaluator.restrict(window.out_of_sample_start, window.out_of_sample_end);
+        match oos_evaluator.trades(&best.params) {
+            Ok(mut oos_trades) => {
+                sort_trades(&mut oos_trades);
+                res.out_of_sample = Some(PerformanceReport::from_trades(&oos_trades, starting_balance));
+                trades.extend(oos_trades);
+            },
+            Err(err) => res.error = Some(format!("Out-of-sample backtest failed: {}", err)),
+        }
+
+        res
+    }
+}
+
+/// Makes one trade spanning the whole period it is restricted to, earning more the closer `a` is to the optimum
+/// for that period.  The optimum is 3 before timestamp 1000 and 6 after it.
+#[cfg(test)]
+pub struct WindowTestEvaluator {
+    pub start: u64,
+    pub end: u64,
+}
+
+#[cfg(test)]
+impl Evaluator for WindowTestEvaluator {
+    fn evaluate(&mut self, params: &ParamSet) -> Result<PerformanceReport, String> {
+        let trades = try!(self.trades(params));
+        Ok(PerformanceReport::from_trades(&trades, 5000000.))
+    }
+}
+
+#[cfg(test)]
+impl WindowedEvaluator for WindowTestEvaluator {
+    fn restrict(&self, start: u64, end: u64) -> WindowTestEvaluator {
+        WindowTestEvaluator {start: start, end: end}
+    }
+
+    fn trades(&mut self, params: &ParamSet) -> Result<Vec<Trade>, String> {
+        let a: f64 = params["a"].parse().unwrap();
+        if a == 0. {
+            return Err(String::from("a is 0"));
+        }
+
+        let optimum = if self.start < 1000 { 3. } else { 6. };
+        let pnl = (self.end - self.start) as f64 * (10. - (a - optimum).powi(2));
+        Ok(vec![Trade {
+            symbol_id: 0,
+            long: true,
+            size: 1,
+            entry_price: 100000,
+            exit_price: (100000. + pnl) as usize,
+            entry_time: self.start,
+            exit_time: self.end - 1,
+        }])
+    }
+}
+
+#[cfg(test)]
+fn test_walk_forward(mode: WindowMode) -> WalkForwardDefinition {
+    use search::{SearchMethod, test_definition};
+    use tickgrinder_util::trading::performance::Objective;
+
+    let mut optimization = test_definition(SearchMethod::Grid);
+    optimization.objective = Objective::TotalReturn;
+
+    WalkForwardDefinition {
+        optimization: optimization,
+        start_time: 0,
+        end_time: 2200,
+        in_sample_len: 1000,
+        out_of_sample_len: 500,
+        mode: mode,
+    }
+}
+
+#[test]
+fn window_splitting() {
+    let rolling = test_walk_forward(WindowMode::Rolling).windows().unwrap();
+    let bounds: Vec<(u64, u64, u64)> = rolling.iter()
+        .map(|w| (w.in_sample_start, w.out_of_sample_start, w.out_of_sample_end))
+        .collect();
+    assert_eq!(bounds, vec![(0, 1000, 1500), (500, 1500, 2000), (1000, 2000, 2200)]);
+    assert!(rolling.iter().all(|w| w.in_sample_end == w.out_of_sample_start));
+
+    let anchored = test_walk_forward(WindowMode::Anchored).windows().unwrap();
+    assert!(anchored.iter().all(|w| w.in_sample_start == 0));
+    assert_eq!(anchored[2].in_sample_end, 2000);
+
+    let mut too_short = test_walk_forward(WindowMode::Rolling);
+    too_short.end_time = 1000;
+    assert!(too_short.windows().is_err());
+}
+
+#[test]
+fn walk_forward_stitches_out_of_sample_results() {
+    let def = test_walk_forward(WindowMode::Rolling);
+    let mut wf = WalkForward::new(Uuid::new_v4(), def, WindowTestEvaluator {start: 0, end: 0});
+    let report = wf.run().unwrap();
+
+    assert_eq!(report.windows.len(), 3);
+    assert!(report.windows.iter().all(|w| w.error.is_none()));
+    // the first window is optimized entirely on data where 3 is best, so it is wrong out of sample
+    assert_eq!(report.windows[0].params.as_ref().unwrap()["a"], "3");
+    assert_eq!(report.windows[2].params.as_ref().unwrap()["a"], "6");
+
+    assert_eq!(report.out_of_sample.trade_count, 3);
+    assert_eq!(report.equity_curve.len(), 4);
+    assert_eq!(report.equity_curve.last().unwrap().1, report.out_of_sample.final_balance);
+    let efficiency = report.efficiency.unwrap();
+    assert!(efficiency > 0. && efficiency < 1.);
+}
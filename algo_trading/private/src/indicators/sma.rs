@@ -1,14 +1,9 @@
 use std::collections::{VecDeque, HashMap};
-use std::error::Error;
 
 #[allow(unused_imports)]
 use test;
-use postgres::rows::Row;
-use serde_json;
-
 use tickgrinder_util::trading::indicators::*;
 use tickgrinder_util::trading::tick::*;
-use tickgrinder_util::transport::postgres::*;
 // use tickgrinder_util::trading::trading_condition::*;
 
 /// Alteration of a simple moving average using ticks as input where the prices in a time frame
@@ -136,66 +131,57 @@ impl Sma {
     }
 }
 
-impl HistQuery for Sma {
-    /// Queries the database for ticks in a range and returns the average bid and ask in that range.
-    fn get(start_time: u64, end_time: u64, period: u64, args: HashMap<String, String>) -> Result<String, String> {
-        let connection_opt = get_client();
-        if connection_opt.is_err() {
-            return Err(String::from("Unable to connect to PostgreSQL!"))
-        }
-        let conn = connection_opt.unwrap();
-
-        let table_name = try!( args.get("table_name").ok_or(no_arg_error("table_name")) );
-
-        let query = format!(
-            "SELECT (tick_time, bid, ask) FROM {} WHERE tick_time > {} AND tick_time < {};",
-            table_name,
-            start_time,
-            end_time
-        );
-        let rows = try!(
-            conn.query(&query, &[]).map_err( |err| format!("{}", err) )
-        );
-
-        let mut sma = Sma::new(period);
-        let mut last_time = 0;
-        let mut res = Vec::new();
-
-        for row in rows.iter() {
-            let t: Tick = tick_from_row(&row);
-            let res_t = sma.push_tick(t);
-
-            if last_time == 0 || (t.timestamp - last_time) > period {
-                res.push(res_t);
-                last_time = t.timestamp;
-            }
-        }
+impl LiveQuery<Tick> for Sma {
+    /// Returns a tick representing the average bid and ask once a full period of ticks has been seen.
+    fn tick(&mut self, t: &Tick) -> Option<Tick> {
+        let avg = self.push_tick(*t);
+        if self.is_ready() { Some(avg) } else { None }
+    }
 
-        serde_json::to_string(&res).map_err(|err| String::from(err.description()) )
+    fn is_ready(&self) -> bool {
+        // the reference tick is only set once a tick has fallen out of the period
+        self.ref_tick.bid != 0
     }
 }
 
-/// Takes a row and returns a tick from the values of its columns.
-/// Panics if the row doesn't contain properly formatted `tick_time`, `bid`, and `ask` columns.
-fn tick_from_row(row: &Row) -> Tick {
-    let timestamp = row.get::<_, i64>("tick_time") as u64;
-    let bid = row.get::<_, i64>("bid") as usize;
-    let ask = row.get::<_, i64>("ask") as usize;
-
-    Tick {
-        timestamp: timestamp,
-        bid: bid,
-        ask: ask,
+impl FromArgs for Sma {
+    fn name() -> &'static str { "time_weighted_sma" }
+
+    fn from_args(args: &HashMap<String, String>) -> Result<Self, String> {
+        Ok(Sma::new(try!(parse_arg(args, "period"))))
     }
 }
 
-fn no_arg_error(name: &str) -> String {
-    format!("No argument \"{}\" provided in the arguments HashMap.", name)
+impl HistQuery for Sma {
+    /// Replays ticks in a range through the SMA and returns the average bid and ask in that range.  Unless
+    /// a `period` argument is supplied, the period of the SMA is the same as the period between values.  The SMA
+    /// is warmed up with one period of ticks before `start_time` unless a `warmup` argument is supplied.
+    fn get(start_time: u64, end_time: u64, period: u64, mut args: HashMap<String, String>) -> Result<String, String> {
+        if !args.contains_key("period") {
+            args.insert(String::from("period"), period.to_string());
+        }
+        if !args.contains_key("warmup") {
+            let warmup = args["period"].clone();
+            args.insert(String::from("warmup"), warmup);
+        }
+
+        HistReplay::<Sma, Tick>::get(start_time, end_time, period, args)
+    }
 }
 
 #[test]
 fn hist_sma_accuracy() {
-    // TODO: Write this test >.>
+    let ticks = vec![
+        Tick {bid: 101, ask: 107, timestamp: 1},
+        Tick {bid: 103, ask: 108, timestamp: 5},
+        Tick {bid: 105, ask: 109, timestamp: 13},
+        Tick {bid: 104, ask: 1088, timestamp: 18},
+    ];
+
+    let mut sma = Sma::new(15);
+    let res = replay_ticks(&mut sma, ticks.into_iter(), 0, 100, 0);
+    assert_eq!(res.len(), 1);
+    assert_eq!(res[0].timestamp, 18);
 }
 
 #[test]
@@ -250,6 +236,16 @@ fn tick_sma_accuracy() {
     assert_eq!(avg_t.mid(), man_avg);
 }
 
+#[test]
+fn live_sma_warmup() {
+    let mut sma = Sma::new(15);
+    assert_eq!(sma.tick(&Tick {bid: 101, ask: 107, timestamp: 1}), None);
+    assert_eq!(sma.tick(&Tick {bid: 103, ask: 108, timestamp: 5}), None);
+    assert!(!sma.is_ready());
+    assert!(sma.tick(&Tick {bid: 105, ask: 109, timestamp: 18}).is_some());
+    assert!(sma.is_ready());
+}
+
 // insert a tick into a DataField
 #[bench]
 fn tick_insertion(b: &mut test::Bencher) {
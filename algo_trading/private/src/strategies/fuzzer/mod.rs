@@ -15,8 +15,11 @@ use tickgrinder_util::trading::objects::{BrokerAction, BrokerMessage, Account, L
 use tickgrinder_util::trading::tick::{Tick, GenTick};
 use tickgrinder_util::trading::trading_condition::TradingAction;
 use tickgrinder_util::transport::textlog::get_logger_handle;
+use tickgrinder_util::trading::indicators::parse_arg_or;
 use tickgrinder_util::conf::CONF;
 
+use super::check_params;
+
 // link with the libboost_random wrapper
 #[link(name="rand_bindings")]
 extern {
@@ -31,14 +34,28 @@ fn random_bool(void_rng: *mut c_void) -> bool {
 pub struct FuzzerState {
     account_uuid: Option<Uuid>,
     account: Option<Account>,
+    /// The symbols that orders are placed for
+    pairs: Vec<String>,
 }
 
 impl FuzzerState {
-    pub fn new() -> FuzzerState {
+    pub fn new(pairs: Vec<String>) -> FuzzerState {
         FuzzerState {
             account_uuid: None,
             account: None,
+            pairs: pairs,
+        }
+    }
+
+    /// Picks the symbol to place an order for.  The PRNG is only used if there's more than one to choose from so
+    /// that the actions taken with a single pair don't depend on how many pairs could have been configured.
+    fn pick_pair(&self, rng: *mut c_void) -> String {
+        if self.pairs.len() == 1 {
+            return self.pairs[0].clone();
         }
+
+        let ix = unsafe { rand_int_range(rng, 0, self.pairs.len() as i32 - 1) } as usize;
+        self.pairs[ix].clone()
     }
 }
 
@@ -54,13 +71,33 @@ pub struct Fuzzer {
     pub state: FuzzerState,
 }
 
+/// The parameters that the fuzzer accepts:
+///
+///  - `pairs`: Comma-separated list of the symbols that orders are placed for.  Defaults to "TEST".
+///  - `deterministic`: Whether the PRNG is seeded with `seed` so that runs can be repeated exactly.  Defaults to
+///    the `fuzzer_deterministic_rng` setting.
+///  - `seed`: The seed used for deterministic fuzzing.  Defaults to the `fuzzer_seed` setting.
+pub const PARAMS: &'static [&'static str] = &["pairs", "deterministic", "seed"];
+
 impl Fuzzer {
-    pub fn new(_: HashMap<String, String>) -> Fuzzer {
+    /// Creates a fuzzer out of its parameters, returning an error if any of them are unknown or invalid.
+    pub fn new(params: HashMap<String, String>) -> Result<Fuzzer, String> {
+        try!(check_params("fuzzer", &params, PARAMS));
+        let pairs: Vec<String> = params.get("pairs").map(|s| s.as_str()).unwrap_or("TEST")
+            .split(',')
+            .map(|pair| String::from(pair.trim()))
+            .collect();
+        if pairs.iter().any(|pair| pair.is_empty()) {
+            return Err(String::from("`pairs` must be a comma-separated list of symbols."));
+        }
+        let deterministic: bool = try!(parse_arg_or(&params, "deterministic", CONF.fuzzer_deterministic_rng));
+        let seed_string = params.get("seed").map(|s| s.as_str()).unwrap_or(CONF.fuzzer_seed);
+
         // convert the seed string into an integer we can use to seen the PNRG if deterministic fuzzing is enabled
-        let seed: u32 = if CONF.fuzzer_deterministic_rng {
+        let seed: u32 = if deterministic {
             let mut sum = 0;
             // convert the seed string into an integer for seeding the fuzzer
-            for c in CONF.fuzzer_seed.chars() {
+            for c in seed_string.chars() {
                 sum += c as u32;
             }
             sum
@@ -69,11 +106,11 @@ impl Fuzzer {
             rng.gen()
         };
 
-        Fuzzer {
+        Ok(Fuzzer {
             gen: unsafe { init_rng(seed)},
             logger: EventLogger::new(),
-            state: FuzzerState::new(),
-        }
+            state: FuzzerState::new(pairs),
+        })
     }
 
     pub fn get_logger(&self) -> EventLogger {
@@ -141,7 +178,7 @@ pub fn get_action(state: &mut FuzzerState, t: &Tick, rng: *mut c_void) -> Option
         1 => { // random market open order
             let price = unsafe { rand_int_range(rng, 25, 75) } as usize;
             let order = TradingAction::MarketOrder{
-                symbol: String::from("TEST"),
+                symbol: state.pick_pair(rng),
                 long: random_bool(rng),
                 size: unsafe { rand_int_range(rng, 0, 5) as usize },
                 stop: if random_bool(rng) { Some(price + unsafe { rand_int_range(rng, 0, 5) as usize }) } else { None },
@@ -164,7 +201,7 @@ pub fn get_action(state: &mut FuzzerState, t: &Tick, rng: *mut c_void) -> Option
         3 => { // random limit order
             let price = unsafe { rand_int_range(rng, 25, 75) } as usize;
             let order = TradingAction::LimitOrder{
-                symbol: String::from("TEST"),
+                symbol: state.pick_pair(rng),
                 long: random_bool(rng),
                 size: unsafe { rand_int_range(rng, 0, 5) as usize },
                 stop: if random_bool(rng) { Some(price + unsafe { rand_int_range(rng, 0, 5) as usize }) } else { None },
@@ -396,3 +433,22 @@ fn deterministic_rng() {
         assert_eq!(rand1, rand2);
     }
 }
+
+#[test]
+fn fuzzer_params() {
+    let mut params = HashMap::new();
+    params.insert(String::from("pairs"), String::from("EURUSD, GBPUSD"));
+    params.insert(String::from("seed"), String::from("abc"));
+    params.insert(String::from("deterministic"), String::from("true"));
+    let fuzzer = Fuzzer::new(params.clone()).unwrap();
+    assert_eq!(fuzzer.state.pairs, vec![String::from("EURUSD"), String::from("GBPUSD")]);
+
+    params.insert(String::from("deterministic"), String::from("yes"));
+    assert!(Fuzzer::new(params.clone()).is_err());
+    params.remove("deterministic");
+    params.insert(String::from("pairs"), String::from("EURUSD,,"));
+    assert!(Fuzzer::new(params.clone()).is_err());
+    params.remove("pairs");
+    params.insert(String::from("pair"), String::from("EURUSD"));
+    assert!(Fuzzer::new(params).is_err());
+}
@@ -2,7 +2,8 @@
 
 use std::collections::HashMap;
 
-use tickgrinder_util::strategies::Strategy;
+use tickgrinder_util::strategies::{Strategy, ManagedStrategy};
+use tickgrinder_util::trading::broker::Broker;
 
 pub mod sma_cross;
 pub mod fuzzer;
@@ -14,3 +15,27 @@ pub use self::sma_cross::SmaCross as ActiveStrategy;
 pub fn get_broker_settings() -> HashMap<String, String> {
     HashMap::new() // TODO
 }
+
+/// Creates the strategy with the given name, configuring it with the supplied parameters.  This is used to run
+/// strategies by name in backtests and optimizations.  Returns an error if any of the parameters are unknown to the
+/// strategy or invalid.
+pub fn get_strategy<B: Broker>(name: &str, params: HashMap<String, String>) -> Result<Box<ManagedStrategy<B, ()>>, String> {
+    match name {
+        "sma_cross" => Ok(Box::new(try!(sma_cross::SmaCross::new(params)))),
+        "fuzzer" => Ok(Box::new(try!(fuzzer::Fuzzer::new(params)))),
+        _ => Err(format!("No strategy exists with the name \"{}\".", name)),
+    }
+}
+
+/// Returns an error if `params` contains any parameters that aren't in the list of parameters the strategy accepts.
+pub fn check_params(strategy: &str, params: &HashMap<String, String>, known: &[&str]) -> Result<(), String> {
+    let mut unknown: Vec<&str> = params.keys().map(|k| k.as_str()).filter(|k| !known.contains(k)).collect();
+    if unknown.is_empty() {
+        return Ok(());
+    }
+
+    unknown.sort();
+    Err(format!(
+        "Unknown parameters for strategy \"{}\": {}; it accepts {}.", strategy, unknown.join(", "), known.join(", ")
+    ))
+}
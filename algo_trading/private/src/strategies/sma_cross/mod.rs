@@ -1,5 +1,5 @@
 //! A basic "hello world" strategy to show the platform's functionality.  The strategy places buy orders when
-//! the price crosses over the SMA and places sell orders when it crosses back.
+//! the price crosses over the SMA and closes them when it crosses back.
 //!
 //! See /util/src/strategies.rs for more detailed documentation on how to implement the Strategy trait.
 
@@ -8,31 +8,138 @@ use std::time::Duration;
 use std::collections::HashMap;
 use std::fmt::Debug;
 
-use futures::{Future, Complete};
+use futures::Future;
+use uuid::Uuid;
 
-use tickgrinder_util::strategies::{StrategyManager, ManagedStrategy, Helper, StrategyAction, Tickstream, Merged};
+use tickgrinder_util::strategies::{ManagedStrategy, Helper, StrategyAction, Tickstream, Merged};
 use tickgrinder_util::transport::command_server::CommandServer;
-use tickgrinder_util::transport::query_server::QueryServer;
-use tickgrinder_util::trading::broker::Broker;
-use tickgrinder_util::trading::objects::BrokerAction;
+use tickgrinder_util::trading::broker::{Broker, BrokerResult};
+use tickgrinder_util::trading::objects::{BrokerAction, BrokerMessage};
 use tickgrinder_util::trading::tick::{Tick, GenTick};
+use tickgrinder_util::trading::trading_condition::TradingAction;
+use tickgrinder_util::trading::indicators::{Indicator, Sma, parse_arg, parse_arg_or};
 
-use super::get_broker_settings;
+use super::check_params;
 
-#[derive(Clone)]
-pub struct SmaCross {}
+/// The parameters that the strategy accepts:
+///
+///  - `symbol`: The symbol that orders are placed for.  Its ticks must be the first tickstream sent to the strategy.
+///  - `period`: The number of ticks that the SMA is calculated over.  Defaults to 20.
+///  - `size`: The number of units bought in each trade.  Defaults to 1.
+pub const PARAMS: &'static [&'static str] = &["symbol", "period", "size"];
+
+pub struct SmaCross {
+    pub symbol: String,
+    pub size: usize,
+    sma: Sma,
+    /// Whether the price was above the SMA as of the last tick or `None` if the SMA isn't ready yet
+    above: Option<bool>,
+    account_uuid: Option<Uuid>,
+    /// The currently open position
+    position: Option<Uuid>,
+    /// Set while an order has been sent but the position hasn't been opened yet
+    pending: bool,
+}
+
+impl SmaCross {
+    /// Creates the strategy out of its parameters, returning an error if any of them are missing or invalid.
+    pub fn new(params: HashMap<String, String>) -> Result<SmaCross, String> {
+        try!(check_params("sma_cross", &params, PARAMS));
+        let symbol: String = try!(parse_arg(&params, "symbol"));
+        let period: usize = try!(parse_arg_or(&params, "period", 20));
+        let size: usize = try!(parse_arg_or(&params, "size", 1));
+        if period == 0 {
+            return Err(String::from("The `period` of the SMA must be greater than 0."));
+        }
+        if size == 0 {
+            return Err(String::from("The `size` of orders must be greater than 0."));
+        }
+
+        Ok(SmaCross {
+            symbol: symbol,
+            size: size,
+            sma: Sma::new(period),
+            above: None,
+            account_uuid: None,
+            position: None,
+            pending: false,
+        })
+    }
+
+    /// Updates the SMA with the tick's price and returns the action to take if the price crossed it.
+    fn price_tick(&mut self, t: &Tick) -> Option<TradingAction> {
+        let price = t.mid() as f64;
+        let avg = match self.sma.update(price) {
+            Some(avg) => avg,
+            None => return None,
+        };
+        let was_above = self.above;
+        let above = price > avg;
+        self.above = Some(above);
+
+        match (was_above, above, self.position) {
+            (Some(false), true, None) if !self.pending => {
+                self.pending = true;
+                Some(TradingAction::MarketOrder{
+                    symbol: self.symbol.clone(), long: true, size: self.size, stop: None, take_profit: None, max_range: None,
+                })
+            },
+            (Some(true), false, Some(uuid)) => {
+                self.position = None;
+                Some(TradingAction::MarketClose{uuid: uuid, size: self.size})
+            },
+            _ => None,
+        }
+    }
+
+    /// Keeps track of the strategy's position using the messages pushed from the broker.
+    fn pushstream_tick(&mut self, msg: &BrokerResult) {
+        match *msg {
+            Ok(BrokerMessage::PositionOpened{position_id, ..}) if self.pending => {
+                self.pending = false;
+                self.position = Some(position_id);
+            },
+            Ok(BrokerMessage::PositionClosed{position_id, ..}) => {
+                if self.position == Some(position_id) {
+                    self.position = None;
+                }
+            },
+            Err(_) => self.pending = false,
+            _ => (),
+        }
+    }
+}
 
 impl<B: Broker> ManagedStrategy<B, ()> for SmaCross {
     /// Called when we are to start actively trading this strategy and initialize trading activity.
-    fn init(&mut self, helper: &mut Helper<B>, subscriptions: &[Tickstream]) {
+    fn init(&mut self, helper: &mut Helper<B>, _: &[Tickstream]) {
         helper.cs.notice(Some("Startup"), "SMA Cross strategy is being initialized...");
-            let accounts = unwrap_log_panic(helper.broker.execute(BrokerAction::ListAccounts).wait().unwrap(), &mut helper.cs);
-            let accounts_dbg = format!("Accounts on the broker: {:?}", accounts);
-            println!("{}", accounts_dbg);
+        let accounts = unwrap_log_panic(helper.broker.execute(BrokerAction::ListAccounts).wait().unwrap(), &mut helper.cs);
+        match accounts {
+            BrokerMessage::AccountListing{accounts} => self.account_uuid = accounts.first().map(|account| account.uuid),
+            msg => helper.cs.error(Some("Startup"), &format!("Unexpected response to `ListAccounts`: {:?}", msg)),
+        }
+        if self.account_uuid.is_none() {
+            helper.cs.error(Some("Startup"), "The broker has no accounts to trade on; no orders will be placed.");
+        }
     }
 
-    fn tick(&mut self, helper: &mut Helper<B>, t: &GenTick<Merged<()>>) -> Option<StrategyAction> {
-        unimplemented!();
+    fn tick(&mut self, _: &mut Helper<B>, t: &GenTick<Merged<()>>) -> Option<StrategyAction> {
+        let action = match t.data {
+            Merged::BrokerTick(0, ref tick) => self.price_tick(tick),
+            Merged::BrokerPushstream(ref msg) => {
+                self.pushstream_tick(msg);
+                None
+            },
+            _ => None,
+        };
+
+        match (action, self.account_uuid) {
+            (Some(action), Some(account_uuid)) => {
+                Some(StrategyAction::BrokerAction(BrokerAction::TradingAction{account_uuid: account_uuid, action: action}))
+            },
+            _ => None,
+        }
     }
 
     /// Indicates that the platform is shutting down and that we need to do anything necessary (closing positions)
@@ -53,3 +160,58 @@ fn unwrap_log_panic<T, E>(res: Result<T, E>, cs: &mut CommandServer) -> T where
         }
     }
 }
+
+#[cfg(test)]
+fn test_params(period: &str) -> HashMap<String, String> {
+    let mut params = HashMap::new();
+    params.insert(String::from("symbol"), String::from("TEST"));
+    params.insert(String::from("period"), String::from(period));
+    params
+}
+
+#[test]
+fn sma_cross_params() {
+    let strategy = SmaCross::new(test_params("5")).unwrap();
+    assert_eq!(strategy.symbol, "TEST");
+    assert_eq!(strategy.size, 1);
+
+    assert!(SmaCross::new(test_params("0")).is_err());
+    assert!(SmaCross::new(test_params("five")).is_err());
+    assert!(SmaCross::new(HashMap::new()).is_err());
+    let mut unknown = test_params("5");
+    unknown.insert(String::from("perod"), String::from("5"));
+    assert!(SmaCross::new(unknown).is_err());
+}
+
+#[test]
+fn sma_cross_signals() {
+    use tickgrinder_util::trading::objects::Position;
+
+    let mut strategy = SmaCross::new(test_params("3")).unwrap();
+    let tick = |price: usize, timestamp: u64| Tick {bid: price, ask: price, timestamp: timestamp};
+
+    // falling prices keep the price below the SMA
+    for (i, &price) in [10, 9, 8, 7].iter().enumerate() {
+        assert!(strategy.price_tick(&tick(price, i as u64)).is_none());
+    }
+    // crossing above it opens a position
+    match strategy.price_tick(&tick(12, 4)) {
+        Some(TradingAction::MarketOrder{ref symbol, long: true, size: 1, ..}) => assert_eq!(symbol, "TEST"),
+        action => panic!("Expected a market order; got {:?}", action),
+    }
+    // no more orders are sent until the position has been opened
+    assert!(strategy.price_tick(&tick(13, 5)).is_none());
+    let position = Position {
+        creation_time: 4, symbol_id: 0, size: 1, price: Some(12), long: true, stop: None, take_profit: None,
+        execution_time: Some(4), execution_price: Some(12), exit_price: None, exit_time: None,
+    };
+    let position_id = Uuid::new_v4();
+    strategy.pushstream_tick(&Ok(BrokerMessage::PositionOpened{position_id: position_id, position: position, timestamp: 5}));
+
+    // crossing back below closes it
+    match strategy.price_tick(&tick(5, 6)) {
+        Some(TradingAction::MarketClose{uuid, size: 1}) => assert_eq!(uuid, position_id),
+        action => panic!("Expected the position to be closed; got {:?}", action),
+    }
+    assert!(strategy.price_tick(&tick(4, 7)).is_none());
+}
@@ -73,7 +73,7 @@ fn main() {
     hm.insert(String::from("pairs"), String::from("TEST"));
 
     // create a Fuzzer instance and grab some internals to use here
-    let fuzzer = Fuzzer::new(hm.clone());
+    let fuzzer = Fuzzer::new(hm.clone()).expect("Invalid fuzzer settings");
 
     // create a strategy manager to manage the fuzzer and initialize it, then initialize the simbroker simulation loop
     let mut manager: StrategyManager<SimBrokerClient, ()> = StrategyManager::new(Box::new(fuzzer), client, Vec::new());
@@ -6,20 +6,26 @@
 extern crate redis;
 extern crate futures;
 extern crate serde_json;
+#[macro_use]
+extern crate serde_derive;
 extern crate postgres;
 extern crate test;
 extern crate uuid;
 extern crate tickgrinder_util;
+extern crate fxcm;
 
 mod transport;
 mod processor;
 
 use std::env;
+use std::thread;
+use std::time::Duration;
+use std::sync::mpsc::channel;
 
 use futures::stream::Stream;
 use uuid::Uuid;
 
-use processor::Processor;
+use processor::{Processor, ProcessorEvent};
 use tickgrinder_util::transport::postgres::{get_client, reset_db};
 use tickgrinder_util::transport::redis::sub_multiple;
 use tickgrinder_util::transport::commands::{Command, send_command};
@@ -36,33 +42,61 @@ impl TickProcessor {
         }
     }
 
-    /// Subscribes to Command channels
+    /// Subscribes to Command channels and processes ticks received from the broker
     pub fn listen(&self, symbol: String) {
         let control_channel = CONF.redis_control_channel;
         let uuid_string = self.uuid.hyphenated().to_string();
 
         let mut processor = Processor::new(symbol, &self.uuid);
 
-        let rx = sub_multiple(
+        // commands and ticks are both funneled into a single channel so they can be handled in order
+        let (tx, rx) = channel();
+        processor.event_tx = Some(tx.clone());
+
+        // periodically store buffered ticks so that they aren't held back while ticks arrive slowly
+        let flush_tx = tx.clone();
+        thread::spawn(move || {
+            loop {
+                thread::sleep(Duration::from_millis(CONF.tick_parser_flush_interval as u64));
+                if flush_tx.send(ProcessorEvent::FlushTicks).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let redis_rx = sub_multiple(
             CONF.redis_host, &[control_channel, uuid_string.as_str()]
         );
+        thread::spawn(move || {
+            for res in redis_rx.wait() {
+                let (channel, message) = res.unwrap();
+                if tx.send(ProcessorEvent::Message{channel: channel, message: message}).is_err() {
+                    break;
+                }
+            }
+        });
 
         let _ = send_command(&Command::Ready{
             instance_type: "Tick Processor".to_string(),
             uuid: self.uuid,
         }.wrap(), &processor.redis_client, CONF.redis_control_channel);
 
-        for res in rx.wait() {
-            let (channel, message) = res.unwrap();
-            if channel == uuid_string.as_str()
-                   || channel == control_channel {
-                processor.execute_command(CONF.redis_responses_channel, message)
-            } else {
-                println!(
-                    "Unexpected channel/message combination received: {},{}",
-                    channel,
-                    message
-                );
+        for event in rx.iter() {
+            match event {
+                ProcessorEvent::Message{channel, message} => {
+                    if channel == uuid_string.as_str()
+                           || channel == control_channel {
+                        processor.execute_command(CONF.redis_responses_channel, message)
+                    } else {
+                        println!(
+                            "Unexpected channel/message combination received: {},{}",
+                            channel,
+                            message
+                        );
+                    }
+                },
+                ProcessorEvent::Tick(t) => processor.process(t),
+                ProcessorEvent::FlushTicks => processor.flush_ticks(),
             }
         }
     }
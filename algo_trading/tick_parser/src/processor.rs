@@ -3,26 +3,87 @@
 // deterine a trading signal.  The main goal is to produce a result as quickly as
 // possible, so non-essential operations should be deferred asynchronously.
 
-use std::{thread, process};
+use std::{thread, process, mem};
 use std::time::Duration;
 use std::env;
+use std::collections::HashMap;
+use std::sync::mpsc::{channel, Sender};
 
 use redis;
 use uuid::Uuid;
+use serde_json;
+use futures::{Future, Stream};
+use fxcm::FXCMNative;
 use tickgrinder_util::transport::commands::*;
 use tickgrinder_util::trading::datafield::DataField;
-use tickgrinder_util::trading::tick::Tick;
-use tickgrinder_util::transport::postgres::{get_client, init_tick_table};
+use tickgrinder_util::trading::tick::{Tick, SymbolTick};
+use tickgrinder_util::trading::bar::{Bar, BarBuilder, BarDefinition};
+use tickgrinder_util::trading::broker::{Broker, BrokerAction, PendingResult};
+use tickgrinder_util::trading::trading_condition::{TradingCondition, TradingAction};
+use tickgrinder_util::trading::condition_lang::parse_condition;
+use tickgrinder_util::transport::tickstream::{Pipeline, PipelineDefinition};
+use tickgrinder_util::transport::postgres::{get_client, init_tick_table, insert_ticks_query};
 use tickgrinder_util::transport::query_server::QueryServer;
-use tickgrinder_util::transport::redis::get_client as get_redis_client;
+use tickgrinder_util::transport::command_server::CommandServer;
+use tickgrinder_util::transport::redis::{get_client as get_redis_client, publish};
+use tickgrinder_util::transport::textlog::debug;
 use tickgrinder_util::conf::CONF;
 
+/// Events handled by the main loop of the Tick Processor.
+pub enum ProcessorEvent {
+    /// A message received on one of the Tick Processor's Redis channels
+    Message{channel: String, message: String},
+    /// A live tick received from the broker
+    Tick(Tick),
+    /// Sent periodically so that buffered ticks are stored even if the buffer doesn't fill up
+    FlushTicks,
+}
+
+/// Sent as the `broker_def` of a `SubTicks` command.  Determines which broker ticks are received from and
+/// trading actions are sent to, as well as which derived data is published on Redis.
+#[derive(Deserialize)]
+pub struct BrokerDefinition {
+    /// The broker to connect to; currently only "FXCM" is supported.
+    pub broker: String,
+    /// The account on which `TradingAction`s are executed.
+    pub account_uuid: Uuid,
+    /// Settings passed to the broker's `init()` function.
+    pub settings: HashMap<String, String>,
+    /// The bars published on Redis.  No bars are published if not supplied.
+    pub bars: Option<BarDefinition>,
+    /// An indicator pipeline whose values are published on Redis for every tick.
+    pub pipeline: Option<PipelineDefinition>,
+}
+
+/// A set of indicator values published on Redis.
+#[derive(Serialize)]
+struct IndicatorUpdate {
+    timestamp: u64,
+    values: Vec<(String, f64)>,
+}
+
 pub struct Processor {
     pub uuid: Uuid,
     pub symbol: String,
     pub ticks: DataField<Tick>,
     pub qs: QueryServer,
-    pub redis_client: redis::Client
+    pub redis_client: redis::Client,
+    /// the conditions evaluated for every tick along with their ids and the strings they were parsed from
+    pub conditions: Vec<(Uuid, String, Box<TradingCondition>)>,
+    /// ticks waiting to be written to the database
+    pub tick_buffer: Vec<Tick>,
+    /// whether buffered ticks are stored; false once the processor is shutting down
+    pub storing_ticks: bool,
+    /// sends the broker's pending responses to trading actions to the thread that logs them
+    pub response_tx: Sender<PendingResult>,
+    pub cs: CommandServer,
+    /// the broker that `TradingAction`s are sent to along with the uuid of the account to trade on
+    pub broker: Option<(Box<Broker>, Uuid)>,
+    /// builds the bars published on Redis
+    pub bar_builder: Option<BarBuilder>,
+    pub pipeline: Option<Pipeline>,
+    /// used to send the ticks from the broker's tickstream to the main loop
+    pub event_tx: Option<Sender<ProcessorEvent>>,
 }
 
 impl Processor {
@@ -32,27 +93,207 @@ impl Processor {
 
         println!("Successfully connected to Postgres");
         let _ = init_tick_table(symbol.as_str(), &pg_client, CONF.postgres_user);
+        let cs = CommandServer::new(*uuid, "Tick Processor");
 
         Processor {
             uuid: *uuid,
             symbol: symbol,
             ticks: DataField::new(),
-            qs: QueryServer::new(10),
-            redis_client: get_redis_client(CONF.redis_host)
+            qs: QueryServer::with_logging(10, cs.clone()),
+            redis_client: get_redis_client(CONF.redis_host),
+            conditions: Vec::new(),
+            tick_buffer: Vec::with_capacity(CONF.tick_parser_batch_size),
+            storing_ticks: true,
+            response_tx: spawn_response_logger(cs.clone()),
+            cs: cs,
+            broker: None,
+            bar_builder: None,
+            pipeline: None,
+            event_tx: None,
         }
     }
 
     // Called for each new tick received by the tick processor
     pub fn process(&mut self, t: Tick) {
+        // trading actions are time-sensitive so they're handled first
+        for action in self.eval_conditions(&t) {
+            self.execute_action(action);
+        }
 
+        self.ticks.push(t);
+        // ticks are no longer stored once the processor is shutting down
+        if self.storing_ticks {
+            self.tick_buffer.push(t);
+            if self.tick_buffer.len() >= CONF.tick_parser_batch_size {
+                self.flush_ticks();
+            }
+        }
+
+        for bar in self.update_bar(&t) {
+            let bar_json = serde_json::to_string(&bar).expect("Unable to serialize bar");
+            publish(&self.redis_client, &format!("bars_{}", self.symbol), &bar_json);
+        }
+
+        let values = match self.pipeline {
+            Some(ref mut pipeline) => pipeline.update(&SymbolTick::from_tick(t, self.symbol.clone())),
+            None => None,
+        };
+        if let Some(values) = values {
+            let update = IndicatorUpdate {timestamp: t.timestamp, values: values};
+            let update_json = serde_json::to_string(&update).expect("Unable to serialize indicator values");
+            publish(&self.redis_client, &format!("indicators_{}", self.symbol), &update_json);
+        }
+    }
+
+    /// Sends all buffered ticks to the `QueryServer` to be stored with a single query.
+    pub fn flush_ticks(&mut self) {
+        if self.tick_buffer.is_empty() {
+            return;
+        }
+
+        let ticks = mem::replace(&mut self.tick_buffer, Vec::with_capacity(CONF.tick_parser_batch_size));
+        self.qs.execute(insert_ticks_query(&self.symbol, &ticks));
+    }
+
+    /// Flushes all buffered ticks and blocks until the `QueryServer` has stored them.  No more ticks are stored
+    /// after this is called.
+    pub fn stop_storing_ticks(&mut self) {
+        self.flush_ticks();
+        self.storing_ticks = false;
+        self.qs.wait_idle();
+    }
+
+    /// Adds the tick to the current bar, returning the bars that the tick completed.
+    pub fn update_bar(&mut self, t: &Tick) -> Vec<Bar> {
+        match self.bar_builder {
+            Some(ref mut builder) => builder.push(t, 1).into_iter().map(|bar| bar.data).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Sends a `TradingAction` to the configured broker, logging the result once it's received.
+    pub fn execute_action(&mut self, action: TradingAction) {
+        let pending = match self.broker {
+            Some((ref mut broker, account_uuid)) => {
+                broker.execute(BrokerAction::TradingAction{account_uuid: account_uuid, action: action})
+            },
+            None => {
+                let msg = format!("Trading condition triggered but no broker is configured: {:?}", action);
+                self.cs.warning(Some("Trading Action"), &msg);
+                return;
+            }
+        };
+
+        if self.response_tx.send(pending).is_err() {
+            self.cs.error(Some("Trading Action"), "Unable to log broker response; the response logger has exited.");
+        }
+    }
+
+    /// Connects to the broker in the definition, subscribes to its ticks for the processor's symbol, and sets up
+    /// the derived data published on Redis.  Returns an error if the processor is already subscribed to ticks.
+    pub fn sub_ticks(&mut self, broker_def: &str) -> Result<(), String> {
+        if self.broker.is_some() {
+            return Err(String::from("The processor is already subscribed to ticks."));
+        }
+        let def: BrokerDefinition = try!(serde_json::from_str(broker_def).map_err(debug));
+        let pipeline = match def.pipeline {
+            Some(ref pipeline_def) => Some(try!(Pipeline::from_definition(pipeline_def))),
+            None => None,
+        };
+        let bar_builder = match def.bars {
+            Some(bars) => Some(try!(BarBuilder::new(bars))),
+            None => None,
+        };
+        let tx = try!(self.event_tx.clone().ok_or(String::from("The processor has no event channel to send ticks through.")));
+
+        let mut broker: Box<Broker> = match def.broker.as_str() {
+            "FXCM" => {
+                let res = try!(FXCMNative::init(def.settings).wait().map_err(debug));
+                Box::new(try!(res.map_err(debug)))
+            },
+            _ => return Err(format!("Unsupported broker: {}", def.broker)),
+        };
+        let stream = try!(broker.sub_ticks(self.symbol.clone()).map_err(debug));
+
+        // forward the broker's ticks to the main loop
+        thread::spawn(move || {
+            for t in stream.wait() {
+                match t {
+                    Ok(t) => if tx.send(ProcessorEvent::Tick(t)).is_err() { break; },
+                    Err(_) => break,
+                }
+            }
+        });
+
+        self.broker = Some((broker, def.account_uuid));
+        self.bar_builder = bar_builder;
+        self.pipeline = pipeline;
+
+        Ok(())
+    }
+
+    /// Evaluates all trading conditions for the tick, returning the actions of those that were triggered.
+    pub fn eval_conditions(&mut self, t: &Tick) -> Vec<TradingAction> {
+        let mut actions = Vec::new();
+        for &mut (_, _, ref mut condition) in self.conditions.iter_mut() {
+            if let Some(action) = condition.eval(t) {
+                actions.push(action);
+            }
+        }
+
+        actions
+    }
+
+    /// Parses a condition string and adds it to the list of conditions, returning its id.
+    pub fn add_condition(&mut self, condition_string: String) -> Result<Uuid, String> {
+        let condition = try!(parse_condition(&condition_string));
+        let id = Uuid::new_v4();
+        self.conditions.push((id, condition_string, condition));
+
+        Ok(id)
+    }
+
+    /// Removes the condition identified by `condition_string`, which is either the id returned when the condition
+    /// was added or the string it was parsed from, returning an error if no condition matches.
+    pub fn remove_condition(&mut self, condition_string: &str) -> Result<(), String> {
+        let id = Uuid::parse_str(condition_string).ok();
+        let pos = self.conditions.iter().position(|&(cond_id, ref cond_string, _)| {
+            Some(cond_id) == id || cond_string == condition_string
+        });
+        match pos {
+            Some(i) => {
+                self.conditions.remove(i);
+                Ok(())
+            },
+            None => Err(format!("No condition matching {}", condition_string)),
+        }
+    }
+
+    /// Returns a JSON-encoded list of the ids and strings of all active conditions.
+    pub fn list_conditions(&self) -> String {
+        let list: Vec<HashMap<&str, String>> = self.conditions.iter().map(|&(id, ref condition_string, _)| {
+            let mut hm = HashMap::new();
+            hm.insert("id", id.hyphenated().to_string());
+            hm.insert("condition", condition_string.clone());
+            hm
+        }).collect();
+
+        serde_json::to_string(&list).expect("Unable to serialize condition list")
     }
 
     /// Handle an incoming Command, take action, and return a Response
     pub fn execute_command(&mut self, res_channel: &str, raw_cmd: String) {
         let wrapped_cmd: WrappedCommand = parse_wrapped_command(raw_cmd);
+        let mut exit = false;
         let res = match wrapped_cmd.cmd {
-            Command::Shutdown => unimplemented!(),
+            Command::Shutdown => {
+                self.stop_storing_ticks();
+                exit = true;
+                Response::Info{info: "Shutting down...".to_string()}
+            },
             Command::Kill => {
+                // make sure that no received ticks are lost
+                self.stop_storing_ticks();
                 // initiate suicide from another thread after a 3-second timeout
                 thread::spawn(|| {
                     thread::sleep(Duration::from_secs(3));
@@ -68,14 +309,25 @@ impl Processor {
                 Response::Info{info: "Tick Processor".to_string()}
             },
             Command::AddCondition{condition_string} => {
-                unimplemented!();
+                match self.add_condition(condition_string) {
+                    Ok(id) => Response::Info{info: id.hyphenated().to_string()},
+                    Err(err) => Response::Error{status: format!("Unable to parse condition: {}", err)},
+                }
             },
             Command::RemoveCondition{condition_string} => {
-                unimplemented!();
+                match self.remove_condition(&condition_string) {
+                    Ok(()) => Response::Ok,
+                    Err(err) => Response::Error{status: err},
+                }
             },
             Command::ListConditions => {
-                unimplemented!();
-                // Response::Info{info: }
+                Response::Info{info: self.list_conditions()}
+            },
+            Command::SubTicks{broker_def} => {
+                match self.sub_ticks(&broker_def) {
+                    Ok(()) => Response::Ok,
+                    Err(err) => Response::Error{status: format!("Unable to subscribe to ticks: {}", err)},
+                }
             },
             _ => {
                 Response::Error{status: "Command not recognized".to_string()}
@@ -84,5 +336,27 @@ impl Processor {
 
         let wr = res.wrap(wrapped_cmd.uuid);
         let _ = send_response(&wr, &self.redis_client, res_channel);
+        if exit {
+            process::exit(0);
+        }
     }
 }
+
+/// Spawns a thread that waits for the broker's responses to trading actions and logs them in the order that the
+/// actions were sent.
+fn spawn_response_logger(mut cs: CommandServer) -> Sender<PendingResult> {
+    let (tx, rx) = channel::<PendingResult>();
+    thread::spawn(move || {
+        for pending in rx.iter() {
+            match pending.wait() {
+                Ok(Ok(msg)) => cs.notice(Some("Trading Action"), &format!("Broker response to trading action: {:?}", msg)),
+                Ok(Err(err)) => cs.error(
+                    Some("Trading Action"), &format!("Broker returned error in response to trading action: {:?}", err)
+                ),
+                Err(_) => cs.error(Some("Trading Action"), "Broker dropped trading action without responding"),
+            }
+        }
+    });
+
+    tx
+}
@@ -13,6 +13,7 @@ use tickgrinder_util::transport::postgres;
 use tickgrinder_util::transport::query_server::QueryServer;
 use tickgrinder_util::transport::command_server::*;
 use tickgrinder_util::trading::tick::{Tick, SymbolTick};
+use tickgrinder_util::trading::bar::{BarBuilder, BarDefinition, BarType};
 use tickgrinder_util::conf::CONF;
 use processor::Processor;
 
@@ -56,8 +57,36 @@ fn tick_ingestion() {
     for json_tick in rx.wait().take(5) {
         processor.process(Tick::from_json_string(json_tick.expect("unable to unwrap json_tick")));
     }
-    // assert_eq!(processor.ticks.len(), 5);
-    // TODO: Update to modern tick processing stuff
+    assert_eq!(processor.ticks.len(), 5);
+    assert_eq!(processor.tick_buffer.len(), 5);
+    processor.flush_ticks();
+    assert!(processor.tick_buffer.is_empty());
+
+    // stopping tick storage blocks until all flushed ticks are stored
+    processor.process(Tick {bid: 1, ask: 1, timestamp: 6});
+    processor.stop_storing_ticks();
+    let client = postgres::get_client().unwrap();
+    let rows = client.query("SELECT COUNT(*) FROM ticks_test8 WHERE tick_time BETWEEN 1 AND 6;", &[]).unwrap();
+    assert_eq!(rows.get(0).get::<_, i64>(0), 6);
+    // ticks received after that aren't buffered
+    processor.process(Tick {bid: 1, ask: 1, timestamp: 7});
+    assert!(processor.tick_buffer.is_empty());
+}
+
+#[test]
+fn bar_aggregation() {
+    let mut processor = Processor::new("test10".to_string(), &Uuid::new_v4());
+    assert!(processor.update_bar(&Tick {bid: 1, ask: 2, timestamp: 5}).is_empty());
+
+    let def = BarDefinition {bar_type: BarType::Time{period: 10}, session: None};
+    processor.bar_builder = Some(BarBuilder::new(def).unwrap());
+    assert!(processor.update_bar(&Tick {bid: 1, ask: 2, timestamp: 12}).is_empty());
+    assert!(processor.update_bar(&Tick {bid: 3, ask: 4, timestamp: 19}).is_empty());
+    let bars = processor.update_bar(&Tick {bid: 2, ask: 3, timestamp: 20});
+    assert_eq!(bars.len(), 1);
+    let bar = bars[0];
+    assert_eq!(bar.tick_count, 2);
+    assert_eq!((bar.bid.open, bar.bid.high, bar.bid.close), (1, 3, 3));
 }
 
 #[test]
@@ -86,3 +115,23 @@ fn command_server_broadcast() {
     assert_eq!(responses.len(), 2);
     thread::sleep(Duration::new(3,0));
 }
+
+#[test]
+fn condition_management() {
+    let mut processor = Processor::new("test9".to_string(), &Uuid::new_v4());
+    assert!(processor.add_condition(String::from("bid >> 5 => market_buy(TEST9, 1)")).is_err());
+    let id = processor.add_condition(String::from("bid > 5 => market_buy(TEST9, 1)")).unwrap();
+    assert!(processor.list_conditions().contains(&id.hyphenated().to_string()));
+
+    assert_eq!(processor.eval_conditions(&Tick {bid: 4, ask: 5, timestamp: 1}).len(), 0);
+    assert_eq!(processor.eval_conditions(&Tick {bid: 6, ask: 7, timestamp: 2}).len(), 1);
+
+    processor.remove_condition(&id.hyphenated().to_string()).unwrap();
+    assert!(processor.remove_condition(&id.hyphenated().to_string()).is_err());
+    assert_eq!(processor.list_conditions(), "[]");
+
+    // conditions can also be removed by the string they were added with
+    processor.add_condition(String::from("bid > 5 => market_buy(TEST9, 1)")).unwrap();
+    processor.remove_condition("bid > 5 => market_buy(TEST9, 1)").unwrap();
+    assert_eq!(processor.list_conditions(), "[]");
+}
@@ -11,6 +11,9 @@ const REDIS_CHANNEL = 2; // { host: String, channel: String },
 const REDIS_SET = 3; // { host: String, set_name: String },
 const CONSOLE = 4;
 const CSV = 5;
+const BINARY = 6; // { filename: String }
+const PARQUET = 7; // { filename: String, row_group_size: Option<String> }
+const SCHEMA_CSV = 8; // { filename: String, schema: String (JSON-encoded `CsvSchema`) }
 
 const POLONIEX_BOOK_MODIFY = 25;
 const POLONIEX_BOOK_REMOVE = 26;
@@ -80,6 +83,9 @@ module.exports = {
   REDIS_SET: REDIS_SET,
   CONSOLE: CONSOLE,
   CSV: CSV,
+  BINARY: BINARY,
+  PARQUET: PARQUET,
+  SCHEMA_CSV: SCHEMA_CSV,
 
   POLONIEX_BOOK_MODIFY: POLONIEX_BOOK_MODIFY,
   POLONIEX_BOOK_REMOVE: POLONIEX_BOOK_REMOVE,
@@ -19,6 +19,12 @@ extern crate rand;
 extern crate time;
 extern crate test;
 extern crate libc;
+extern crate libflate;
+extern crate memmap;
+extern crate xz2;
+extern crate zstd;
+extern crate chrono;
+extern crate chrono_tz;
 
 pub mod transport;
 pub mod strategies;
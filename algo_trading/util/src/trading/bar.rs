@@ -0,0 +1,494 @@
+//! Bars summarize all the ticks received over some interval into open, high, low, and close prices.
+//!
+//! The `BarBuilder` turns a stream of ticks into a stream of bars of any of the types in `BarType`.  Bars are
+//! never carried across session boundaries and no bars are created for intervals that contain no ticks.  Ticks are
+//! expected in order; a tick stamped before one that was already pushed is late and gets dropped, so it can't reopen
+//! or start a bar before the current one.
+
+use trading::tick::{Tick, GenTick};
+
+/// Milliseconds in a day
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// Open, high, low, and close prices over some interval.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ohlc {
+    pub open: usize,
+    pub high: usize,
+    pub low: usize,
+    pub close: usize,
+}
+
+impl Ohlc {
+    pub fn new(price: usize) -> Ohlc {
+        Ohlc {
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+        }
+    }
+
+    pub fn update(&mut self, price: usize) {
+        if price > self.high {
+            self.high = price;
+        }
+        if price < self.low {
+            self.low = price;
+        }
+        self.close = price;
+    }
+}
+
+/// A bar containing OHLC prices of the bid, ask, and mid price of all the ticks in it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bar {
+    pub bid: Ohlc,
+    pub ask: Ohlc,
+    pub mid: Ohlc,
+    pub tick_count: usize,
+    /// total volume of the ticks in the bar; only counted by the `BarBuilder`
+    pub volume: usize,
+    /// timestamp of the first tick in the bar
+    pub open_time: u64,
+    /// timestamp of the last tick in the bar
+    pub close_time: u64,
+}
+
+impl Bar {
+    /// Creates a new bar containing only the given tick.
+    pub fn new(t: &Tick) -> Bar {
+        Bar {
+            bid: Ohlc::new(t.bid),
+            ask: Ohlc::new(t.ask),
+            mid: Ohlc::new(t.mid()),
+            tick_count: 1,
+            volume: 0,
+            open_time: t.timestamp,
+            close_time: t.timestamp,
+        }
+    }
+
+    /// Adds a tick to the bar.
+    pub fn update(&mut self, t: &Tick) {
+        self.bid.update(t.bid);
+        self.ask.update(t.ask);
+        self.mid.update(t.mid());
+        self.tick_count += 1;
+        self.close_time = t.timestamp;
+    }
+}
+
+/// The condition that determines when a bar is complete.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum BarType {
+    /// Bars covering fixed intervals of `period` ms, aligned to multiples of `period`.
+    Time{period: u64},
+    /// Bars containing `count` ticks.
+    Tick{count: usize},
+    /// Bars containing at least `volume` volume.
+    Volume{volume: usize},
+    /// Bars whose mid price high-low range doesn't exceed `range`.  The tick that would push the range above
+    /// `range` starts a new bar.
+    Range{range: usize},
+    /// Renko-style bars that are completed once the mid price moves `brick_size` away from the bar's open.
+    Renko{brick_size: usize},
+}
+
+/// The trading hours during which bars are built, in ms since midnight UTC.  If `start` is greater than
+/// `end`, the session wraps around midnight.  Ticks outside of the session are ignored.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl Session {
+    /// Returns `true` if the timestamp falls within the session.
+    pub fn contains(&self, timestamp: u64) -> bool {
+        let time_of_day = timestamp % DAY_MS;
+        if self.start <= self.end {
+            time_of_day >= self.start && time_of_day < self.end
+        } else {
+            time_of_day >= self.start || time_of_day < self.end
+        }
+    }
+
+    /// Returns the timestamp at which the session containing `timestamp` opened.
+    pub fn open_time(&self, timestamp: u64) -> u64 {
+        let day_start = timestamp - (timestamp % DAY_MS);
+        let time_of_day = timestamp % DAY_MS;
+        if self.start > self.end && time_of_day < self.end {
+            // the session started the previous day
+            day_start.saturating_sub(DAY_MS) + self.start
+        } else {
+            day_start + self.start
+        }
+    }
+}
+
+/// Serializable description of the bars to build.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BarDefinition {
+    pub bar_type: BarType,
+    pub session: Option<Session>,
+}
+
+impl BarDefinition {
+    pub fn validate(&self) -> Result<(), String> {
+        let positive = match self.bar_type {
+            BarType::Time{period} => period > 0,
+            BarType::Tick{count} => count > 0,
+            BarType::Volume{volume} => volume > 0,
+            BarType::Range{..} => true,
+            BarType::Renko{brick_size} => brick_size > 0,
+        };
+        if !positive {
+            return Err(format!("The size of {:?} bars must be positive.", self.bar_type));
+        }
+        if let Some(session) = self.session {
+            if session.start >= DAY_MS || session.end >= DAY_MS || session.start == session.end {
+                return Err(String::from("Session start and end times must be different times of day."));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Builds bars out of ticks one tick at a time.
+pub struct BarBuilder {
+    pub def: BarDefinition,
+    cur: Option<Bar>,
+    /// timestamp the bar is labeled with; the interval start for time bars
+    cur_start: u64,
+    /// open time of the session the current bar belongs to
+    cur_session: u64,
+    /// the brick boundary that renko bars are measured from; always a multiple of the brick size
+    renko_level: Option<usize>,
+    /// ticks stamped before this are late; the latest tick timestamp or the time a bar was last closed on the clock
+    watermark: u64,
+}
+
+impl BarBuilder {
+    /// Creates a builder for the bars in the definition, returning an error if the definition is invalid.
+    pub fn new(def: BarDefinition) -> Result<BarBuilder, String> {
+        try!(def.validate());
+
+        Ok(BarBuilder {
+            def: def,
+            cur: None,
+            cur_start: 0,
+            cur_session: 0,
+            renko_level: None,
+            watermark: 0,
+        })
+    }
+
+    /// Starts a new bar with the tick, returning the bar it replaced.
+    fn start_bar(&mut self, t: &Tick, volume: usize, start: u64, session: u64) -> Option<GenTick<Bar>> {
+        let mut bar = Bar::new(t);
+        bar.volume = volume;
+        if let BarType::Renko{brick_size} = self.def.bar_type {
+            // bricks are aligned to multiples of the brick size, starting from the one below the first price
+            if self.renko_level.is_none() {
+                self.renko_level = Some(bar.mid.open - bar.mid.open % brick_size);
+            }
+        }
+        let finished = self.take();
+        self.cur = Some(bar);
+        self.cur_start = start;
+        self.cur_session = session;
+
+        finished
+    }
+
+    /// Removes and returns the bar currently being built, if there is one.
+    pub fn take(&mut self) -> Option<GenTick<Bar>> {
+        let start = self.cur_start;
+        self.cur.take().map(|bar| {
+            let timestamp = match self.def.bar_type {
+                BarType::Time{..} => start,
+                _ => bar.close_time,
+            };
+            GenTick {timestamp: timestamp, data: bar}
+        })
+    }
+
+    /// Closes the current bar if no tick stamped at or after `timestamp` could belong in it, returning it.  This
+    /// lets time and session bars be closed on the clock rather than by the next tick; ticks stamped before
+    /// `timestamp` that are pushed afterwards are dropped as late.
+    pub fn close_until(&mut self, timestamp: u64) -> Option<GenTick<Bar>> {
+        if self.cur.is_none() {
+            return None;
+        }
+
+        let session_over = match self.def.session {
+            Some(session) => !session.contains(timestamp) || session.open_time(timestamp) != self.cur_session,
+            None => false,
+        };
+        let closed = session_over || match self.def.bar_type {
+            BarType::Time{period} => timestamp >= self.cur_start + period,
+            _ => false,
+        };
+        if !closed {
+            return None;
+        }
+
+        if timestamp > self.watermark {
+            self.watermark = timestamp;
+        }
+        self.take()
+    }
+
+    /// Adds a tick with the given volume, returning the bars that it completed.  Time, range, and session bars are
+    /// completed by the first tick that doesn't belong in them; tick, volume, and renko bars are completed by the
+    /// last tick that belongs in them.  Two bars are returned if the first tick of a session closes the bar left
+    /// over from the previous session and completes a bar by itself.  Late ticks are dropped without completing
+    /// any bars.
+    pub fn push(&mut self, t: &Tick, volume: usize) -> Vec<GenTick<Bar>> {
+        let mut bars = Vec::new();
+        if t.timestamp < self.watermark {
+            return bars;
+        }
+        self.watermark = t.timestamp;
+
+        let session = match self.def.session {
+            Some(session) => {
+                if !session.contains(t.timestamp) {
+                    // close out the bar at the end of the session
+                    bars.extend(self.take());
+                    return bars;
+                }
+                session.open_time(t.timestamp)
+            },
+            None => 0,
+        };
+
+        let start = match self.def.bar_type {
+            BarType::Time{period} => t.timestamp - (t.timestamp % period),
+            _ => t.timestamp,
+        };
+
+        let continues = match self.cur {
+            None => false,
+            Some(ref bar) => self.cur_session == session && match self.def.bar_type {
+                BarType::Time{..} => self.cur_start == start,
+                BarType::Range{range} => {
+                    let mid = t.mid();
+                    let high = if mid > bar.mid.high { mid } else { bar.mid.high };
+                    let low = if mid < bar.mid.low { mid } else { bar.mid.low };
+                    high - low <= range
+                },
+                _ => true,
+            },
+        };
+
+        if continues {
+            let bar = self.cur.as_mut().unwrap();
+            bar.update(t);
+            bar.volume += volume;
+        } else {
+            bars.extend(self.start_bar(t, volume, start, session));
+        }
+
+        if self.is_complete() {
+            if let BarType::Renko{brick_size} = self.def.bar_type {
+                // the next brick is measured from the boundary that this one reached
+                let close = self.cur.as_ref().unwrap().mid.close;
+                self.renko_level = Some(if close >= self.renko_level.unwrap() {
+                    close - close % brick_size
+                } else {
+                    (close + brick_size - 1) / brick_size * brick_size
+                });
+            }
+            bars.extend(self.take());
+        }
+
+        bars
+    }
+
+    /// Returns `true` if the current bar is complete according to its tick, volume, or renko condition.
+    fn is_complete(&self) -> bool {
+        let bar = match self.cur {
+            Some(ref bar) => bar,
+            None => return false,
+        };
+
+        match self.def.bar_type {
+            BarType::Tick{count} => bar.tick_count >= count,
+            BarType::Volume{volume} => bar.volume >= volume,
+            BarType::Renko{brick_size} => {
+                let (level, close) = (self.renko_level.unwrap(), bar.mid.close);
+                close >= level + brick_size || close + brick_size <= level
+            },
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+fn bar_tick(price: usize, timestamp: u64) -> Tick {
+    Tick {bid: price, ask: price, timestamp: timestamp}
+}
+
+/// Returns the only bar completed by a tick.
+#[cfg(test)]
+fn single(mut bars: Vec<GenTick<Bar>>) -> GenTick<Bar> {
+    assert_eq!(bars.len(), 1);
+    bars.pop().unwrap()
+}
+
+#[test]
+fn time_bars_skip_gaps() {
+    let mut builder = BarBuilder::new(BarDefinition {bar_type: BarType::Time{period: 10}, session: None}).unwrap();
+    assert!(builder.push(&bar_tick(5, 3), 1).is_empty());
+    assert!(builder.push(&bar_tick(7, 9), 1).is_empty());
+    // a gap of several periods only produces the one finished bar
+    let bar = single(builder.push(&bar_tick(6, 42), 1));
+    assert_eq!(bar.timestamp, 0);
+    assert_eq!(bar.data.mid, Ohlc {open: 5, high: 7, low: 5, close: 7});
+    assert_eq!(bar.data.volume, 2);
+
+    let bar = builder.take().unwrap();
+    assert_eq!(bar.timestamp, 40);
+    assert_eq!(bar.data.tick_count, 1);
+}
+
+#[test]
+fn late_ticks_are_dropped() {
+    let mut builder = BarBuilder::new(BarDefinition {bar_type: BarType::Time{period: 10}, session: None}).unwrap();
+    assert!(builder.push(&bar_tick(5, 21), 1).is_empty());
+    // a tick from an earlier interval neither closes the bar nor starts an earlier one
+    assert!(builder.push(&bar_tick(9, 14), 1).is_empty());
+    assert!(builder.push(&bar_tick(6, 25), 1).is_empty());
+    let bar = single(builder.push(&bar_tick(6, 30), 1));
+    assert_eq!(bar.timestamp, 20);
+    assert_eq!(bar.data.mid, Ohlc {open: 5, high: 6, low: 5, close: 6});
+    assert_eq!((bar.data.open_time, bar.data.close_time), (21, 25));
+}
+
+#[test]
+fn bars_closed_on_the_clock() {
+    let mut builder = BarBuilder::new(BarDefinition {bar_type: BarType::Time{period: 10}, session: None}).unwrap();
+    assert!(builder.close_until(50).is_none());
+    assert!(builder.push(&bar_tick(5, 12), 1).is_empty());
+    assert!(builder.close_until(19).is_none());
+    let bar = builder.close_until(20).unwrap();
+    assert_eq!((bar.timestamp, bar.data.tick_count), (10, 1));
+    // ticks stamped before the close are late
+    assert!(builder.push(&bar_tick(7, 19), 1).is_empty());
+    assert!(builder.take().is_none());
+    assert!(builder.push(&bar_tick(7, 20), 1).is_empty());
+    assert_eq!(builder.take().unwrap().timestamp, 20);
+
+    // tick bars only close on the clock at the end of their session
+    let hour = 60 * 60 * 1000;
+    let session = Session {start: 8 * hour, end: 16 * hour};
+    let mut builder = BarBuilder::new(BarDefinition {bar_type: BarType::Tick{count: 3}, session: Some(session)}).unwrap();
+    assert!(builder.push(&bar_tick(1, 9 * hour), 1).is_empty());
+    assert!(builder.close_until(15 * hour).is_none());
+    assert_eq!(builder.close_until(16 * hour).unwrap().data.tick_count, 1);
+}
+
+#[test]
+fn tick_and_volume_bars() {
+    let mut builder = BarBuilder::new(BarDefinition {bar_type: BarType::Tick{count: 3}, session: None}).unwrap();
+    let bars: Vec<GenTick<Bar>> = (0..7).flat_map(|i| builder.push(&bar_tick(i, i as u64), 1)).collect();
+    assert_eq!(bars.len(), 2);
+    assert_eq!(bars[1].data.mid, Ohlc {open: 3, high: 5, low: 3, close: 5});
+    assert_eq!(bars[1].timestamp, 5);
+
+    let mut builder = BarBuilder::new(BarDefinition {bar_type: BarType::Volume{volume: 10}, session: None}).unwrap();
+    assert!(builder.push(&bar_tick(1, 1), 4).is_empty());
+    assert_eq!(single(builder.push(&bar_tick(1, 2), 7)).data.volume, 11);
+    // a single large tick completes a bar by itself
+    assert_eq!(single(builder.push(&bar_tick(1, 3), 25)).data.tick_count, 1);
+}
+
+#[test]
+fn range_and_renko_bars() {
+    let mut builder = BarBuilder::new(BarDefinition {bar_type: BarType::Range{range: 4}, session: None}).unwrap();
+    assert!(builder.push(&bar_tick(10, 1), 1).is_empty());
+    assert!(builder.push(&bar_tick(13, 2), 1).is_empty());
+    assert!(builder.push(&bar_tick(9, 3), 1).is_empty());
+    let bar = single(builder.push(&bar_tick(15, 4), 1));
+    assert_eq!(bar.data.mid, Ohlc {open: 10, high: 13, low: 9, close: 9});
+
+    let mut builder = BarBuilder::new(BarDefinition {bar_type: BarType::Renko{brick_size: 5}, session: None}).unwrap();
+    assert!(builder.push(&bar_tick(100, 1), 1).is_empty());
+    assert!(builder.push(&bar_tick(103, 2), 1).is_empty());
+    let bar = single(builder.push(&bar_tick(95, 3), 1));
+    assert_eq!(bar.data.mid, Ohlc {open: 100, high: 103, low: 95, close: 95});
+}
+
+#[test]
+fn renko_bricks_are_aligned() {
+    let mut builder = BarBuilder::new(BarDefinition {bar_type: BarType::Renko{brick_size: 5}, session: None}).unwrap();
+    // the first brick is measured from 100, not from the first price
+    assert!(builder.push(&bar_tick(102, 1), 1).is_empty());
+    assert!(builder.push(&bar_tick(104, 2), 1).is_empty());
+    assert_eq!(single(builder.push(&bar_tick(105, 3), 1)).data.mid.close, 105);
+    // the next brick is measured from 105
+    assert!(builder.push(&bar_tick(108, 4), 1).is_empty());
+    assert!(builder.push(&bar_tick(101, 5), 1).is_empty());
+    assert_eq!(single(builder.push(&bar_tick(100, 6), 1)).data.mid.open, 108);
+    // and the one after from 100
+    assert!(builder.push(&bar_tick(104, 7), 1).is_empty());
+    assert!(builder.push(&bar_tick(96, 8), 1).is_empty());
+    assert_eq!(single(builder.push(&bar_tick(95, 9), 1)).data.tick_count, 3);
+    assert!(builder.push(&bar_tick(94, 10), 1).is_empty());
+    assert_eq!(single(builder.push(&bar_tick(90, 11), 1)).data.tick_count, 2);
+}
+
+#[test]
+fn invalid_bar_definitions() {
+    assert!(BarBuilder::new(BarDefinition {bar_type: BarType::Time{period: 0}, session: None}).is_err());
+    assert!(BarBuilder::new(BarDefinition {bar_type: BarType::Tick{count: 0}, session: None}).is_err());
+    assert!(BarBuilder::new(BarDefinition {bar_type: BarType::Renko{brick_size: 0}, session: None}).is_err());
+    let session = Session {start: 0, end: DAY_MS};
+    assert!(BarBuilder::new(BarDefinition {bar_type: BarType::Range{range: 0}, session: Some(session)}).is_err());
+    assert!(BarBuilder::new(BarDefinition {bar_type: BarType::Range{range: 0}, session: None}).is_ok());
+}
+
+#[test]
+fn bars_split_at_session_boundaries() {
+    let hour = 60 * 60 * 1000;
+    // session from 22:00 to 21:00 the next day, like forex markets
+    let session = Session {start: 22 * hour, end: 21 * hour};
+    assert!(session.contains(DAY_MS + 23 * hour));
+    assert!(session.contains(DAY_MS + 2 * hour));
+    assert!(!session.contains(DAY_MS + 21 * hour + 30));
+    assert_eq!(session.open_time(DAY_MS + 2 * hour), 22 * hour);
+
+    let mut builder = BarBuilder::new(BarDefinition {bar_type: BarType::Tick{count: 100}, session: Some(session)}).unwrap();
+    assert!(builder.push(&bar_tick(1, DAY_MS + 20 * hour), 1).is_empty());
+    // leaving the session closes the current bar
+    let bar = single(builder.push(&bar_tick(2, DAY_MS + 21 * hour + 5), 1));
+    assert_eq!(bar.data.tick_count, 1);
+    assert!(builder.push(&bar_tick(3, DAY_MS + 22 * hour), 1).is_empty());
+    assert!(builder.push(&bar_tick(4, DAY_MS + 23 * hour), 1).is_empty());
+    assert_eq!(builder.take().unwrap().data.tick_count, 2);
+
+    // a bar left open over the session close is returned along with a bar completed by the next session's first tick
+    let mut builder = BarBuilder::new(BarDefinition {bar_type: BarType::Volume{volume: 10}, session: Some(session)}).unwrap();
+    assert!(builder.push(&bar_tick(1, DAY_MS + 20 * hour), 4).is_empty());
+    let bars = builder.push(&bar_tick(2, DAY_MS + 22 * hour), 15);
+    assert_eq!(bars.len(), 2);
+    assert_eq!((bars[0].data.volume, bars[1].data.volume), (4, 15));
+    assert!(builder.take().is_none());
+}
+
+#[test]
+fn bar_ohlc() {
+    let mut bar = Bar::new(&Tick {bid: 10, ask: 12, timestamp: 1});
+    bar.update(&Tick {bid: 14, ask: 16, timestamp: 2});
+    bar.update(&Tick {bid: 8, ask: 9, timestamp: 3});
+    bar.update(&Tick {bid: 11, ask: 13, timestamp: 4});
+
+    assert_eq!(bar.bid, Ohlc {open: 10, high: 14, low: 8, close: 11});
+    assert_eq!(bar.ask, Ohlc {open: 12, high: 16, low: 9, close: 13});
+    assert_eq!(bar.mid, Ohlc {open: 11, high: 15, low: 8, close: 12});
+    assert_eq!(bar.tick_count, 4);
+    assert_eq!((bar.open_time, bar.close_time), (1, 4));
+}
@@ -0,0 +1,282 @@
+//! Builds alternative price histories out of real ones with a block bootstrap.  The log returns, inter-arrival times,
+//! and spreads between consecutive historical ticks are cut into blocks and stitched back together in a random
+//! order, so the new paths keep the short-term structure of the data (volatility clustering, bursts of ticks)
+//! while taking a different route.
+//!
+//! To preserve intraday seasonality, the day is split into time-of-day buckets and every block is drawn from the
+//! bucket that the generated path is currently in, so quiet sessions stay quiet and busy ones stay busy.
+//! Timestamps are assumed to be in milliseconds.
+
+use rand::{Rng, SeedableRng, Isaac64Rng};
+
+use trading::tick::Tick;
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+fn default_bucket_ms() -> u64 { 60 * 60 * 1000 }
+fn default_max_interval_ms() -> u64 { 60 * 60 * 1000 }
+
+/// Settings for bootstrapping a history.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BootstrapConfig {
+    pub seed: u64,
+    /// Every path of a seed is a different history, so many paths can be created from a single seed.
+    #[serde(default)]
+    pub path: usize,
+    /// Maximum number of consecutive historical steps copied at a time
+    pub block_size: usize,
+    /// Length of the time-of-day buckets that blocks are drawn from
+    #[serde(default = "default_bucket_ms")]
+    pub bucket_ms: u64,
+    /// Steps with a longer inter-arrival time than this, such as the gaps over weekends and holidays, aren't used.
+    #[serde(default = "default_max_interval_ms")]
+    pub max_interval_ms: u64,
+    /// Number of ticks to generate; defaults to the length of the history.
+    #[serde(default)]
+    pub tick_count: Option<usize>,
+}
+
+impl BootstrapConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.block_size == 0 {
+            return Err(String::from("The block size must be positive."));
+        }
+        if self.bucket_ms == 0 || self.bucket_ms > DAY_MS {
+            return Err(String::from("The bucket length must be positive and no longer than a day."));
+        }
+
+        Ok(())
+    }
+}
+
+/// The change from one historical tick to the next
+#[derive(Clone, Copy, Debug)]
+struct Step {
+    /// Index of the tick that the step ends at, used to tell whether two steps are consecutive
+    index: usize,
+    log_return: f64,
+    interval_ms: u64,
+    spread: usize,
+}
+
+/// An iterator over the ticks of a bootstrapped history.  The first tick is a copy of the first historical tick.
+pub struct BootstrapTicks {
+    config: BootstrapConfig,
+    steps: Vec<Step>,
+    /// The indexes into `steps` of the steps that start in each time-of-day bucket
+    buckets: Vec<Vec<usize>>,
+    first: Tick,
+    tick_count: usize,
+    rng: Isaac64Rng,
+    timestamp: u64,
+    mid: f64,
+    emitted: usize,
+    /// The next step of the current block, how many more steps can be taken from it, and the bucket it was drawn from
+    block_pos: usize,
+    block_left: usize,
+    block_bucket: usize,
+    /// The tick found by a seek, returned before any new ones are generated
+    pending: Option<Tick>,
+}
+
+impl BootstrapTicks {
+    /// Creates a bootstrapped version of `history`, which should be sorted by timestamp.
+    pub fn new(history: &[Tick], config: BootstrapConfig) -> Result<BootstrapTicks, String> {
+        try!(config.validate());
+
+        let bucket_count = ((DAY_MS + config.bucket_ms - 1) / config.bucket_ms) as usize;
+        let mut steps = Vec::new();
+        let mut buckets = vec![Vec::new(); bucket_count];
+        for (i, w) in history.windows(2).enumerate() {
+            let (prev_mid, mid) = (mid_price(&w[0]), mid_price(&w[1]));
+            if w[1].timestamp < w[0].timestamp || w[1].timestamp - w[0].timestamp > config.max_interval_ms {
+                continue;
+            }
+            if prev_mid <= 0. || mid <= 0. {
+                continue;
+            }
+
+            buckets[bucket_of(w[0].timestamp, config.bucket_ms)].push(steps.len());
+            steps.push(Step {
+                index: i + 1,
+                log_return: (mid / prev_mid).ln(),
+                interval_ms: w[1].timestamp - w[0].timestamp,
+                spread: w[1].ask.saturating_sub(w[1].bid),
+            });
+        }
+        if steps.is_empty() {
+            return Err(String::from("The history doesn't contain any usable ticks to bootstrap from."));
+        }
+
+        let first = history[0];
+        Ok(BootstrapTicks {
+            rng: Isaac64Rng::from_seed(&[config.seed, config.path as u64][..]),
+            tick_count: config.tick_count.unwrap_or(history.len()),
+            timestamp: first.timestamp,
+            mid: mid_price(&first),
+            emitted: 0,
+            block_pos: 0,
+            block_left: 0,
+            block_bucket: 0,
+            pending: None,
+            first: first,
+            steps: steps,
+            buckets: buckets,
+            config: config,
+        })
+    }
+
+    /// Goes back to the start of the path.
+    fn reset(&mut self) {
+        self.rng = Isaac64Rng::from_seed(&[self.config.seed, self.config.path as u64][..]);
+        self.timestamp = self.first.timestamp;
+        self.mid = mid_price(&self.first);
+        self.emitted = 0;
+        self.block_left = 0;
+        self.pending = None;
+    }
+
+    /// Moves the path to the first tick at or after `timestamp`.  Seeking backwards regenerates the path from the
+    /// beginning.
+    pub fn seek(&mut self, timestamp: u64) {
+        let past = match self.pending {
+            Some(t) => t.timestamp > timestamp,
+            None => self.emitted > 0 && self.timestamp >= timestamp,
+        };
+        if past {
+            self.reset();
+        }
+
+        self.pending = None;
+        while let Some(t) = self.next() {
+            if t.timestamp >= timestamp {
+                self.pending = Some(t);
+                break;
+            }
+        }
+    }
+
+    /// Returns the next step, starting a new block if the current one is used up, has run into a gap in the
+    /// history, or the path has moved into a different time-of-day bucket.
+    fn next_step(&mut self) -> Step {
+        let bucket = bucket_of(self.timestamp, self.config.bucket_ms);
+        let continues = self.block_left > 0 && bucket == self.block_bucket && self.block_pos < self.steps.len()
+            && self.steps[self.block_pos].index == self.steps[self.block_pos - 1].index + 1;
+        if !continues {
+            let candidates = &self.buckets[self.nearest_bucket(bucket)];
+            self.block_pos = candidates[self.rng.gen_range(0, candidates.len())];
+            self.block_left = self.config.block_size;
+            self.block_bucket = bucket;
+        }
+
+        let step = self.steps[self.block_pos];
+        self.block_pos += 1;
+        self.block_left -= 1;
+        step
+    }
+
+    /// Returns the closest bucket to `bucket` that contains steps, wrapping around midnight.
+    fn nearest_bucket(&self, bucket: usize) -> usize {
+        let count = self.buckets.len();
+        (0..count)
+            .flat_map(|d| vec![(bucket + d) % count, (bucket + count - d) % count])
+            .find(|&b| !self.buckets[b].is_empty())
+            .unwrap()
+    }
+}
+
+impl Iterator for BootstrapTicks {
+    type Item = Tick;
+
+    fn next(&mut self) -> Option<Tick> {
+        if let Some(t) = self.pending.take() {
+            return Some(t);
+        }
+        if self.emitted >= self.tick_count {
+            return None;
+        }
+
+        self.emitted += 1;
+        if self.emitted == 1 {
+            return Some(self.first);
+        }
+
+        let step = self.next_step();
+        self.timestamp += step.interval_ms;
+        self.mid *= step.log_return.exp();
+        let bid = (self.mid - step.spread as f64 / 2.).round().max(0.) as usize;
+        Some(Tick {
+            timestamp: self.timestamp,
+            bid: bid,
+            ask: bid + step.spread,
+        })
+    }
+}
+
+fn bucket_of(timestamp: u64, bucket_ms: u64) -> usize {
+    ((timestamp % DAY_MS) / bucket_ms) as usize
+}
+
+fn mid_price(t: &Tick) -> f64 {
+    (t.bid + t.ask) as f64 / 2.
+}
+
+#[test]
+fn bootstrap_ticks() {
+    use trading::synthetic::{SyntheticConfig, SyntheticTicks, PriceModel, SpreadModel, ArrivalProcess};
+
+    let hour = 60 * 60 * 1000;
+    // two days of history with a tick every second in the first half of the day and every ten seconds in the second
+    let prices = SyntheticTicks::new(SyntheticConfig {
+        seed: 3,
+        start_time: 0,
+        tick_count: None,
+        initial_price: 1.1,
+        pip_scale: 100_000.,
+        model: PriceModel::Gbm{drift: 0., volatility: 0.1},
+        spread: SpreadModel::Uniform{min: 1, max: 4},
+        arrivals: ArrivalProcess::Fixed{interval_ms: 1},
+    }).unwrap();
+    let mut history = Vec::new();
+    let mut timestamp = 0;
+    for t in prices {
+        if timestamp >= 2 * DAY_MS {
+            break;
+        }
+        history.push(Tick {timestamp: timestamp, ..t});
+        timestamp += if timestamp % DAY_MS < 12 * hour { 1000 } else { 10_000 };
+    }
+
+    let config = BootstrapConfig {
+        seed: 1,
+        path: 0,
+        block_size: 50,
+        bucket_ms: hour,
+        max_interval_ms: hour,
+        tick_count: None,
+    };
+    let a: Vec<Tick> = BootstrapTicks::new(&history, config.clone()).unwrap().collect();
+    let b: Vec<Tick> = BootstrapTicks::new(&history, config.clone()).unwrap().collect();
+    assert!(a == b);
+    assert_eq!(a.len(), history.len());
+    assert_eq!(a[0], history[0]);
+    assert!(a != history);
+    assert!(a.windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+    assert!(a.iter().all(|t| t.ask - t.bid >= 1 && t.ask - t.bid <= 4));
+
+    // the busy and quiet halves of the day are kept
+    let busy = a.iter().filter(|t| t.timestamp % DAY_MS < 12 * hour).count();
+    assert!(busy as f64 / (a.len() - busy) as f64 > 8.);
+
+    let other: Vec<Tick> = BootstrapTicks::new(&history, BootstrapConfig{path: 1, ..config.clone()}).unwrap().collect();
+    assert!(a != other);
+
+    let mut ticks = BootstrapTicks::new(&history, BootstrapConfig{tick_count: Some(1000), ..config.clone()}).unwrap();
+    ticks.seek(a[500].timestamp);
+    assert_eq!(ticks.next(), Some(a[500]));
+    ticks.seek(0);
+    assert_eq!(ticks.count(), 1000);
+
+    assert!(BootstrapTicks::new(&history[..1], config.clone()).is_err());
+    assert!(BootstrapTicks::new(&history, BootstrapConfig{block_size: 0, ..config}).is_err());
+}
@@ -0,0 +1,293 @@
+//! Compiles syntax trees into stateful expressions that are evaluated for every tick.
+
+use trading::tick::Tick;
+use trading::indicators::*;
+use trading::trading_condition::{TradingCondition, TradingAction};
+use super::parser::{Ast, ActionAst, BinOp, ConditionAst};
+
+/// The type of value an expression produces.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Num,
+    Bool,
+}
+
+/// A compiled expression.  Booleans are represented as `1.0` and `0.0`; expressions evaluate to `None` while
+/// any of the indicators they contain are warming up.
+pub enum Expr {
+    Const(f64),
+    Price(PriceField),
+    /// ask minus bid
+    Spread,
+    Indicator{input: Box<Expr>, indicator: Box<Indicator<Input=f64, Output=f64> + Send>},
+    /// True on the tick that `a` crosses above (or below) `b`.
+    Cross{a: Box<Expr>, b: Box<Expr>, above: bool, prev: Option<(f64, f64)>},
+    Binary{op: BinOp, lhs: Box<Expr>, rhs: Box<Expr>},
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+}
+
+fn as_bool(b: bool) -> f64 {
+    if b { 1.0 } else { 0.0 }
+}
+
+impl Expr {
+    /// Evaluates the expression for a new tick.  Every sub-expression is evaluated on every tick (there is no
+    /// short-circuiting) so that all indicators see every tick.
+    pub fn eval(&mut self, t: &Tick) -> Option<f64> {
+        match *self {
+            Expr::Const(n) => Some(n),
+            Expr::Price(field) => Some(field.get(t)),
+            Expr::Spread => Some(t.ask as f64 - t.bid as f64),
+            Expr::Indicator{ref mut input, ref mut indicator} => {
+                match input.eval(t) {
+                    Some(x) => indicator.update(x),
+                    None => None,
+                }
+            },
+            Expr::Cross{ref mut a, ref mut b, above, ref mut prev} => {
+                let (cur_a, cur_b) = match (a.eval(t), b.eval(t)) {
+                    (Some(a), Some(b)) => (a, b),
+                    _ => return None,
+                };
+                let crossed = match *prev {
+                    Some((prev_a, prev_b)) if above => prev_a <= prev_b && cur_a > cur_b,
+                    Some((prev_a, prev_b)) => prev_a >= prev_b && cur_a < cur_b,
+                    None => false,
+                };
+                *prev = Some((cur_a, cur_b));
+
+                Some(as_bool(crossed))
+            },
+            Expr::Binary{op, ref mut lhs, ref mut rhs} => {
+                let (l, r) = match (lhs.eval(t), rhs.eval(t)) {
+                    (Some(l), Some(r)) => (l, r),
+                    _ => return None,
+                };
+
+                Some(match op {
+                    BinOp::Add => l + r,
+                    BinOp::Sub => l - r,
+                    BinOp::Mul => l * r,
+                    BinOp::Div => if r == 0.0 { return None } else { l / r },
+                    BinOp::Lt => as_bool(l < r),
+                    BinOp::Gt => as_bool(l > r),
+                    BinOp::Le => as_bool(l <= r),
+                    BinOp::Ge => as_bool(l >= r),
+                    BinOp::Eq => as_bool(l == r),
+                    BinOp::Ne => as_bool(l != r),
+                    BinOp::And => as_bool(l != 0.0 && r != 0.0),
+                    BinOp::Or => as_bool(l != 0.0 || r != 0.0),
+                })
+            },
+            Expr::Neg(ref mut inner) => inner.eval(t).map(|x| -x),
+            Expr::Not(ref mut inner) => inner.eval(t).map(|x| as_bool(x == 0.0)),
+        }
+    }
+}
+
+/// Returns an error if `kind` isn't `expected`.
+fn check_kind(kind: Kind, expected: Kind, context: &str) -> Result<(), String> {
+    if kind != expected {
+        return Err(format!("Expected a {:?} value for {} but found a {:?} value", expected, context, kind));
+    }
+
+    Ok(())
+}
+
+/// Returns the value of an argument that must be a positive whole number.
+fn whole_number(ast: &Ast, context: &str) -> Result<usize, String> {
+    match *ast {
+        Ast::Number(n) if n >= 1.0 && n.fract() == 0.0 => Ok(n as usize),
+        _ => Err(format!("{} must be a positive whole number but was {:?}", context, ast)),
+    }
+}
+
+/// Compiles an expression, returning it along with the type of value it produces.
+pub fn compile_expr(ast: &Ast) -> Result<(Expr, Kind), String> {
+    match *ast {
+        Ast::Number(n) => Ok((Expr::Const(n), Kind::Num)),
+        Ast::Ident(ref name) => match name.as_str() {
+            "bid" => Ok((Expr::Price(PriceField::Bid), Kind::Num)),
+            "ask" => Ok((Expr::Price(PriceField::Ask), Kind::Num)),
+            "mid" | "close" => Ok((Expr::Price(PriceField::Mid), Kind::Num)),
+            "spread" => Ok((Expr::Spread, Kind::Num)),
+            _ => Err(format!("Unknown value: `{}`", name)),
+        },
+        Ast::Call{ref name, ref args} => compile_call(name, args),
+        Ast::Binary{op, ref lhs, ref rhs} => {
+            let (lhs, lhs_kind) = try!(compile_expr(lhs));
+            let (rhs, rhs_kind) = try!(compile_expr(rhs));
+            let (operand_kind, kind) = match op {
+                BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div => (Kind::Num, Kind::Num),
+                BinOp::And | BinOp::Or => (Kind::Bool, Kind::Bool),
+                _ => (Kind::Num, Kind::Bool),
+            };
+            let context = format!("the operands of {:?}", op);
+            try!(check_kind(lhs_kind, operand_kind, &context));
+            try!(check_kind(rhs_kind, operand_kind, &context));
+
+            Ok((Expr::Binary{op: op, lhs: Box::new(lhs), rhs: Box::new(rhs)}, kind))
+        },
+        Ast::Neg(ref inner) => {
+            let (inner, kind) = try!(compile_expr(inner));
+            try!(check_kind(kind, Kind::Num, "negation"));
+            Ok((Expr::Neg(Box::new(inner)), Kind::Num))
+        },
+        Ast::Not(ref inner) => {
+            let (inner, kind) = try!(compile_expr(inner));
+            try!(check_kind(kind, Kind::Bool, "`!`"));
+            Ok((Expr::Not(Box::new(inner)), Kind::Bool))
+        },
+    }
+}
+
+fn compile_call(name: &str, args: &[Ast]) -> Result<(Expr, Kind), String> {
+    if args.len() != 2 {
+        return Err(format!("`{}` takes 2 arguments but {} were supplied", name, args.len()));
+    }
+
+    match name {
+        "sma" | "ema" | "wma" | "rsi" | "stddev" => {
+            let (input, kind) = try!(compile_expr(&args[0]));
+            try!(check_kind(kind, Kind::Num, &format!("the input of `{}`", name)));
+            let period = try!(whole_number(&args[1], &format!("The period of `{}`", name)));
+            let indicator: Box<Indicator<Input=f64, Output=f64> + Send> = match name {
+                "sma" => Box::new(Sma::new(period)),
+                "ema" => Box::new(Ema::new(period)),
+                "wma" => Box::new(Wma::new(period)),
+                "rsi" => Box::new(Rsi::new(period)),
+                _ => Box::new(RollingStdDev::new(period)),
+            };
+
+            Ok((Expr::Indicator{input: Box::new(input), indicator: indicator}, Kind::Num))
+        },
+        "cross_above" | "cross_below" => {
+            let (a, a_kind) = try!(compile_expr(&args[0]));
+            let (b, b_kind) = try!(compile_expr(&args[1]));
+            try!(check_kind(a_kind, Kind::Num, &format!("the arguments of `{}`", name)));
+            try!(check_kind(b_kind, Kind::Num, &format!("the arguments of `{}`", name)));
+
+            Ok((Expr::Cross{a: Box::new(a), b: Box::new(b), above: name == "cross_above", prev: None}, Kind::Bool))
+        },
+        _ => Err(format!("Unknown function: `{}`", name)),
+    }
+}
+
+/// The action taken when a condition is met.  Stops and take profits are offsets from the entry price, in
+/// the same units as tick prices.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ActionTemplate {
+    pub symbol: String,
+    pub long: bool,
+    pub size: usize,
+    pub stop: Option<usize>,
+    pub take_profit: Option<usize>,
+    /// `None` for market orders
+    pub entry_price: Option<usize>,
+    pub max_range: Option<usize>,
+}
+
+impl ActionTemplate {
+    /// Creates the `TradingAction` to take given the tick that triggered it.
+    pub fn build(&self, t: &Tick) -> TradingAction {
+        let entry = match self.entry_price {
+            Some(price) => price,
+            None => if self.long { t.ask } else { t.bid },
+        };
+        let (stop, take_profit) = if self.long {
+            (self.stop.map(|s| entry.saturating_sub(s)), self.take_profit.map(|tp| entry + tp))
+        } else {
+            (self.stop.map(|s| entry + s), self.take_profit.map(|tp| entry.saturating_sub(tp)))
+        };
+
+        match self.entry_price {
+            Some(entry_price) => TradingAction::LimitOrder {
+                symbol: self.symbol.clone(), long: self.long, size: self.size, stop: stop,
+                take_profit: take_profit, entry_price: entry_price,
+            },
+            None => TradingAction::MarketOrder {
+                symbol: self.symbol.clone(), long: self.long, size: self.size, stop: stop,
+                take_profit: take_profit, max_range: self.max_range,
+            },
+        }
+    }
+}
+
+/// Compiles an action of the form `market_buy(SYMBOL, size, stop=N, take_profit=N, max_range=N)`,
+/// `market_sell(..)`, `limit_buy(SYMBOL, size, price=N, stop=N, take_profit=N)`, or `limit_sell(..)`.
+pub fn compile_action(ast: &ActionAst) -> Result<ActionTemplate, String> {
+    let (long, is_limit) = match ast.name.as_str() {
+        "market_buy" => (true, false),
+        "market_sell" => (false, false),
+        "limit_buy" => (true, true),
+        "limit_sell" => (false, true),
+        _ => return Err(format!("Unknown action: `{}`", ast.name)),
+    };
+
+    if ast.args.len() != 2 {
+        return Err(format!("`{}` takes a symbol and a size but {} arguments were supplied", ast.name, ast.args.len()));
+    }
+    let symbol = match ast.args[0] {
+        Ast::Ident(ref symbol) => symbol.clone(),
+        ref other => return Err(format!("Expected a symbol but found {:?}", other)),
+    };
+
+    let mut template = ActionTemplate {
+        symbol: symbol,
+        long: long,
+        size: try!(whole_number(&ast.args[1], "The order size")),
+        stop: None,
+        take_profit: None,
+        entry_price: None,
+        max_range: None,
+    };
+
+    for &(ref key, val) in &ast.kwargs {
+        let val = try!(whole_number(&Ast::Number(val), &format!("`{}`", key)));
+        match (key.as_str(), is_limit) {
+            ("stop", _) => template.stop = Some(val),
+            ("take_profit", _) => template.take_profit = Some(val),
+            ("max_range", false) => template.max_range = Some(val),
+            ("price", true) => template.entry_price = Some(val),
+            _ => return Err(format!("Invalid argument for `{}`: `{}`", ast.name, key)),
+        }
+    }
+
+    if is_limit && template.entry_price.is_none() {
+        return Err(format!("`{}` requires a `price` argument", ast.name));
+    }
+
+    Ok(template)
+}
+
+/// A compiled condition.  Its action is only taken on the tick where the condition becomes true, not on every
+/// tick that it stays true.
+pub struct CompiledCondition {
+    pub expr: Expr,
+    pub action: ActionTemplate,
+    was_true: bool,
+}
+
+impl CompiledCondition {
+    pub fn compile(ast: &ConditionAst) -> Result<CompiledCondition, String> {
+        let (expr, kind) = try!(compile_expr(&ast.condition));
+        try!(check_kind(kind, Kind::Bool, "the condition"));
+
+        Ok(CompiledCondition {
+            expr: expr,
+            action: try!(compile_action(&ast.action)),
+            was_true: false,
+        })
+    }
+}
+
+impl TradingCondition for CompiledCondition {
+    fn eval(&mut self, t: &Tick) -> Option<TradingAction> {
+        let is_true = self.expr.eval(t).map(|x| x != 0.0).unwrap_or(false);
+        let triggered = is_true && !self.was_true;
+        self.was_true = is_true;
+
+        if triggered { Some(self.action.build(t)) } else { None }
+    }
+}
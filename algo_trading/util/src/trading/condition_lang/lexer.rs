@@ -0,0 +1,118 @@
+//! Splits condition strings into tokens.
+
+/// A single token of a condition string.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token {
+    Ident(String),
+    Number(f64),
+    LParen,
+    RParen,
+    Comma,
+    /// `=>`, separating the condition from the action
+    Arrow,
+    /// `=`, used for keyword arguments of actions
+    Assign,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    EqEq,
+    NotEq,
+    And,
+    Or,
+    Not,
+}
+
+/// Converts a condition string into a list of tokens, returning an error describing the position of the
+/// first invalid character if there is one.
+pub fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).cloned();
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+            continue;
+        }
+
+        if c.is_digit(10) || (c == '.' && next.map(|n| n.is_digit(10)).unwrap_or(false)) {
+            let start = i;
+            while i < chars.len() && (chars[i].is_digit(10) || chars[i] == '.') {
+                i += 1;
+            }
+            let num_str: String = chars[start..i].iter().collect();
+            let num = try!(num_str.parse::<f64>().map_err(|_| format!("Invalid number \"{}\" at position {}", num_str, start)));
+            tokens.push(Token::Number(num));
+            continue;
+        }
+
+        // two-character tokens
+        let double = match (c, next) {
+            ('=', Some('>')) => Some(Token::Arrow),
+            ('=', Some('=')) => Some(Token::EqEq),
+            ('!', Some('=')) => Some(Token::NotEq),
+            ('<', Some('=')) => Some(Token::Le),
+            ('>', Some('=')) => Some(Token::Ge),
+            ('&', Some('&')) => Some(Token::And),
+            ('|', Some('|')) => Some(Token::Or),
+            _ => None,
+        };
+        if let Some(token) = double {
+            tokens.push(token);
+            i += 2;
+            continue;
+        }
+
+        let single = match c {
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            ',' => Token::Comma,
+            '=' => Token::Assign,
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '*' => Token::Star,
+            '/' => Token::Slash,
+            '<' => Token::Lt,
+            '>' => Token::Gt,
+            '!' => Token::Not,
+            _ => return Err(format!("Unexpected character '{}' at position {}", c, i)),
+        };
+        tokens.push(single);
+        i += 1;
+    }
+
+    Ok(tokens)
+}
+
+#[test]
+fn tokenize_condition() {
+    use self::Token::*;
+
+    let tokens = tokenize("cross_above(sma(close,10), 1.5) && !(bid >= -2) => market_buy(EURUSD, 1, stop=20)").unwrap();
+    assert_eq!(tokens, vec![
+        Ident(String::from("cross_above")), LParen, Ident(String::from("sma")), LParen, Ident(String::from("close")),
+        Comma, Number(10.0), RParen, Comma, Number(1.5), RParen, And, Not, LParen, Ident(String::from("bid")), Ge,
+        Minus, Number(2.0), RParen, Arrow, Ident(String::from("market_buy")), LParen, Ident(String::from("EURUSD")),
+        Comma, Number(1.0), Comma, Ident(String::from("stop")), Assign, Number(20.0), RParen,
+    ]);
+
+    assert!(tokenize("bid > 1 ; ask").is_err());
+    assert!(tokenize("bid > 1.2.3").is_err());
+}
@@ -0,0 +1,97 @@
+//! A small expression language for defining trading conditions as strings so that they can be sent to the
+//! Tick Processor with `Command::AddCondition`.
+//!
+//! A condition is an expression that is evaluated for every tick followed by an action that is taken when
+//! the expression becomes true:
+//!
+//! ```text
+//! cross_above(sma(close, 10), sma(close, 50)) && spread < 5 => market_buy(EURUSD, 1, stop=20)
+//! ```
+//!
+//! Values: `bid`, `ask`, `mid` (or `close`), `spread`, and numbers.  Functions: `sma`, `ema`, `wma`, `rsi`,
+//! and `stddev` taking an input expression and a period, and `cross_above`/`cross_below` which are true on
+//! the tick where their first argument crosses their second.  Expressions can be combined with arithmetic
+//! (`+ - * /`), comparisons (`< > <= >= == !=`), and boolean operators (`&& || !`).
+//!
+//! Actions: `market_buy`/`market_sell(SYMBOL, size, stop=N, take_profit=N, max_range=N)` and
+//! `limit_buy`/`limit_sell(SYMBOL, size, price=N, stop=N, take_profit=N)`.  Stops and take profits are
+//! offsets from the entry price.
+
+use trading::trading_condition::TradingCondition;
+
+pub mod lexer;
+pub mod parser;
+pub mod eval;
+
+pub use self::eval::{CompiledCondition, ActionTemplate};
+
+/// Compiles a condition string into a `CompiledCondition`.
+pub fn compile_condition(s: &str) -> Result<CompiledCondition, String> {
+    let tokens = try!(lexer::tokenize(s));
+    let ast = try!(parser::parse(tokens));
+    CompiledCondition::compile(&ast)
+}
+
+/// Parses a condition string into a `TradingCondition` that can be evaluated for every tick.
+pub fn parse_condition(s: &str) -> Result<Box<TradingCondition>, String> {
+    let condition = try!(compile_condition(s));
+    Ok(Box::new(condition))
+}
+
+#[cfg(test)]
+use trading::tick::Tick;
+#[cfg(test)]
+use trading::trading_condition::TradingAction;
+
+#[cfg(test)]
+fn tick(price: usize, timestamp: u64) -> Tick {
+    Tick {bid: price, ask: price + 2, timestamp: timestamp}
+}
+
+#[test]
+fn sma_crossover_condition() {
+    let mut cond = parse_condition("cross_above(sma(bid, 2), sma(bid, 4)) => market_buy(EURUSD, 1, stop=20, take_profit=30)").unwrap();
+
+    // falling prices, then a jump that pulls the fast average above the slow one
+    let prices = [110, 108, 106, 104, 102, 100, 120, 125, 130];
+    let mut actions = Vec::new();
+    for (i, &p) in prices.iter().enumerate() {
+        if let Some(action) = cond.eval(&tick(p, i as u64)) {
+            actions.push((i, action));
+        }
+    }
+
+    assert_eq!(actions.len(), 1);
+    assert_eq!(actions[0], (6, TradingAction::MarketOrder {
+        symbol: String::from("EURUSD"), long: true, size: 1, stop: Some(102),
+        take_profit: Some(152), max_range: None,
+    }));
+}
+
+#[test]
+fn conditions_trigger_on_rising_edge() {
+    let mut cond = parse_condition("bid > 100 => limit_sell(GBPUSD, 3, price=150, stop=10)").unwrap();
+    assert_eq!(cond.eval(&tick(99, 1)), None);
+    assert_eq!(cond.eval(&tick(101, 2)), Some(TradingAction::LimitOrder {
+        symbol: String::from("GBPUSD"), long: false, size: 3, stop: Some(160), take_profit: None, entry_price: 150,
+    }));
+    // still true; no new action
+    assert_eq!(cond.eval(&tick(102, 3)), None);
+    assert_eq!(cond.eval(&tick(98, 4)), None);
+    assert!(cond.eval(&tick(103, 5)).is_some());
+}
+
+#[test]
+fn invalid_conditions() {
+    // condition must be boolean
+    assert!(parse_condition("sma(bid, 10) => market_buy(EURUSD, 1)").is_err());
+    // can't compare booleans
+    assert!(parse_condition("(bid > 1) > 2 => market_buy(EURUSD, 1)").is_err());
+    assert!(parse_condition("foo > 1 => market_buy(EURUSD, 1)").is_err());
+    assert!(parse_condition("sma(bid, 0) > 1 => market_buy(EURUSD, 1)").is_err());
+    assert!(parse_condition("sma(bid, 2.5) > 1 => market_buy(EURUSD, 1)").is_err());
+    assert!(parse_condition("bid > 1 => market_buy(EURUSD, 1, price=10)").is_err());
+    assert!(parse_condition("bid > 1 => limit_buy(EURUSD, 1)").is_err());
+    assert!(parse_condition("bid > 1 => teleport(EURUSD, 1)").is_err());
+    assert!(parse_condition("bid > 1 && rsi(ema(close, 5), 14) < 30 => market_sell(EURUSD, 2)").is_ok());
+}
@@ -0,0 +1,292 @@
+//! Parses lists of tokens into syntax trees.
+//!
+//! Grammar, from lowest to highest precedence:
+//!
+//! ```text
+//! condition := expr "=>" action
+//! action    := ident "(" [arg ("," arg)*] ")"
+//! arg       := expr | ident "=" number
+//! expr      := and ("||" and)*
+//! and       := cmp ("&&" cmp)*
+//! cmp       := sum [("<" | ">" | "<=" | ">=" | "==" | "!=") sum]
+//! sum       := product (("+" | "-") product)*
+//! product   := unary (("*" | "/") unary)*
+//! unary     := ("-" | "!") unary | primary
+//! primary   := number | ident | ident "(" [expr ("," expr)*] ")" | "(" expr ")"
+//! ```
+
+use super::lexer::Token;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Eq,
+    Ne,
+    And,
+    Or,
+}
+
+/// Syntax tree of an expression.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Ast {
+    Number(f64),
+    Ident(String),
+    Call{name: String, args: Vec<Ast>},
+    Binary{op: BinOp, lhs: Box<Ast>, rhs: Box<Ast>},
+    Neg(Box<Ast>),
+    Not(Box<Ast>),
+}
+
+/// Syntax tree of the action taken when a condition is met.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ActionAst {
+    pub name: String,
+    pub args: Vec<Ast>,
+    pub kwargs: Vec<(String, f64)>,
+}
+
+/// Syntax tree of an entire condition string.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConditionAst {
+    pub condition: Ast,
+    pub action: ActionAst,
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+/// Parses a full condition of the form `expr => action`.
+pub fn parse(tokens: Vec<Token>) -> Result<ConditionAst, String> {
+    let mut parser = Parser {tokens: tokens, pos: 0};
+    let condition = try!(parser.expr());
+    try!(parser.expect(Token::Arrow));
+    let action = try!(parser.action());
+    if parser.pos != parser.tokens.len() {
+        return Err(format!("Unexpected trailing token: {:?}", parser.tokens[parser.pos]));
+    }
+
+    Ok(ConditionAst {
+        condition: condition,
+        action: action,
+    })
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + offset)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next() {
+            Some(ref token) if *token == expected => Ok(()),
+            Some(token) => Err(format!("Expected {:?} but found {:?}", expected, token)),
+            None => Err(format!("Expected {:?} but reached the end of the condition", expected)),
+        }
+    }
+
+    /// Parses a left-associative chain of binary operators, using `operand` to parse the operands.
+    fn chain<F>(&mut self, ops: &[(Token, BinOp)], operand: F) -> Result<Ast, String> where F: Fn(&mut Parser) -> Result<Ast, String> {
+        let mut lhs = try!(operand(self));
+        loop {
+            let op = match self.peek() {
+                Some(token) => ops.iter().find(|&&(ref t, _)| t == token).map(|&(_, op)| op),
+                None => None,
+            };
+            match op {
+                Some(op) => {
+                    self.pos += 1;
+                    let rhs = try!(operand(self));
+                    lhs = Ast::Binary{op: op, lhs: Box::new(lhs), rhs: Box::new(rhs)};
+                },
+                None => return Ok(lhs),
+            }
+        }
+    }
+
+    fn expr(&mut self) -> Result<Ast, String> {
+        self.chain(&[(Token::Or, BinOp::Or)], Parser::and)
+    }
+
+    fn and(&mut self) -> Result<Ast, String> {
+        self.chain(&[(Token::And, BinOp::And)], Parser::cmp)
+    }
+
+    fn cmp(&mut self) -> Result<Ast, String> {
+        let lhs = try!(self.sum());
+        let op = match self.peek() {
+            Some(&Token::Lt) => BinOp::Lt,
+            Some(&Token::Gt) => BinOp::Gt,
+            Some(&Token::Le) => BinOp::Le,
+            Some(&Token::Ge) => BinOp::Ge,
+            Some(&Token::EqEq) => BinOp::Eq,
+            Some(&Token::NotEq) => BinOp::Ne,
+            _ => return Ok(lhs),
+        };
+        self.pos += 1;
+        let rhs = try!(self.sum());
+
+        Ok(Ast::Binary{op: op, lhs: Box::new(lhs), rhs: Box::new(rhs)})
+    }
+
+    fn sum(&mut self) -> Result<Ast, String> {
+        self.chain(&[(Token::Plus, BinOp::Add), (Token::Minus, BinOp::Sub)], Parser::product)
+    }
+
+    fn product(&mut self) -> Result<Ast, String> {
+        self.chain(&[(Token::Star, BinOp::Mul), (Token::Slash, BinOp::Div)], Parser::unary)
+    }
+
+    fn unary(&mut self) -> Result<Ast, String> {
+        match self.peek() {
+            Some(&Token::Minus) => {
+                self.pos += 1;
+                Ok(Ast::Neg(Box::new(try!(self.unary()))))
+            },
+            Some(&Token::Not) => {
+                self.pos += 1;
+                Ok(Ast::Not(Box::new(try!(self.unary()))))
+            },
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Ast, String> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Ast::Number(n)),
+            Some(Token::LParen) => {
+                let inner = try!(self.expr());
+                try!(self.expect(Token::RParen));
+                Ok(inner)
+            },
+            Some(Token::Ident(name)) => {
+                if self.peek() != Some(&Token::LParen) {
+                    return Ok(Ast::Ident(name));
+                }
+                self.pos += 1;
+
+                let mut args = Vec::new();
+                if self.peek() != Some(&Token::RParen) {
+                    loop {
+                        args.push(try!(self.expr()));
+                        if self.peek() != Some(&Token::Comma) {
+                            break;
+                        }
+                        self.pos += 1;
+                    }
+                }
+                try!(self.expect(Token::RParen));
+
+                Ok(Ast::Call{name: name, args: args})
+            },
+            Some(token) => Err(format!("Unexpected token: {:?}", token)),
+            None => Err(String::from("Unexpected end of condition")),
+        }
+    }
+
+    fn action(&mut self) -> Result<ActionAst, String> {
+        let name = match self.next() {
+            Some(Token::Ident(name)) => name,
+            Some(token) => return Err(format!("Expected an action but found {:?}", token)),
+            None => return Err(String::from("Expected an action after `=>`")),
+        };
+        try!(self.expect(Token::LParen));
+
+        let mut args = Vec::new();
+        let mut kwargs = Vec::new();
+        if self.peek() != Some(&Token::RParen) {
+            loop {
+                let is_kwarg = match (self.peek(), self.peek_at(1)) {
+                    (Some(&Token::Ident(_)), Some(&Token::Assign)) => true,
+                    _ => false,
+                };
+
+                if is_kwarg {
+                    let key = match self.next() {
+                        Some(Token::Ident(key)) => key,
+                        _ => unreachable!(),
+                    };
+                    self.pos += 1;
+                    match self.next() {
+                        Some(Token::Number(n)) => kwargs.push((key, n)),
+                        other => return Err(format!("Expected a number for argument `{}` but found {:?}", key, other)),
+                    }
+                } else if !kwargs.is_empty() {
+                    return Err(String::from("Positional arguments can't come after keyword arguments"));
+                } else {
+                    args.push(try!(self.expr()));
+                }
+
+                if self.peek() != Some(&Token::Comma) {
+                    break;
+                }
+                self.pos += 1;
+            }
+        }
+        try!(self.expect(Token::RParen));
+
+        Ok(ActionAst {
+            name: name,
+            args: args,
+            kwargs: kwargs,
+        })
+    }
+}
+
+#[test]
+fn operator_precedence() {
+    use super::lexer::tokenize;
+
+    let ast = parse(tokenize("bid + 2 * 3 > 4 || !ask => close_all()").unwrap()).unwrap();
+    let expected = Ast::Binary {
+        op: BinOp::Or,
+        lhs: Box::new(Ast::Binary {
+            op: BinOp::Gt,
+            lhs: Box::new(Ast::Binary {
+                op: BinOp::Add,
+                lhs: Box::new(Ast::Ident(String::from("bid"))),
+                rhs: Box::new(Ast::Binary {
+                    op: BinOp::Mul,
+                    lhs: Box::new(Ast::Number(2.0)),
+                    rhs: Box::new(Ast::Number(3.0)),
+                }),
+            }),
+            rhs: Box::new(Ast::Number(4.0)),
+        }),
+        rhs: Box::new(Ast::Not(Box::new(Ast::Ident(String::from("ask"))))),
+    };
+    assert_eq!(ast.condition, expected);
+    assert_eq!(ast.action, ActionAst {name: String::from("close_all"), args: Vec::new(), kwargs: Vec::new()});
+}
+
+#[test]
+fn parse_action_args() {
+    use super::lexer::tokenize;
+
+    let ast = parse(tokenize("bid > 1 => market_buy(EURUSD, 1, stop=20, take_profit=40)").unwrap()).unwrap();
+    assert_eq!(ast.action.args, vec![Ast::Ident(String::from("EURUSD")), Ast::Number(1.0)]);
+    assert_eq!(ast.action.kwargs, vec![(String::from("stop"), 20.0), (String::from("take_profit"), 40.0)]);
+
+    assert!(parse(tokenize("bid > 1 => market_buy(stop=20, EURUSD)").unwrap()).is_err());
+    assert!(parse(tokenize("bid > 1").unwrap()).is_err());
+    assert!(parse(tokenize("(bid > 1 => market_buy(EURUSD, 1)").unwrap()).is_err());
+    assert!(parse(tokenize("bid > 1 => market_buy(EURUSD, 1) bid").unwrap()).is_err());
+}
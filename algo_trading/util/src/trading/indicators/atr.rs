@@ -0,0 +1,100 @@
+//! Average True Range.
+
+use super::*;
+
+/// Wilder's Average True Range.  The true range of a candle is the largest of its high-low range
+/// and the distance from the previous close to its high or low.  The first ATR value is the simple
+/// average of the first `period` true ranges.
+#[derive(Clone, Debug)]
+pub struct Atr {
+    period: usize,
+    prev_close: Option<f64>,
+    count: usize,
+    value: f64,
+}
+
+impl Atr {
+    pub fn new(period: usize) -> Atr {
+        assert!(period > 0, "Indicator periods must be greater than zero!");
+        Atr {
+            period: period,
+            prev_close: None,
+            count: 0,
+            value: 0.0,
+        }
+    }
+}
+
+/// Returns the true range of a candle given the close of the one before it.
+fn true_range(c: &Candle, prev_close: Option<f64>) -> f64 {
+    let range = c.high - c.low;
+    match prev_close {
+        Some(prev) => range.max((c.high - prev).abs()).max((c.low - prev).abs()),
+        None => range,
+    }
+}
+
+impl Indicator for Atr {
+    type Input = Candle;
+    type Output = f64;
+
+    fn update(&mut self, c: Candle) -> Option<f64> {
+        let tr = true_range(&c, self.prev_close);
+        self.prev_close = Some(c.close);
+        self.count += 1;
+        let n = self.period as f64;
+
+        if self.count < self.period {
+            self.value += tr;
+            None
+        } else if self.count == self.period {
+            self.value = (self.value + tr) / n;
+            Some(self.value)
+        } else {
+            self.value = (self.value * (n - 1.0) + tr) / n;
+            Some(self.value)
+        }
+    }
+
+    fn is_ready(&self) -> bool {
+        self.count >= self.period
+    }
+
+    fn warmup_period(&self) -> usize {
+        self.period
+    }
+
+    fn reset(&mut self) {
+        self.prev_close = None;
+        self.count = 0;
+        self.value = 0.0;
+    }
+}
+
+#[test]
+fn atr_matches_batch() {
+    let candles = test_candles();
+    let period = 14;
+    let n = period as f64;
+    let trs: Vec<f64> = candles.iter().enumerate().map(|(i, c)| {
+        let prev = if i == 0 { None } else { Some(candles[i - 1].close) };
+        true_range(c, prev)
+    }).collect();
+
+    let mut expected = vec![None; candles.len()];
+    let mut atr_val = trs[..period].iter().sum::<f64>() / n;
+    expected[period - 1] = Some(atr_val);
+    for i in period..trs.len() {
+        atr_val = (atr_val * (n - 1.0) + trs[i]) / n;
+        expected[i] = Some(atr_val);
+    }
+
+    let mut atr = Atr::new(period);
+    for (c, exp) in candles.iter().zip(expected.iter()) {
+        match (atr.update(*c), *exp) {
+            (Some(a), Some(b)) => assert_close(a, b),
+            (None, None) => (),
+            (a, b) => panic!("Warmup mismatch: {:?} vs {:?}", a, b),
+        }
+    }
+}
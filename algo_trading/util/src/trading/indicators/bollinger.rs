@@ -0,0 +1,82 @@
+//! Bollinger Bands.
+
+use super::*;
+
+/// The value of a set of Bollinger Bands at a point in time.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Bands {
+    pub upper: f64,
+    pub middle: f64,
+    pub lower: f64,
+}
+
+/// Bollinger Bands: a simple moving average plus and minus `k` population standard deviations.
+#[derive(Clone, Debug)]
+pub struct BollingerBands {
+    k: f64,
+    stddev: RollingStdDev,
+}
+
+impl BollingerBands {
+    pub fn new(period: usize, k: f64) -> BollingerBands {
+        BollingerBands {
+            k: k,
+            stddev: RollingStdDev::new(period),
+        }
+    }
+}
+
+impl Indicator for BollingerBands {
+    type Input = f64;
+    type Output = Bands;
+
+    fn update(&mut self, x: f64) -> Option<Bands> {
+        let sd = match self.stddev.update(x) {
+            Some(sd) => sd,
+            None => return None,
+        };
+        let middle = self.stddev.mean();
+
+        Some(Bands {
+            upper: middle + self.k * sd,
+            middle: middle,
+            lower: middle - self.k * sd,
+        })
+    }
+
+    fn is_ready(&self) -> bool {
+        self.stddev.is_ready()
+    }
+
+    fn warmup_period(&self) -> usize {
+        self.stddev.warmup_period()
+    }
+
+    fn reset(&mut self) {
+        self.stddev.reset();
+    }
+}
+
+#[test]
+fn bollinger_matches_batch() {
+    use super::stddev::batch_stddev;
+
+    let series = test_series();
+    let period = 20;
+    let mut bb = BollingerBands::new(period, 2.0);
+    for (i, &x) in series.iter().enumerate() {
+        let res = bb.update(x);
+        if i + 1 < period {
+            assert_eq!(res, None);
+            continue;
+        }
+
+        let window = &series[i + 1 - period..i + 1];
+        let mean = window.iter().sum::<f64>() / period as f64;
+        let sd = batch_stddev(window);
+        let bands = res.unwrap();
+        assert_close(bands.middle, mean);
+        assert_close(bands.upper, mean + 2.0 * sd);
+        assert_close(bands.lower, mean - 2.0 * sd);
+    }
+}
@@ -0,0 +1,83 @@
+//! Donchian channels.
+
+use super::*;
+
+/// The value of a Donchian channel at a point in time.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DonchianChannel {
+    pub upper: f64,
+    pub middle: f64,
+    pub lower: f64,
+}
+
+/// Donchian channel: the highest high and lowest low of the last `period` candles.
+#[derive(Clone, Debug)]
+pub struct Donchian {
+    period: usize,
+    highs: RollingExtremum,
+    lows: RollingExtremum,
+}
+
+impl Donchian {
+    pub fn new(period: usize) -> Donchian {
+        Donchian {
+            period: period,
+            highs: RollingExtremum::max(period),
+            lows: RollingExtremum::min(period),
+        }
+    }
+}
+
+impl Indicator for Donchian {
+    type Input = Candle;
+    type Output = DonchianChannel;
+
+    fn update(&mut self, c: Candle) -> Option<DonchianChannel> {
+        let upper = self.highs.push(c.high);
+        let lower = self.lows.push(c.low);
+        if !self.highs.is_full() {
+            return None;
+        }
+
+        Some(DonchianChannel {
+            upper: upper,
+            middle: (upper + lower) / 2.0,
+            lower: lower,
+        })
+    }
+
+    fn is_ready(&self) -> bool {
+        self.highs.is_full()
+    }
+
+    fn warmup_period(&self) -> usize {
+        self.period
+    }
+
+    fn reset(&mut self) {
+        self.highs.clear();
+        self.lows.clear();
+    }
+}
+
+#[test]
+fn donchian_matches_batch() {
+    let candles = test_candles();
+    let period = 20;
+    let mut donchian = Donchian::new(period);
+    for (i, c) in candles.iter().enumerate() {
+        let res = donchian.update(*c);
+        if i + 1 < period {
+            assert_eq!(res, None);
+            continue;
+        }
+
+        let window = &candles[i + 1 - period..i + 1];
+        let high = window.iter().map(|c| c.high).fold(::std::f64::MIN, f64::max);
+        let low = window.iter().map(|c| c.low).fold(::std::f64::MAX, f64::min);
+        let val = res.unwrap();
+        assert_close(val.upper, high);
+        assert_close(val.lower, low);
+        assert_close(val.middle, (high + low) / 2.0);
+    }
+}
@@ -0,0 +1,105 @@
+//! Exponential moving average.
+
+use super::*;
+
+/// Exponential moving average with a smoothing factor of `2 / (period + 1)`.  The first value is
+/// seeded with the simple average of the first `period` values.
+#[derive(Clone, Debug)]
+pub struct Ema {
+    period: usize,
+    alpha: f64,
+    count: usize,
+    /// sum of the values seen during warmup
+    seed_sum: f64,
+    value: Option<f64>,
+}
+
+impl Ema {
+    pub fn new(period: usize) -> Ema {
+        assert!(period > 0, "Indicator periods must be greater than zero!");
+        Ema {
+            period: period,
+            alpha: 2.0 / (period as f64 + 1.0),
+            count: 0,
+            seed_sum: 0.0,
+            value: None,
+        }
+    }
+
+    /// Returns the current value of the EMA without updating it.
+    pub fn value(&self) -> Option<f64> {
+        self.value
+    }
+}
+
+impl Indicator for Ema {
+    type Input = f64;
+    type Output = f64;
+
+    fn update(&mut self, x: f64) -> Option<f64> {
+        self.count += 1;
+        self.value = match self.value {
+            Some(prev) => Some(prev + self.alpha * (x - prev)),
+            None => {
+                self.seed_sum += x;
+                if self.count == self.period {
+                    Some(self.seed_sum / self.period as f64)
+                } else {
+                    None
+                }
+            },
+        };
+
+        self.value
+    }
+
+    fn is_ready(&self) -> bool {
+        self.value.is_some()
+    }
+
+    fn warmup_period(&self) -> usize {
+        self.period
+    }
+
+    fn reset(&mut self) {
+        self.count = 0;
+        self.seed_sum = 0.0;
+        self.value = None;
+    }
+}
+
+/// Computes the EMA of the entire series at once.
+#[cfg(test)]
+pub fn batch_ema(series: &[f64], period: usize) -> Vec<Option<f64>> {
+    let alpha = 2.0 / (period as f64 + 1.0);
+    let mut res = Vec::with_capacity(series.len());
+    let mut prev: Option<f64> = None;
+    for i in 0..series.len() {
+        let val = if i + 1 < period {
+            None
+        } else if i + 1 == period {
+            Some(series[..period].iter().sum::<f64>() / period as f64)
+        } else {
+            Some(alpha * series[i] + (1.0 - alpha) * prev.unwrap())
+        };
+        res.push(val);
+        prev = val;
+    }
+
+    res
+}
+
+#[test]
+fn ema_matches_batch() {
+    let series = test_series();
+    let expected = batch_ema(&series, 12);
+    let mut ema = Ema::new(12);
+    for (x, exp) in series.iter().zip(expected.iter()) {
+        match (ema.update(*x), *exp) {
+            (Some(a), Some(b)) => assert_close(a, b),
+            (None, None) => (),
+            (a, b) => panic!("Warmup mismatch: {:?} vs {:?}", a, b),
+        }
+    }
+    assert_eq!(ema.warmup_period(), 12);
+}
@@ -0,0 +1,363 @@
+//! Generic adapter that lets any live indicator serve historical queries by replaying stored ticks through it.
+
+use std::collections::{HashMap, BTreeMap};
+use std::fs;
+use std::time::UNIX_EPOCH;
+use std::marker::PhantomData;
+use std::str::FromStr;
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicBool, Ordering, ATOMIC_BOOL_INIT};
+
+use futures::Stream;
+use serde::Serialize;
+use serde_json;
+use postgres::Connection;
+
+use super::*;
+use transport::tickstream::TickGenerators;
+use transport::postgres::{
+    get_client, init_indicator_cache_table, get_cached_indicator, cache_indicator, tick_time_before, tick_range_version,
+};
+use conf::CONF;
+
+/// Set once the cache table has been created so that it's only set up once per process
+static CACHE_TABLE_READY: AtomicBool = ATOMIC_BOOL_INIT;
+
+/// Implemented for indicators that can be created from the arguments `HashMap` of a historical query.
+pub trait FromArgs: Sized {
+    /// A unique name for the indicator, used to identify cached query results.
+    fn name() -> &'static str;
+
+    fn from_args(args: &HashMap<String, String>) -> Result<Self, String>;
+}
+
+/// A single timestamped indicator value returned from a historical query.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HistValue<T> {
+    pub timestamp: u64,
+    pub value: T,
+}
+
+/// Implements `HistQuery` for any `LiveQuery` indicator that can be created from arguments.
+///
+/// The following arguments are used by the adapter; all others are passed on to the indicator:
+///
+///  - `source`: JSON-encoded `TickGenerators` to read ticks from.  If not supplied, ticks are read out of
+///    the Postgres table for `symbol`.
+///  - `symbol`: The symbol to read ticks for if no `source` is supplied.
+///  - `no_cache`: If "true", results are neither read from nor written to the cache.
+///  - `warmup`: How many ms of ticks before `start_time` are fed into the indicator to warm it up.  If not supplied
+///    and ticks are read out of Postgres, the indicator is warmed up with as many ticks as it needs (see
+///    `LiveQuery::warmup_period`).
+///
+/// Generators that never finish on their own (such as the `RedisReader`) are read until they produce a tick
+/// after `end_time`.
+///
+/// Cached results are keyed by the indicator, query, and arguments as well as the number and last timestamp of the
+/// stored ticks in the range, so adding ticks to the range invalidates them.  For a `source`, the size and
+/// modification time of each of its files are used instead; results for sources that can't be versioned that way,
+/// such as Redis, aren't cached.  Results for an indicator can also be removed with
+/// `clear_cached_indicators(I::name(), ..)`.
+pub struct HistReplay<I, T> {
+    _i: PhantomData<I>,
+    _t: PhantomData<T>,
+}
+
+/// Parses the argument with the given name out of the arguments `HashMap`.
+pub fn parse_arg<T>(args: &HashMap<String, String>, name: &str) -> Result<T, String> where T: FromStr, T::Err: Debug {
+    let val = try!(args.get(name).ok_or(format!("No argument \"{}\" provided in the arguments HashMap.", name)));
+    val.parse::<T>().map_err(|err| format!("Unable to parse argument \"{}\": {:?}", name, err))
+}
+
+/// Like `parse_arg` but returns `default` if the argument isn't supplied.
+pub fn parse_arg_or<T>(args: &HashMap<String, String>, name: &str, default: T) -> Result<T, String> where T: FromStr, T::Err: Debug {
+    if args.contains_key(name) { parse_arg(args, name) } else { Ok(default) }
+}
+
+/// Feeds all ticks between `start_time` and `end_time` into the indicator, returning its values no more often
+/// than once every `period`.  Ticks before `start_time` are still used to warm up the indicator.
+pub fn replay_ticks<I, T, S>(
+    indicator: &mut I, ticks: S, start_time: u64, end_time: u64, period: u64
+) -> Vec<HistValue<T>> where I: LiveQuery<T>, S: Iterator<Item=Tick> {
+    let mut last_time = 0;
+    let mut res = Vec::new();
+
+    for t in ticks {
+        if t.timestamp > end_time {
+            break;
+        }
+
+        let val = indicator.tick(&t);
+        if t.timestamp < start_time {
+            continue;
+        }
+
+        if let Some(val) = val {
+            if last_time == 0 || (t.timestamp - last_time) > period {
+                res.push(HistValue {timestamp: t.timestamp, value: val});
+                last_time = t.timestamp;
+            }
+        }
+    }
+
+    res
+}
+
+/// Builds the key under which the result of a query is cached.  Arguments are sorted so that the key doesn't
+/// depend on the iteration order of the `HashMap`; `version` identifies the data the result was computed from.
+fn cache_key(
+    name: &str, start_time: u64, end_time: u64, period: u64, args: &HashMap<String, String>, version: &str
+) -> String {
+    let sorted: BTreeMap<&String, &String> = args.iter().filter(|&(k, _)| k != "no_cache").collect();
+    let args_str: Vec<String> = sorted.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+    format!("{}:{}:{}:{}:{}:{}", name, start_time, end_time, period, args_str.join("&"), version)
+}
+
+/// Returns the `TickGenerators` that ticks should be read from given the query arguments.
+fn get_source(start_time: u64, args: &HashMap<String, String>) -> Result<TickGenerators, String> {
+    match args.get("source") {
+        Some(s) => serde_json::from_str(s).map_err(|err| format!("Unable to parse `source`: {:?}", err)),
+        None => {
+            let symbol = try!(args.get("symbol").ok_or(String::from("Either `source` or `symbol` must be supplied.")));
+            Ok(TickGenerators::PostgresReader{symbol: symbol.clone(), start_time: Some(start_time)})
+        },
+    }
+}
+
+/// Identifies the data of a source by the size and modification time of its files.  Returns `None` if the source
+/// doesn't read from files or one of them can't be inspected.
+fn source_version(source: &TickGenerators) -> Option<String> {
+    let files = match source.data_files() {
+        Some(files) => files,
+        None => return None,
+    };
+
+    let mut parts = Vec::with_capacity(files.len());
+    for path in files {
+        let meta = match fs::metadata(&path) {
+            Ok(meta) => meta,
+            Err(_) => return None,
+        };
+        let modified = match meta.modified().ok().and_then(|time| time.duration_since(UNIX_EPOCH).ok()) {
+            Some(modified) => modified,
+            None => return None,
+        };
+        parts.push(format!("{}:{}:{}.{}", path.display(), meta.len(), modified.as_secs(), modified.subsec_nanos()));
+    }
+
+    Some(parts.join("|"))
+}
+
+/// Returns a connection to the database with the indicator cache table set up, creating the table the first time.
+fn cache_client() -> Result<Connection, String> {
+    let conn = try!(get_client().map_err(|_| String::from("Unable to connect to PostgreSQL!")));
+    if !CACHE_TABLE_READY.load(Ordering::SeqCst) {
+        try!(init_indicator_cache_table(&conn, CONF.postgres_user));
+        CACHE_TABLE_READY.store(true, Ordering::SeqCst);
+    }
+
+    Ok(conn)
+}
+
+impl<I, T> HistQuery for HistReplay<I, T> where I: LiveQuery<T> + FromArgs, T: Serialize {
+    fn get(start_time: u64, end_time: u64, period: u64, args: HashMap<String, String>) -> Result<String, String> {
+        let mut use_cache = args.get("no_cache").map(|s| s.as_str()) != Some("true");
+        let mut indicator = try!(I::from_args(&args));
+        let pg_symbol = if args.contains_key("source") { None } else { args.get("symbol").cloned() };
+        // results for sources whose data can't be versioned could go stale, so they aren't cached
+        let files_version = match args.get("source") {
+            Some(_) => {
+                let version = source_version(&try!(get_source(start_time, &args)));
+                use_cache = use_cache && version.is_some();
+                version
+            },
+            None => None,
+        };
+        let conn = if use_cache || pg_symbol.is_some() { Some(try!(cache_client())) } else { None };
+
+        // find where to start reading ticks so that the indicator is warmed up by `start_time`
+        let read_start = match (args.get("warmup"), &pg_symbol, &conn) {
+            (Some(_), _, _) => start_time.saturating_sub(try!(parse_arg(&args, "warmup"))),
+            (None, &Some(ref symbol), &Some(ref conn)) => {
+                try!(tick_time_before(symbol, start_time, indicator.warmup_period(), conn)).unwrap_or(0)
+            },
+            _ => start_time,
+        };
+
+        let key = if use_cache {
+            let version = match (&pg_symbol, &conn) {
+                (&Some(ref symbol), &Some(ref conn)) => {
+                    let (count, last) = try!(tick_range_version(symbol, read_start, end_time, conn));
+                    format!("{}:{}:{}", read_start, count, last)
+                },
+                _ => files_version.unwrap_or(String::new()),
+            };
+            let key = cache_key(I::name(), start_time, end_time, period, &args, &version);
+            if let Some(cached) = try!(get_cached_indicator(&key, conn.as_ref().unwrap())) {
+                return Ok(cached);
+            }
+            Some(key)
+        } else {
+            None
+        };
+
+        let mut gen = try!(get_source(read_start, &args)).get();
+        let stream = try!(gen.get_raw());
+        let ticks = stream.wait().take_while(|t| t.is_ok()).map(|t| t.unwrap());
+        let res = replay_ticks(&mut indicator, ticks, start_time, end_time, period);
+
+        let json = try!(serde_json::to_string(&res).map_err(|err| format!("{:?}", err)));
+        if let (Some(key), Some(conn)) = (key, conn) {
+            try!(cache_indicator(&key, &json, &conn));
+        }
+
+        Ok(json)
+    }
+}
+
+impl<I> FromArgs for TickIndicator<I> where I: FromArgs {
+    fn name() -> &'static str {
+        I::name()
+    }
+
+    /// Reads the price to use out of the `field` argument ("Bid", "Ask", or "Mid"), defaulting to "Mid".
+    fn from_args(args: &HashMap<String, String>) -> Result<Self, String> {
+        let field = match args.get("field").map(|s| s.as_str()) {
+            Some("Bid") => PriceField::Bid,
+            Some("Ask") => PriceField::Ask,
+            Some("Mid") | None => PriceField::Mid,
+            Some(s) => return Err(format!("Invalid price field: \"{}\"", s)),
+        };
+
+        Ok(TickIndicator::new(try!(I::from_args(args)), field))
+    }
+}
+
+/// Implements `FromArgs` for indicators that are created with only a `period`.
+macro_rules! period_from_args {
+    ($ind:ident, $name:expr) => {
+        impl FromArgs for $ind {
+            fn name() -> &'static str { $name }
+
+            fn from_args(args: &HashMap<String, String>) -> Result<Self, String> {
+                Ok($ind::new(try!(parse_arg(args, "period"))))
+            }
+        }
+    }
+}
+
+period_from_args!(Sma, "sma");
+period_from_args!(Ema, "ema");
+period_from_args!(Wma, "wma");
+period_from_args!(Rsi, "rsi");
+period_from_args!(Atr, "atr");
+period_from_args!(RollingStdDev, "stddev");
+period_from_args!(Donchian, "donchian");
+
+impl FromArgs for Macd {
+    fn name() -> &'static str { "macd" }
+
+    fn from_args(args: &HashMap<String, String>) -> Result<Self, String> {
+        Ok(Macd::new(
+            try!(parse_arg_or(args, "fast", 12)),
+            try!(parse_arg_or(args, "slow", 26)),
+            try!(parse_arg_or(args, "signal", 9)),
+        ))
+    }
+}
+
+impl FromArgs for BollingerBands {
+    fn name() -> &'static str { "bollinger" }
+
+    fn from_args(args: &HashMap<String, String>) -> Result<Self, String> {
+        Ok(BollingerBands::new(try!(parse_arg(args, "period")), try!(parse_arg_or(args, "k", 2.0))))
+    }
+}
+
+impl FromArgs for Stochastic {
+    fn name() -> &'static str { "stochastic" }
+
+    fn from_args(args: &HashMap<String, String>) -> Result<Self, String> {
+        Ok(Stochastic::new(try!(parse_arg(args, "k_period")), try!(parse_arg_or(args, "d_period", 3))))
+    }
+}
+
+impl FromArgs for Vwap {
+    fn name() -> &'static str { "vwap" }
+
+    fn from_args(_: &HashMap<String, String>) -> Result<Self, String> {
+        Ok(Vwap::new())
+    }
+}
+
+#[cfg(test)]
+fn test_ticks() -> Vec<Tick> {
+    test_series().iter().enumerate().map(|(i, &p)| {
+        Tick {bid: p as usize, ask: p as usize + 2, timestamp: (i as u64 + 1) * 10}
+    }).collect()
+}
+
+#[test]
+fn replay_matches_live() {
+    let ticks = test_ticks();
+    let mut args = HashMap::new();
+    args.insert(String::from("period"), String::from("5"));
+    args.insert(String::from("field"), String::from("Bid"));
+
+    let mut replayed: TickIndicator<Sma> = TickIndicator::from_args(&args).unwrap();
+    let res = replay_ticks(&mut replayed, ticks.clone().into_iter(), 0, ::std::u64::MAX, 0);
+    assert_eq!(res.len(), ticks.len() - 4);
+
+    let mut live = TickIndicator::new(Sma::new(5), PriceField::Bid);
+    let expected: Vec<HistValue<f64>> = ticks.iter().filter_map(|t| {
+        live.tick(t).map(|v| HistValue {timestamp: t.timestamp, value: v})
+    }).collect();
+    assert_eq!(res, expected);
+}
+
+#[test]
+fn replay_respects_range_and_period() {
+    let ticks = test_ticks();
+    let mut ind = TickIndicator::new(Ema::new(3), PriceField::Mid);
+    // ticks are 10ms apart, so a period of 25 should return every third tick
+    let res = replay_ticks(&mut ind, ticks.into_iter(), 200, 400, 25);
+
+    let timestamps: Vec<u64> = res.iter().map(|v| v.timestamp).collect();
+    assert_eq!(timestamps, vec![200, 230, 260, 290, 320, 350, 380]);
+}
+
+#[test]
+fn from_args_errors() {
+    let args = HashMap::new();
+    assert!(Sma::from_args(&args).is_err());
+    assert!(Vwap::from_args(&args).is_ok());
+
+    let mut args = HashMap::new();
+    args.insert(String::from("period"), String::from("abc"));
+    assert!(Rsi::from_args(&args).is_err());
+}
+
+#[test]
+fn cache_key_is_stable() {
+    let mut a = HashMap::new();
+    a.insert(String::from("period"), String::from("5"));
+    a.insert(String::from("symbol"), String::from("EURUSD"));
+    let mut b = a.clone();
+    b.insert(String::from("no_cache"), String::from("true"));
+    assert_eq!(cache_key("sma", 1, 2, 3, &a, ""), cache_key("sma", 1, 2, 3, &b, ""));
+    assert!(cache_key("sma", 1, 2, 3, &a, "") != cache_key("ema", 1, 2, 3, &a, ""));
+    // new ticks in the range give a new key
+    assert!(cache_key("sma", 1, 2, 3, &a, "0:10:2") != cache_key("sma", 1, 2, 3, &a, "0:11:2"));
+    assert!(cache_key("sma", 1, 2, 3, &a, "").starts_with("sma:"));
+}
+
+#[test]
+fn unversioned_sources() {
+    let random = TickGenerators::RandomReader;
+    assert_eq!(source_version(&random), None);
+    let postgres = TickGenerators::PostgresReader{symbol: String::from("EURUSD"), start_time: None};
+    assert_eq!(source_version(&postgres), None);
+    // a file that doesn't exist can't be versioned either
+    let missing = TickGenerators::BinaryReader{symbol: String::from("NO_SUCH_SYMBOL"), start_time: None};
+    assert_eq!(source_version(&missing), None);
+}
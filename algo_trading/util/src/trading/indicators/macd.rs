@@ -0,0 +1,100 @@
+//! Moving Average Convergence Divergence.
+
+use super::*;
+
+/// The value of a MACD at a point in time.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MacdValue {
+    /// fast EMA minus slow EMA
+    pub macd: f64,
+    /// EMA of the MACD line
+    pub signal: f64,
+    /// MACD line minus signal line
+    pub histogram: f64,
+}
+
+/// MACD made up of a fast and slow EMA of the price and a signal EMA of their difference.  The
+/// signal line only starts warming up once the slow EMA is ready.
+#[derive(Clone, Debug)]
+pub struct Macd {
+    fast: Ema,
+    slow: Ema,
+    signal: Ema,
+}
+
+impl Macd {
+    pub fn new(fast: usize, slow: usize, signal: usize) -> Macd {
+        assert!(fast < slow, "The fast period of a MACD must be shorter than the slow period!");
+        Macd {
+            fast: Ema::new(fast),
+            slow: Ema::new(slow),
+            signal: Ema::new(signal),
+        }
+    }
+}
+
+impl Indicator for Macd {
+    type Input = f64;
+    type Output = MacdValue;
+
+    fn update(&mut self, x: f64) -> Option<MacdValue> {
+        let fast = self.fast.update(x);
+        let slow = self.slow.update(x);
+        let macd = match (fast, slow) {
+            (Some(fast), Some(slow)) => fast - slow,
+            _ => return None,
+        };
+
+        self.signal.update(macd).map(|signal| MacdValue {
+            macd: macd,
+            signal: signal,
+            histogram: macd - signal,
+        })
+    }
+
+    fn is_ready(&self) -> bool {
+        self.signal.is_ready()
+    }
+
+    fn warmup_period(&self) -> usize {
+        self.slow.warmup_period() + self.signal.warmup_period() - 1
+    }
+
+    fn reset(&mut self) {
+        self.fast.reset();
+        self.slow.reset();
+        self.signal.reset();
+    }
+}
+
+#[test]
+fn macd_matches_batch() {
+    use super::ema::batch_ema;
+
+    let series = test_series();
+    let fast = batch_ema(&series, 12);
+    let slow = batch_ema(&series, 26);
+    let macd_line: Vec<f64> = fast.iter().zip(slow.iter())
+        .filter_map(|(f, s)| match (*f, *s) {
+            (Some(f), Some(s)) => Some(f - s),
+            _ => None,
+        }).collect();
+    let signal = batch_ema(&macd_line, 9);
+
+    let mut macd = Macd::new(12, 26, 9);
+    let warmup = macd.warmup_period();
+    assert_eq!(warmup, 34);
+    for (i, &x) in series.iter().enumerate() {
+        let res = macd.update(x);
+        if i + 1 < warmup {
+            assert_eq!(res, None);
+            continue;
+        }
+
+        let val = res.unwrap();
+        let j = i + 1 - 26;
+        assert_close(val.macd, macd_line[j]);
+        assert_close(val.signal, signal[j].unwrap());
+        assert_close(val.histogram, macd_line[j] - signal[j].unwrap());
+    }
+}
@@ -0,0 +1,355 @@
+//! Indicators transform data from a live data source, database, or other indicator into some
+//! metric that can be used to create trading signals or analyze data.
+//!
+//! Live indicators are stateful and are updated one data point at a time.  Each update is computed
+//! incrementally from the indicator's previous state in constant time rather than by re-scanning its
+//! whole window.  Until an indicator has seen enough data to produce a meaningful value, it is said to
+//! be warming up and returns `None` for every update.
+
+use std::collections::{HashMap, VecDeque};
+
+use trading::tick::*;
+
+pub mod sma;
+pub mod ema;
+pub mod wma;
+pub mod rsi;
+pub mod macd;
+pub mod bollinger;
+pub mod atr;
+pub mod stochastic;
+pub mod vwap;
+pub mod stddev;
+pub mod donchian;
+pub mod hist;
+
+pub use self::sma::Sma;
+pub use self::ema::Ema;
+pub use self::wma::Wma;
+pub use self::rsi::Rsi;
+pub use self::macd::{Macd, MacdValue};
+pub use self::bollinger::{BollingerBands, Bands};
+pub use self::atr::Atr;
+pub use self::stochastic::{Stochastic, StochasticValue};
+pub use self::vwap::Vwap;
+pub use self::stddev::RollingStdDev;
+pub use self::donchian::{Donchian, DonchianChannel};
+pub use self::hist::{HistReplay, HistValue, FromArgs, replay_ticks, parse_arg, parse_arg_or};
+
+/// This trait is used to use an indicator to read historical data from the database or some
+/// other source and return in in a format that the Monitor plotting API understands (JSON).
+///
+/// Any `LiveQuery` indicator can implement this trait through the `HistReplay` adapter.
+pub trait HistQuery {
+    /// Returns a JSON-formatted string containing an Array of timestamped indicator values
+    /// through the specified time range.  Args is any additional indicator-specific
+    /// arguments that need to be given.  Period is the minimum time that must elapse between
+    /// two distinct indicator values returned.
+    fn get(start_time: u64, end_time: u64, period: u64, args: HashMap<String, String>) -> Result<String, String>;
+}
+
+/// Implemented for indicators that can process live ticks.  The `tick()` function should be
+/// called for every live tick and an arbitrary data type returned.
+pub trait LiveQuery<T> {
+    /// Called every new tick received; returns the indicator's new value or `None` if the
+    /// indicator is still warming up.
+    fn tick(&mut self, t: &Tick) -> Option<T>;
+
+    /// Returns `true` once the indicator has received enough data to produce values.
+    fn is_ready(&self) -> bool;
+
+    /// Returns the number of ticks the indicator needs before it produces values, or 0 if it isn't known ahead of
+    /// time (for example because the indicator's window is measured in time rather than ticks).
+    fn warmup_period(&self) -> usize {
+        0
+    }
+}
+
+/// A stateful indicator that is updated one data point at a time.  Calls to `update()` must run in
+/// constant (amortized) time regardless of the period of the indicator.
+pub trait Indicator {
+    /// The type of data point fed into the indicator; usually a price or a `Candle`.
+    type Input;
+    /// The type of value produced by the indicator.
+    type Output;
+
+    /// Pushes a new data point into the indicator, returning its new value or `None` if it is
+    /// still warming up.
+    fn update(&mut self, input: Self::Input) -> Option<Self::Output>;
+
+    /// Returns `true` once the indicator has received enough data to produce values.
+    fn is_ready(&self) -> bool;
+
+    /// Returns the number of data points that must be pushed before the indicator produces values.
+    fn warmup_period(&self) -> usize;
+
+    /// Clears all internal state, returning the indicator to how it was when first created.
+    fn reset(&mut self);
+}
+
+/// Selects which price of a `Tick` is fed into an indicator.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum PriceField {
+    Bid,
+    Ask,
+    Mid,
+}
+
+impl PriceField {
+    /// Returns the selected price of the tick.
+    pub fn get(&self, t: &Tick) -> f64 {
+        match *self {
+            PriceField::Bid => t.bid as f64,
+            PriceField::Ask => t.ask as f64,
+            PriceField::Mid => (t.bid as f64 + t.ask as f64) / 2.0,
+        }
+    }
+}
+
+/// A single high/low/close observation with an associated volume.  Used as the input of indicators
+/// that need more than one price per data point.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Candle {
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+}
+
+impl Candle {
+    /// Returns the average of the high, low, and close.
+    pub fn typical_price(&self) -> f64 {
+        (self.high + self.low + self.close) / 3.0
+    }
+}
+
+/// Implemented for indicator inputs that can be created from a `Tick`.
+pub trait FromTick {
+    fn from_tick(t: &Tick, field: PriceField) -> Self;
+}
+
+impl FromTick for f64 {
+    fn from_tick(t: &Tick, field: PriceField) -> f64 {
+        field.get(t)
+    }
+}
+
+/// A tick is treated as a candle with a volume of one whose high, low, and close are all the selected price.
+impl FromTick for Candle {
+    fn from_tick(t: &Tick, field: PriceField) -> Candle {
+        let price = field.get(t);
+        Candle {
+            high: price,
+            low: price,
+            close: price,
+            volume: 1.0,
+        }
+    }
+}
+
+/// Wraps an `Indicator` so that it can be fed live ticks, using `field` to pick the price of each tick.
+pub struct TickIndicator<I> {
+    pub inner: I,
+    pub field: PriceField,
+}
+
+impl<I> TickIndicator<I> {
+    pub fn new(inner: I, field: PriceField) -> TickIndicator<I> {
+        TickIndicator {
+            inner: inner,
+            field: field,
+        }
+    }
+}
+
+impl<I> LiveQuery<I::Output> for TickIndicator<I> where I: Indicator, I::Input: FromTick {
+    fn tick(&mut self, t: &Tick) -> Option<I::Output> {
+        self.inner.update(<I::Input as FromTick>::from_tick(t, self.field))
+    }
+
+    fn is_ready(&self) -> bool {
+        self.inner.is_ready()
+    }
+
+    fn warmup_period(&self) -> usize {
+        Indicator::warmup_period(&self.inner)
+    }
+}
+
+/// A fixed-size window over the most recent values pushed into it.
+#[derive(Clone, Debug)]
+pub struct RollingWindow {
+    period: usize,
+    buf: VecDeque<f64>,
+    /// number of values pushed since the window was last cleared
+    count: usize,
+}
+
+impl RollingWindow {
+    pub fn new(period: usize) -> RollingWindow {
+        assert!(period > 0, "Indicator periods must be greater than zero!");
+        RollingWindow {
+            period: period,
+            buf: VecDeque::with_capacity(period + 1),
+            count: 0,
+        }
+    }
+
+    /// Adds a value to the window, returning the value that fell out of it if it was full.
+    pub fn push(&mut self, x: f64) -> Option<f64> {
+        self.buf.push_back(x);
+        self.count = self.count.wrapping_add(1);
+        if self.buf.len() > self.period {
+            self.buf.pop_front()
+        } else {
+            None
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.buf.len() == self.period
+    }
+
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    pub fn iter(&self) -> ::std::collections::vec_deque::Iter<f64> {
+        self.buf.iter()
+    }
+
+    /// Returns `true` once every `period` pushes while the window is full.  Indicators that keep running
+    /// sums over the window recompute them from scratch at these points so that rounding error can't
+    /// build up over long streams; this keeps updates constant time on average.
+    pub fn resync_due(&self) -> bool {
+        self.is_full() && self.count % self.period == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.buf.clear();
+        self.count = 0;
+    }
+}
+
+/// Keeps track of the highest (or lowest) value in a sliding window using a monotonic queue so that
+/// each update runs in amortized constant time.
+#[derive(Clone, Debug)]
+pub struct RollingExtremum {
+    period: usize,
+    is_max: bool,
+    /// index of the next value pushed
+    count: usize,
+    /// (index, value) pairs with values monotonically decreasing (for max) or increasing (for min)
+    queue: VecDeque<(usize, f64)>,
+}
+
+impl RollingExtremum {
+    pub fn max(period: usize) -> RollingExtremum {
+        RollingExtremum::new(period, true)
+    }
+
+    pub fn min(period: usize) -> RollingExtremum {
+        RollingExtremum::new(period, false)
+    }
+
+    fn new(period: usize, is_max: bool) -> RollingExtremum {
+        assert!(period > 0, "Indicator periods must be greater than zero!");
+        RollingExtremum {
+            period: period,
+            is_max: is_max,
+            count: 0,
+            queue: VecDeque::with_capacity(period),
+        }
+    }
+
+    /// Adds a value to the window and returns the current extremum of the window.
+    pub fn push(&mut self, x: f64) -> f64 {
+        // drop values that can never be the extremum again now that `x` is in the window
+        while let Some(&(_, back)) = self.queue.back() {
+            let dominated = if self.is_max { back <= x } else { back >= x };
+            if !dominated {
+                break;
+            }
+            self.queue.pop_back();
+        }
+        self.queue.push_back((self.count, x));
+        self.count += 1;
+
+        // drop the front value if it's fallen out of the window
+        if self.queue.front().unwrap().0 + self.period < self.count {
+            self.queue.pop_front();
+        }
+
+        self.queue.front().unwrap().1
+    }
+
+    /// Returns `true` once `period` values have been pushed.
+    pub fn is_full(&self) -> bool {
+        self.count >= self.period
+    }
+
+    pub fn clear(&mut self) {
+        self.count = 0;
+        self.queue.clear();
+    }
+}
+
+/// A deterministic, trending and oscillating price series used to check indicators against their
+/// batch reference implementations.
+#[cfg(test)]
+pub fn test_series() -> Vec<f64> {
+    let mut seed: u64 = 1234567;
+    let mut price = 1000.0;
+    let mut series = Vec::with_capacity(120);
+    for i in 0..120 {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        let noise = ((seed >> 33) % 21) as f64 - 10.0;
+        price += noise + ((i as f64) / 7.0).sin() * 4.0;
+        series.push(price);
+    }
+    series
+}
+
+/// Candles built out of `test_series()` with highs and lows spread around the close.
+#[cfg(test)]
+pub fn test_candles() -> Vec<Candle> {
+    test_series().iter().enumerate().map(|(i, &close)| {
+        Candle {
+            high: close + (i % 5) as f64 + 1.0,
+            low: close - (i % 3) as f64 - 1.0,
+            close: close,
+            volume: ((i % 7) + 1) as f64 * 10.0,
+        }
+    }).collect()
+}
+
+#[cfg(test)]
+pub fn assert_close(a: f64, b: f64) {
+    assert!((a - b).abs() < 1e-6, "{} != {}", a, b);
+}
+
+#[test]
+fn rolling_extremum_matches_scan() {
+    let series = test_series();
+    let mut max = RollingExtremum::max(9);
+    let mut min = RollingExtremum::min(9);
+    for (i, &x) in series.iter().enumerate() {
+        let start = if i >= 8 { i - 8 } else { 0 };
+        let window = &series[start..i + 1];
+        assert_close(max.push(x), window.iter().cloned().fold(::std::f64::MIN, f64::max));
+        assert_close(min.push(x), window.iter().cloned().fold(::std::f64::MAX, f64::min));
+    }
+}
+
+#[test]
+fn tick_indicator_price_field() {
+    let mut ind = TickIndicator::new(Sma::new(2), PriceField::Bid);
+    assert_eq!(ind.tick(&Tick {bid: 10, ask: 12, timestamp: 1}), None);
+    assert!(!ind.is_ready());
+    assert_eq!(ind.tick(&Tick {bid: 20, ask: 22, timestamp: 2}), Some(15.0));
+    assert!(ind.is_ready());
+}
@@ -0,0 +1,148 @@
+//! Relative Strength Index using Wilder's smoothing.
+
+use super::*;
+
+/// Wilder's Relative Strength Index.  The first average gain/loss is the simple average over the
+/// first `period` price changes; after that they are smoothed as `(prev * (period - 1) + cur) / period`.
+/// Values range from 0 to 100.
+#[derive(Clone, Debug)]
+pub struct Rsi {
+    period: usize,
+    last: Option<f64>,
+    /// how many price changes have been seen
+    changes: usize,
+    avg_gain: f64,
+    avg_loss: f64,
+}
+
+impl Rsi {
+    pub fn new(period: usize) -> Rsi {
+        assert!(period > 0, "Indicator periods must be greater than zero!");
+        Rsi {
+            period: period,
+            last: None,
+            changes: 0,
+            avg_gain: 0.0,
+            avg_loss: 0.0,
+        }
+    }
+
+    fn value(&self) -> f64 {
+        rsi_from_averages(self.avg_gain, self.avg_loss)
+    }
+}
+
+/// Converts average gains and losses into an RSI value.  A flat series has an RSI of 50.
+fn rsi_from_averages(avg_gain: f64, avg_loss: f64) -> f64 {
+    if avg_loss == 0.0 {
+        if avg_gain == 0.0 { 50.0 } else { 100.0 }
+    } else {
+        100.0 - (100.0 / (1.0 + avg_gain / avg_loss))
+    }
+}
+
+impl Indicator for Rsi {
+    type Input = f64;
+    type Output = f64;
+
+    fn update(&mut self, x: f64) -> Option<f64> {
+        let last = match self.last {
+            Some(last) => last,
+            None => {
+                self.last = Some(x);
+                return None;
+            },
+        };
+        self.last = Some(x);
+
+        let change = x - last;
+        let (gain, loss) = if change > 0.0 { (change, 0.0) } else { (0.0, -change) };
+        self.changes += 1;
+        let n = self.period as f64;
+
+        if self.changes < self.period {
+            self.avg_gain += gain;
+            self.avg_loss += loss;
+            None
+        } else if self.changes == self.period {
+            self.avg_gain = (self.avg_gain + gain) / n;
+            self.avg_loss = (self.avg_loss + loss) / n;
+            Some(self.value())
+        } else {
+            self.avg_gain = (self.avg_gain * (n - 1.0) + gain) / n;
+            self.avg_loss = (self.avg_loss * (n - 1.0) + loss) / n;
+            Some(self.value())
+        }
+    }
+
+    fn is_ready(&self) -> bool {
+        self.changes >= self.period
+    }
+
+    fn warmup_period(&self) -> usize {
+        self.period + 1
+    }
+
+    fn reset(&mut self) {
+        self.last = None;
+        self.changes = 0;
+        self.avg_gain = 0.0;
+        self.avg_loss = 0.0;
+    }
+}
+
+/// Computes the RSI of the entire series at once.
+#[cfg(test)]
+pub fn batch_rsi(series: &[f64], period: usize) -> Vec<Option<f64>> {
+    let n = period as f64;
+    let mut res = vec![None; series.len()];
+    if series.len() <= period {
+        return res;
+    }
+
+    let changes: Vec<f64> = series.windows(2).map(|w| w[1] - w[0]).collect();
+    let mut avg_gain = changes[..period].iter().filter(|c| **c > 0.0).sum::<f64>() / n;
+    let mut avg_loss = -changes[..period].iter().filter(|c| **c < 0.0).sum::<f64>() / n;
+    res[period] = Some(rsi_from_averages(avg_gain, avg_loss));
+    for i in period..changes.len() {
+        let c = changes[i];
+        avg_gain = (avg_gain * (n - 1.0) + c.max(0.0)) / n;
+        avg_loss = (avg_loss * (n - 1.0) + (-c).max(0.0)) / n;
+        res[i + 1] = Some(rsi_from_averages(avg_gain, avg_loss));
+    }
+
+    res
+}
+
+#[test]
+fn rsi_matches_batch() {
+    let series = test_series();
+    let expected = batch_rsi(&series, 14);
+    let mut rsi = Rsi::new(14);
+    for (x, exp) in series.iter().zip(expected.iter()) {
+        match (rsi.update(*x), *exp) {
+            (Some(a), Some(b)) => {
+                assert_close(a, b);
+                assert!(a >= 0.0 && a <= 100.0);
+            },
+            (None, None) => (),
+            (a, b) => panic!("Warmup mismatch: {:?} vs {:?}", a, b),
+        }
+    }
+}
+
+#[test]
+fn rsi_extremes() {
+    let mut rsi = Rsi::new(3);
+    let mut last = None;
+    for x in 0..10 {
+        last = rsi.update(x as f64);
+    }
+    assert_eq!(last, Some(100.0));
+
+    rsi.reset();
+    for _ in 0..10 {
+        last = rsi.update(5.0);
+    }
+    assert_eq!(last, Some(50.0));
+}
@@ -0,0 +1,87 @@
+//! Simple moving average over the last `period` values.
+
+use super::*;
+
+/// The unweighted mean of the last `period` values.
+#[derive(Clone, Debug)]
+pub struct Sma {
+    period: usize,
+    window: RollingWindow,
+    sum: f64,
+}
+
+impl Sma {
+    pub fn new(period: usize) -> Sma {
+        Sma {
+            period: period,
+            window: RollingWindow::new(period),
+            sum: 0.0,
+        }
+    }
+}
+
+impl Indicator for Sma {
+    type Input = f64;
+    type Output = f64;
+
+    fn update(&mut self, x: f64) -> Option<f64> {
+        self.sum += x;
+        if let Some(old) = self.window.push(x) {
+            self.sum -= old;
+        }
+        if self.window.resync_due() {
+            self.sum = self.window.iter().sum();
+        }
+
+        if self.window.is_full() {
+            Some(self.sum / self.period as f64)
+        } else {
+            None
+        }
+    }
+
+    fn is_ready(&self) -> bool {
+        self.window.is_full()
+    }
+
+    fn warmup_period(&self) -> usize {
+        self.period
+    }
+
+    fn reset(&mut self) {
+        self.window.clear();
+        self.sum = 0.0;
+    }
+}
+
+#[test]
+fn sma_matches_batch() {
+    let series = test_series();
+    let period = 10;
+    let mut sma = Sma::new(period);
+    for (i, &x) in series.iter().enumerate() {
+        let res = sma.update(x);
+        if i + 1 < period {
+            assert_eq!(res, None);
+        } else {
+            let window = &series[i + 1 - period..i + 1];
+            let expected = window.iter().sum::<f64>() / period as f64;
+            assert_close(res.unwrap(), expected);
+        }
+    }
+}
+
+#[test]
+fn sma_long_stream_with_offset() {
+    // a long stream of large prices would let a naive running sum drift away from the real mean
+    let series: Vec<f64> = test_series().iter().cycle().take(50_000).map(|x| x * 1e4 + 1e9).collect();
+    let period = 10;
+    let mut sma = Sma::new(period);
+    let mut last = None;
+    for &x in &series {
+        last = sma.update(x);
+    }
+    let window = &series[series.len() - period..];
+    let expected = window.iter().sum::<f64>() / period as f64;
+    assert!((last.unwrap() - expected).abs() < 1e-5);
+}
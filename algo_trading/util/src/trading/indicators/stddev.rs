@@ -0,0 +1,137 @@
+//! Rolling (population) standard deviation.
+
+use super::*;
+
+/// Population standard deviation of the last `period` values.  The mean and the sum of squared
+/// deviations from it are updated with Welford's method, which stays accurate for large values with a
+/// small spread (such as prices in pips), and are recomputed from the window every `period` updates.
+#[derive(Clone, Debug)]
+pub struct RollingStdDev {
+    period: usize,
+    window: RollingWindow,
+    mean: f64,
+    /// sum of the squared deviations of the values in the window from `mean`
+    m2: f64,
+}
+
+impl RollingStdDev {
+    pub fn new(period: usize) -> RollingStdDev {
+        RollingStdDev {
+            period: period,
+            window: RollingWindow::new(period),
+            mean: 0.0,
+            m2: 0.0,
+        }
+    }
+
+    /// Returns the mean of the values currently in the window.
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// Recomputes the mean and squared deviations from the values in the window.
+    fn resync(&mut self) {
+        let n = self.window.len() as f64;
+        let mean = self.window.iter().sum::<f64>() / n;
+        self.m2 = self.window.iter().map(|x| (x - mean) * (x - mean)).sum();
+        self.mean = mean;
+    }
+}
+
+impl Indicator for RollingStdDev {
+    type Input = f64;
+    type Output = f64;
+
+    fn update(&mut self, x: f64) -> Option<f64> {
+        match self.window.push(x) {
+            Some(old) => {
+                // replace the oldest value with the new one; the size of the window stays the same
+                let old_mean = self.mean;
+                self.mean += (x - old) / self.period as f64;
+                self.m2 += (x - old) * (x - self.mean + old - old_mean);
+            },
+            None => {
+                let delta = x - self.mean;
+                self.mean += delta / self.window.len() as f64;
+                self.m2 += delta * (x - self.mean);
+            },
+        }
+        if self.window.resync_due() {
+            self.resync();
+        }
+
+        if !self.window.is_full() {
+            return None;
+        }
+
+        // floating point error can push the variance of a flat series slightly below zero
+        let variance = (self.m2 / self.period as f64).max(0.0);
+        Some(variance.sqrt())
+    }
+
+    fn is_ready(&self) -> bool {
+        self.window.is_full()
+    }
+
+    fn warmup_period(&self) -> usize {
+        self.period
+    }
+
+    fn reset(&mut self) {
+        self.window.clear();
+        self.mean = 0.0;
+        self.m2 = 0.0;
+    }
+}
+
+/// Computes the population standard deviation of a slice in two passes.
+#[cfg(test)]
+pub fn batch_stddev(window: &[f64]) -> f64 {
+    let n = window.len() as f64;
+    let mean = window.iter().sum::<f64>() / n;
+    (window.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / n).sqrt()
+}
+
+#[test]
+fn stddev_matches_batch() {
+    let series = test_series();
+    let period = 20;
+    let mut sd = RollingStdDev::new(period);
+    for (i, &x) in series.iter().enumerate() {
+        let res = sd.update(x);
+        if i + 1 < period {
+            assert_eq!(res, None);
+        } else {
+            assert_close(res.unwrap(), batch_stddev(&series[i + 1 - period..i + 1]));
+        }
+    }
+}
+
+#[test]
+fn stddev_flat_series() {
+    let mut sd = RollingStdDev::new(5);
+    let mut last = None;
+    for _ in 0..10 {
+        last = sd.update(1.1);
+    }
+    assert_eq!(last, Some(0.0));
+}
+
+#[test]
+fn stddev_large_offset() {
+    // pip-scale prices far from zero with a small spread; the spread doesn't depend on the offset
+    let series = test_series();
+    let period = 20;
+    let mut sd = RollingStdDev::new(period);
+    let mut offset_sd = RollingStdDev::new(period);
+    for _ in 0..500 {
+        for (i, &x) in series.iter().enumerate() {
+            let res = sd.update(x);
+            let offset_res = offset_sd.update(x + 1e9);
+            if let (Some(a), Some(b)) = (res, offset_res) {
+                assert!((a - b).abs() < 1e-4, "{} != {} at {}", a, b, i);
+            }
+        }
+    }
+    assert!((offset_sd.mean() - 1e9 - sd.mean()).abs() < 1e-5);
+}
@@ -0,0 +1,93 @@
+//! Stochastic oscillator.
+
+use super::*;
+
+/// The value of a stochastic oscillator at a point in time.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StochasticValue {
+    /// where the close sits in the high-low range of the last `k_period` candles, from 0 to 100
+    pub k: f64,
+    /// simple moving average of %K over the last `d_period` values
+    pub d: f64,
+}
+
+/// Stochastic oscillator (%K and %D).  If the high-low range of the window is zero, %K is 50.
+#[derive(Clone, Debug)]
+pub struct Stochastic {
+    highs: RollingExtremum,
+    lows: RollingExtremum,
+    d: Sma,
+    k_period: usize,
+    d_period: usize,
+}
+
+impl Stochastic {
+    pub fn new(k_period: usize, d_period: usize) -> Stochastic {
+        Stochastic {
+            highs: RollingExtremum::max(k_period),
+            lows: RollingExtremum::min(k_period),
+            d: Sma::new(d_period),
+            k_period: k_period,
+            d_period: d_period,
+        }
+    }
+}
+
+impl Indicator for Stochastic {
+    type Input = Candle;
+    type Output = StochasticValue;
+
+    fn update(&mut self, c: Candle) -> Option<StochasticValue> {
+        let high = self.highs.push(c.high);
+        let low = self.lows.push(c.low);
+        if !self.highs.is_full() {
+            return None;
+        }
+
+        let range = high - low;
+        let k = if range == 0.0 { 50.0 } else { 100.0 * (c.close - low) / range };
+        self.d.update(k).map(|d| StochasticValue {k: k, d: d})
+    }
+
+    fn is_ready(&self) -> bool {
+        self.d.is_ready()
+    }
+
+    fn warmup_period(&self) -> usize {
+        self.k_period + self.d_period - 1
+    }
+
+    fn reset(&mut self) {
+        self.highs.clear();
+        self.lows.clear();
+        self.d.reset();
+    }
+}
+
+#[test]
+fn stochastic_matches_batch() {
+    let candles = test_candles();
+    let (kp, dp) = (14, 3);
+    let ks: Vec<f64> = (kp - 1..candles.len()).map(|i| {
+        let window = &candles[i + 1 - kp..i + 1];
+        let high = window.iter().map(|c| c.high).fold(::std::f64::MIN, f64::max);
+        let low = window.iter().map(|c| c.low).fold(::std::f64::MAX, f64::min);
+        100.0 * (candles[i].close - low) / (high - low)
+    }).collect();
+
+    let mut stoch = Stochastic::new(kp, dp);
+    assert_eq!(stoch.warmup_period(), 16);
+    for (i, c) in candles.iter().enumerate() {
+        let res = stoch.update(*c);
+        if i + 1 < stoch.warmup_period() {
+            assert_eq!(res, None);
+            continue;
+        }
+
+        let j = i + 1 - kp;
+        let val = res.unwrap();
+        assert_close(val.k, ks[j]);
+        assert_close(val.d, ks[j + 1 - dp..j + 1].iter().sum::<f64>() / dp as f64);
+        assert!(val.k >= 0.0 && val.k <= 100.0);
+    }
+}
@@ -0,0 +1,66 @@
+//! Volume-weighted average price.
+
+use super::*;
+
+/// Cumulative volume-weighted average of the typical price of each candle.  Call `reset()` at the
+/// start of each session to start a new average.
+#[derive(Clone, Debug)]
+pub struct Vwap {
+    price_volume: f64,
+    volume: f64,
+}
+
+impl Vwap {
+    pub fn new() -> Vwap {
+        Vwap {
+            price_volume: 0.0,
+            volume: 0.0,
+        }
+    }
+}
+
+impl Indicator for Vwap {
+    type Input = Candle;
+    type Output = f64;
+
+    fn update(&mut self, c: Candle) -> Option<f64> {
+        self.price_volume += c.typical_price() * c.volume;
+        self.volume += c.volume;
+
+        if self.volume == 0.0 {
+            None
+        } else {
+            Some(self.price_volume / self.volume)
+        }
+    }
+
+    fn is_ready(&self) -> bool {
+        self.volume != 0.0
+    }
+
+    fn warmup_period(&self) -> usize {
+        1
+    }
+
+    fn reset(&mut self) {
+        self.price_volume = 0.0;
+        self.volume = 0.0;
+    }
+}
+
+#[test]
+fn vwap_matches_batch() {
+    let candles = test_candles();
+    let mut vwap = Vwap::new();
+    for (i, c) in candles.iter().enumerate() {
+        let res = vwap.update(*c).unwrap();
+        let seen = &candles[..i + 1];
+        let pv: f64 = seen.iter().map(|c| c.typical_price() * c.volume).sum();
+        let v: f64 = seen.iter().map(|c| c.volume).sum();
+        assert_close(res, pv / v);
+    }
+
+    vwap.reset();
+    assert!(!vwap.is_ready());
+    assert_close(vwap.update(candles[5]).unwrap(), candles[5].typical_price());
+}
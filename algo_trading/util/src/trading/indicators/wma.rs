@@ -0,0 +1,95 @@
+//! Linearly weighted moving average.
+
+use super::*;
+
+/// Moving average where the most recent value has a weight of `period`, the one before it
+/// `period - 1`, and so on down to a weight of 1 for the oldest value in the window.
+#[derive(Clone, Debug)]
+pub struct Wma {
+    period: usize,
+    window: RollingWindow,
+    /// plain sum of the values in the window
+    sum: f64,
+    /// weighted sum of the values in the window
+    numerator: f64,
+    denominator: f64,
+}
+
+impl Wma {
+    pub fn new(period: usize) -> Wma {
+        Wma {
+            period: period,
+            window: RollingWindow::new(period),
+            sum: 0.0,
+            numerator: 0.0,
+            denominator: (period * (period + 1)) as f64 / 2.0,
+        }
+    }
+}
+
+impl Indicator for Wma {
+    type Input = f64;
+    type Output = f64;
+
+    fn update(&mut self, x: f64) -> Option<f64> {
+        let was_full = self.window.is_full();
+        let evicted = self.window.push(x);
+
+        if was_full {
+            // every value already in the window loses one unit of weight, the oldest falls out
+            // entirely and the new one comes in with the full weight.
+            self.numerator += self.period as f64 * x - self.sum;
+            self.sum += x - evicted.unwrap();
+        } else {
+            // while filling the window, the new value's weight is its position in the window
+            self.sum += x;
+            self.numerator += self.window.len() as f64 * x;
+        }
+
+        if self.window.resync_due() {
+            self.sum = self.window.iter().sum();
+            self.numerator = self.window.iter().enumerate().map(|(w, val)| (w + 1) as f64 * val).sum();
+        }
+
+        if self.window.is_full() {
+            Some(self.numerator / self.denominator)
+        } else {
+            None
+        }
+    }
+
+    fn is_ready(&self) -> bool {
+        self.window.is_full()
+    }
+
+    fn warmup_period(&self) -> usize {
+        self.period
+    }
+
+    fn reset(&mut self) {
+        self.window.clear();
+        self.sum = 0.0;
+        self.numerator = 0.0;
+    }
+}
+
+#[test]
+fn wma_matches_batch() {
+    let series = test_series();
+    let period = 8;
+    let mut wma = Wma::new(period);
+    for (i, &x) in series.iter().enumerate() {
+        let res = wma.update(x);
+        if i + 1 < period {
+            assert_eq!(res, None);
+            continue;
+        }
+
+        let window = &series[i + 1 - period..i + 1];
+        let mut num = 0.0;
+        for (w, val) in window.iter().enumerate() {
+            num += (w + 1) as f64 * val;
+        }
+        assert_close(res.unwrap(), num / 36.0);
+    }
+}
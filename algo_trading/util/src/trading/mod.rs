@@ -2,8 +2,14 @@
 //! used for interacting with brokers and backtesting.
 
 pub mod tick;
+pub mod bar;
 pub mod broker;
 pub mod indicators;
 pub mod trading_condition;
+pub mod condition_lang;
 pub mod datafield;
 pub mod objects;
+pub mod performance;
+pub mod monte_carlo;
+pub mod synthetic;
+pub mod bootstrap;
@@ -0,0 +1,347 @@
+//! Monte Carlo analysis of the trades made during a backtest.  A single backtest is only one of the many paths
+//! that a strategy could have taken, so the trade list is resampled many times (reordered, drawn with replacement,
+//! thinned out, and hit with extra slippage) to estimate how much of the result was down to luck.
+//!
+//! Every simulation gets its own ISAAC-64 PRNG derived from the seed and the index of the simulation, so results are
+//! reproducible.
+
+use rand::{Rng, SeedableRng, Isaac64Rng};
+
+use trading::performance::{Trade, max_drawdown};
+
+/// The percentiles included in every `Distribution`.
+const PERCENTILES: &'static [f64] = &[1., 5., 10., 25., 50., 75., 90., 95., 99.];
+
+/// How the trade list is resampled in each simulation.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Resampling {
+    /// Keep the trades in their original order; only useful in combination with skipping or slippage
+    Original,
+    /// Randomly reorder the trades
+    Shuffle,
+    /// Draw as many trades as the original list contains with replacement
+    Bootstrap,
+}
+
+/// Settings for a Monte Carlo analysis.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MonteCarloSettings {
+    /// How many simulated trade sequences to create
+    pub simulations: usize,
+    pub seed: u64,
+    pub resampling: Resampling,
+    /// Probability that each trade is left out of a simulation
+    pub skip_probability: f64,
+    /// Maximum additional slippage in price units applied against each fill; the actual slippage of each
+    /// fill is chosen uniformly between 0 and this.
+    pub max_slippage: f64,
+    /// An account is considered ruined if its balance ever falls to this fraction of the starting balance
+    pub ruin_level: f64,
+    /// Confidence level of the reported intervals, for example 0.95
+    pub confidence: f64,
+}
+
+impl MonteCarloSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.simulations == 0 {
+            return Err(String::from("At least one simulation must be run."));
+        }
+        if self.skip_probability < 0. || self.skip_probability >= 1. {
+            return Err(String::from("The skip probability must be at least 0 and less than 1."));
+        }
+        if self.max_slippage < 0. || self.ruin_level < 0. {
+            return Err(String::from("The slippage and ruin level can't be negative."));
+        }
+        if self.confidence <= 0. || self.confidence >= 1. {
+            return Err(String::from("The confidence level must be between 0 and 1."));
+        }
+
+        Ok(())
+    }
+}
+
+/// Summary of the values that a statistic took over all simulations.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Distribution {
+    pub mean: f64,
+    pub std_dev: f64,
+    pub min: f64,
+    pub max: f64,
+    /// `(percentile, value)` pairs
+    pub percentiles: Vec<(f64, f64)>,
+    /// Lower bound of the central confidence interval
+    pub lower: f64,
+    /// Upper bound of the central confidence interval
+    pub upper: f64,
+}
+
+impl Distribution {
+    /// Summarizes a list of values.  The confidence interval contains the central `confidence` fraction of them.
+    /// Returns an error if there are no values or any of them is NaN, since they can't be ordered.
+    pub fn from_values(values: &[f64], confidence: f64) -> Result<Distribution, String> {
+        if values.is_empty() {
+            return Err(String::from("Can't summarize an empty list of values."));
+        }
+        if values.iter().any(|v| v.is_nan()) {
+            return Err(String::from("Can't summarize values that include NaN."));
+        }
+        let mut sorted = values.to_vec();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let n = sorted.len() as f64;
+        let mean = sorted.iter().sum::<f64>() / n;
+        let variance = if sorted.len() > 1 {
+            sorted.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.)
+        } else {
+            0.
+        };
+        let tail = (1. - confidence) / 2. * 100.;
+
+        Ok(Distribution {
+            mean: mean,
+            std_dev: variance.sqrt(),
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            percentiles: PERCENTILES.iter().map(|&p| (p, percentile(&sorted, p))).collect(),
+            lower: percentile(&sorted, tail),
+            upper: percentile(&sorted, 100. - tail),
+        })
+    }
+}
+
+/// Returns the `p`th percentile of sorted values, interpolating linearly between neighboring values.
+pub fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = p / 100. * (sorted.len() - 1) as f64;
+    let below = rank.floor() as usize;
+    let above = rank.ceil() as usize;
+    sorted[below] + (sorted[above] - sorted[below]) * (rank - below as f64)
+}
+
+/// The results of a Monte Carlo analysis.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MonteCarloReport {
+    pub settings: MonteCarloSettings,
+    pub starting_balance: f64,
+    /// The final balance of the original, unaltered trade sequence
+    pub original_final_equity: f64,
+    /// The largest drawdown of the original, unaltered trade sequence as a fraction of the peak
+    pub original_max_drawdown: f64,
+    pub final_equity: Distribution,
+    pub max_drawdown: Distribution,
+    /// Fraction of simulations in which the account was ruined
+    pub risk_of_ruin: f64,
+    /// Wilson score confidence interval of the risk of ruin as `(lower, upper)`
+    pub risk_of_ruin_interval: (f64, f64),
+}
+
+/// The outcome of a single simulated sequence of trades.
+struct Simulation {
+    final_equity: f64,
+    max_drawdown: f64,
+    ruined: bool,
+}
+
+/// Returns the balance after each of the profits is realized, starting with the starting balance.
+fn balances(pnls: &[f64], starting_balance: f64) -> Vec<f64> {
+    let mut balances = Vec::with_capacity(pnls.len() + 1);
+    balances.push(starting_balance);
+    let mut balance = starting_balance;
+    for pnl in pnls {
+        balance += *pnl;
+        balances.push(balance);
+    }
+
+    balances
+}
+
+/// Creates one randomized sequence of trade profits out of the original trades.
+fn resample<R: Rng>(trades: &[Trade], settings: &MonteCarloSettings, rng: &mut R) -> Vec<f64> {
+    let mut sequence: Vec<&Trade> = match settings.resampling {
+        Resampling::Original => trades.iter().collect(),
+        Resampling::Shuffle => {
+            let mut shuffled: Vec<&Trade> = trades.iter().collect();
+            rng.shuffle(&mut shuffled);
+            shuffled
+        },
+        Resampling::Bootstrap => (0..trades.len()).map(|_| &trades[rng.gen_range(0, trades.len())]).collect(),
+    };
+
+    if settings.skip_probability > 0. {
+        sequence.retain(|_| rng.gen::<f64>() >= settings.skip_probability);
+    }
+
+    sequence.iter().map(|trade| {
+        let mut pnl = trade.pnl();
+        if settings.max_slippage > 0. {
+            // both the entry and exit fill are made worse
+            let slippage = (rng.gen::<f64>() + rng.gen::<f64>()) * settings.max_slippage;
+            pnl -= slippage * trade.size as f64;
+        }
+        pnl
+    }).collect()
+}
+
+fn simulate(trades: &[Trade], starting_balance: f64, settings: &MonteCarloSettings, index: usize) -> Simulation {
+    let mut rng = Isaac64Rng::from_seed(&[settings.seed, index as u64][..]);
+    let pnls = resample(trades, settings, &mut rng);
+    let balances = balances(&pnls, starting_balance);
+    let ruin_balance = starting_balance * settings.ruin_level;
+
+    Simulation {
+        final_equity: *balances.last().unwrap(),
+        max_drawdown: max_drawdown(&balances),
+        ruined: balances.iter().any(|&b| b <= ruin_balance),
+    }
+}
+
+/// Returns the value below which the standard normal distribution falls with probability `p`.  Uses the rational
+/// approximation 26.2.23 from Abramowitz and Stegun, which has an absolute error below 4.5e-4.
+fn normal_quantile(p: f64) -> f64 {
+    if p > 0.5 {
+        return -normal_quantile(1. - p);
+    }
+
+    let t = (-2. * p.ln()).sqrt();
+    let num = 2.515517 + 0.802853 * t + 0.010328 * t * t;
+    let den = 1. + 1.432788 * t + 0.189269 * t * t + 0.001308 * t * t * t;
+    -(t - num / den)
+}
+
+/// Returns the Wilson score interval of a proportion observed in `n` trials.
+fn wilson_interval(proportion: f64, n: usize, confidence: f64) -> (f64, f64) {
+    let n = n as f64;
+    let z = normal_quantile(1. - (1. - confidence) / 2.);
+    let z2 = z * z;
+    let center = (proportion + z2 / (2. * n)) / (1. + z2 / n);
+    let half_width = z / (1. + z2 / n) * (proportion * (1. - proportion) / n + z2 / (4. * n * n)).sqrt();
+
+    ((center - half_width).max(0.), (center + half_width).min(1.))
+}
+
+/// Everything needed to run a Monte Carlo analysis of a completed backtest.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MonteCarloDefinition {
+    /// The trades made during the backtest, such as the `trades` of a `SimBacktestResult`
+    pub trades: Vec<Trade>,
+    pub starting_balance: f64,
+    pub settings: MonteCarloSettings,
+}
+
+impl MonteCarloDefinition {
+    pub fn run(&self) -> Result<MonteCarloReport, String> {
+        run_monte_carlo(&self.trades, self.starting_balance, &self.settings)
+    }
+}
+
+/// Runs a Monte Carlo analysis of a list of trades sorted by exit time.
+pub fn run_monte_carlo(
+    trades: &[Trade], starting_balance: f64, settings: &MonteCarloSettings
+) -> Result<MonteCarloReport, String> {
+    try!(settings.validate());
+    if trades.is_empty() {
+        return Err(String::from("There are no trades to resample."));
+    }
+
+    let sims: Vec<Simulation> = (0..settings.simulations)
+        .map(|i| simulate(trades, starting_balance, settings, i))
+        .collect();
+    let final_equities: Vec<f64> = sims.iter().map(|s| s.final_equity).collect();
+    let drawdowns: Vec<f64> = sims.iter().map(|s| s.max_drawdown).collect();
+    let risk_of_ruin = sims.iter().filter(|s| s.ruined).count() as f64 / sims.len() as f64;
+
+    let original_pnls: Vec<f64> = trades.iter().map(|t| t.pnl()).collect();
+    let original_balances = balances(&original_pnls, starting_balance);
+
+    Ok(MonteCarloReport {
+        settings: settings.clone(),
+        starting_balance: starting_balance,
+        original_final_equity: *original_balances.last().unwrap(),
+        original_max_drawdown: max_drawdown(&original_balances),
+        final_equity: try!(Distribution::from_values(&final_equities, settings.confidence)),
+        max_drawdown: try!(Distribution::from_values(&drawdowns, settings.confidence)),
+        risk_of_ruin: risk_of_ruin,
+        risk_of_ruin_interval: wilson_interval(risk_of_ruin, sims.len(), settings.confidence),
+    })
+}
+
+#[cfg(test)]
+fn test_trades() -> Vec<Trade> {
+    [30isize, -20, 50, -40, 10, -10, 60, -30].iter().enumerate().map(|(i, &pnl)| Trade {
+        symbol_id: 0,
+        long: true,
+        size: 1,
+        entry_price: 1000,
+        exit_price: (1000 + pnl) as usize,
+        entry_time: i as u64 * 10,
+        exit_time: i as u64 * 10 + 5,
+    }).collect()
+}
+
+#[cfg(test)]
+fn test_settings(resampling: Resampling) -> MonteCarloSettings {
+    MonteCarloSettings {
+        simulations: 500,
+        seed: 7,
+        resampling: resampling,
+        skip_probability: 0.,
+        max_slippage: 0.,
+        ruin_level: 0.9,
+        confidence: 0.9,
+    }
+}
+
+#[test]
+fn distribution_and_intervals() {
+    let values: Vec<f64> = (0..101).map(|i| i as f64).collect();
+    let dist = Distribution::from_values(&values, 0.9).unwrap();
+    assert_eq!(dist.mean, 50.);
+    assert_eq!((dist.min, dist.max), (0., 100.));
+    assert!((dist.lower - 5.).abs() < 1e-9 && (dist.upper - 95.).abs() < 1e-9);
+    assert_eq!(percentile(&[1., 2.], 25.), 1.25);
+    assert!(Distribution::from_values(&[1., ::std::f64::NAN, 2.], 0.9).is_err());
+    assert!(Distribution::from_values(&[], 0.9).is_err());
+
+    assert!((normal_quantile(0.975) - 1.96).abs() < 1e-3);
+    let (lower, upper) = wilson_interval(0., 100, 0.95);
+    assert_eq!(lower, 0.);
+    assert!(upper > 0. && upper < 0.05);
+}
+
+#[test]
+fn shuffling_preserves_final_equity() {
+    let trades = test_trades();
+    let report = run_monte_carlo(&trades, 200., &test_settings(Resampling::Shuffle)).unwrap();
+    assert_eq!(report.original_final_equity, 250.);
+    // reordering trades changes the path but never the destination
+    assert_eq!(report.final_equity.min, 250.);
+    assert_eq!(report.final_equity.max, 250.);
+    assert!(report.max_drawdown.max > report.max_drawdown.min);
+    assert!(report.risk_of_ruin > 0. && report.risk_of_ruin < 1.);
+    let (lower, upper) = report.risk_of_ruin_interval;
+    assert!(lower <= report.risk_of_ruin && report.risk_of_ruin <= upper);
+
+    assert_eq!(report, run_monte_carlo(&trades, 200., &test_settings(Resampling::Shuffle)).unwrap());
+}
+
+#[test]
+fn bootstrap_skipping_and_slippage() {
+    let trades = test_trades();
+    let bootstrap = run_monte_carlo(&trades, 200., &test_settings(Resampling::Bootstrap)).unwrap();
+    assert!(bootstrap.final_equity.min < 250. && bootstrap.final_equity.max > 250.);
+    assert!(bootstrap.final_equity.lower <= bootstrap.final_equity.upper);
+
+    let mut settings = test_settings(Resampling::Original);
+    settings.max_slippage = 2.;
+    let slipped = run_monte_carlo(&trades, 200., &settings).unwrap();
+    // every fill can only get worse, by at most 4 per trade
+    assert!(slipped.final_equity.max < 250. && slipped.final_equity.min >= 250. - 4. * 8.);
+
+    settings.max_slippage = 0.;
+    settings.skip_probability = 0.5;
+    let skipped = run_monte_carlo(&trades, 200., &settings).unwrap();
+    assert!(skipped.final_equity.std_dev > 0.);
+
+    settings.skip_probability = 1.;
+    assert!(run_monte_carlo(&trades, 200., &settings).is_err());
+    assert!(run_monte_carlo(&[], 200., &test_settings(Resampling::Shuffle)).is_err());
+}
@@ -0,0 +1,235 @@
+//! Statistics about the performance of a strategy calculated from the trades that it made during a backtest.
+//!
+//! Profits are measured in price units multiplied by position size, and the same units are used for the starting
+//! balance that returns are calculated against.
+
+use std::f64;
+
+use trading::objects::Position;
+
+/// A round-trip trade: a position that was opened and then fully closed.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Trade {
+    pub symbol_id: usize,
+    pub long: bool,
+    pub size: usize,
+    pub entry_price: usize,
+    pub exit_price: usize,
+    pub entry_time: u64,
+    pub exit_time: u64,
+}
+
+impl Trade {
+    /// Creates a `Trade` out of a closed `Position`.  Returns `None` if the position was never opened or closed.
+    pub fn from_position(pos: &Position) -> Option<Trade> {
+        match (pos.execution_price, pos.exit_price, pos.execution_time, pos.exit_time) {
+            (Some(entry_price), Some(exit_price), Some(entry_time), Some(exit_time)) => Some(Trade {
+                symbol_id: pos.symbol_id,
+                long: pos.long,
+                size: pos.size,
+                entry_price: entry_price,
+                exit_price: exit_price,
+                entry_time: entry_time,
+                exit_time: exit_time,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Returns the profit (or loss if negative) of the trade.
+    pub fn pnl(&self) -> f64 {
+        let diff = self.exit_price as f64 - self.entry_price as f64;
+        let diff = if self.long { diff } else { -diff };
+        diff * self.size as f64
+    }
+}
+
+/// Sorts trades by the time they were closed, which is the order in which they affect the balance.
+pub fn sort_trades(trades: &mut Vec<Trade>) {
+    trades.sort_by(|a, b| a.exit_time.cmp(&b.exit_time).then(a.entry_time.cmp(&b.entry_time)));
+}
+
+/// Returns the balance after each trade is closed as `(timestamp, balance)` pairs, starting with the starting
+/// balance at the time the first trade was opened.  Trades are expected to be sorted by exit time.
+pub fn equity_curve(trades: &[Trade], starting_balance: f64) -> Vec<(u64, f64)> {
+    let mut curve = Vec::with_capacity(trades.len() + 1);
+    let start_time = trades.iter().map(|t| t.entry_time).min().unwrap_or(0);
+    curve.push((start_time, starting_balance));
+
+    let mut balance = starting_balance;
+    for trade in trades {
+        balance += trade.pnl();
+        curve.push((trade.exit_time, balance));
+    }
+
+    curve
+}
+
+/// Returns the largest drop from a peak as a fraction of that peak.
+pub fn max_drawdown(equity: &[f64]) -> f64 {
+    let mut peak = f64::MIN;
+    let mut max_dd = 0.;
+    for &val in equity {
+        if val > peak {
+            peak = val;
+        }
+        if peak > 0. {
+            let dd = (peak - val) / peak;
+            if dd > max_dd {
+                max_dd = dd;
+            }
+        }
+    }
+
+    max_dd
+}
+
+/// Returns the mean of the returns divided by their sample standard deviation.  The ratio isn't annualized since
+/// returns are per-trade rather than per-period.  Returns 0 if there are too few returns or they don't vary.
+pub fn sharpe_ratio(returns: &[f64]) -> f64 {
+    if returns.len() < 2 {
+        return 0.;
+    }
+
+    let n = returns.len() as f64;
+    let mean = returns.iter().sum::<f64>() / n;
+    let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.);
+    let stddev = variance.sqrt();
+    if stddev == 0. { 0. } else { mean / stddev }
+}
+
+/// Returns gross profit divided by gross loss.
+pub fn profit_factor(trades: &[Trade]) -> f64 {
+    let gross_profit: f64 = trades.iter().map(|t| t.pnl()).filter(|&p| p > 0.).sum();
+    let gross_loss: f64 = trades.iter().map(|t| t.pnl()).filter(|&p| p < 0.).map(|p| -p).sum();
+    safe_ratio(gross_profit, gross_loss)
+}
+
+/// Divides `num` by `den`, returning `f64::MAX` for positive numerators over a zero denominator rather than
+/// infinity (which can't be serialized into JSON).
+fn safe_ratio(num: f64, den: f64) -> f64 {
+    if den != 0. {
+        num / den
+    } else if num > 0. {
+        f64::MAX
+    } else {
+        0.
+    }
+}
+
+/// Summary statistics about a backtest.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PerformanceReport {
+    pub starting_balance: f64,
+    pub final_balance: f64,
+    /// Change in balance as a fraction of the starting balance
+    pub total_return: f64,
+    /// Largest peak-to-trough drop in the balance as a fraction of the peak
+    pub max_drawdown: f64,
+    /// Sharpe ratio of the per-trade returns
+    pub sharpe: f64,
+    pub profit_factor: f64,
+    pub return_over_drawdown: f64,
+    pub trade_count: usize,
+    /// Fraction of trades that were profitable
+    pub win_rate: f64,
+}
+
+impl PerformanceReport {
+    /// Calculates the statistics for a list of trades sorted by exit time.
+    pub fn from_trades(trades: &[Trade], starting_balance: f64) -> PerformanceReport {
+        let curve = equity_curve(trades, starting_balance);
+        let balances: Vec<f64> = curve.iter().map(|&(_, b)| b).collect();
+        let final_balance = *balances.last().unwrap();
+
+        // each trade's return relative to the balance before it was closed
+        let returns: Vec<f64> = balances.windows(2)
+            .map(|w| if w[0] != 0. { (w[1] - w[0]) / w[0] } else { 0. })
+            .collect();
+
+        let total_return = if starting_balance != 0. { (final_balance - starting_balance) / starting_balance } else { 0. };
+        let max_dd = max_drawdown(&balances);
+        let wins = trades.iter().filter(|t| t.pnl() > 0.).count();
+
+        PerformanceReport {
+            starting_balance: starting_balance,
+            final_balance: final_balance,
+            total_return: total_return,
+            max_drawdown: max_dd,
+            sharpe: sharpe_ratio(&returns),
+            profit_factor: profit_factor(trades),
+            return_over_drawdown: safe_ratio(total_return, max_dd),
+            trade_count: trades.len(),
+            win_rate: if trades.is_empty() { 0. } else { wins as f64 / trades.len() as f64 },
+        }
+    }
+}
+
+/// The value that an optimization tries to maximize.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Objective {
+    Sharpe,
+    ReturnOverDrawdown,
+    ProfitFactor,
+    TotalReturn,
+}
+
+impl Objective {
+    /// Returns the score of a backtest's results; higher is better.
+    pub fn score(&self, report: &PerformanceReport) -> f64 {
+        match *self {
+            Objective::Sharpe => report.sharpe,
+            Objective::ReturnOverDrawdown => report.return_over_drawdown,
+            Objective::ProfitFactor => report.profit_factor,
+            Objective::TotalReturn => report.total_return,
+        }
+    }
+}
+
+#[cfg(test)]
+fn test_trade(long: bool, entry_price: usize, exit_price: usize, exit_time: u64) -> Trade {
+    Trade {
+        symbol_id: 0,
+        long: long,
+        size: 10,
+        entry_price: entry_price,
+        exit_price: exit_price,
+        entry_time: exit_time - 1,
+        exit_time: exit_time,
+    }
+}
+
+#[test]
+fn trade_pnl() {
+    assert_eq!(test_trade(true, 100, 105, 1).pnl(), 50.);
+    assert_eq!(test_trade(false, 100, 105, 1).pnl(), -50.);
+    assert_eq!(test_trade(false, 105, 100, 1).pnl(), 50.);
+}
+
+#[test]
+fn drawdown_and_profit_factor() {
+    assert_eq!(max_drawdown(&[100., 120., 90., 130., 117.]), 0.25);
+    assert_eq!(max_drawdown(&[100., 110., 120.]), 0.);
+
+    let trades = vec![test_trade(true, 100, 110, 1), test_trade(true, 100, 95, 2), test_trade(false, 100, 95, 3)];
+    assert_eq!(profit_factor(&trades), 3.);
+    assert_eq!(profit_factor(&trades[0..1]), f64::MAX);
+    assert_eq!(profit_factor(&[]), 0.);
+}
+
+#[test]
+fn report_from_trades() {
+    let trades = vec![test_trade(true, 100, 110, 1), test_trade(true, 100, 95, 2), test_trade(false, 100, 95, 3)];
+    let report = PerformanceReport::from_trades(&trades, 1000.);
+    assert_eq!(report.final_balance, 1100.);
+    assert!((report.total_return - 0.1).abs() < 1e-9);
+    assert!((report.max_drawdown - 50. / 1100.).abs() < 1e-9);
+    assert_eq!(report.trade_count, 3);
+    assert!((report.win_rate - 2. / 3.).abs() < 1e-9);
+    assert!(report.sharpe > 0.);
+    assert_eq!(Objective::ProfitFactor.score(&report), 3.);
+
+    let empty = PerformanceReport::from_trades(&[], 1000.);
+    assert_eq!(empty.final_balance, 1000.);
+    assert_eq!(empty.sharpe, 0.);
+}
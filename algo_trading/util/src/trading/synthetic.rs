@@ -0,0 +1,470 @@
+//! Seeded synthetic price data for testing strategies.  A price process (geometric Brownian motion, Ornstein-Uhlenbeck
+//! mean reversion, GARCH(1,1), Merton jump diffusion, or regime switching) is sampled at the times produced by an
+//! arrival process and turned into bid/ask quotes by a spread model.
+//!
+//! All randomness comes from a single ISAAC-64 PRNG created from the seed, so the same settings always produce the
+//! same ticks.  The algorithm is named explicitly since `StdRng` may change between versions of `rand`.  Drifts,
+//! volatilities, and rates are annualized unless noted otherwise.
+
+use rand::{Rng, SeedableRng, Isaac64Rng};
+use rand::distributions::normal::StandardNormal;
+use rand::distributions::exponential::Exp1;
+
+use trading::tick::Tick;
+
+/// Length of a year in milliseconds, used to convert annualized parameters to the time between ticks
+pub const YEAR_MS: f64 = 365. * 24. * 60. * 60. * 1000.;
+/// Means above which Poisson samples are drawn from a normal approximation
+const POISSON_NORMAL_MEAN: f64 = 30.;
+
+fn default_pip_scale() -> f64 { 1. }
+
+/// How the price evolves between ticks.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum PriceModel {
+    /// Geometric Brownian motion
+    Gbm{drift: f64, volatility: f64},
+    /// Mean reversion towards `mean` at the speed `reversion`.  The volatility is in price units rather than a
+    /// fraction of the price.
+    OrnsteinUhlenbeck{mean: f64, reversion: f64, volatility: f64},
+    /// GARCH(1,1) volatility clustering of the log returns between ticks.  Unlike the other models the parameters are
+    /// per tick: the variance of the next return is `omega + alpha * last_shock^2 + beta * last_variance`, and
+    /// `alpha + beta` must be less than 1.
+    Garch{drift: f64, omega: f64, alpha: f64, beta: f64},
+    /// Geometric Brownian motion plus jumps arriving at `jump_intensity` per year whose log sizes are normally
+    /// distributed.  The drift is compensated so that jumps don't change the expected return.
+    JumpDiffusion{drift: f64, volatility: f64, jump_intensity: f64, jump_mean: f64, jump_std: f64},
+    /// Geometric Brownian motion whose parameters switch between regimes.  The process starts in the first regime
+    /// and moves to one of the others, chosen uniformly, after an exponentially distributed time.
+    RegimeSwitching{regimes: Vec<Regime>},
+}
+
+/// One of the states of `PriceModel::RegimeSwitching`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Regime {
+    pub drift: f64,
+    pub volatility: f64,
+    /// Average time spent in the regime before switching, in milliseconds
+    pub mean_duration_ms: f64,
+}
+
+/// How wide the quotes around the price are, in pips.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SpreadModel {
+    Fixed{spread: usize},
+    /// Chosen uniformly for each tick from `min` to `max` inclusive
+    Uniform{min: usize, max: usize},
+    /// `base` widened by `multiplier` times the size of the last move in pips, so spreads blow out when the price
+    /// moves quickly
+    Volatility{base: usize, multiplier: f64},
+}
+
+/// When ticks arrive.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ArrivalProcess {
+    Fixed{interval_ms: u64},
+    /// `rate` ticks per second on average, with exponentially distributed gaps
+    Poisson{rate: f64},
+    /// A self-exciting process where every tick raises the rate by `excitation` ticks per second, decaying back
+    /// towards `base_rate` at `decay` per second.  `excitation` must be less than `decay`.
+    Hawkes{base_rate: f64, excitation: f64, decay: f64},
+}
+
+/// Settings for a stream of synthetic ticks.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SyntheticConfig {
+    pub seed: u64,
+    /// Timestamp of the first tick
+    pub start_time: u64,
+    /// Stop after this many ticks; the stream never ends if not set.
+    #[serde(default)]
+    pub tick_count: Option<usize>,
+    pub initial_price: f64,
+    /// Prices are multiplied by this and rounded to get pips, so `100000` converts `1.08617` into `108617`.
+    #[serde(default = "default_pip_scale")]
+    pub pip_scale: f64,
+    pub model: PriceModel,
+    pub spread: SpreadModel,
+    pub arrivals: ArrivalProcess,
+}
+
+impl SyntheticConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.initial_price > 0.) || !(self.pip_scale > 0.) {
+            return Err(String::from("The initial price and pip scale must be positive."));
+        }
+
+        match self.model {
+            PriceModel::Gbm{volatility, ..} if volatility < 0. => {
+                return Err(String::from("The volatility can't be negative."));
+            },
+            PriceModel::OrnsteinUhlenbeck{reversion, volatility, ..} if reversion <= 0. || volatility < 0. => {
+                return Err(String::from("The reversion speed must be positive and the volatility can't be negative."));
+            },
+            PriceModel::Garch{omega, alpha, beta, ..} if omega <= 0. || alpha < 0. || beta < 0. || alpha + beta >= 1. => {
+                return Err(String::from("GARCH parameters must satisfy omega > 0, alpha, beta >= 0, and alpha + beta < 1."));
+            },
+            PriceModel::JumpDiffusion{volatility, jump_intensity, jump_std, ..}
+                if volatility < 0. || jump_intensity < 0. || jump_std < 0. => {
+                return Err(String::from("The volatility, jump intensity, and jump size deviation can't be negative."));
+            },
+            PriceModel::RegimeSwitching{ref regimes} => {
+                if regimes.is_empty() {
+                    return Err(String::from("At least one regime is required."));
+                }
+                if regimes.iter().any(|r| r.volatility < 0. || !(r.mean_duration_ms > 0.)) {
+                    return Err(String::from("Regime volatilities can't be negative and their durations must be positive."));
+                }
+            },
+            _ => (),
+        }
+
+        match self.spread {
+            SpreadModel::Uniform{min, max} if min > max => {
+                return Err(String::from("The minimum spread can't be larger than the maximum."));
+            },
+            SpreadModel::Volatility{multiplier, ..} if multiplier < 0. => {
+                return Err(String::from("The spread multiplier can't be negative."));
+            },
+            _ => (),
+        }
+
+        match self.arrivals {
+            ArrivalProcess::Fixed{interval_ms: 0} => {
+                return Err(String::from("The tick interval must be positive."));
+            },
+            ArrivalProcess::Poisson{rate} if !(rate > 0.) => {
+                return Err(String::from("The tick rate must be positive."));
+            },
+            ArrivalProcess::Hawkes{base_rate, excitation, decay} if !(base_rate > 0.) || excitation < 0. || excitation >= decay => {
+                return Err(String::from("Hawkes parameters must satisfy base_rate > 0 and 0 <= excitation < decay."));
+            },
+            _ => (),
+        }
+
+        Ok(())
+    }
+}
+
+/// The state of the price process that changes between ticks
+enum ModelState {
+    None,
+    /// The variance of the next return
+    Garch{variance: f64},
+    /// Index of the current regime and the time left in it
+    Regime{index: usize, remaining_ms: f64},
+}
+
+/// An endless (or `tick_count` long) iterator over synthetic ticks.
+pub struct SyntheticTicks {
+    config: SyntheticConfig,
+    rng: Isaac64Rng,
+    /// Continuous time of the last tick in milliseconds
+    time: f64,
+    /// Excess rate of a Hawkes process above the base rate, as of `time`
+    excitation: f64,
+    price: f64,
+    last_move: f64,
+    state: ModelState,
+    emitted: usize,
+    /// The tick found by a seek, returned before any new ones are generated
+    pending: Option<Tick>,
+}
+
+impl SyntheticTicks {
+    pub fn new(config: SyntheticConfig) -> Result<SyntheticTicks, String> {
+        try!(config.validate());
+        let mut rng = Isaac64Rng::from_seed(&[config.seed][..]);
+        let state = match config.model {
+            PriceModel::Garch{omega, alpha, beta, ..} => ModelState::Garch{variance: omega / (1. - alpha - beta)},
+            PriceModel::RegimeSwitching{ref regimes} => {
+                ModelState::Regime{index: 0, remaining_ms: exp_sample(&mut rng) * regimes[0].mean_duration_ms}
+            },
+            _ => ModelState::None,
+        };
+
+        Ok(SyntheticTicks {
+            time: config.start_time as f64,
+            excitation: 0.,
+            price: config.initial_price,
+            last_move: 0.,
+            state: state,
+            emitted: 0,
+            pending: None,
+            rng: rng,
+            config: config,
+        })
+    }
+
+    /// Moves the stream to the first tick at or after `timestamp`.  Since the ticks are generated from the seed,
+    /// seeking backwards starts over from the beginning.
+    pub fn seek(&mut self, timestamp: u64) {
+        let past = match self.pending {
+            Some(t) => t.timestamp > timestamp,
+            None => self.emitted > 0 && self.time as u64 >= timestamp,
+        };
+        if past {
+            *self = SyntheticTicks::new(self.config.clone()).unwrap();
+        }
+
+        self.pending = None;
+        while let Some(t) = self.next() {
+            if t.timestamp >= timestamp {
+                self.pending = Some(t);
+                break;
+            }
+        }
+    }
+
+    /// Returns the number of milliseconds until the next tick.
+    fn next_interval(&mut self) -> f64 {
+        match self.config.arrivals {
+            ArrivalProcess::Fixed{interval_ms} => interval_ms as f64,
+            ArrivalProcess::Poisson{rate} => exp_sample(&mut self.rng) / rate * 1000.,
+            ArrivalProcess::Hawkes{base_rate, excitation, decay} => {
+                // Ogata's thinning: the rate only decays until the next tick, so the current rate bounds it
+                let mut elapsed = 0.;
+                loop {
+                    let bound = base_rate + self.excitation;
+                    let wait = exp_sample(&mut self.rng) / bound;
+                    elapsed += wait;
+                    self.excitation *= (-decay * wait).exp();
+                    if self.rng.gen::<f64>() * bound <= base_rate + self.excitation {
+                        self.excitation += excitation;
+                        return elapsed * 1000.;
+                    }
+                }
+            },
+        }
+    }
+
+    /// Moves the price forward by `dt_ms` milliseconds.
+    fn step_price(&mut self, dt_ms: f64) {
+        let dt = dt_ms / YEAR_MS;
+        let z = normal_sample(&mut self.rng);
+        let price = self.price;
+        self.price = match self.config.model {
+            PriceModel::Gbm{drift, volatility} => gbm_step(price, drift, volatility, dt, z),
+            PriceModel::OrnsteinUhlenbeck{mean, reversion, volatility} => {
+                let decay = (-reversion * dt).exp();
+                let std = volatility * ((1. - decay * decay) / (2. * reversion)).sqrt();
+                mean + (price - mean) * decay + std * z
+            },
+            PriceModel::Garch{drift, omega, alpha, beta} => {
+                let variance = match self.state {
+                    ModelState::Garch{variance} => variance,
+                    _ => unreachable!(),
+                };
+                let shock = variance.sqrt() * z;
+                self.state = ModelState::Garch{variance: omega + alpha * shock * shock + beta * variance};
+                price * (drift + shock).exp()
+            },
+            PriceModel::JumpDiffusion{drift, volatility, jump_intensity, jump_mean, jump_std} => {
+                let compensation = jump_intensity * ((jump_mean + jump_std * jump_std / 2.).exp() - 1.);
+                let mut log_jump = 0.;
+                for _ in 0..poisson_sample(&mut self.rng, jump_intensity * dt) {
+                    log_jump += jump_mean + jump_std * normal_sample(&mut self.rng);
+                }
+                gbm_step(price, drift - compensation, volatility, dt, z) * log_jump.exp()
+            },
+            PriceModel::RegimeSwitching{ref regimes} => {
+                let (mut index, mut remaining_ms) = match self.state {
+                    ModelState::Regime{index, remaining_ms} => (index, remaining_ms),
+                    _ => unreachable!(),
+                };
+                // the step is split between the regimes that it passes through
+                let mut price = price;
+                let mut left_ms = dt_ms;
+                while left_ms > 0. {
+                    let regime = &regimes[index];
+                    let part_ms = left_ms.min(remaining_ms);
+                    let z = if part_ms == dt_ms { z } else { normal_sample(&mut self.rng) };
+                    price = gbm_step(price, regime.drift, regime.volatility, part_ms / YEAR_MS, z);
+                    left_ms -= part_ms;
+                    remaining_ms -= part_ms;
+                    if remaining_ms <= 0. {
+                        if regimes.len() > 1 {
+                            let next = self.rng.gen_range(0, regimes.len() - 1);
+                            index = if next >= index { next + 1 } else { next };
+                        }
+                        remaining_ms = exp_sample(&mut self.rng) * regimes[index].mean_duration_ms;
+                    }
+                }
+                self.state = ModelState::Regime{index: index, remaining_ms: remaining_ms};
+                price
+            },
+        };
+
+        // keep the price positive even if a mean-reverting process overshoots zero
+        self.price = self.price.max(1. / self.config.pip_scale);
+        self.last_move = (self.price - price).abs() * self.config.pip_scale;
+    }
+
+    /// Quotes the current price.
+    fn quote(&mut self) -> Tick {
+        let spread = match self.config.spread {
+            SpreadModel::Fixed{spread} => spread,
+            SpreadModel::Uniform{min, max} => self.rng.gen_range(min, max + 1),
+            SpreadModel::Volatility{base, multiplier} => base + (multiplier * self.last_move).round() as usize,
+        };
+        let mid = self.price * self.config.pip_scale;
+        let bid = (mid - spread as f64 / 2.).round().max(0.) as usize;
+
+        Tick {
+            timestamp: self.time as u64,
+            bid: bid,
+            ask: bid + spread,
+        }
+    }
+}
+
+impl Iterator for SyntheticTicks {
+    type Item = Tick;
+
+    fn next(&mut self) -> Option<Tick> {
+        if let Some(t) = self.pending.take() {
+            return Some(t);
+        }
+        if self.config.tick_count.map(|n| self.emitted >= n).unwrap_or(false) {
+            return None;
+        }
+
+        // the first tick is at the start time with the initial price
+        if self.emitted > 0 {
+            let dt_ms = self.next_interval();
+            self.time += dt_ms;
+            self.step_price(dt_ms);
+        }
+        self.emitted += 1;
+
+        Some(self.quote())
+    }
+}
+
+fn gbm_step(price: f64, drift: f64, volatility: f64, dt: f64, z: f64) -> f64 {
+    price * ((drift - volatility * volatility / 2.) * dt + volatility * dt.sqrt() * z).exp()
+}
+
+fn normal_sample<R: Rng>(rng: &mut R) -> f64 {
+    let StandardNormal(z) = rng.gen::<StandardNormal>();
+    z
+}
+
+fn exp_sample<R: Rng>(rng: &mut R) -> f64 {
+    let Exp1(x) = rng.gen::<Exp1>();
+    x
+}
+
+/// Samples the number of events of a Poisson process with the given mean.  Small means use the multiplication
+/// method; larger ones would underflow `exp(-mean)` and take `mean` steps, so a normal approximation is used instead.
+fn poisson_sample<R: Rng>(rng: &mut R, mean: f64) -> usize {
+    if mean > POISSON_NORMAL_MEAN {
+        return (mean + mean.sqrt() * normal_sample(rng) + 0.5).max(0.) as usize;
+    }
+
+    let limit = (-mean).exp();
+    let mut product = rng.gen::<f64>();
+    let mut count = 0;
+    while product > limit {
+        product *= rng.gen::<f64>();
+        count += 1;
+    }
+
+    count
+}
+
+#[cfg(test)]
+fn test_config(model: PriceModel, arrivals: ArrivalProcess) -> SyntheticConfig {
+    SyntheticConfig {
+        seed: 42,
+        start_time: 1_000_000,
+        tick_count: Some(20_000),
+        initial_price: 1.1,
+        pip_scale: 100_000.,
+        model: model,
+        spread: SpreadModel::Uniform{min: 1, max: 3},
+        arrivals: arrivals,
+    }
+}
+
+#[test]
+fn synthetic_ticks_are_seeded() {
+    let config = test_config(PriceModel::Gbm{drift: 0., volatility: 0.1}, ArrivalProcess::Poisson{rate: 5.});
+    let a: Vec<Tick> = SyntheticTicks::new(config.clone()).unwrap().collect();
+    let b: Vec<Tick> = SyntheticTicks::new(config.clone()).unwrap().collect();
+    assert_eq!(a.len(), 20_000);
+    assert!(a == b);
+    assert_eq!(a[0].timestamp, 1_000_000);
+    assert!(a.windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+    assert!(a.iter().all(|t| t.ask - t.bid >= 1 && t.ask - t.bid <= 3));
+    // about 5 ticks per second
+    let seconds = (a[a.len() - 1].timestamp - a[0].timestamp) as f64 / 1000.;
+    assert!((a.len() as f64 / seconds - 5.).abs() < 0.25);
+
+    let mut other = config.clone();
+    other.seed = 43;
+    let c: Vec<Tick> = SyntheticTicks::new(other).unwrap().collect();
+    assert!(a != c);
+
+    // seeking backwards and forwards gives the same ticks as reading straight through
+    let mut ticks = SyntheticTicks::new(config).unwrap();
+    ticks.seek(a[5000].timestamp);
+    assert_eq!(ticks.next().unwrap().timestamp, a[5000].timestamp);
+    ticks.seek(a[100].timestamp);
+    let rest: Vec<Tick> = ticks.collect();
+    assert!(&rest[..] == &a[a.len() - rest.len()..]);
+    assert_eq!(rest[0].timestamp, a[100].timestamp);
+}
+
+#[test]
+fn synthetic_models() {
+    let fixed = ArrivalProcess::Fixed{interval_ms: 60_000};
+    let mid = |t: &Tick| (t.bid + t.ask) as f64 / 2. / 100_000.;
+
+    // a strongly mean-reverting process stays near its mean
+    let ou = PriceModel::OrnsteinUhlenbeck{mean: 1.2, reversion: 5000., volatility: 0.5};
+    let ticks: Vec<Tick> = SyntheticTicks::new(test_config(ou, fixed.clone())).unwrap().collect();
+    let avg = ticks[1000..].iter().map(&mid).sum::<f64>() / (ticks.len() - 1000) as f64;
+    assert!((avg - 1.2).abs() < 0.005);
+
+    // large jumps show up as large moves
+    let jumps = PriceModel::JumpDiffusion{drift: 0., volatility: 0.01, jump_intensity: 10_000., jump_mean: 0., jump_std: 0.05};
+    let ticks: Vec<Tick> = SyntheticTicks::new(test_config(jumps, fixed.clone())).unwrap().collect();
+    let big_moves = ticks.windows(2).filter(|w| (mid(&w[1]) / mid(&w[0])).ln().abs() > 0.02).count();
+    assert!(big_moves > 150 && big_moves < 400);
+
+    // GARCH returns have the unconditional variance and fat tails
+    let garch = PriceModel::Garch{drift: 0., omega: 1e-7, alpha: 0.1, beta: 0.85};
+    let ticks: Vec<Tick> = SyntheticTicks::new(test_config(garch, fixed.clone())).unwrap().collect();
+    let returns: Vec<f64> = ticks.windows(2).map(|w| (mid(&w[1]) / mid(&w[0])).ln()).collect();
+    let variance = returns.iter().map(|r| r * r).sum::<f64>() / returns.len() as f64;
+    assert!((variance / 2e-6 - 1.).abs() < 0.2);
+    let kurtosis = returns.iter().map(|r| r.powi(4)).sum::<f64>() / returns.len() as f64 / (variance * variance);
+    assert!(kurtosis > 3.5);
+
+    let regimes = PriceModel::RegimeSwitching{regimes: vec![
+        Regime{drift: 0., volatility: 0.01, mean_duration_ms: 3_600_000.},
+        Regime{drift: 0., volatility: 0.5, mean_duration_ms: 3_600_000.},
+    ]};
+    assert_eq!(SyntheticTicks::new(test_config(regimes, fixed)).unwrap().count(), 20_000);
+
+    // Hawkes arrivals cluster, averaging base_rate / (1 - excitation / decay) ticks per second
+    let hawkes = ArrivalProcess::Hawkes{base_rate: 1., excitation: 0.5, decay: 1.};
+    let ticks: Vec<Tick> = SyntheticTicks::new(test_config(PriceModel::Gbm{drift: 0., volatility: 0.1}, hawkes)).unwrap().collect();
+    let seconds = (ticks[ticks.len() - 1].timestamp - ticks[0].timestamp) as f64 / 1000.;
+    assert!((ticks.len() as f64 / seconds - 2.).abs() < 0.2);
+
+    let bad = test_config(PriceModel::Garch{drift: 0., omega: 1e-7, alpha: 0.5, beta: 0.6}, ArrivalProcess::Poisson{rate: 1.});
+    assert!(SyntheticTicks::new(bad).is_err());
+}
+
+#[test]
+fn poisson_sampling() {
+    let mut rng = Isaac64Rng::from_seed(&[1][..]);
+    let mean_of = |rng: &mut Isaac64Rng, mean: f64| {
+        (0..2000).map(|_| poisson_sample(rng, mean)).sum::<usize>() as f64 / 2000.
+    };
+    assert!((mean_of(&mut rng, 2.) - 2.).abs() < 0.2);
+    // `exp(-mean)` underflows for means this large
+    assert!((mean_of(&mut rng, 5000.) - 5000.).abs() < 10.);
+}
@@ -11,6 +11,7 @@ use test;
 use transport::query_server::QueryServer;
 
 /// A generic tick.  The data it holds is defined by the user.
+#[derive(Serialize, Deserialize)]
 pub struct GenTick<T> {
     pub timestamp: u64,
     pub data: T,
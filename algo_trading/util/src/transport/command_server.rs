@@ -18,6 +18,7 @@ use std::collections::VecDeque;
 use std::thread::{self, Thread};
 use std::time::Duration;
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering, ATOMIC_BOOL_INIT};
 use std::str::FromStr;
 
 use futures::{Stream, Canceled};
@@ -31,6 +32,21 @@ use transport::redis::{get_client, sub_channel};
 use transport::commands::*;
 use conf::CONF;
 
+/// Set once the process has switched into offline mode.
+static OFFLINE: AtomicBool = ATOMIC_BOOL_INIT;
+
+/// Makes every `CommandServer` and `QueryServer` created afterwards run without connecting to Redis or Postgres.
+/// Offline `CommandServer`s print log messages to the console and fail all commands immediately.  This is used
+/// by standalone tools that run parts of the platform outside of it, such as the CLI backtest runner.
+pub fn set_offline_mode() {
+    OFFLINE.store(true, Ordering::SeqCst);
+}
+
+/// Returns `true` if `set_offline_mode()` has been called.
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::SeqCst)
+}
+
 /// A command waiting to be sent plus a Sender to send the Response/Error String
 /// through and the channel on which to broadcast the Command.
 struct CommandRequest {
@@ -110,6 +126,7 @@ pub struct CommandServer {
     conn_queue: UnboundedSenderQueue, // UnboundedSenders for idle command-UnboundedSender threadss
     client: redis::Client,
     instance: Instance, // The instance that owns this CommandServer
+    offline: bool,
 }
 
 /// Locks the `CommandQueue` and returns a queued command, if there are any.
@@ -244,6 +261,17 @@ fn init_command_processor(
 
 impl CommandServer {
     pub fn new(instance_uuid: Uuid, instance_type: &str) -> CommandServer {
+        if is_offline() {
+            return CommandServer {
+                al: Arc::new(Mutex::new(AlertList::new())),
+                command_queue: Arc::new(Mutex::new(VecDeque::new())),
+                conn_queue: Arc::new(Mutex::new(VecDeque::new())),
+                client: get_client(CONF.redis_host),
+                instance: Instance{ uuid: instance_uuid, instance_type: String::from(instance_type), },
+                offline: true,
+            };
+        }
+
         let mut conn_queue = VecDeque::with_capacity(CONF.conn_senders);
         let command_queue = Arc::new(Mutex::new(VecDeque::new()));
         let al = Arc::new(Mutex::new(AlertList::new()));
@@ -280,6 +308,7 @@ impl CommandServer {
             conn_queue: Arc::new(Mutex::new(conn_queue)),
             client: client,
             instance: Instance{ uuid: instance_uuid, instance_type: String::from(instance_type), },
+            offline: false,
         }
     }
 
@@ -288,6 +317,12 @@ impl CommandServer {
     pub fn execute(
         &mut self, command: Command, commands_channel: String
     ) -> Receiver<Result<Response, String>> {
+        if self.offline {
+            let (res_c, res_o) = oneshot::<Result<Response, String>>();
+            res_c.complete(Err(String::from("Commands can't be sent in offline mode.")));
+            return res_o;
+        }
+
         let temp_lock_res = self.conn_queue.lock().unwrap().is_empty();
         // Force the guard locking conn_queue to go out of scope
         // this prevents the lock from being held through the entire if/else
@@ -327,6 +362,12 @@ impl CommandServer {
     pub fn broadcast(
         &mut self, command: Command, commands_channel: String
     ) -> Receiver<Vec<Response>> {
+        if self.offline {
+            let (all_responses_c, all_responses_o) = oneshot::<Vec<Response>>();
+            all_responses_c.complete(Vec::new());
+            return all_responses_o;
+        }
+
         // spawn a new timeout thread just for this request
         let (sleeper_tx, sleeper_rx) = unbounded::<TimeoutRequest>();
         let dur = Duration::from_millis(CONF.cs_timeout as u64);
@@ -397,6 +438,9 @@ impl CommandServer {
 
     /// Sends a command asynchronously without bothering to wait for responses.
     pub fn send_forget(&self, cmd: &Command, channel: &str) {
+        if self.offline {
+            return;
+        }
         let _ = send_command(&cmd.wrap(), &self.client, channel);
     }
 
@@ -412,6 +456,10 @@ impl CommandServer {
             message: String::from(message),
             sender: self.instance.clone(),
         };
+        if self.offline {
+            println!("[{:?}] {}: {}", line.level, line.message_type, line.message);
+            return;
+        }
         self.send_forget(&Command::Log{msg: line}, CONF.redis_log_channel);
     }
 
@@ -9,6 +9,8 @@ use uuid::Uuid;
 #[allow(unused_imports)]
 use test;
 
+use transport::csv_schema::CsvSchema;
+
 use std::collections::HashMap;
 
 /// Represents a Command that can be serde'd and sent over Redis.
@@ -23,7 +25,7 @@ pub enum Command {
     Ready {instance_type: String, uuid: Uuid}, /* signals that a newly spawned instance is ready to receive commands */
     // Tick Processor Commands
     AddCondition {condition_string: String},
-    RemoveCondition {condition_string: String},
+    RemoveCondition {condition_string: String}, // either the condition itself or the id returned when adding it
     ListConditions,
     SubTicks {broker_def: String},
     // Spawner Commands
@@ -47,9 +49,27 @@ pub enum Command {
     PauseBacktest{uuid: Uuid},
     ResumeBacktest{uuid: Uuid},
     StopBacktest{uuid: Uuid},
+    /// Sends the given number of ticks and then pauses
+    StepBacktest{uuid: Uuid, ticks: usize},
+    SeekBacktest{uuid: Uuid, timestamp: u64},
+    /// Changes the playback speed of a `Live` backtest
+    SetBacktestSpeed{uuid: Uuid, multiplier: f64},
     ListBacktests,
+    /// Starts a JSON-encoded list of `BatchDefinition`s running in parallel
+    StartBacktestBatch{definitions: String},
+    GetBacktestBatch{uuid: Uuid},
+    /// Lists the most recent stored backtest runs whose ID, strategy, or git revision contain `query`
+    ListBacktestRuns{query: String},
+    GetBacktestRun{id: Uuid},
+    /// Compares the parameters and metrics of two stored backtest runs
+    DiffBacktestRuns{a: Uuid, b: Uuid},
     ListSimbrokers,
     SpawnSimbroker{settings: HashMap<String, String>},
+    // Optimizer Commands
+    StartOptimization{definition: String},
+    ResumeOptimization{id: Uuid},
+    StartWalkForward{definition: String},
+    RunMonteCarlo{definition: String},
     // Data Downloader Commands
     // TODO: Create a `DataDownload` struct and replace these with that
     DownloadTicks {
@@ -109,6 +129,12 @@ impl FromStr for Command {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum HistTickDst {
     Flatfile { filename: String },
+    /// A CSV file or archive of CSV files in a third-party format described by the schema.  Can only be read from.
+    Csv { filename: String, schema: CsvSchema },
+    /// A file in the binary tick format of `transport::tickfile`
+    Binary { filename: String },
+    /// An Apache Parquet file.  If no row group size is given, `parquet_file::DEFAULT_ROW_GROUP_SIZE` is used.
+    Parquet { filename: String, #[serde(default)] row_group_size: Option<usize> },
     Postgres { table: String },
     RedisChannel { host: String, channel: String },
     RedisSet { host: String, set_name: String },
@@ -0,0 +1,589 @@
+//! Reading of tick data out of CSV files in formats other than the platform's own, such as those exported by
+//! Dukascopy, TrueFX, or HistData.
+//!
+//! A `CsvSchema` describes which columns hold the timestamp, bid, and ask of each tick, how the timestamp is
+//! formatted and which timezone it's in, and how decimal prices are converted into pips.  Rows that can't be
+//! parsed are reported as errors along with their position instead of stopping the read; `ValidCsvTicks` logs and
+//! counts them instead.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, FixedOffset, LocalResult, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use csv::{ReaderBuilder, StringRecord, StringRecordsIntoIter, Trim};
+
+use trading::tick::Tick;
+use transport::command_server::CommandServer;
+use transport::flatfile::{archive_files, open_file};
+
+/// Refers to a column either by its zero-based index or by its name in the header row
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum CsvColumn {
+    Index(usize),
+    Name(String),
+}
+
+/// A unit of time that timestamps are counted in
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum TimeUnit {
+    Seconds,
+    Millis,
+    Micros,
+    Nanos,
+}
+
+impl TimeUnit {
+    fn per_second(&self) -> u64 {
+        match *self {
+            TimeUnit::Seconds => 1,
+            TimeUnit::Millis => 1_000,
+            TimeUnit::Micros => 1_000_000,
+            TimeUnit::Nanos => 1_000_000_000,
+        }
+    }
+
+    /// Converts a count of this unit into a count of `unit`.
+    pub fn convert(&self, n: u64, unit: TimeUnit) -> u64 {
+        let (from, to) = (self.per_second(), unit.per_second());
+        if to >= from { n * (to / from) } else { n / (from / to) }
+    }
+}
+
+/// How the timestamps of a CSV file are written
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum TimestampFormat {
+    /// A number of units since the Unix epoch, which may have a fractional part
+    Epoch(TimeUnit),
+    /// A date and time in the given `chrono` format such as `%Y%m%d %H:%M:%S%.3f`.  Formats without a time of day
+    /// are treated as midnight.
+    DateTime(String),
+}
+
+fn default_delimiter() -> char { ',' }
+fn default_timezone() -> String { String::from("UTC") }
+fn default_pip_scale() -> f64 { 1. }
+fn default_output_unit() -> TimeUnit { TimeUnit::Millis }
+
+/// Describes the layout of a CSV file containing ticks.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CsvSchema {
+    pub timestamp_column: CsvColumn,
+    /// A column containing the time of day if it's stored separately from the date in `timestamp_column`.  The
+    /// two are joined with a space before being parsed.
+    #[serde(default)]
+    pub time_column: Option<CsvColumn>,
+    pub bid_column: CsvColumn,
+    pub ask_column: CsvColumn,
+    #[serde(default)]
+    pub bid_volume_column: Option<CsvColumn>,
+    #[serde(default)]
+    pub ask_volume_column: Option<CsvColumn>,
+    #[serde(default = "default_delimiter")]
+    pub delimiter: char,
+    /// Whether the first row after the skipped rows is a header row.  Columns can only be referred to by name if
+    /// there is a header.
+    #[serde(default)]
+    pub has_header: bool,
+    /// Number of lines at the start of every file that are skipped before the header or data
+    #[serde(default)]
+    pub skip_rows: usize,
+    pub timestamp_format: TimestampFormat,
+    /// The timezone of date and time timestamps; either `UTC`, a fixed offset such as `-05:00`, or a name from the
+    /// tz database such as `Europe/London`.  Ignored for epoch timestamps.
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+    /// Prices are multiplied by this and rounded to get pips, so `100000` converts `1.08617` into `108617`.
+    #[serde(default = "default_pip_scale")]
+    pub pip_scale: f64,
+    /// The unit that the timestamps of the read ticks are in
+    #[serde(default = "default_output_unit")]
+    pub output_unit: TimeUnit,
+}
+
+impl CsvSchema {
+    /// The schema of the platform's own flatfiles: `{timestamp}, {bid}, {ask}` with no header.
+    pub fn native() -> CsvSchema {
+        CsvSchema {
+            timestamp_column: CsvColumn::Index(0),
+            time_column: None,
+            bid_column: CsvColumn::Index(1),
+            ask_column: CsvColumn::Index(2),
+            bid_volume_column: None,
+            ask_volume_column: None,
+            delimiter: default_delimiter(),
+            has_header: false,
+            skip_rows: 0,
+            timestamp_format: TimestampFormat::Epoch(TimeUnit::Millis),
+            timezone: default_timezone(),
+            pip_scale: default_pip_scale(),
+            output_unit: default_output_unit(),
+        }
+    }
+
+    /// Dukascopy tick exports: `Gmt time,Ask,Bid,AskVolume,BidVolume` with times like `01.01.2016 22:00:00.123`.
+    pub fn dukascopy(pip_scale: f64) -> CsvSchema {
+        CsvSchema {
+            timestamp_column: CsvColumn::Name(String::from("Gmt time")),
+            bid_column: CsvColumn::Name(String::from("Bid")),
+            ask_column: CsvColumn::Name(String::from("Ask")),
+            bid_volume_column: Some(CsvColumn::Name(String::from("BidVolume"))),
+            ask_volume_column: Some(CsvColumn::Name(String::from("AskVolume"))),
+            has_header: true,
+            timestamp_format: TimestampFormat::DateTime(String::from("%d.%m.%Y %H:%M:%S%.3f")),
+            pip_scale: pip_scale,
+            ..CsvSchema::native()
+        }
+    }
+
+    /// TrueFX tick files: `EUR/USD,20160104 00:00:00.094,1.08617,1.08634` with no header.
+    pub fn truefx(pip_scale: f64) -> CsvSchema {
+        CsvSchema {
+            timestamp_column: CsvColumn::Index(1),
+            bid_column: CsvColumn::Index(2),
+            ask_column: CsvColumn::Index(3),
+            timestamp_format: TimestampFormat::DateTime(String::from("%Y%m%d %H:%M:%S%.3f")),
+            pip_scale: pip_scale,
+            ..CsvSchema::native()
+        }
+    }
+
+    /// HistData ASCII tick files: `20160103 170000128,1.087010,1.087240,0` in EST without daylight saving time.
+    pub fn histdata(pip_scale: f64) -> CsvSchema {
+        CsvSchema {
+            timestamp_format: TimestampFormat::DateTime(String::from("%Y%m%d %H%M%S%3f")),
+            timezone: String::from("-05:00"),
+            pip_scale: pip_scale,
+            ..CsvSchema::native()
+        }
+    }
+}
+
+/// A tick read from a CSV file along with the volumes from the file if the schema has volume columns
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvTick {
+    pub tick: Tick,
+    pub bid_volume: Option<f64>,
+    pub ask_volume: Option<f64>,
+}
+
+/// An error that occurred while reading a CSV file.  `line` is the line of the file that the error occurred on
+/// or 0 if it wasn't caused by a specific line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvRowError {
+    pub file: PathBuf,
+    pub line: u64,
+    pub message: String,
+}
+
+impl fmt::Display for CsvRowError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.file.display(), self.line, self.message)
+    }
+}
+
+/// A timezone that date and time timestamps are in
+#[derive(Debug, Clone, Copy)]
+enum Timezone {
+    Fixed(FixedOffset),
+    Named(Tz),
+}
+
+impl Timezone {
+    fn parse(s: &str) -> Result<Timezone, String> {
+        if s.eq_ignore_ascii_case("UTC") || s.eq_ignore_ascii_case("GMT") {
+            return Ok(Timezone::Fixed(FixedOffset::east(0)));
+        }
+        if s.starts_with('+') || s.starts_with('-') {
+            let parts: Vec<&str> = s[1..].split(':').collect();
+            let hours: i32 = try!(parts[0].parse().map_err(|_| format!("Invalid UTC offset: {}", s)));
+            let minutes: i32 = match parts.get(1) {
+                Some(m) => try!(m.parse().map_err(|_| format!("Invalid UTC offset: {}", s))),
+                None => 0,
+            };
+            let secs = (hours * 60 + minutes) * 60;
+            let secs = if s.starts_with('-') { -secs } else { secs };
+            return FixedOffset::east_opt(secs).map(Timezone::Fixed).ok_or(format!("Invalid UTC offset: {}", s));
+        }
+
+        s.parse::<Tz>().map(Timezone::Named).map_err(|_| format!("Unknown timezone: {}", s))
+    }
+
+    /// Converts a local time into UTC.  Times that occur twice when clocks are turned back are treated as the
+    /// earlier of the two.
+    fn to_utc(&self, naive: &NaiveDateTime) -> Result<DateTime<Utc>, String> {
+        let res = match *self {
+            Timezone::Fixed(ref offset) => offset.from_local_datetime(naive).map(|dt| dt.with_timezone(&Utc)),
+            Timezone::Named(ref tz) => tz.from_local_datetime(naive).map(|dt| dt.with_timezone(&Utc)),
+        };
+        match res {
+            LocalResult::Single(dt) | LocalResult::Ambiguous(dt, _) => Ok(dt),
+            LocalResult::None => Err(format!("{} doesn't exist in the timezone", naive)),
+        }
+    }
+}
+
+/// The indexes of the columns of a schema within a particular file
+#[derive(Debug, Clone, PartialEq)]
+struct ColumnIndexes {
+    timestamp: usize,
+    time: Option<usize>,
+    bid: usize,
+    ask: usize,
+    bid_volume: Option<usize>,
+    ask_volume: Option<usize>,
+}
+
+fn resolve_column(column: &CsvColumn, headers: Option<&StringRecord>) -> Result<usize, String> {
+    match *column {
+        CsvColumn::Index(i) => Ok(i),
+        CsvColumn::Name(ref name) => match headers {
+            Some(headers) => headers.iter().position(|header| header == name)
+                .ok_or(format!("The header doesn't contain a column named `{}`", name)),
+            None => Err(format!("The column `{}` can't be found by name since the file has no header", name)),
+        },
+    }
+}
+
+impl ColumnIndexes {
+    fn resolve(schema: &CsvSchema, headers: Option<&StringRecord>) -> Result<ColumnIndexes, String> {
+        let resolve_opt = |column: &Option<CsvColumn>| -> Result<Option<usize>, String> {
+            match *column {
+                Some(ref column) => resolve_column(column, headers).map(Some),
+                None => Ok(None),
+            }
+        };
+
+        Ok(ColumnIndexes {
+            timestamp: try!(resolve_column(&schema.timestamp_column, headers)),
+            time: try!(resolve_opt(&schema.time_column)),
+            bid: try!(resolve_column(&schema.bid_column, headers)),
+            ask: try!(resolve_column(&schema.ask_column, headers)),
+            bid_volume: try!(resolve_opt(&schema.bid_volume_column)),
+            ask_volume: try!(resolve_opt(&schema.ask_volume_column)),
+        })
+    }
+}
+
+fn get_field<'a>(record: &'a StringRecord, i: usize) -> Result<&'a str, String> {
+    record.get(i).ok_or(format!("The row has no column {}", i))
+}
+
+/// Parses a timestamp counted in `unit` since the epoch, converting it into `output_unit`.
+fn parse_epoch(s: &str, unit: TimeUnit, output_unit: TimeUnit) -> Result<u64, String> {
+    if let Ok(n) = s.parse::<u64>() {
+        return Ok(unit.convert(n, output_unit));
+    }
+
+    let n: f64 = try!(s.parse().map_err(|_| format!("Invalid timestamp: {}", s)));
+    if !n.is_finite() || n < 0. {
+        return Err(format!("Invalid timestamp: {}", s));
+    }
+    Ok((n * output_unit.per_second() as f64 / unit.per_second() as f64).round() as u64)
+}
+
+/// Converts a decimal price into pips.
+fn parse_price(s: &str, pip_scale: f64) -> Result<usize, String> {
+    let price: f64 = try!(s.parse().map_err(|_| format!("Invalid price: {}", s)));
+    let pips = (price * pip_scale).round();
+    if !pips.is_finite() || pips < 0. {
+        return Err(format!("Invalid price: {}", s));
+    }
+
+    Ok(pips as usize)
+}
+
+fn parse_volume(record: &StringRecord, i: Option<usize>) -> Result<Option<f64>, String> {
+    match i {
+        Some(i) => {
+            let s = try!(get_field(record, i));
+            s.parse().map(Some).map_err(|_| format!("Invalid volume: {}", s))
+        },
+        None => Ok(None),
+    }
+}
+
+/// Parses a row of a CSV file according to the schema.
+fn parse_record(
+    schema: &CsvSchema, tz: &Timezone, cols: &ColumnIndexes, record: &StringRecord
+) -> Result<CsvTick, String> {
+    let timestamp_field = try!(get_field(record, cols.timestamp));
+    let timestamp = match schema.timestamp_format {
+        TimestampFormat::Epoch(unit) => try!(parse_epoch(timestamp_field, unit, schema.output_unit)),
+        TimestampFormat::DateTime(ref format) => {
+            let joined;
+            let s = match cols.time {
+                Some(i) => {
+                    joined = format!("{} {}", timestamp_field, try!(get_field(record, i)));
+                    joined.as_str()
+                },
+                None => timestamp_field,
+            };
+            let naive = try!(
+                NaiveDateTime::parse_from_str(s, format)
+                    .or_else(|_| NaiveDate::parse_from_str(s, format).map(|date| date.and_hms(0, 0, 0)))
+                    .map_err(|err| format!("Unable to parse `{}` with the format `{}`: {}", s, format, err))
+            );
+            let dt = try!(tz.to_utc(&naive));
+            if dt.timestamp() < 0 {
+                return Err(format!("Timestamps before 1970 aren't supported: {}", s));
+            }
+            TimeUnit::Seconds.convert(dt.timestamp() as u64, schema.output_unit) +
+                TimeUnit::Nanos.convert(dt.timestamp_subsec_nanos() as u64, schema.output_unit)
+        },
+    };
+
+    Ok(CsvTick {
+        tick: Tick {
+            timestamp: timestamp,
+            bid: try!(parse_price(try!(get_field(record, cols.bid)), schema.pip_scale)),
+            ask: try!(parse_price(try!(get_field(record, cols.ask)), schema.pip_scale)),
+        },
+        bid_volume: try!(parse_volume(record, cols.bid_volume)),
+        ask_volume: try!(parse_volume(record, cols.ask_volume)),
+    })
+}
+
+/// The file that's currently being read
+struct OpenFile {
+    path: PathBuf,
+    records: StringRecordsIntoIter<Box<BufRead + Send>>,
+    cols: ColumnIndexes,
+}
+
+/// Reads ticks out of a CSV file or an archive of CSV files as described by a `CsvSchema`.  Files can be compressed
+/// as described in `transport::flatfile`.  Every row yields either a tick or an error describing why it couldn't be
+/// parsed; a file that can't be opened yields a single error and is skipped.
+pub struct CsvTickReader {
+    schema: CsvSchema,
+    tz: Timezone,
+    files: VecDeque<PathBuf>,
+    cur: Option<OpenFile>,
+}
+
+impl CsvTickReader {
+    /// Reads the ticks of the file or archive directory at `path`.
+    pub fn open(path: &Path, schema: CsvSchema) -> Result<CsvTickReader, String> {
+        let tz = try!(Timezone::parse(&schema.timezone));
+        if schema.pip_scale <= 0. {
+            return Err(format!("The pip scale must be positive but is {}", schema.pip_scale));
+        }
+        if !schema.delimiter.is_ascii() {
+            return Err(format!("The delimiter must be an ASCII character but is `{}`", schema.delimiter));
+        }
+        // make sure that named columns can be found before reading any files
+        if !schema.has_header {
+            try!(ColumnIndexes::resolve(&schema, None));
+        }
+
+        let files = try!(archive_files(path));
+        if files.is_empty() {
+            return Err(format!("The archive at {:?} doesn't contain any files", path));
+        }
+
+        Ok(CsvTickReader {
+            schema: schema,
+            tz: tz,
+            files: files.into_iter().collect(),
+            cur: None,
+        })
+    }
+
+    /// Opens a file, skips its leading rows, and finds the indexes of the schema's columns in it.
+    fn open_next(&self, path: &Path) -> Result<OpenFile, String> {
+        let mut reader = try!(open_file(path));
+        for _ in 0..self.schema.skip_rows {
+            try!(reader.read_line(&mut String::new()).map_err(|err| format!("{:?}", err)));
+        }
+
+        let mut csv_reader = ReaderBuilder::new()
+            .delimiter(self.schema.delimiter as u8)
+            .has_headers(self.schema.has_header)
+            .flexible(true)
+            .trim(Trim::All)
+            .from_reader(reader);
+        let cols = if self.schema.has_header {
+            let headers = try!(csv_reader.headers().map_err(|err| format!("Unable to read the header: {}", err)));
+            try!(ColumnIndexes::resolve(&self.schema, Some(headers)))
+        } else {
+            try!(ColumnIndexes::resolve(&self.schema, None))
+        };
+
+        Ok(OpenFile {
+            path: path.to_path_buf(),
+            records: csv_reader.into_records(),
+            cols: cols,
+        })
+    }
+}
+
+impl Iterator for CsvTickReader {
+    type Item = Result<CsvTick, CsvRowError>;
+
+    fn next(&mut self) -> Option<Result<CsvTick, CsvRowError>> {
+        loop {
+            if let Some(ref mut file) = self.cur {
+                if let Some(res) = file.records.next() {
+                    let res = match res {
+                        Ok(record) => {
+                            // lines are counted from the start of the file including any skipped rows
+                            let line = record.position().map(|pos| pos.line()).unwrap_or(0) + self.schema.skip_rows as u64;
+                            parse_record(&self.schema, &self.tz, &file.cols, &record)
+                                .map_err(|message| CsvRowError {file: file.path.clone(), line: line, message: message})
+                        },
+                        Err(err) => Err(CsvRowError {
+                            file: file.path.clone(),
+                            line: err.position().map(|pos| pos.line()).unwrap_or(0) + self.schema.skip_rows as u64,
+                            message: err.to_string(),
+                        }),
+                    };
+                    return Some(res);
+                }
+            }
+
+            let path = match self.files.pop_front() {
+                Some(path) => path,
+                None => return None,
+            };
+            match self.open_next(&path) {
+                Ok(file) => self.cur = Some(file),
+                Err(message) => {
+                    self.cur = None;
+                    return Some(Err(CsvRowError {file: path, line: 0, message: message}));
+                },
+            }
+        }
+    }
+}
+
+/// Yields the ticks of a `CsvTickReader`, logging each row that can't be parsed as a warning and skipping it.  The
+/// number of skipped rows is logged once the reader runs out of rows.
+pub struct ValidCsvTicks {
+    reader: CsvTickReader,
+    cs: CommandServer,
+    skipped: usize,
+    done: bool,
+}
+
+impl ValidCsvTicks {
+    pub fn new(reader: CsvTickReader, cs: CommandServer) -> ValidCsvTicks {
+        ValidCsvTicks {
+            reader: reader,
+            cs: cs,
+            skipped: 0,
+            done: false,
+        }
+    }
+
+    /// Returns the number of rows that have been skipped so far.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+}
+
+impl Iterator for ValidCsvTicks {
+    type Item = Tick;
+
+    fn next(&mut self) -> Option<Tick> {
+        if self.done {
+            return None;
+        }
+
+        loop {
+            match self.reader.next() {
+                Some(Ok(csv_tick)) => return Some(csv_tick.tick),
+                Some(Err(err)) => {
+                    self.skipped += 1;
+                    self.cs.warning(Some("Tick Loading"), &format!("Skipping invalid CSV row: {}", err));
+                },
+                None => {
+                    self.done = true;
+                    if self.skipped > 0 {
+                        self.cs.notice(Some("Tick Loading"), &format!("Skipped {} invalid CSV rows", self.skipped));
+                    }
+                    return None;
+                },
+            }
+        }
+    }
+}
+
+#[test]
+fn csv_schema_parsing() {
+    use std::env;
+    use std::fs;
+    use std::io::Write;
+
+    let mut dir = env::temp_dir();
+    dir.push("csv_schema_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let write = |name: &str, contents: &str| {
+        let path = dir.join(name);
+        fs::File::create(&path).unwrap().write_all(contents.as_bytes()).unwrap();
+        path
+    };
+    let read = |path: &Path, schema: CsvSchema| -> Vec<Result<CsvTick, CsvRowError>> {
+        CsvTickReader::open(path, schema).unwrap().collect()
+    };
+
+    let path = write("duka.csv", "Gmt time,Ask,Bid,AskVolume,BidVolume\n\
+        01.01.2016 22:00:00.123,1.08634,1.08617,1.5,2.25\n\
+        01.01.2016 22:00:01.000,abc,1.08617,1,1\n");
+    let rows = read(&path, CsvSchema::dukascopy(100000.));
+    assert_eq!(rows[0], Ok(CsvTick {
+        tick: Tick {timestamp: 1451685600123, bid: 108617, ask: 108634},
+        bid_volume: Some(2.25),
+        ask_volume: Some(1.5),
+    }));
+    let err = rows[1].clone().unwrap_err();
+    assert_eq!(err.line, 3);
+    assert!(err.message.contains("abc"));
+
+    let path = write("truefx.csv", "EUR/USD,20160104 00:00:00.094,1.08617,1.08634\n");
+    let rows = read(&path, CsvSchema::truefx(100000.));
+    assert_eq!(rows[0].clone().unwrap().tick, Tick {timestamp: 1451865600094, bid: 108617, ask: 108634});
+
+    // HistData times are in EST, so 17:00 is 22:00 UTC
+    let path = write("histdata.csv", "20160103 170000128,1.087010,1.087240,0\n");
+    let rows = read(&path, CsvSchema::histdata(100000.));
+    assert_eq!(rows[0].clone().unwrap().tick, Tick {timestamp: 1451858400128, bid: 108701, ask: 108724});
+
+    // separate date and time columns in a named timezone with seconds as the output unit
+    let path = write("split.csv", "# exported data\ndate;time;bid;ask\n2016-07-01;12:00:00;1.1;1.2\n");
+    let schema = CsvSchema {
+        timestamp_column: CsvColumn::Name(String::from("date")),
+        time_column: Some(CsvColumn::Name(String::from("time"))),
+        bid_column: CsvColumn::Name(String::from("bid")),
+        ask_column: CsvColumn::Index(3),
+        delimiter: ';',
+        has_header: true,
+        skip_rows: 1,
+        timestamp_format: TimestampFormat::DateTime(String::from("%Y-%m-%d %H:%M:%S")),
+        timezone: String::from("Europe/London"),
+        pip_scale: 10.,
+        output_unit: TimeUnit::Seconds,
+        ..CsvSchema::native()
+    };
+    let rows = read(&path, schema);
+    assert_eq!(rows[0].clone().unwrap().tick, Tick {timestamp: 1467370800, bid: 11, ask: 12});
+
+    let path = write("native.csv", "1476650327123, 123134, 123156\n1476650327124, 123134\n");
+    let rows = read(&path, CsvSchema::native());
+    assert_eq!(rows[0].clone().unwrap().tick, Tick {timestamp: 1476650327123, bid: 123134, ask: 123156});
+    assert_eq!(rows[1].clone().unwrap_err().line, 2);
+
+    // invalid rows are skipped and counted
+    let cs = CommandServer::new(::uuid::Uuid::new_v4(), "CSV Schema Test");
+    let mut ticks = ValidCsvTicks::new(CsvTickReader::open(&path, CsvSchema::native()).unwrap(), cs);
+    assert_eq!(ticks.by_ref().count(), 1);
+    assert_eq!(ticks.skipped(), 1);
+
+    let bad_name = CsvSchema {bid_column: CsvColumn::Name(String::from("bid")), ..CsvSchema::native()};
+    assert!(CsvTickReader::open(&path, bad_name).is_err());
+    assert!(CsvTickReader::open(&path, CsvSchema {timezone: String::from("Mars/Olympus"), ..CsvSchema::native()}).is_err());
+    assert_eq!(parse_epoch("1476650327.5", TimeUnit::Seconds, TimeUnit::Millis), Ok(1476650327500));
+}
@@ -1,12 +1,10 @@
 //! Utilities related to the transfer of data from one place to another.  Handles the conversion of data from one format
 //! to another and from one storage type to another.
 
-use std::fs::{File, OpenOptions};
 use std::path::Path;
-use std::io::prelude::*;
-use std::io::BufReader;
 use std::fmt;
 use std::thread;
+use std::sync::Arc;
 
 use serde_json;
 use redis;
@@ -19,6 +17,13 @@ use transport::postgres::get_client as get_postgres_client;
 use transport::postgres::init_hist_data_table;
 use transport::query_server::QueryServer;
 use transport::command_server::CommandServer;
+use transport::tickfile::{TickFile, TickFileIter, TickFileWriter};
+use transport::flatfile::{ArchiveLines, create_file};
+use transport::csv_schema::{CsvSchema, CsvTickReader, ValidCsvTicks};
+#[cfg(feature = "parquet")]
+use transport::parquet_file::{ParquetTickWriter, ParquetTicks, ParquetQuery, DEFAULT_ROW_GROUP_SIZE};
+#[cfg(not(feature = "parquet"))]
+use transport::NO_PARQUET_SUPPORT;
 use trading::tick::Tick;
 use conf::CONF;
 
@@ -26,10 +31,22 @@ use conf::CONF;
 
 /// Initializes the transfer of data from a `HistTickGen` to a `HistTickDst`.  Data is read into an internal buffer within
 /// the generator and then written into the sink.
-pub fn transfer_data(src: HistTickDst, dst: HistTickDst, cs: CommandServer) {
+pub fn transfer_data(src: HistTickDst, dst: HistTickDst, mut cs: CommandServer) {
     thread::spawn(move || {
-        let tx_iterator = get_tx_iterator(src, cs);
-        let mut rx_closure = get_rx_closure(dst).unwrap();
+        let tx_iterator = match get_tx_iterator(src, cs.clone()) {
+            Ok(tx_iterator) => tx_iterator,
+            Err(err) => {
+                cs.error(Some("Data Transfer"), &format!("Unable to read from the source: {}", err));
+                return;
+            },
+        };
+        let mut rx_closure = match get_rx_closure(dst) {
+            Ok(rx_closure) => rx_closure,
+            Err(err) => {
+                cs.error(Some("Data Transfer"), &format!("Unable to write to the destination: {}", err));
+                return;
+            },
+        };
 
         for tick in tx_iterator {
             rx_closure(tick);
@@ -107,19 +124,9 @@ pub fn get_rx_closure(dst: HistTickDst) -> Result<RxCallback, String> {
             }
         },
         HistTickDst::Flatfile{filename} => {
-            let fnc = filename.clone();
-            let path = Path::new(&fnc);
-            // create the file if it doesn't exist
-            if !path.exists() {
-                let _ = File::create(path).unwrap();
-            }
-
-            // try to open the specified filename in append mode
-            let file_opt = OpenOptions::new().append(true).open(path);
-            if file_opt.is_err() {
-                return Err(format!("Unable to open file with path {}", filename));
-            }
-            let mut file = file_opt.unwrap();
+            // open the specified filename in append mode, creating it if it doesn't exist.  The file is compressed
+            // according to its extension.
+            let mut file = try!(create_file(Path::new(&filename), true));
 
             let inner = move |t: Tick| {
                 let tick_string = t.to_csv_row();
@@ -132,6 +139,44 @@ pub fn get_rx_closure(dst: HistTickDst) -> Result<RxCallback, String> {
                 inner: Box::new(inner),
             }
         },
+        HistTickDst::Csv{..} => {
+            return Err(String::from("CSV files with a schema can only be read from; use a `Flatfile` to write ticks as CSV."));
+        },
+        HistTickDst::Binary{filename} => {
+            // the file is finished when the writer is dropped along with the callback
+            let mut writer = try!(TickFileWriter::create(Path::new(&filename)));
+
+            let inner = move |t: Tick| {
+                if let Err(err) = writer.write(t) {
+                    println!("Couldn't write to output file {}: {}", filename, err);
+                }
+            };
+
+            RxCallback {
+                dst: dst,
+                inner: Box::new(inner),
+            }
+        },
+        #[cfg(not(feature = "parquet"))]
+        HistTickDst::Parquet{..} => return Err(String::from(NO_PARQUET_SUPPORT)),
+        #[cfg(feature = "parquet")]
+        HistTickDst::Parquet{filename, row_group_size} => {
+            // the last row group and the footer are written when the writer is dropped along with the callback
+            let mut writer = try!(
+                ParquetTickWriter::create(Path::new(&filename), row_group_size.unwrap_or(DEFAULT_ROW_GROUP_SIZE))
+            );
+
+            let inner = move |t: Tick| {
+                if let Err(err) = writer.write(t) {
+                    println!("Couldn't write to output file {}: {}", filename, err);
+                }
+            };
+
+            RxCallback {
+                dst: dst,
+                inner: Box::new(inner),
+            }
+        },
         HistTickDst::Postgres{table} => {
             let connection_opt = get_postgres_client();
             if connection_opt.is_err() {
@@ -211,16 +256,30 @@ impl FnMut<(uint64_t, c_double, c_double,)> for TxCallback {
     }
 }
 
-fn get_tx_iterator(src: HistTickDst, cs: CommandServer) -> Box<HistTickGen> {
-    match src {
+fn get_tx_iterator(src: HistTickDst, cs: CommandServer) -> Result<Box<HistTickGen>, String> {
+    let gen: Box<HistTickGen> = match src {
         HistTickDst::Flatfile{filename} => {
             Box::new(FlatfileReader::new(filename, cs))
         },
+        HistTickDst::Csv{filename, schema} => {
+            Box::new(try!(CsvFileReader::new(filename, schema, cs)))
+        },
         HistTickDst::Postgres{table} => {
             Box::new(PostgresReader::new(table.to_string(), cs))
-        }
+        },
+        HistTickDst::Binary{filename} => {
+            Box::new(BinaryFileReader::new(filename, cs))
+        },
+        #[cfg(feature = "parquet")]
+        HistTickDst::Parquet{filename, ..} => {
+            Box::new(try!(ParquetFileReader::new(filename, cs)))
+        },
+        #[cfg(not(feature = "parquet"))]
+        HistTickDst::Parquet{..} => return Err(String::from(NO_PARQUET_SUPPORT)),
         _ => unimplemented!(),
-    }
+    };
+
+    Ok(gen)
 }
 
 /// This trait is implemented by objects that generate historical ticks from stored source.
@@ -250,10 +309,11 @@ impl Iterator for HistTickGen {
     }
 }
 
-/// A historical tick reader that draws upon a CSV file as a data source
+/// A historical tick reader that draws upon a CSV file, which may be compressed, or an archive directory of CSV
+/// files as a data source
 struct FlatfileReader {
     buffer: Vec<Tick>,
-    buf_reader: BufReader<File>,
+    lines: ArchiveLines,
     cs: CommandServer,
 }
 
@@ -262,16 +322,15 @@ impl HistTickGen for FlatfileReader {
         &mut self.buffer
     }
 
-    /// Reads lines out of the file to fill the buffer
+    /// Reads lines out of the file to fill the buffer, storing them in reverse order since they're popped off the end
     fn populate_buffer(&mut self) -> Result<(), String> {
         assert_eq!(self.buffer.len(), 0);
 
-        for _ in 0..500 {
-            let mut buf = String::new();
-            let _ = self.buf_reader.read_line(&mut buf).unwrap();
-            let tick = Tick::from_csv_string(&buf);
-            self.buffer.push(tick);
+        for line in self.lines.by_ref().take(500) {
+            let line = try!(line);
+            self.buffer.push(Tick::from_csv_string(&line));
         }
+        self.buffer.reverse();
 
         Ok(())
     }
@@ -283,19 +342,135 @@ impl HistTickGen for FlatfileReader {
 
 impl FlatfileReader {
     pub fn new(filename: String, cs: CommandServer) -> FlatfileReader {
-        let path = Path::new(&filename);
-        let file = File::open(path).expect(&format!("Unable to open file at {:?}", path));
-        let mut reader = BufReader::new(file);
-        // skip header row
-        let _ = reader.read_line(&mut String::new());
+        // skip the header row of every file
+        let lines = ArchiveLines::open(Path::new(&filename), true).expect(&format!("Unable to open file at {}", filename));
         FlatfileReader {
-            buf_reader: reader,
+            lines: lines,
             buffer: Vec::with_capacity(500),
             cs: cs,
         }
     }
 }
 
+/// A historical tick reader that draws upon a CSV file or archive of CSV files in a third-party format
+struct CsvFileReader {
+    buffer: Vec<Tick>,
+    ticks: ValidCsvTicks,
+    cs: CommandServer,
+}
+
+impl HistTickGen for CsvFileReader {
+    fn get_buffer(&mut self) -> &mut Vec<Tick> {
+        &mut self.buffer
+    }
+
+    /// Parses up to 500 rows out of the file, storing the ticks in reverse order since they're popped off the end.
+    /// Rows that can't be parsed are logged and skipped.
+    fn populate_buffer(&mut self) -> Result<(), String> {
+        assert_eq!(self.buffer.len(), 0);
+
+        self.buffer.extend(self.ticks.by_ref().take(500));
+        self.buffer.reverse();
+
+        Ok(())
+    }
+
+    fn get_cs(&mut self) -> &mut CommandServer {
+        &mut self.cs
+    }
+}
+
+impl CsvFileReader {
+    pub fn new(filename: String, schema: CsvSchema, cs: CommandServer) -> Result<CsvFileReader, String> {
+        let reader = try!(CsvTickReader::open(Path::new(&filename), schema));
+
+        Ok(CsvFileReader {
+            buffer: Vec::with_capacity(500),
+            ticks: ValidCsvTicks::new(reader, cs.clone()),
+            cs: cs,
+        })
+    }
+}
+
+/// A historical tick reader that draws upon a file in the binary tick format as a data source
+struct BinaryFileReader {
+    buffer: Vec<Tick>,
+    iter: TickFileIter,
+    cs: CommandServer,
+}
+
+impl HistTickGen for BinaryFileReader {
+    fn get_buffer(&mut self) -> &mut Vec<Tick> {
+        &mut self.buffer
+    }
+
+    /// Reads the next 500 ticks out of the file, storing them in reverse order since they're popped off the end
+    fn populate_buffer(&mut self) -> Result<(), String> {
+        assert_eq!(self.buffer.len(), 0);
+        self.buffer.extend(self.iter.by_ref().take(500));
+        self.buffer.reverse();
+
+        Ok(())
+    }
+
+    fn get_cs(&mut self) -> &mut CommandServer {
+        &mut self.cs
+    }
+}
+
+impl BinaryFileReader {
+    pub fn new(filename: String, cs: CommandServer) -> BinaryFileReader {
+        let file = TickFile::open(Path::new(&filename)).expect(&format!("Unable to open file at {}", filename));
+
+        BinaryFileReader {
+            buffer: Vec::with_capacity(500),
+            iter: TickFileIter::new(Arc::new(file), None),
+            cs: cs,
+        }
+    }
+}
+
+/// A historical tick reader that draws upon a Parquet file as a data source
+#[cfg(feature = "parquet")]
+struct ParquetFileReader {
+    buffer: Vec<Tick>,
+    ticks: ParquetTicks,
+    cs: CommandServer,
+}
+
+#[cfg(feature = "parquet")]
+impl HistTickGen for ParquetFileReader {
+    fn get_buffer(&mut self) -> &mut Vec<Tick> {
+        &mut self.buffer
+    }
+
+    /// Reads the next 500 ticks out of the file, storing them in reverse order since they're popped off the end
+    fn populate_buffer(&mut self) -> Result<(), String> {
+        assert_eq!(self.buffer.len(), 0);
+        self.buffer.extend(self.ticks.by_ref().take(500));
+        self.buffer.reverse();
+
+        Ok(())
+    }
+
+    fn get_cs(&mut self) -> &mut CommandServer {
+        &mut self.cs
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl ParquetFileReader {
+    pub fn new(filename: String, cs: CommandServer) -> Result<ParquetFileReader, String> {
+        let ticks = try!(ParquetTicks::open(Path::new(&filename), ParquetQuery::default()));
+
+        Ok(ParquetFileReader {
+            buffer: Vec::with_capacity(500),
+            ticks: ticks,
+            cs: cs,
+        })
+    }
+}
+
 /// A historical tick generator that draws upon a PostgreSQL table as its data source
 struct PostgresReader {
     buffer: Vec<Tick>,
@@ -2,10 +2,12 @@
 //! so that it is usable by external applications such as the NodeJS FFI.
 
 use std::ffi::CString;
+use std::ptr;
 use std::slice;
 use libc::{c_int, c_char, c_void, memchr};
 use std::mem;
 use uuid::Uuid;
+use serde_json;
 
 use transport::data::{RxCallback, get_rx_closure};
 use transport::tickstream::sinks::csv_sink;
@@ -34,6 +36,9 @@ const REDIS_CHANNEL: c_int = 2; // { host: String, channel: String },
 const REDIS_SET: c_int = 3; // { host: String, set_name: String },
 const CONSOLE: c_int = 4;
 const CSV: c_int = 5;
+const BINARY: c_int = 6; // { filename: String }
+const PARQUET: c_int = 7; // { filename: String, row_group_size: Option<String> }
+const SCHEMA_CSV: c_int = 8; // { filename: String, schema: String (JSON-encoded `CsvSchema`) }
 
 // TODO: Convert the old `RxCallback`-based sinks into real `TickSink`s.
 
@@ -51,8 +56,14 @@ pub unsafe extern "C" fn c_transfer_data(
 
     // try to build a `HistTickDst` from the given src arguments
     let src_htd: HistTickDst = match src {
-        FLATFILE | POSTGRES | REDIS_CHANNEL | REDIS_SET => {
-            build_htd(src, src_arg1, src_arg2)
+        FLATFILE | POSTGRES | REDIS_CHANNEL | REDIS_SET | BINARY | PARQUET | SCHEMA_CSV => {
+            match build_htd(src, src_arg1, src_arg2) {
+                Ok(htd) => htd,
+                Err(err) => {
+                    cs.error(None as Option<&str>, &err);
+                    return false;
+                },
+            }
         },
         CONSOLE => { // can't use the console as a data source, so error out and return
             cs.error(None as Option<&str>, "Unable to use the console as a source of historical ticks!");
@@ -65,7 +76,13 @@ pub unsafe extern "C" fn c_transfer_data(
     };
 
     // build a `HistTickDst` for the dst arguments and transfer the data
-    let dst_htd = build_htd(dst, dst_arg1, dst_arg2);
+    let dst_htd = match build_htd(dst, dst_arg1, dst_arg2) {
+        Ok(htd) => htd,
+        Err(err) => {
+            cs.error(None as Option<&str>, &err);
+            return false;
+        },
+    };
     rust_transfer_data(src_htd, dst_htd, cs.clone());
 
     // I'm not 100% sure if the `CommandServer` would be dropped here, so I'm `forget`ting it explicitly
@@ -75,11 +92,16 @@ pub unsafe extern "C" fn c_transfer_data(
 }
 
 /// Given a `HistTickDst` as a pointer, returns a `RxCallback` as a pointer.  Takes in the raw parts of a
-/// `HistTickDst` as arguments.
+/// `HistTickDst` as arguments.  Returns a null pointer if no `RxCallback` can be created from the arguments.
 #[no_mangle]
 pub unsafe extern "C" fn c_get_rx_closure(id: c_int, arg1: *mut c_void, arg2: *mut c_void) -> *mut c_void {
-    let htd = build_htd(id, arg1, arg2);
-    Box::into_raw(Box::new(get_rx_closure(htd).expect("Unable to get rx closure"))) as *mut c_void
+    match build_htd(id, arg1, arg2).and_then(get_rx_closure) {
+        Ok(rx_closure) => Box::into_raw(Box::new(rx_closure)) as *mut c_void,
+        Err(err) => {
+            println!("Unable to get rx closure: {}", err);
+            ptr::null_mut()
+        },
+    }
 }
 
 /// Given a `RxCallback` in the form of a `*mut c_void`, executes it with the provided raw tick parts.
@@ -98,8 +120,8 @@ pub unsafe extern "C" fn exec_c_rx_closure(closure: *mut c_void, timestamp: u64,
 }
 
 /// Given the raw parts from the FFI, attempts to build a `HistTickDst` from them.
-pub unsafe fn build_htd(id: c_int, arg1: *mut c_void, arg2: *mut c_void) -> HistTickDst {
-    match id {
+pub unsafe fn build_htd(id: c_int, arg1: *mut c_void, arg2: *mut c_void) -> Result<HistTickDst, String> {
+    Ok(match id {
         FLATFILE => {
             let filename_cstring = ptr_to_cstring(arg1 as *mut c_char);
             let filename_string = String::from(filename_cstring.to_str().expect(CSTRING_CONV_ERR));
@@ -133,8 +155,39 @@ pub unsafe fn build_htd(id: c_int, arg1: *mut c_void, arg2: *mut c_void) -> Hist
             }
         },
         CONSOLE => HistTickDst::Console,
-        _ => panic!("Invalid ID given for `HistTickDst` conversion function"),
-    }
+        BINARY => {
+            let filename_cstring = ptr_to_cstring(arg1 as *mut c_char);
+            let filename_string = String::from(filename_cstring.to_str().expect(CSTRING_CONV_ERR));
+            HistTickDst::Binary{filename: filename_string}
+        },
+        PARQUET => {
+            let filename_cstring = ptr_to_cstring(arg1 as *mut c_char);
+            let filename_string = String::from(filename_cstring.to_str().expect(CSTRING_CONV_ERR));
+            // the row group size is optional and passed as a string
+            let row_group_size = if arg2.is_null() {
+                None
+            } else {
+                let size_cstring = ptr_to_cstring(arg2 as *mut c_char);
+                let size_str = size_cstring.to_str().expect(CSTRING_CONV_ERR);
+                match size_str.parse() {
+                    Ok(size) => Some(size),
+                    Err(_) => return Err(format!("Invalid row group size: {}", size_str)),
+                }
+            };
+            HistTickDst::Parquet{filename: filename_string, row_group_size: row_group_size}
+        },
+        SCHEMA_CSV => {
+            let filename_cstring = ptr_to_cstring(arg1 as *mut c_char);
+            let filename_string = String::from(filename_cstring.to_str().expect(CSTRING_CONV_ERR));
+            let schema_cstring = ptr_to_cstring(arg2 as *mut c_char);
+            let schema = match serde_json::from_str(schema_cstring.to_str().expect(CSTRING_CONV_ERR)) {
+                Ok(schema) => schema,
+                Err(err) => return Err(format!("Unable to parse the supplied CSV schema: {:?}", err)),
+            };
+            HistTickDst::Csv{filename: filename_string, schema: schema}
+        },
+        _ => return Err(format!("Invalid ID given for `HistTickDst` conversion function: {}", id)),
+    })
 }
 
 /// Creates a CommandServer on the heap and returns it as a mutable reference.
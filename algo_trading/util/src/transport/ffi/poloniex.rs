@@ -1,6 +1,7 @@
 //! FFI methods for communicating with the Poloniex streaming API
 
 use std::collections::HashMap;
+use std::ptr;
 use libc::{c_int, c_char, c_void};
 
 use serde::{Serialize, Deserialize};
@@ -10,6 +11,10 @@ use trading::tick::GenTick;
 use transport::tickstream::maps::poloniex::{PoloniexBookModifyMap, PoloniexBookRemovalMap, PolniexOrderBookModification};
 use transport::tickstream::maps::poloniex::{PoloniexOrderBookRemoval, PoloniexTradeMap, PoloniexTrade};
 use transport::tickstream::sinks::csv_sink::CsvSink;
+#[cfg(feature = "parquet")]
+use transport::tickstream::sinks::parquet_sink::ParquetSink;
+#[cfg(not(feature = "parquet"))]
+use transport::NO_PARQUET_SUPPORT;
 use transport::tickstream::generics::{GenTickSink, GenTickMap};
 use transport::command_server::CommandServer;
 use super::*;
@@ -53,7 +58,8 @@ trait PoloniexExecutor<T> {
 }
 
 /// Given the ID of the type of executor that's needed, returns a pointer to one.  Internally, this sets up the processing pipeline for
-/// taking ticks in, mapping them to the necessary format, and pushing them into the sink.
+/// taking ticks in, mapping them to the necessary format, and pushing them into the sink.  If the sink can't be created, the error
+/// is logged and a null pointer is returned.
 #[no_mangle]
 pub unsafe extern "C" fn get_executor(
     executor_id: i64, sink_id: i64, sink_arg1: *mut c_void, sink_arg2: *mut c_void
@@ -61,26 +67,47 @@ pub unsafe extern "C" fn get_executor(
     // get a properly named `CommandServer` and the correct map (boxed and cast to a *mut c_void) that correspond to the executor
     match executor_id as i32 {
         POLONIEX_BOOK_MODIFY => {
-            let cs = CommandServer::new(Uuid::new_v4(), "Poloniex Order Book Modification Executor");
+            let mut cs = CommandServer::new(Uuid::new_v4(), "Poloniex Order Book Modification Executor");
+            let sink = match get_poloniex_gen_sink_wrapper(sink_id as i32, sink_arg1, sink_arg2) {
+                Ok(sink) => sink,
+                Err(err) => {
+                    cs.error(None, &format!("Unable to create the executor's sink: {}", err));
+                    return ptr::null_mut();
+                },
+            };
             Box::into_raw(Box::new(BookModificationExecutor {
-                map: Box::into_raw(Box::new(PoloniexBookModifyMap::new(HashMap::new(), cs.clone()))) as *mut c_void,
-                sink: get_poloniex_gen_sink_wrapper(sink_id as i32, sink_arg1, sink_arg2),
+                map: Box::into_raw(Box::new(PoloniexBookModifyMap::new(HashMap::new(), cs.clone()).expect("Unable to create `PoloniexBookModifyMap`!"))) as *mut c_void,
+                sink: sink,
                 cs: cs,
             })) as *mut c_void
         },
         POLONIEX_BOOK_REMOVE => {
-            let cs = CommandServer::new(Uuid::new_v4(), "Poloniex Order Book Removal Executor");
+            let mut cs = CommandServer::new(Uuid::new_v4(), "Poloniex Order Book Removal Executor");
+            let sink = match get_poloniex_gen_sink_wrapper(sink_id as i32, sink_arg1, sink_arg2) {
+                Ok(sink) => sink,
+                Err(err) => {
+                    cs.error(None, &format!("Unable to create the executor's sink: {}", err));
+                    return ptr::null_mut();
+                },
+            };
             Box::into_raw(Box::new(BookRemovalExecutor {
-                map: Box::into_raw(Box::new(PoloniexBookRemovalMap::new(HashMap::new(), cs.clone()))) as *mut c_void,
-                sink: get_poloniex_gen_sink_wrapper(sink_id as i32, sink_arg1, sink_arg2),
+                map: Box::into_raw(Box::new(PoloniexBookRemovalMap::new(HashMap::new(), cs.clone()).expect("Unable to create `PoloniexBookRemovalMap`!"))) as *mut c_void,
+                sink: sink,
                 cs: cs,
             })) as *mut c_void
         },
         POLONIEX_NEW_TRADE => {
-            let cs = CommandServer::new(Uuid::new_v4(), "Poloniex New Trade Executor");
+            let mut cs = CommandServer::new(Uuid::new_v4(), "Poloniex New Trade Executor");
+            let sink = match get_poloniex_gen_sink_wrapper(sink_id as i32, sink_arg1, sink_arg2) {
+                Ok(sink) => sink,
+                Err(err) => {
+                    cs.error(None, &format!("Unable to create the executor's sink: {}", err));
+                    return ptr::null_mut();
+                },
+            };
             Box::into_raw(Box::new(TradeExecutor {
-                map: Box::into_raw(Box::new(PoloniexTradeMap::new(HashMap::new(), cs.clone()))) as *mut c_void,
-                sink: get_poloniex_gen_sink_wrapper(sink_id as i32, sink_arg1, sink_arg2),
+                map: Box::into_raw(Box::new(PoloniexTradeMap::new(HashMap::new(), cs.clone()).expect("Unable to create `PoloniexTradeMap`!"))) as *mut c_void,
+                sink: sink,
                 cs: cs,
             })) as *mut c_void
         }
@@ -109,21 +136,33 @@ pub unsafe extern "C" fn process_event(event_id: i64, state_ptr: *mut c_void, ti
     };
 }
 
-/// Given a sink ID, returns a `GenTickSink` for it.  I'm putting a lot of trust in you not providing sinks that don't work, because
-/// that's going to panic for now.  Also panics if you provide it a bad argument.
+/// Given a sink ID, returns a `GenTickSink` for it.  Returns an error if the sink isn't supported or can't be created from the
+/// supplied arguments.  Still panics if the arguments aren't valid C strings.
 unsafe fn get_poloniex_gen_sink_wrapper<T>(
     sink_id: c_int, arg1: *mut c_void, arg2: *mut c_void
-) -> Box<GenTickSink<T>> where T : Serialize, T : for<'de> Deserialize<'de>, T: 'static {
-    // TODO: Convert to returning a nullptr/error message kind of thing instead of just dying so that we can make this dynamic
-    match sink_id {
+) -> Result<Box<GenTickSink<T>>, String> where T : Serialize, T : for<'de> Deserialize<'de>, T: 'static {
+    Ok(match sink_id {
         CSV => {
             let path_string = String::from(ptr_to_cstring(arg1 as *mut c_char).to_str().expect("Bad CString provided as argument to CSV Sink!"));
             let mut settings = HashMap::new();
             settings.insert(String::from("output_path"), path_string);
-            Box::new(CsvSink::new(settings).expect("Unable to create `CsvSink` from supplied settings!") as CsvSink<T>)
+            Box::new(try!(CsvSink::new(settings)) as CsvSink<T>)
         },
-        _ => unimplemented!(),
-    }
+        #[cfg(not(feature = "parquet"))]
+        PARQUET => return Err(String::from(NO_PARQUET_SUPPORT)),
+        #[cfg(feature = "parquet")]
+        PARQUET => {
+            let path_string = String::from(ptr_to_cstring(arg1 as *mut c_char).to_str().expect("Bad CString provided as argument to Parquet Sink!"));
+            let mut settings = HashMap::new();
+            settings.insert(String::from("output_path"), path_string);
+            if !arg2.is_null() {
+                let size_string = String::from(ptr_to_cstring(arg2 as *mut c_char).to_str().expect("Bad CString provided as argument to Parquet Sink!"));
+                settings.insert(String::from("row_group_size"), size_string);
+            }
+            Box::new(try!(ParquetSink::new(settings)) as ParquetSink<T>)
+        },
+        _ => return Err(format!("Unsupported sink ID: {}", sink_id)),
+    })
 }
 
 /// Piece of state holding the maps and sinks required to process JSON-encoded order book modifications into a sink
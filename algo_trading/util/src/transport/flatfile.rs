@@ -0,0 +1,325 @@
+//! Reading and writing of flatfiles that may be compressed or split up into date-partitioned archives.
+//!
+//! Files are transparently compressed and decompressed based on their extension: `.gz` for gzip, `.zst` for zstd,
+//! and `.xz` for xz.  Other files are read and written as-is.  An archive is a directory containing data files,
+//! possibly nested in subdirectories such as `EURUSD/2016/week_12.csv.gz`, that is read as one continuous stream.
+
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Lines, Write};
+use std::path::{Path, PathBuf};
+
+use libflate::gzip;
+use xz2::read::XzDecoder;
+use xz2::write::XzEncoder;
+use zstd;
+
+/// Compression level used when writing zstd and xz files
+const COMPRESSION_LEVEL: u32 = 6;
+
+/// How a flatfile is compressed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+    Xz,
+}
+
+impl Compression {
+    /// Determines the compression of a file from its extension.
+    pub fn from_path(path: &Path) -> Compression {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("gz") => Compression::Gzip,
+            Some("zst") | Some("zstd") => Compression::Zstd,
+            Some("xz") => Compression::Xz,
+            _ => Compression::None,
+        }
+    }
+
+    /// The extension that files compressed this way end with
+    pub fn extension(&self) -> Option<&'static str> {
+        match *self {
+            Compression::None => None,
+            Compression::Gzip => Some("gz"),
+            Compression::Zstd => Some("zst"),
+            Compression::Xz => Some("xz"),
+        }
+    }
+}
+
+fn debug_err<T: ::std::fmt::Debug>(x: T) -> String {
+    format!("{:?}", x)
+}
+
+/// Opens a file for reading, decompressing it if necessary.  Files made up of several concatenated compressed
+/// streams, such as those created by appending to compressed files, are read in their entirety.
+pub fn open_file(path: &Path) -> Result<Box<BufRead + Send>, String> {
+    let file = try!(File::open(path).map_err(|err| format!("Unable to open {:?}: {}", path, err)));
+    let reader: Box<BufRead + Send> = match Compression::from_path(path) {
+        Compression::None => Box::new(BufReader::new(file)),
+        Compression::Gzip => Box::new(BufReader::new(try!(gzip::MultiDecoder::new(file).map_err(debug_err)))),
+        Compression::Zstd => Box::new(BufReader::new(try!(zstd::stream::read::Decoder::new(file).map_err(debug_err)))),
+        Compression::Xz => Box::new(BufReader::new(XzDecoder::new_multi_decoder(file))),
+    };
+
+    Ok(reader)
+}
+
+/// Opens a file for writing, compressing the written data if necessary.  If `append` is true, data is added to the
+/// end of the file if it exists.
+pub fn create_file(path: &Path, append: bool) -> Result<FlatfileWriter, String> {
+    let file = try!(
+        OpenOptions::new().write(true).create(true).append(append).truncate(!append).open(path)
+            .map_err(|err| format!("Unable to open {:?} for writing: {}", path, err))
+    );
+    let encoder = match Compression::from_path(path) {
+        Compression::None => FlatfileEncoder::None(file),
+        Compression::Gzip => FlatfileEncoder::Gzip(try!(gzip::Encoder::new(file).map_err(debug_err))),
+        Compression::Zstd => {
+            FlatfileEncoder::Zstd(try!(zstd::stream::write::Encoder::new(file, COMPRESSION_LEVEL as i32).map_err(debug_err)))
+        },
+        Compression::Xz => FlatfileEncoder::Xz(XzEncoder::new(file, COMPRESSION_LEVEL)),
+    };
+
+    Ok(FlatfileWriter {
+        encoder: Some(encoder),
+    })
+}
+
+enum FlatfileEncoder {
+    None(File),
+    Gzip(gzip::Encoder<File>),
+    Zstd(zstd::stream::write::Encoder<File>),
+    Xz(XzEncoder<File>),
+}
+
+/// Writes to a file created by `create_file()`.  Compressed files aren't complete until `finish()` has been called,
+/// which happens automatically when the writer is dropped, but errors can only be handled by calling it explicitly.
+pub struct FlatfileWriter {
+    encoder: Option<FlatfileEncoder>,
+}
+
+impl FlatfileWriter {
+    fn encoder(&mut self) -> io::Result<&mut Write> {
+        match self.encoder {
+            Some(FlatfileEncoder::None(ref mut file)) => Ok(file as &mut Write),
+            Some(FlatfileEncoder::Gzip(ref mut encoder)) => Ok(encoder as &mut Write),
+            Some(FlatfileEncoder::Zstd(ref mut encoder)) => Ok(encoder as &mut Write),
+            Some(FlatfileEncoder::Xz(ref mut encoder)) => Ok(encoder as &mut Write),
+            None => Err(io::Error::new(io::ErrorKind::Other, "Can't write to a flatfile that has been finished.")),
+        }
+    }
+
+    /// Finishes the compressed stream and flushes everything to the file.
+    pub fn finish(&mut self) -> Result<(), String> {
+        let mut file = match self.encoder.take() {
+            Some(FlatfileEncoder::None(file)) => file,
+            Some(FlatfileEncoder::Gzip(encoder)) => try!(encoder.finish().into_result().map_err(debug_err)),
+            Some(FlatfileEncoder::Zstd(encoder)) => try!(encoder.finish().map_err(debug_err)),
+            Some(FlatfileEncoder::Xz(encoder)) => try!(encoder.finish().map_err(debug_err)),
+            None => return Err(String::from("The flatfile has already been finished.")),
+        };
+
+        file.flush().map_err(debug_err)
+    }
+}
+
+impl Write for FlatfileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        try!(self.encoder()).write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        try!(self.encoder()).flush()
+    }
+}
+
+impl Drop for FlatfileWriter {
+    fn drop(&mut self) {
+        if self.encoder.is_some() {
+            if let Err(err) = self.finish() {
+                println!("Error while finishing flatfile: {}", err);
+            }
+        }
+    }
+}
+
+/// Compares strings so that runs of digits are ordered by their numeric value, putting `week_2` before `week_12`.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        match (a.peek().cloned(), b.peek().cloned()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_digit(10) && y.is_digit(10) => {
+                let mut take_number = |chars: &mut ::std::iter::Peekable<::std::str::Chars>| {
+                    let mut digits = String::new();
+                    while chars.peek().map(|c| c.is_digit(10)).unwrap_or(false) {
+                        digits.push(chars.next().unwrap());
+                    }
+                    digits
+                };
+                let (x_num, y_num) = (take_number(&mut a), take_number(&mut b));
+                let (x_num, y_num) = (x_num.trim_left_matches('0'), y_num.trim_left_matches('0'));
+                let ord = x_num.len().cmp(&y_num.len()).then_with(|| x_num.cmp(y_num));
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+            },
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                a.next();
+                b.next();
+            },
+        }
+    }
+}
+
+/// Returns the data files that make up an archive in the order that they should be read.  Directories are
+/// searched recursively and entries are ordered naturally, so numbered partitions are read in numeric order.
+/// Hidden files are skipped.  If `root` is a file, it is the only file returned.
+pub fn archive_files(root: &Path) -> Result<Vec<PathBuf>, String> {
+    if !root.is_dir() {
+        return Ok(vec![root.to_path_buf()]);
+    }
+
+    let mut entries: Vec<PathBuf> = try!(
+        try!(fs::read_dir(root).map_err(|err| format!("Unable to read directory {:?}: {}", root, err)))
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<io::Result<Vec<PathBuf>>>()
+            .map_err(debug_err)
+    );
+    entries.retain(|path| {
+        !path.file_name().and_then(|name| name.to_str()).map(|name| name.starts_with('.')).unwrap_or(true)
+    });
+    entries.sort_by(|a, b| natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()));
+
+    let mut files = Vec::new();
+    for entry in entries {
+        files.extend(try!(archive_files(&entry)));
+    }
+
+    Ok(files)
+}
+
+/// Iterates over the lines of every file in an archive, one file after another.  A file that can't be read
+/// yields an error and is then skipped.
+pub struct ArchiveLines {
+    files: VecDeque<PathBuf>,
+    cur: Option<Lines<Box<BufRead + Send>>>,
+    /// Whether the first line of every file is a header that should be skipped
+    skip_headers: bool,
+}
+
+impl ArchiveLines {
+    /// Reads the lines of the file or archive directory at `root`.
+    pub fn open(root: &Path, skip_headers: bool) -> Result<ArchiveLines, String> {
+        let files = try!(archive_files(root));
+        if files.is_empty() {
+            return Err(format!("The archive at {:?} doesn't contain any files", root));
+        }
+
+        Ok(ArchiveLines {
+            files: files.into_iter().collect(),
+            cur: None,
+            skip_headers: skip_headers,
+        })
+    }
+}
+
+impl Iterator for ArchiveLines {
+    type Item = Result<String, String>;
+
+    fn next(&mut self) -> Option<Result<String, String>> {
+        loop {
+            if let Some(line) = self.cur.as_mut().and_then(|lines| lines.next()) {
+                return Some(line.map_err(debug_err));
+            }
+
+            let path = match self.files.pop_front() {
+                Some(path) => path,
+                None => return None,
+            };
+            match open_file(&path) {
+                Ok(reader) => {
+                    let mut lines = reader.lines();
+                    if self.skip_headers {
+                        lines.next();
+                    }
+                    self.cur = Some(lines);
+                },
+                Err(err) => {
+                    self.cur = None;
+                    return Some(Err(err));
+                },
+            }
+        }
+    }
+}
+
+/// Returns the path of the file or archive directory for a symbol in `dir` that has the given extension.  A plain
+/// file is preferred over compressed ones, which are preferred over an archive directory named after the symbol.
+pub fn find_symbol_data(dir: &Path, symbol: &str, extension: &str) -> Option<PathBuf> {
+    let compressions = [Compression::None, Compression::Gzip, Compression::Zstd, Compression::Xz];
+    let candidates = compressions.iter().map(|compression| {
+        match compression.extension() {
+            Some(compression_ext) => dir.join(format!("{}.{}.{}", symbol, extension, compression_ext)),
+            None => dir.join(format!("{}.{}", symbol, extension)),
+        }
+    }).chain(Some(dir.join(symbol)));
+
+    for path in candidates {
+        if path.exists() {
+            return Some(path);
+        }
+    }
+
+    None
+}
+
+#[test]
+fn compressed_flatfiles_and_archives() {
+    use std::env;
+
+    assert!(natural_cmp("week_2.csv", "week_12.csv") == Ordering::Less);
+    assert!(natural_cmp("2016/week_52", "2017/week_1") == Ordering::Less);
+    assert!(natural_cmp("a02", "a2") == Ordering::Equal);
+
+    let mut root = env::temp_dir();
+    root.push("flatfile_test");
+    let _ = fs::remove_dir_all(&root);
+    let archive = root.join("EURUSD");
+    fs::create_dir_all(archive.join("2016")).unwrap();
+    fs::create_dir_all(archive.join("2017")).unwrap();
+
+    let parts = [
+        ("2016/week_2.csv.gz", 2), ("2016/week_12.csv.zst", 12), ("2016/week_30.csv", 30), ("2017/week_1.csv.xz", 101),
+    ];
+    for &(name, n) in parts.iter() {
+        let mut writer = create_file(&archive.join(name), false).unwrap();
+        writer.write_all(format!("time,bid,ask\n{},1,1\n", n).as_bytes()).unwrap();
+        writer.finish().unwrap();
+        assert!(writer.write_all(b"4,1,1\n").is_err());
+        assert!(writer.finish().is_err());
+    }
+    // appending to a compressed file adds a second stream which is read as part of the same file
+    create_file(&archive.join("2016/week_2.csv.gz"), true).unwrap().write_all(b"3,1,1\n").unwrap();
+
+    let lines: Vec<String> = ArchiveLines::open(&archive, true).unwrap().map(|line| line.unwrap()).collect();
+    assert_eq!(lines, vec!["2,1,1", "3,1,1", "12,1,1", "30,1,1", "101,1,1"]);
+
+    assert_eq!(find_symbol_data(&root, "EURUSD", "csv"), Some(archive.clone()));
+    let mut single = create_file(&root.join("GBPUSD.csv.xz"), false).unwrap();
+    single.write_all(b"5,1,1\n").unwrap();
+    single.finish().unwrap();
+    let path = find_symbol_data(&root, "GBPUSD", "csv").unwrap();
+    assert_eq!(Compression::from_path(&path), Compression::Xz);
+    let lines: Vec<String> = ArchiveLines::open(&path, false).unwrap().map(|line| line.unwrap()).collect();
+    assert_eq!(lines, vec!["5,1,1"]);
+    assert!(find_symbol_data(&root, "USDJPY", "csv").is_none());
+}
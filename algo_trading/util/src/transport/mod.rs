@@ -8,4 +8,13 @@ pub mod command_server;
 pub mod tickstream;
 pub mod textlog;
 pub mod data;
+pub mod flatfile;
+pub mod csv_schema;
+pub mod tickfile;
+#[cfg(feature = "parquet")]
+pub mod parquet_file;
 pub mod ffi;
+
+/// The error returned when Parquet files are used without the `parquet` feature
+#[cfg(not(feature = "parquet"))]
+pub const NO_PARQUET_SUPPORT: &'static str = "Parquet support isn't enabled; build `tickgrinder-util` with the `parquet` feature to use Parquet files.";
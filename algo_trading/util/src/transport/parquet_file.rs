@@ -0,0 +1,1384 @@
+//! Reading and writing of Apache Parquet files so that tick data can be exchanged with dataframe tools.
+//!
+//! Ticks are written with three required `INT64` columns: `timestamp`, `bid`, and `ask`.  Rows are buffered and
+//! written out in row groups of a configurable size.  When reading, only the timestamp, bid, and ask columns are
+//! decoded and row groups whose timestamp statistics lie entirely outside of the requested range are skipped without
+//! being read.
+//!
+//! Only the part of the format that flat tables need is implemented.  The file metadata and page headers are encoded
+//! with the Thrift compact protocol, each column chunk is written as a single uncompressed data page of `PLAIN`
+//! encoded values, and the nulls of optional columns are marked with bit-packed definition levels.
+//!
+//! ```text
+//! file:    magic ("PAR1") | column chunks | file metadata | metadata length (u32) | magic ("PAR1")
+//! chunk:   page header | definition level length (u32) and levels (optional columns only) | values
+//! ```
+//!
+//! Files written by other tools can be read as long as the timestamp, bid, and ask columns are stored as integers
+//! in uncompressed `PLAIN` encoded pages; compressed or dictionary encoded columns are rejected with an error.  With
+//! pyarrow, for example, such files are written by passing `compression='none', use_dictionary=False`.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{Read, Write, Seek, SeekFrom, BufWriter};
+use std::mem;
+use std::path::Path;
+
+use serde_json::Value;
+
+use trading::tick::Tick;
+
+/// Extension of Parquet files
+pub const PARQUET_EXTENSION: &'static str = "parquet";
+/// Number of rows in each row group if no other size is given
+pub const DEFAULT_ROW_GROUP_SIZE: usize = 65536;
+
+const MAGIC: &'static [u8; 4] = b"PAR1";
+/// Length of the metadata length and the magic at the end of the file
+const FOOTER_LEN: u64 = 8;
+
+// Values of the enums of the Parquet format that are used here
+const TYPE_BOOLEAN: i32 = 0;
+const TYPE_INT32: i32 = 1;
+const TYPE_INT64: i32 = 2;
+const TYPE_DOUBLE: i32 = 5;
+const TYPE_BYTE_ARRAY: i32 = 6;
+const REQUIRED: i32 = 0;
+const OPTIONAL: i32 = 1;
+const CONVERTED_UTF8: i32 = 0;
+const ENCODING_PLAIN: i32 = 0;
+const ENCODING_RLE: i32 = 3;
+const CODEC_UNCOMPRESSED: i32 = 0;
+const PAGE_DATA: i32 = 0;
+const PAGE_DICTIONARY: i32 = 2;
+
+// Types of the fields of the Thrift compact protocol
+const CT_STOP: u8 = 0;
+const CT_TRUE: u8 = 1;
+const CT_FALSE: u8 = 2;
+const CT_BYTE: u8 = 3;
+const CT_I16: u8 = 4;
+const CT_I32: u8 = 5;
+const CT_I64: u8 = 6;
+const CT_DOUBLE: u8 = 7;
+const CT_BINARY: u8 = 8;
+const CT_LIST: u8 = 9;
+const CT_SET: u8 = 10;
+const CT_MAP: u8 = 11;
+const CT_STRUCT: u8 = 12;
+
+fn debug_err<T: ::std::fmt::Debug>(x: T) -> String {
+    format!("{:?}", x)
+}
+
+fn write_varint(buf: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        buf.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+    buf.push(n as u8);
+}
+
+fn read_varint(buf: &[u8], pos: &mut usize) -> Result<u64, String> {
+    let mut n: u64 = 0;
+    let mut shift = 0;
+    loop {
+        if *pos >= buf.len() || shift > 63 {
+            return Err(String::from("Truncated or invalid varint in Parquet file"));
+        }
+        let byte = buf[*pos];
+        *pos += 1;
+        n |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(n);
+        }
+        shift += 7;
+    }
+}
+
+fn zigzag(n: i64) -> u64 {
+    ((n << 1) ^ (n >> 63)) as u64
+}
+
+fn unzigzag(n: u64) -> i64 {
+    ((n >> 1) as i64) ^ -((n & 1) as i64)
+}
+
+/// Appends the lowest `len` bytes of `n` in little-endian order.
+fn write_le(buf: &mut Vec<u8>, n: u64, len: usize) {
+    for i in 0..len {
+        buf.push((n >> (i * 8)) as u8);
+    }
+}
+
+fn read_le(buf: &[u8]) -> u64 {
+    buf.iter().rev().fold(0, |acc, &byte| (acc << 8) | byte as u64)
+}
+
+/// Decodes a little-endian `INT32` or `INT64` value depending on the number of bytes.
+fn read_int(buf: &[u8]) -> i64 {
+    let n = read_le(buf);
+    if buf.len() == 4 { n as u32 as i32 as i64 } else { n as i64 }
+}
+
+fn encode_i64(n: i64) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(8);
+    write_le(&mut buf, n as u64, 8);
+    buf
+}
+
+/// Encodes structs with the Thrift compact protocol.  The fields of a struct have to be written in order of their
+/// ids, and every `begin_struct` or `struct_field` needs a matching `end_struct`.
+struct ThriftWriter {
+    buf: Vec<u8>,
+    /// The id of the last field written to each of the structs that are open
+    last_ids: Vec<i16>,
+}
+
+impl ThriftWriter {
+    fn new() -> ThriftWriter {
+        ThriftWriter {
+            buf: Vec::new(),
+            last_ids: Vec::new(),
+        }
+    }
+
+    fn begin_struct(&mut self) {
+        self.last_ids.push(0);
+    }
+
+    fn end_struct(&mut self) {
+        self.buf.push(CT_STOP);
+        self.last_ids.pop();
+    }
+
+    /// Writes a field header, storing the id as a delta from the previous field's if it's small enough.
+    fn field(&mut self, id: i16, field_type: u8) {
+        let delta = {
+            let last = self.last_ids.last_mut().expect("Thrift field written outside of a struct");
+            let delta = id - *last;
+            *last = id;
+            delta
+        };
+        if delta > 0 && delta <= 15 {
+            self.buf.push(((delta as u8) << 4) | field_type);
+        } else {
+            self.buf.push(field_type);
+            write_varint(&mut self.buf, zigzag(id as i64));
+        }
+    }
+
+    fn i32(&mut self, n: i32) {
+        write_varint(&mut self.buf, zigzag(n as i64));
+    }
+
+    fn binary(&mut self, bytes: &[u8]) {
+        write_varint(&mut self.buf, bytes.len() as u64);
+        self.buf.extend_from_slice(bytes);
+    }
+
+    fn i32_field(&mut self, id: i16, n: i32) {
+        self.field(id, CT_I32);
+        self.i32(n);
+    }
+
+    fn i64_field(&mut self, id: i16, n: i64) {
+        self.field(id, CT_I64);
+        write_varint(&mut self.buf, zigzag(n));
+    }
+
+    fn binary_field(&mut self, id: i16, bytes: &[u8]) {
+        self.field(id, CT_BINARY);
+        self.binary(bytes);
+    }
+
+    fn struct_field(&mut self, id: i16) {
+        self.field(id, CT_STRUCT);
+        self.begin_struct();
+    }
+
+    /// Writes the header of a list field; the `len` elements have to be written after it.
+    fn list_field(&mut self, id: i16, len: usize, elem_type: u8) {
+        self.field(id, CT_LIST);
+        if len < 15 {
+            self.buf.push(((len as u8) << 4) | elem_type);
+        } else {
+            self.buf.push(0xf0 | elem_type);
+            write_varint(&mut self.buf, len as u64);
+        }
+    }
+}
+
+/// Decodes structs encoded with the Thrift compact protocol.  Fields that aren't needed are skipped over.
+struct ThriftReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    /// The id of the last field read from each of the structs that are open
+    last_ids: Vec<i16>,
+}
+
+impl<'a> ThriftReader<'a> {
+    fn new(buf: &'a [u8]) -> ThriftReader<'a> {
+        ThriftReader {
+            buf: buf,
+            pos: 0,
+            last_ids: Vec::new(),
+        }
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        if self.pos >= self.buf.len() {
+            return Err(String::from("Truncated Parquet metadata"));
+        }
+        self.pos += 1;
+        Ok(self.buf[self.pos - 1])
+    }
+
+    fn i32(&mut self) -> Result<i32, String> {
+        Ok(unzigzag(try!(read_varint(self.buf, &mut self.pos))) as i32)
+    }
+
+    fn i64(&mut self) -> Result<i64, String> {
+        Ok(unzigzag(try!(read_varint(self.buf, &mut self.pos))))
+    }
+
+    fn binary(&mut self) -> Result<&'a [u8], String> {
+        let len = try!(read_varint(self.buf, &mut self.pos)) as usize;
+        if self.buf.len() - self.pos < len {
+            return Err(String::from("Truncated Parquet metadata"));
+        }
+        self.pos += len;
+        Ok(&self.buf[self.pos - len..self.pos])
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let bytes = try!(self.binary());
+        String::from_utf8(bytes.to_vec()).map_err(debug_err)
+    }
+
+    fn begin_struct(&mut self) {
+        self.last_ids.push(0);
+    }
+
+    /// Returns the id and type of the next field of the current struct or `None` once the struct has ended.
+    fn field(&mut self) -> Result<Option<(i16, u8)>, String> {
+        let header = try!(self.byte());
+        if header == CT_STOP {
+            self.last_ids.pop();
+            return Ok(None);
+        }
+
+        let delta = (header >> 4) as i16;
+        let id = if delta == 0 {
+            unzigzag(try!(read_varint(self.buf, &mut self.pos))) as i16
+        } else {
+            self.last_ids.last().cloned().unwrap_or(0) + delta
+        };
+        if let Some(last) = self.last_ids.last_mut() {
+            *last = id;
+        }
+
+        Ok(Some((id, header & 0x0f)))
+    }
+
+    /// Returns the length and element type of a list.
+    fn list(&mut self) -> Result<(usize, u8), String> {
+        let header = try!(self.byte());
+        let len = match header >> 4 {
+            15 => try!(read_varint(self.buf, &mut self.pos)) as usize,
+            len => len as usize,
+        };
+
+        Ok((len, header & 0x0f))
+    }
+
+    /// Skips over a value of the given type.  Booleans are stored in the field header unless they're elements of a
+    /// collection, in which case they take up a byte.
+    fn skip(&mut self, value_type: u8, in_collection: bool) -> Result<(), String> {
+        match value_type {
+            CT_TRUE | CT_FALSE => if in_collection {
+                try!(self.byte());
+            },
+            CT_BYTE => {
+                try!(self.byte());
+            },
+            CT_I16 | CT_I32 | CT_I64 => {
+                try!(read_varint(self.buf, &mut self.pos));
+            },
+            CT_DOUBLE => for _ in 0..8 {
+                try!(self.byte());
+            },
+            CT_BINARY => {
+                try!(self.binary());
+            },
+            CT_LIST | CT_SET => {
+                let (len, elem_type) = try!(self.list());
+                for _ in 0..len {
+                    try!(self.skip(elem_type, true));
+                }
+            },
+            CT_MAP => {
+                let len = try!(read_varint(self.buf, &mut self.pos));
+                if len > 0 {
+                    let types = try!(self.byte());
+                    for _ in 0..len {
+                        try!(self.skip(types >> 4, true));
+                        try!(self.skip(types & 0x0f, true));
+                    }
+                }
+            },
+            CT_STRUCT => {
+                self.begin_struct();
+                while let Some((_, field_type)) = try!(self.field()) {
+                    try!(self.skip(field_type, false));
+                }
+            },
+            other => return Err(format!("Invalid Thrift type {} in Parquet metadata", other)),
+        }
+
+        Ok(())
+    }
+}
+
+/// An element of a file's schema, which is either a column or the group containing the columns
+#[derive(Debug, Clone)]
+struct SchemaElement {
+    name: String,
+    /// Not set for groups
+    physical_type: Option<i32>,
+    repetition: Option<i32>,
+    num_children: i32,
+}
+
+/// Where the values of one column of a row group are stored
+#[derive(Debug, Clone)]
+struct ColumnChunk {
+    codec: i32,
+    /// Number of values in the chunk including nulls
+    num_values: i64,
+    /// Length of all of the chunk's pages including their headers
+    total_size: i64,
+    data_page_offset: i64,
+    dictionary_page_offset: Option<i64>,
+    /// The smallest and largest value of the chunk encoded the same way as its values
+    min: Option<Vec<u8>>,
+    max: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone)]
+struct RowGroup {
+    columns: Vec<ColumnChunk>,
+    num_rows: i64,
+}
+
+#[derive(Debug, Clone)]
+struct FileMetadata {
+    schema: Vec<SchemaElement>,
+    row_groups: Vec<RowGroup>,
+}
+
+fn read_schema_element(r: &mut ThriftReader) -> Result<SchemaElement, String> {
+    let mut element = SchemaElement {
+        name: String::new(),
+        physical_type: None,
+        repetition: None,
+        num_children: 0,
+    };
+
+    r.begin_struct();
+    while let Some((id, field_type)) = try!(r.field()) {
+        match (id, field_type) {
+            (1, CT_I32) => element.physical_type = Some(try!(r.i32())),
+            (3, CT_I32) => element.repetition = Some(try!(r.i32())),
+            (4, CT_BINARY) => element.name = try!(r.string()),
+            (5, CT_I32) => element.num_children = try!(r.i32()),
+            _ => try!(r.skip(field_type, false)),
+        }
+    }
+
+    Ok(element)
+}
+
+/// Reads the min and max of the statistics of a column chunk.  The `min_value` and `max_value` fields come after
+/// the deprecated `min` and `max` fields, so they replace them if both are set.
+fn read_statistics(r: &mut ThriftReader, chunk: &mut ColumnChunk) -> Result<(), String> {
+    r.begin_struct();
+    while let Some((id, field_type)) = try!(r.field()) {
+        match (id, field_type) {
+            (1, CT_BINARY) | (5, CT_BINARY) => chunk.max = Some(try!(r.binary()).to_vec()),
+            (2, CT_BINARY) | (6, CT_BINARY) => chunk.min = Some(try!(r.binary()).to_vec()),
+            _ => try!(r.skip(field_type, false)),
+        }
+    }
+
+    Ok(())
+}
+
+fn read_column_chunk(r: &mut ThriftReader) -> Result<ColumnChunk, String> {
+    let mut chunk = ColumnChunk {
+        codec: CODEC_UNCOMPRESSED,
+        num_values: 0,
+        total_size: 0,
+        data_page_offset: 0,
+        dictionary_page_offset: None,
+        min: None,
+        max: None,
+    };
+
+    r.begin_struct();
+    while let Some((id, field_type)) = try!(r.field()) {
+        if (id, field_type) != (3, CT_STRUCT) {
+            try!(r.skip(field_type, false));
+            continue;
+        }
+
+        // the column metadata
+        r.begin_struct();
+        while let Some((id, field_type)) = try!(r.field()) {
+            match (id, field_type) {
+                (4, CT_I32) => chunk.codec = try!(r.i32()),
+                (5, CT_I64) => chunk.num_values = try!(r.i64()),
+                (7, CT_I64) => chunk.total_size = try!(r.i64()),
+                (9, CT_I64) => chunk.data_page_offset = try!(r.i64()),
+                (11, CT_I64) => chunk.dictionary_page_offset = Some(try!(r.i64())),
+                (12, CT_STRUCT) => try!(read_statistics(r, &mut chunk)),
+                _ => try!(r.skip(field_type, false)),
+            }
+        }
+    }
+
+    Ok(chunk)
+}
+
+fn read_row_group_metadata(r: &mut ThriftReader) -> Result<RowGroup, String> {
+    let mut row_group = RowGroup {
+        columns: Vec::new(),
+        num_rows: 0,
+    };
+
+    r.begin_struct();
+    while let Some((id, field_type)) = try!(r.field()) {
+        match (id, field_type) {
+            (1, CT_LIST) => {
+                let (len, _) = try!(r.list());
+                for _ in 0..len {
+                    row_group.columns.push(try!(read_column_chunk(r)));
+                }
+            },
+            (3, CT_I64) => row_group.num_rows = try!(r.i64()),
+            _ => try!(r.skip(field_type, false)),
+        }
+    }
+
+    Ok(row_group)
+}
+
+fn read_file_metadata(r: &mut ThriftReader) -> Result<FileMetadata, String> {
+    let mut metadata = FileMetadata {
+        schema: Vec::new(),
+        row_groups: Vec::new(),
+    };
+
+    r.begin_struct();
+    while let Some((id, field_type)) = try!(r.field()) {
+        match (id, field_type) {
+            (2, CT_LIST) => {
+                let (len, _) = try!(r.list());
+                for _ in 0..len {
+                    metadata.schema.push(try!(read_schema_element(r)));
+                }
+            },
+            (4, CT_LIST) => {
+                let (len, _) = try!(r.list());
+                for _ in 0..len {
+                    metadata.row_groups.push(try!(read_row_group_metadata(r)));
+                }
+            },
+            _ => try!(r.skip(field_type, false)),
+        }
+    }
+
+    Ok(metadata)
+}
+
+/// Encodes the metadata of a file containing the given columns and row groups.
+fn write_file_metadata(columns: &[Column], row_groups: &[RowGroup]) -> Vec<u8> {
+    let mut w = ThriftWriter::new();
+    w.begin_struct();
+    w.i32_field(1, 1); // version
+
+    // the schema is a root group followed by the columns
+    w.list_field(2, columns.len() + 1, CT_STRUCT);
+    w.begin_struct();
+    w.binary_field(4, b"schema");
+    w.i32_field(5, columns.len() as i32);
+    w.end_struct();
+    for column in columns {
+        w.begin_struct();
+        w.i32_field(1, column.physical_type());
+        w.i32_field(3, if column.optional { OPTIONAL } else { REQUIRED });
+        w.binary_field(4, column.name.as_bytes());
+        if column.column_type == ColumnType::Str {
+            w.i32_field(6, CONVERTED_UTF8);
+        }
+        w.end_struct();
+    }
+
+    w.i64_field(3, row_groups.iter().map(|row_group| row_group.num_rows).sum::<i64>());
+    w.list_field(4, row_groups.len(), CT_STRUCT);
+    for row_group in row_groups {
+        w.begin_struct();
+        w.list_field(1, row_group.columns.len(), CT_STRUCT);
+        for (column, chunk) in columns.iter().zip(&row_group.columns) {
+            w.begin_struct();
+            w.i64_field(2, chunk.data_page_offset);
+            w.struct_field(3);
+            w.i32_field(1, column.physical_type());
+            w.list_field(2, 2, CT_I32);
+            w.i32(ENCODING_PLAIN);
+            w.i32(ENCODING_RLE);
+            w.list_field(3, 1, CT_BINARY);
+            w.binary(column.name.as_bytes());
+            w.i32_field(4, chunk.codec);
+            w.i64_field(5, chunk.num_values);
+            w.i64_field(6, chunk.total_size);
+            w.i64_field(7, chunk.total_size);
+            w.i64_field(9, chunk.data_page_offset);
+            if let (&Some(ref min), &Some(ref max)) = (&chunk.min, &chunk.max) {
+                // both the deprecated and the current statistics fields are written for older readers
+                w.struct_field(12);
+                w.binary_field(1, max);
+                w.binary_field(2, min);
+                w.binary_field(5, max);
+                w.binary_field(6, min);
+                w.end_struct();
+            }
+            w.end_struct();
+            w.end_struct();
+        }
+        w.i64_field(2, row_group.columns.iter().map(|chunk| chunk.total_size).sum::<i64>());
+        w.i64_field(3, row_group.num_rows);
+        w.end_struct();
+    }
+
+    w.binary_field(6, b"tickgrinder");
+    w.end_struct();
+    w.buf
+}
+
+/// The parts of a page header that are needed to read a data page
+struct PageHeader {
+    page_type: i32,
+    len: usize,
+    num_values: usize,
+    encoding: i32,
+    def_level_encoding: i32,
+}
+
+fn read_page_header(r: &mut ThriftReader) -> Result<PageHeader, String> {
+    let mut header = PageHeader {
+        page_type: -1,
+        len: 0,
+        num_values: 0,
+        encoding: ENCODING_PLAIN,
+        def_level_encoding: ENCODING_RLE,
+    };
+
+    r.begin_struct();
+    while let Some((id, field_type)) = try!(r.field()) {
+        match (id, field_type) {
+            (1, CT_I32) => header.page_type = try!(r.i32()),
+            (3, CT_I32) => header.len = try!(r.i32()) as usize,
+            (5, CT_STRUCT) => {
+                r.begin_struct();
+                while let Some((id, field_type)) = try!(r.field()) {
+                    match (id, field_type) {
+                        (1, CT_I32) => header.num_values = try!(r.i32()) as usize,
+                        (2, CT_I32) => header.encoding = try!(r.i32()),
+                        (3, CT_I32) => header.def_level_encoding = try!(r.i32()),
+                        _ => try!(r.skip(field_type, false)),
+                    }
+                }
+            },
+            _ => try!(r.skip(field_type, false)),
+        }
+    }
+
+    Ok(header)
+}
+
+/// Encodes the header of an uncompressed data page containing `num_values` values (including nulls) in `len` bytes.
+fn write_page_header(num_values: usize, len: usize) -> Vec<u8> {
+    let mut w = ThriftWriter::new();
+    w.begin_struct();
+    w.i32_field(1, PAGE_DATA);
+    w.i32_field(2, len as i32);
+    w.i32_field(3, len as i32);
+    w.struct_field(5);
+    w.i32_field(1, num_values as i32);
+    w.i32_field(2, ENCODING_PLAIN);
+    w.i32_field(3, ENCODING_RLE);
+    w.i32_field(4, ENCODING_RLE);
+    w.end_struct();
+    w.end_struct();
+    w.buf
+}
+
+/// Appends definition levels of 0 or 1 with their length as bit-packed runs of the RLE/bit-packing hybrid encoding.
+fn write_def_levels(buf: &mut Vec<u8>, levels: &[u8]) {
+    let mut encoded = Vec::with_capacity(levels.len() / 8 + 2);
+    let groups = (levels.len() + 7) / 8;
+    write_varint(&mut encoded, ((groups as u64) << 1) | 1);
+    for group in levels.chunks(8) {
+        encoded.push(group.iter().enumerate().fold(0, |byte, (i, &level)| byte | ((level & 1) << i)));
+    }
+
+    write_le(buf, encoded.len() as u64, 4);
+    buf.extend_from_slice(&encoded);
+}
+
+/// Decodes `count` definition levels of a flat optional column, returning whether each value is defined.  Both
+/// RLE and bit-packed runs are read since other writers use both.
+fn read_def_levels(buf: &[u8], count: usize) -> Result<Vec<bool>, String> {
+    let mut levels = Vec::with_capacity(count);
+    let mut pos = 0;
+    while levels.len() < count {
+        let header = try!(read_varint(buf, &mut pos));
+        if header & 1 == 1 {
+            // groups of 8 levels that take up one byte each
+            let len = (header >> 1) as usize;
+            if buf.len() - pos < len {
+                return Err(String::from("Truncated definition levels in Parquet file"));
+            }
+            for &byte in &buf[pos..pos + len] {
+                for bit in 0..8 {
+                    levels.push((byte >> bit) & 1 == 1);
+                }
+            }
+            pos += len;
+        } else {
+            let run = (header >> 1) as usize;
+            if pos >= buf.len() {
+                return Err(String::from("Truncated definition levels in Parquet file"));
+            }
+            let defined = buf[pos] & 1 == 1;
+            pos += 1;
+            for _ in 0..::std::cmp::min(run, count - levels.len()) {
+                levels.push(defined);
+            }
+        }
+    }
+    levels.truncate(count);
+
+    Ok(levels)
+}
+
+/// The type of the values stored in a column
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColumnType {
+    Long,
+    Double,
+    Bool,
+    Str,
+}
+
+impl ColumnType {
+    /// Returns the type of column that would be used to store a JSON value.  Values that aren't numbers or booleans
+    /// are stored as strings.
+    pub fn of_value(val: &Value) -> ColumnType {
+        match *val {
+            Value::Bool(_) => ColumnType::Bool,
+            Value::Number(ref n) if n.is_i64() || n.is_u64() => ColumnType::Long,
+            Value::Number(_) => ColumnType::Double,
+            _ => ColumnType::Str,
+        }
+    }
+}
+
+/// Describes one column of a Parquet file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    pub name: String,
+    pub column_type: ColumnType,
+    /// Whether the column may contain null values
+    pub optional: bool,
+}
+
+impl Column {
+    pub fn new(name: &str, column_type: ColumnType, optional: bool) -> Column {
+        Column {
+            name: String::from(name),
+            column_type: column_type,
+            optional: optional,
+        }
+    }
+
+    fn physical_type(&self) -> i32 {
+        match self.column_type {
+            ColumnType::Long => TYPE_INT64,
+            ColumnType::Double => TYPE_DOUBLE,
+            ColumnType::Bool => TYPE_BOOLEAN,
+            ColumnType::Str => TYPE_BYTE_ARRAY,
+        }
+    }
+}
+
+/// The buffered values of a column that haven't been written to a row group yet
+enum ColumnValues {
+    Long(Vec<i64>),
+    Double(Vec<f64>),
+    Bool(Vec<bool>),
+    Str(Vec<String>),
+}
+
+impl ColumnValues {
+    /// Appends the values with the `PLAIN` encoding.
+    fn write_plain(&self, buf: &mut Vec<u8>) {
+        match *self {
+            ColumnValues::Long(ref v) => for &n in v {
+                write_le(buf, n as u64, 8);
+            },
+            ColumnValues::Double(ref v) => for &n in v {
+                write_le(buf, unsafe { mem::transmute::<f64, u64>(n) }, 8);
+            },
+            ColumnValues::Bool(ref v) => for group in v.chunks(8) {
+                buf.push(group.iter().enumerate().fold(0, |byte, (i, &b)| byte | ((b as u8) << i)));
+            },
+            ColumnValues::Str(ref v) => for s in v {
+                write_le(buf, s.len() as u64, 4);
+                buf.extend_from_slice(s.as_bytes());
+            },
+        }
+    }
+}
+
+/// A column's buffered values along with the definition levels that mark which of its rows are null
+struct ColumnBuffer {
+    values: ColumnValues,
+    def_levels: Vec<u8>,
+}
+
+impl ColumnBuffer {
+    fn new(column_type: ColumnType) -> ColumnBuffer {
+        let values = match column_type {
+            ColumnType::Long => ColumnValues::Long(Vec::new()),
+            ColumnType::Double => ColumnValues::Double(Vec::new()),
+            ColumnType::Bool => ColumnValues::Bool(Vec::new()),
+            ColumnType::Str => ColumnValues::Str(Vec::new()),
+        };
+
+        ColumnBuffer {
+            values: values,
+            def_levels: Vec::new(),
+        }
+    }
+
+    /// Adds a value to the column.  Values of a different type than the column are converted if possible.
+    fn push(&mut self, val: &Value) -> Result<(), String> {
+        if val.is_null() {
+            self.def_levels.push(0);
+            return Ok(());
+        }
+
+        match self.values {
+            ColumnValues::Long(ref mut v) => v.push(try!(
+                val.as_i64().or(val.as_u64().map(|n| n as i64)).ok_or(format!("Expected an integer but got {}", val))
+            )),
+            ColumnValues::Double(ref mut v) => v.push(try!(
+                val.as_f64().ok_or(format!("Expected a number but got {}", val))
+            )),
+            ColumnValues::Bool(ref mut v) => v.push(try!(
+                val.as_bool().ok_or(format!("Expected a boolean but got {}", val))
+            )),
+            ColumnValues::Str(ref mut v) => match *val {
+                Value::String(ref s) => v.push(s.clone()),
+                ref other => v.push(other.to_string()),
+            },
+        }
+        self.def_levels.push(1);
+
+        Ok(())
+    }
+
+    /// Returns the smallest and largest value of integer columns encoded as `INT64` values.
+    fn bounds(&self) -> (Option<Vec<u8>>, Option<Vec<u8>>) {
+        match self.values {
+            ColumnValues::Long(ref v) => (v.iter().min().map(|&n| encode_i64(n)), v.iter().max().map(|&n| encode_i64(n))),
+            _ => (None, None),
+        }
+    }
+
+    fn clear(&mut self) {
+        self.def_levels.clear();
+        match self.values {
+            ColumnValues::Long(ref mut v) => v.clear(),
+            ColumnValues::Double(ref mut v) => v.clear(),
+            ColumnValues::Bool(ref mut v) => v.clear(),
+            ColumnValues::Str(ref mut v) => v.clear(),
+        }
+    }
+}
+
+/// Writes rows to a Parquet file.  Rows are buffered and written out in row groups of `row_group_size` rows.  The
+/// last row group and the footer are written when the writer is finished or dropped.
+pub struct ParquetWriter {
+    out: Option<BufWriter<File>>,
+    /// Number of bytes that have been written to the file
+    offset: u64,
+    columns: Vec<Column>,
+    buffers: Vec<ColumnBuffer>,
+    row_group_size: usize,
+    buffered_rows: usize,
+    /// The metadata of the row groups that have been written
+    row_groups: Vec<RowGroup>,
+}
+
+impl ParquetWriter {
+    /// Creates a Parquet file at `path` with the given columns, replacing it if it exists.
+    pub fn create(path: &Path, columns: Vec<Column>, row_group_size: usize) -> Result<ParquetWriter, String> {
+        if row_group_size == 0 {
+            return Err(String::from("The row group size must be greater than zero."));
+        }
+        let file = try!(File::create(path).map_err(|err| format!("Unable to create {:?}: {}", path, err)));
+        let mut out = BufWriter::new(file);
+        try!(out.write_all(MAGIC).map_err(debug_err));
+
+        Ok(ParquetWriter {
+            out: Some(out),
+            offset: MAGIC.len() as u64,
+            buffers: columns.iter().map(|column| ColumnBuffer::new(column.column_type)).collect(),
+            columns: columns,
+            row_group_size: row_group_size,
+            buffered_rows: 0,
+            row_groups: Vec::new(),
+        })
+    }
+
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    /// Adds a row to the file.  The row must contain one value for every column in the order of the columns.  If
+    /// the row can't be written, none of its values are added.
+    pub fn write_row(&mut self, row: &[Value]) -> Result<(), String> {
+        if row.len() != self.columns.len() {
+            return Err(format!("Expected {} values but got {}", self.columns.len(), row.len()));
+        }
+        for (column, val) in self.columns.iter().zip(row) {
+            if val.is_null() && !column.optional {
+                return Err(format!("The column `{}` can't contain null values", column.name));
+            }
+        }
+
+        let lengths: Vec<usize> = self.buffers.iter().map(|buf| buf.def_levels.len()).collect();
+        for (i, val) in row.iter().enumerate() {
+            if let Err(err) = self.buffers[i].push(val) {
+                // roll back the values of the row that were already added
+                for (buf, &len) in self.buffers.iter_mut().zip(&lengths) {
+                    truncate_buffer(buf, len);
+                }
+                return Err(format!("Invalid value for column `{}`: {}", self.columns[i].name, err));
+            }
+        }
+
+        self.buffered_rows += 1;
+        if self.buffered_rows >= self.row_group_size {
+            try!(self.flush_row_group());
+        }
+
+        Ok(())
+    }
+
+    /// Writes all buffered rows to a new row group with one data page per column.
+    fn flush_row_group(&mut self) -> Result<(), String> {
+        if self.buffered_rows == 0 {
+            return Ok(());
+        }
+        let out = try!(self.out.as_mut().ok_or(String::from("The Parquet file has already been finished.")));
+
+        let mut chunks = Vec::with_capacity(self.columns.len());
+        for (column, buf) in self.columns.iter().zip(self.buffers.iter()) {
+            let mut page = Vec::new();
+            if column.optional {
+                write_def_levels(&mut page, &buf.def_levels);
+            }
+            buf.values.write_plain(&mut page);
+            let header = write_page_header(buf.def_levels.len(), page.len());
+            try!(out.write_all(&header).map_err(debug_err));
+            try!(out.write_all(&page).map_err(debug_err));
+
+            let len = (header.len() + page.len()) as u64;
+            let (min, max) = buf.bounds();
+            chunks.push(ColumnChunk {
+                codec: CODEC_UNCOMPRESSED,
+                num_values: buf.def_levels.len() as i64,
+                total_size: len as i64,
+                data_page_offset: self.offset as i64,
+                dictionary_page_offset: None,
+                min: min,
+                max: max,
+            });
+            self.offset += len;
+        }
+        self.row_groups.push(RowGroup {
+            columns: chunks,
+            num_rows: self.buffered_rows as i64,
+        });
+
+        for buf in self.buffers.iter_mut() {
+            buf.clear();
+        }
+        self.buffered_rows = 0;
+
+        Ok(())
+    }
+
+    /// Writes the buffered rows and the file's footer.  Rows can't be written after the writer is finished.
+    pub fn finish(&mut self) -> Result<(), String> {
+        try!(self.flush_row_group());
+        let mut out = match self.out.take() {
+            Some(out) => out,
+            None => return Ok(()),
+        };
+
+        let mut footer = write_file_metadata(&self.columns, &self.row_groups);
+        let metadata_len = footer.len() as u64;
+        write_le(&mut footer, metadata_len, 4);
+        footer.extend_from_slice(MAGIC);
+        try!(out.write_all(&footer).map_err(debug_err));
+        out.flush().map_err(debug_err)
+    }
+}
+
+impl Drop for ParquetWriter {
+    fn drop(&mut self) {
+        if let Err(err) = self.finish() {
+            println!("Error while finishing Parquet file: {}", err);
+        }
+    }
+}
+
+/// Removes the values of a column buffer past the row `len`.
+fn truncate_buffer(buf: &mut ColumnBuffer, len: usize) {
+    let non_null = buf.def_levels[..len].iter().filter(|&&level| level == 1).count();
+    buf.def_levels.truncate(len);
+    match buf.values {
+        ColumnValues::Long(ref mut v) => v.truncate(non_null),
+        ColumnValues::Double(ref mut v) => v.truncate(non_null),
+        ColumnValues::Bool(ref mut v) => v.truncate(non_null),
+        ColumnValues::Str(ref mut v) => v.truncate(non_null),
+    }
+}
+
+/// Returns the columns that ticks are written with
+pub fn tick_columns() -> Vec<Column> {
+    vec![
+        Column::new("timestamp", ColumnType::Long, false),
+        Column::new("bid", ColumnType::Long, false),
+        Column::new("ask", ColumnType::Long, false),
+    ]
+}
+
+/// Writes ticks to a Parquet file with the columns of `tick_columns`.
+pub struct ParquetTickWriter {
+    writer: ParquetWriter,
+}
+
+impl ParquetTickWriter {
+    pub fn create(path: &Path, row_group_size: usize) -> Result<ParquetTickWriter, String> {
+        Ok(ParquetTickWriter {
+            writer: try!(ParquetWriter::create(path, tick_columns(), row_group_size)),
+        })
+    }
+
+    pub fn write(&mut self, t: Tick) -> Result<(), String> {
+        self.writer.write_row(&[Value::from(t.timestamp), Value::from(t.bid as u64), Value::from(t.ask as u64)])
+    }
+
+    pub fn finish(&mut self) -> Result<(), String> {
+        self.writer.finish()
+    }
+}
+
+/// Which ticks to read out of a Parquet file and which columns to read them from
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ParquetQuery {
+    pub timestamp_column: String,
+    pub bid_column: String,
+    pub ask_column: String,
+    /// Only ticks with timestamps greater than or equal to this are read
+    pub start_time: Option<u64>,
+    /// Only ticks with timestamps less than this are read
+    pub end_time: Option<u64>,
+}
+
+impl Default for ParquetQuery {
+    fn default() -> ParquetQuery {
+        ParquetQuery {
+            timestamp_column: String::from("timestamp"),
+            bid_column: String::from("bid"),
+            ask_column: String::from("ask"),
+            start_time: None,
+            end_time: None,
+        }
+    }
+}
+
+impl ParquetQuery {
+    fn contains(&self, timestamp: u64) -> bool {
+        self.start_time.map(|start| timestamp >= start).unwrap_or(true) &&
+            self.end_time.map(|end| timestamp < end).unwrap_or(true)
+    }
+
+    /// Returns false if the row group with the given timestamp bounds can't contain any ticks in the range
+    fn overlaps(&self, min: u64, max: u64) -> bool {
+        self.start_time.map(|start| max >= start).unwrap_or(true) &&
+            self.end_time.map(|end| min < end).unwrap_or(true)
+    }
+}
+
+
+/// Reads the metadata at the end of a Parquet file.
+fn read_metadata(file: &mut File) -> Result<FileMetadata, String> {
+    let file_len = try!(file.seek(SeekFrom::End(0)).map_err(debug_err));
+    if file_len < MAGIC.len() as u64 + FOOTER_LEN {
+        return Err(String::from("The file is too short to be a Parquet file"));
+    }
+    let mut footer = [0u8; 8];
+    try!(file.seek(SeekFrom::Start(file_len - FOOTER_LEN)).map_err(debug_err));
+    try!(file.read_exact(&mut footer).map_err(debug_err));
+    if &footer[4..] != &MAGIC[..] {
+        return Err(String::from("The file doesn't end with the Parquet magic number"));
+    }
+
+    let metadata_len = read_le(&footer[..4]);
+    if metadata_len > file_len - MAGIC.len() as u64 - FOOTER_LEN {
+        return Err(format!("Invalid metadata length: {}", metadata_len));
+    }
+    let mut buf = vec![0; metadata_len as usize];
+    try!(file.seek(SeekFrom::Start(file_len - FOOTER_LEN - metadata_len)).map_err(debug_err));
+    try!(file.read_exact(&mut buf).map_err(debug_err));
+
+    read_file_metadata(&mut ThriftReader::new(&buf))
+}
+
+/// Returns the number of bytes that the values of an `INT32` or `INT64` column take up.
+fn int_width(field: &SchemaElement) -> usize {
+    if field.physical_type == Some(TYPE_INT32) { 4 } else { 8 }
+}
+
+/// Reads the values of a column chunk of integers.  Null values are returned as `None`.
+fn read_int_column(file: &mut File, chunk: &ColumnChunk, field: &SchemaElement) -> Result<Vec<Option<i64>>, String> {
+    let mut buf = vec![0; chunk.total_size as usize];
+    try!(file.seek(SeekFrom::Start(chunk.data_page_offset as u64)).map_err(debug_err));
+    try!(file.read_exact(&mut buf).map_err(debug_err));
+
+    let width = int_width(field);
+    let optional = field.repetition == Some(OPTIONAL);
+    let mut values = Vec::with_capacity(chunk.num_values as usize);
+    let mut pos = 0;
+    while (values.len() as i64) < chunk.num_values {
+        let header = {
+            let mut r = ThriftReader::new(&buf[pos..]);
+            let header = try!(read_page_header(&mut r));
+            pos += r.pos;
+            header
+        };
+        if buf.len() - pos < header.len {
+            return Err(format!("Truncated page in column `{}`", field.name));
+        }
+        let page = &buf[pos..pos + header.len];
+        pos += header.len;
+
+        match header.page_type {
+            PAGE_DATA => (),
+            PAGE_DICTIONARY => return Err(format!(
+                "The column `{}` is dictionary encoded; only PLAIN encoded Parquet files can be read", field.name
+            )),
+            other => return Err(format!(
+                "The column `{}` contains a page of type {}; only version 1 data pages can be read", field.name, other
+            )),
+        }
+        if header.encoding != ENCODING_PLAIN {
+            return Err(format!(
+                "The column `{}` uses encoding {}; only PLAIN encoded Parquet files can be read", field.name, header.encoding
+            ));
+        }
+
+        let (defined, mut page_pos) = if optional {
+            if header.def_level_encoding != ENCODING_RLE {
+                return Err(format!("The definition levels of column `{}` aren't RLE encoded", field.name));
+            }
+            let len = if page.len() >= 4 { read_le(&page[..4]) as usize } else { 0 };
+            if page.len() < 4 || page.len() - 4 < len {
+                return Err(format!("Truncated definition levels in column `{}`", field.name));
+            }
+            (try!(read_def_levels(&page[4..4 + len], header.num_values)), 4 + len)
+        } else {
+            (vec![true; header.num_values], 0)
+        };
+        for is_defined in defined {
+            if !is_defined {
+                values.push(None);
+                continue;
+            }
+            if page.len() - page_pos < width {
+                return Err(format!("Truncated values in column `{}`", field.name));
+            }
+            values.push(Some(read_int(&page[page_pos..page_pos + width])));
+            page_pos += width;
+        }
+    }
+
+    Ok(values)
+}
+
+/// Converts a value of an integer column into a `u64`.
+fn value_to_u64(val: Option<i64>) -> Result<u64, String> {
+    match val {
+        Some(n) if n >= 0 => Ok(n as u64),
+        Some(n) => Err(format!("Expected a positive integer but got {}", n)),
+        None => Err(String::from("Expected a non-null integer but got null")),
+    }
+}
+
+/// Returns the indexes of the timestamp, bid, and ask columns of a file, checking that they can be read.
+fn find_columns(metadata: &FileMetadata, query: &ParquetQuery) -> Result<[usize; 3], String> {
+    // a flat schema consists of the root group followed by the columns
+    let fields = match metadata.schema.split_first() {
+        Some((_, fields)) => fields,
+        None => return Err(String::from("The file doesn't have a schema")),
+    };
+    if fields.iter().any(|field| field.num_children > 0) {
+        return Err(String::from("Files with nested columns can't be read"));
+    }
+
+    let mut indexes = [0; 3];
+    let names = [&query.timestamp_column, &query.bid_column, &query.ask_column];
+    for (i, name) in names.iter().enumerate() {
+        let index = try!(
+            fields.iter().position(|field| &field.name == *name)
+                .ok_or(format!("The file doesn't contain a column named `{}`", name))
+        );
+        let field = &fields[index];
+        if field.physical_type != Some(TYPE_INT64) && field.physical_type != Some(TYPE_INT32) {
+            return Err(format!("The column `{}` doesn't contain integers", name));
+        }
+        if field.repetition != Some(REQUIRED) && field.repetition != Some(OPTIONAL) {
+            return Err(format!("The column `{}` is repeated; only flat columns can be read", name));
+        }
+        for row_group in &metadata.row_groups {
+            let chunk = try!(row_group.columns.get(index).ok_or(format!("A row group is missing the column `{}`", name)));
+            if chunk.codec != CODEC_UNCOMPRESSED {
+                return Err(format!("The column `{}` is compressed; only uncompressed Parquet files can be read", name));
+            }
+            if chunk.dictionary_page_offset.is_some() {
+                return Err(format!(
+                    "The column `{}` is dictionary encoded; only PLAIN encoded Parquet files can be read", name
+                ));
+            }
+        }
+        indexes[i] = index;
+    }
+
+    Ok(indexes)
+}
+
+/// Reads ticks out of a Parquet file one row group at a time.  Rows that can't be converted into ticks are
+/// skipped after logging an error.
+pub struct ParquetTicks {
+    file: File,
+    metadata: FileMetadata,
+    query: ParquetQuery,
+    /// Indexes of the timestamp, bid, and ask columns in the file
+    columns: [usize; 3],
+    next_row_group: usize,
+    buffer: VecDeque<Tick>,
+}
+
+impl ParquetTicks {
+    pub fn open(path: &Path, query: ParquetQuery) -> Result<ParquetTicks, String> {
+        let mut file = try!(File::open(path).map_err(|err| format!("Unable to open {:?}: {}", path, err)));
+        let metadata = try!(read_metadata(&mut file).map_err(|err| format!("Unable to read {:?}: {}", path, err)));
+        let columns = try!(find_columns(&metadata, &query).map_err(|err| format!("Unable to read {:?}: {}", path, err)));
+
+        Ok(ParquetTicks {
+            file: file,
+            metadata: metadata,
+            query: query,
+            columns: columns,
+            next_row_group: 0,
+            buffer: VecDeque::new(),
+        })
+    }
+
+    /// Restarts reading from the first tick with a timestamp greater than or equal to `timestamp`.
+    pub fn seek(&mut self, timestamp: u64) {
+        self.query.start_time = Some(timestamp);
+        self.next_row_group = 0;
+        self.buffer.clear();
+    }
+
+    /// Returns the schema element of a column.  The first element of the schema is the root group.
+    fn field(&self, column: usize) -> &SchemaElement {
+        &self.metadata.schema[column + 1]
+    }
+
+    /// Returns false if the statistics of the row group show that none of its ticks are in the queried range.
+    /// Row groups without statistics are always read.
+    fn row_group_matches(&self, i: usize) -> bool {
+        let chunk = &self.metadata.row_groups[i].columns[self.columns[0]];
+        let width = int_width(self.field(self.columns[0]));
+
+        match (&chunk.min, &chunk.max) {
+            (&Some(ref min), &Some(ref max)) if min.len() == width && max.len() == width => {
+                let (min, max) = (read_int(min), read_int(max));
+                self.query.overlaps(if min < 0 { 0 } else { min as u64 }, if max < 0 { 0 } else { max as u64 })
+            },
+            _ => true,
+        }
+    }
+
+    /// Reads the ticks of the next matching row group into the buffer.  Returns false if there are no more row
+    /// groups to read.
+    fn read_row_group(&mut self) -> Result<bool, String> {
+        let row_group_count = self.metadata.row_groups.len();
+        while self.next_row_group < row_group_count && !self.row_group_matches(self.next_row_group) {
+            self.next_row_group += 1;
+        }
+        if self.next_row_group >= row_group_count {
+            return Ok(false);
+        }
+
+        let i = self.next_row_group;
+        self.next_row_group += 1;
+        let mut values = Vec::with_capacity(3);
+        for &column in self.columns.iter() {
+            let chunk = &self.metadata.row_groups[i].columns[column];
+            values.push(try!(read_int_column(&mut self.file, chunk, &self.metadata.schema[column + 1])));
+        }
+
+        for ((&timestamp, &bid), &ask) in values[0].iter().zip(&values[1]).zip(&values[2]) {
+            let tick = value_to_u64(timestamp).and_then(|timestamp| {
+                Ok(Tick {
+                    timestamp: timestamp,
+                    bid: try!(value_to_u64(bid)) as usize,
+                    ask: try!(value_to_u64(ask)) as usize,
+                })
+            });
+            match tick {
+                Ok(tick) => if self.query.contains(tick.timestamp) {
+                    self.buffer.push_back(tick);
+                },
+                Err(err) => println!("Skipping invalid row in Parquet file: {}", err),
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+impl Iterator for ParquetTicks {
+    type Item = Tick;
+
+    fn next(&mut self) -> Option<Tick> {
+        while self.buffer.is_empty() {
+            match self.read_row_group() {
+                Ok(true) => (),
+                Ok(false) => return None,
+                Err(err) => {
+                    println!("Error while reading Parquet file: {}", err);
+                    return None;
+                },
+            }
+        }
+
+        self.buffer.pop_front()
+    }
+}
+
+#[test]
+fn parquet_round_trip_and_pushdown() {
+    use std::env;
+
+    let mut path = env::temp_dir();
+    path.push("parquet_file_test.parquet");
+    {
+        let mut writer = ParquetTickWriter::create(&path, 100).unwrap();
+        for i in 0..1000u64 {
+            writer.write(Tick {timestamp: i * 10, bid: i as usize, ask: i as usize + 2}).unwrap();
+        }
+    }
+
+    let all: Vec<Tick> = ParquetTicks::open(&path, ParquetQuery::default()).unwrap().collect();
+    assert_eq!(all.len(), 1000);
+    assert_eq!(all[999], Tick {timestamp: 9990, bid: 999, ask: 1001});
+
+    let query = ParquetQuery {start_time: Some(2505), end_time: Some(3000), ..ParquetQuery::default()};
+    let mut ticks = ParquetTicks::open(&path, query).unwrap();
+    assert!(!ticks.row_group_matches(0));
+    assert!(ticks.row_group_matches(2));
+    let in_range: Vec<u64> = ticks.by_ref().map(|t| t.timestamp).collect();
+    assert_eq!(in_range.first(), Some(&2510));
+    assert_eq!(in_range.len(), 49);
+    ticks.seek(2990);
+    assert_eq!(ticks.next().map(|t| t.timestamp), Some(2990));
+
+    // extra columns are skipped and columns can have other names
+    let columns = vec![
+        Column::new("time", ColumnType::Long, false), Column::new("note", ColumnType::Str, true),
+        Column::new("b", ColumnType::Long, false), Column::new("a", ColumnType::Long, false),
+    ];
+    {
+        let mut writer = ParquetWriter::create(&path, columns, 10).unwrap();
+        writer.write_row(&[Value::from(1), Value::Null, Value::from(5), Value::from(6)]).unwrap();
+        assert!(writer.write_row(&[Value::from(2), Value::from("x"), Value::from("y"), Value::from(6)]).is_err());
+        writer.write_row(&[Value::from(3), Value::from("x"), Value::from(7), Value::from(8)]).unwrap();
+    }
+    let query = ParquetQuery {
+        timestamp_column: String::from("time"), bid_column: String::from("b"), ask_column: String::from("a"),
+        ..ParquetQuery::default()
+    };
+    let ticks: Vec<Tick> = ParquetTicks::open(&path, query).unwrap().collect();
+    assert_eq!(ticks, vec![Tick {timestamp: 1, bid: 5, ask: 6}, Tick {timestamp: 3, bid: 7, ask: 8}]);
+}
+
+#[test]
+fn parquet_nulls_and_unsupported_files() {
+    use std::env;
+
+    let mut path = env::temp_dir();
+    path.push("parquet_file_nulls_test.parquet");
+    let columns = vec![
+        Column::new("timestamp", ColumnType::Long, false), Column::new("bid", ColumnType::Long, true),
+        Column::new("ask", ColumnType::Long, true), Column::new("spread", ColumnType::Double, true),
+        Column::new("buy", ColumnType::Bool, false),
+    ];
+    {
+        let mut writer = ParquetWriter::create(&path, columns.clone(), 100).unwrap();
+        for i in 0..20 {
+            let bid = if i % 3 == 0 { Value::Null } else { Value::from(i) };
+            writer.write_row(&[Value::from(i), bid, Value::from(i + 1), Value::from(0.5), Value::from(i % 2 == 0)]).unwrap();
+        }
+    }
+    let timestamps: Vec<u64> = ParquetTicks::open(&path, ParquetQuery::default()).unwrap().map(|t| t.timestamp).collect();
+    assert_eq!(timestamps, vec![1, 2, 4, 5, 7, 8, 10, 11, 13, 14, 16, 17, 19]);
+
+    // rewrite the metadata so that the bid column is marked as being compressed
+    let mut metadata = read_metadata(&mut File::open(&path).unwrap()).unwrap();
+    metadata.row_groups[0].columns[1].codec = 1;
+    let mut data = Vec::new();
+    File::open(&path).unwrap().read_to_end(&mut data).unwrap();
+    let data_len = metadata.row_groups[0].columns.iter().map(|chunk| chunk.total_size as usize).sum::<usize>() + 4;
+    data.truncate(data_len);
+    let encoded = write_file_metadata(&columns, &metadata.row_groups);
+    data.extend_from_slice(&encoded);
+    write_le(&mut data, encoded.len() as u64, 4);
+    data.extend_from_slice(MAGIC);
+    File::create(&path).unwrap().write_all(&data).unwrap();
+
+    let err = ParquetTicks::open(&path, ParquetQuery::default()).err().unwrap();
+    assert!(err.contains("only uncompressed Parquet files can be read"));
+    let query = ParquetQuery {bid_column: String::from("spread"), ..ParquetQuery::default()};
+    assert!(ParquetTicks::open(&path, query).err().unwrap().contains("doesn't contain integers"));
+}
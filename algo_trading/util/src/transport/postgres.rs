@@ -5,6 +5,7 @@
 use postgres::{Connection, Error, TlsMode};
 
 use conf::CONF;
+use trading::tick::Tick;
 
 pub fn get_client() -> Result<Connection, Error> {
     let conn_string = format!("postgres://{}:{}@{}:{}/{}",
@@ -64,6 +65,382 @@ fn tick_table_inner(table_name: &str, client: &Connection, pg_user: &str) -> Res
     Ok(())
 }
 
+/// Returns a query that inserts the ticks into the tick table of `symbol` as a single statement, skipping ticks whose
+/// timestamps are already stored.  `ticks` must not be empty.
+pub fn insert_ticks_query(symbol: &str, ticks: &[Tick]) -> String {
+    let values: Vec<String> = ticks.iter()
+        .map(|t| format!("({}, {}, {})", t.timestamp as i64, t.bid as i64, t.ask as i64))
+        .collect();
+
+    format!("INSERT INTO ticks_{} (tick_time, bid, ask) VALUES {} ON CONFLICT DO NOTHING;", symbol, values.join(", "))
+}
+
+/// Returns the timestamp of the `n`th tick before `timestamp` in the tick table of `symbol`, or `None` if there are
+/// fewer than `n` ticks before it.
+pub fn tick_time_before(symbol: &str, timestamp: u64, n: usize, client: &Connection) -> Result<Option<u64>, String> {
+    if n == 0 {
+        return Ok(Some(timestamp));
+    }
+    let query = format!(
+        "SELECT tick_time FROM ticks_{} WHERE tick_time < $1 ORDER BY tick_time DESC LIMIT 1 OFFSET $2;", symbol
+    );
+    let rows = try!(
+        client.query(&query, &[&(timestamp as i64), &(n as i64 - 1)]).map_err(|err| format!("{:?}", err))
+    );
+
+    Ok(rows.iter().next().map(|row| row.get::<_, i64>(0) as u64))
+}
+
+/// Returns the number of ticks between `start_time` and `end_time` (inclusive) in the tick table of `symbol` along
+/// with the timestamp of the last of them.  Used to tell whether the ticks in a range have changed.
+pub fn tick_range_version(symbol: &str, start_time: u64, end_time: u64, client: &Connection) -> Result<(u64, u64), String> {
+    let query = format!("SELECT COUNT(*), MAX(tick_time) FROM ticks_{} WHERE tick_time >= $1 AND tick_time <= $2;", symbol);
+    let rows = try!(
+        client.query(&query, &[&(start_time as i64), &(end_time as i64)]).map_err(|err| format!("{:?}", err))
+    );
+    let row = try!(rows.iter().next().ok_or(String::from("No rows returned from the tick range query.")));
+
+    Ok((row.get::<_, i64>(0) as u64, row.get::<_, Option<i64>>(1).unwrap_or(0) as u64))
+}
+
+/*****************************\
+*  INDICATOR CACHE FUNCTIONS  *
+\*****************************/
+
+/// Creates the table used to cache the results of historical indicator queries if it doesn't already exist.
+pub fn init_indicator_cache_table(client: &Connection, pg_user: &str) -> Result<(), String> {
+    let query1 = "CREATE TABLE IF NOT EXISTS indicator_cache
+    (
+      cache_key TEXT NOT NULL PRIMARY KEY UNIQUE,
+      result TEXT NOT NULL
+    )
+    WITH (
+      OIDS=FALSE
+    );";
+    let query2 = format!(
+    "ALTER TABLE indicator_cache
+      OWNER TO {};", pg_user);
+    try!(client.execute(query1, &[]).map_err(|err| format!("Error while setting up indicator cache table: {:?}", err)));
+    try!(client.execute(&query2, &[]).map_err(|err| format!("Error while setting up indicator cache table: {:?}", err)));
+
+    Ok(())
+}
+
+/// Returns the cached result stored under the given key, if there is one.
+pub fn get_cached_indicator(cache_key: &str, client: &Connection) -> Result<Option<String>, String> {
+    let rows = try!(
+        client.query("SELECT result FROM indicator_cache WHERE cache_key = $1;", &[&cache_key])
+            .map_err(|err| format!("{:?}", err))
+    );
+
+    Ok(rows.iter().next().map(|row| row.get::<_, String>(0)))
+}
+
+/// Removes all cached results whose keys start with `prefix`, returning how many were removed.
+pub fn clear_cached_indicators(prefix: &str, client: &Connection) -> Result<u64, String> {
+    client.execute(
+        "DELETE FROM indicator_cache WHERE left(cache_key, length($1)) = $1;", &[&prefix]
+    ).map_err(|err| format!("{:?}", err))
+}
+
+/// Stores the result of a historical indicator query under the given key, overwriting any existing value.
+pub fn cache_indicator(cache_key: &str, result: &str, client: &Connection) -> Result<(), String> {
+    client.execute(
+        "INSERT INTO indicator_cache (cache_key, result) VALUES ($1, $2)
+        ON CONFLICT (cache_key) DO UPDATE SET result = EXCLUDED.result;",
+        &[&cache_key, &result]
+    ).map(|_| ()).map_err(|err| format!("{:?}", err))
+}
+
+/**************************\
+*  BACKTEST RUN FUNCTIONS  *
+\**************************/
+
+/// Creates the table in which the results of completed backtests are stored if it doesn't already exist.
+pub fn init_backtest_run_table(client: &Connection, pg_user: &str) -> Result<(), String> {
+    let query1 = "CREATE TABLE IF NOT EXISTS backtest_runs
+    (
+      id TEXT NOT NULL PRIMARY KEY UNIQUE,
+      finished_at BIGINT NOT NULL,
+      strategy TEXT NOT NULL,
+      git_revision TEXT NOT NULL,
+      summary TEXT NOT NULL,
+      run TEXT NOT NULL
+    )
+    WITH (
+      OIDS=FALSE
+    );";
+    let query2 = format!(
+    "ALTER TABLE backtest_runs
+      OWNER TO {};", pg_user);
+    try!(client.execute(query1, &[]).map_err(|err| format!("Error while setting up backtest run table: {:?}", err)));
+    try!(client.execute(&query2, &[]).map_err(|err| format!("Error while setting up backtest run table: {:?}", err)));
+
+    Ok(())
+}
+
+/// Stores a completed backtest run.  `summary` and `run` are the JSON-encoded summary and full record of the run.
+pub fn store_backtest_run(
+    id: &str, finished_at: u64, strategy: &str, git_revision: &str, summary: &str, run: &str, client: &Connection
+) -> Result<(), String> {
+    client.execute(
+        "INSERT INTO backtest_runs (id, finished_at, strategy, git_revision, summary, run) VALUES ($1, $2, $3, $4, $5, $6);",
+        &[&id, &(finished_at as i64), &strategy, &git_revision, &summary, &run]
+    ).map(|_| ()).map_err(|err| format!("{:?}", err))
+}
+
+/// Returns the summaries of the most recent backtest runs, newest first.  If `search` isn't empty, only runs whose
+/// ID, strategy, or git revision contain it are returned.
+pub fn search_backtest_runs(search: &str, limit: usize, client: &Connection) -> Result<Vec<String>, String> {
+    let pattern = format!("%{}%", like_escape(search));
+    let rows = try!(
+        client.query(
+            "SELECT summary FROM backtest_runs
+            WHERE id ILIKE $1 OR strategy ILIKE $1 OR git_revision ILIKE $1
+            ORDER BY finished_at DESC LIMIT $2;",
+            &[&pattern, &(limit as i64)]
+        ).map_err(|err| format!("{:?}", err))
+    );
+
+    Ok(rows.iter().map(|row| row.get::<_, String>(0)).collect())
+}
+
+/// Escapes the wildcards of a `LIKE` pattern so that the value only matches itself.
+fn like_escape(val: &str) -> String {
+    let mut escaped = String::with_capacity(val.len());
+    for c in val.chars() {
+        match c {
+            '\\' | '%' | '_' => {
+                escaped.push('\\');
+                escaped.push(c);
+            },
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Returns the full record of the backtest run with the given ID, if there is one.
+pub fn get_backtest_run(id: &str, client: &Connection) -> Result<Option<String>, String> {
+    let rows = try!(
+        client.query("SELECT run FROM backtest_runs WHERE id = $1;", &[&id])
+            .map_err(|err| format!("{:?}", err))
+    );
+
+    Ok(rows.iter().next().map(|row| row.get::<_, String>(0)))
+}
+
+/****************************\
+*  GENERIC TICK FUNCTIONS  *
+\****************************/
+
+/// Describes how the data of a generic tick is stored in a postgres table.  Every table of generic ticks has a
+/// `tick_time` column holding the tick's timestamp followed by the columns declared here.  Values are moved in and
+/// out of the database in the text format of `COPY`, so they only need to be converted to and from strings.
+pub trait PostgresSchema: Sized {
+    /// Returns the names and SQL types of the columns that the data is stored in.
+    fn columns() -> Vec<(&'static str, &'static str)>;
+
+    /// Returns the values of the columns in the same order as `columns`.
+    fn to_values(&self) -> Vec<String>;
+
+    /// Parses the data back out of the values of its columns.
+    fn from_values(values: &[String]) -> Result<Self, String>;
+}
+
+fn parse_value<T>(values: &[String], i: usize) -> Result<T, String> where T: ::std::str::FromStr, T::Err: ::std::fmt::Debug {
+    match values.get(i) {
+        Some(val) => val.parse::<T>().map_err(|err| format!("Unable to parse column value `{}`: {:?}", val, err)),
+        None => Err(format!("Expected at least {} column values but got {}.", i + 1, values.len())),
+    }
+}
+
+/// Stores (bid, ask) pairs the same way as the tables of regular ticks.
+impl PostgresSchema for (usize, usize) {
+    fn columns() -> Vec<(&'static str, &'static str)> {
+        vec![("bid", "BIGINT NOT NULL"), ("ask", "BIGINT NOT NULL")]
+    }
+
+    fn to_values(&self) -> Vec<String> {
+        vec![self.0.to_string(), self.1.to_string()]
+    }
+
+    fn from_values(values: &[String]) -> Result<Self, String> {
+        Ok((try!(parse_value(values, 0)), try!(parse_value(values, 1))))
+    }
+}
+
+/// Stores whole ticks; the tick's own timestamp is stored along with its prices.
+impl PostgresSchema for Tick {
+    fn columns() -> Vec<(&'static str, &'static str)> {
+        vec![("timestamp", "BIGINT NOT NULL"), ("bid", "BIGINT NOT NULL"), ("ask", "BIGINT NOT NULL")]
+    }
+
+    fn to_values(&self) -> Vec<String> {
+        vec![self.timestamp.to_string(), self.bid.to_string(), self.ask.to_string()]
+    }
+
+    fn from_values(values: &[String]) -> Result<Self, String> {
+        Ok(Tick {
+            timestamp: try!(parse_value(values, 0)),
+            bid: try!(parse_value(values, 1)),
+            ask: try!(parse_value(values, 2)),
+        })
+    }
+}
+
+impl PostgresSchema for f64 {
+    fn columns() -> Vec<(&'static str, &'static str)> {
+        vec![("value", "DOUBLE PRECISION NOT NULL")]
+    }
+
+    fn to_values(&self) -> Vec<String> {
+        // postgres spells the special values differently than Rust
+        let val = if self.is_nan() {
+            String::from("NaN")
+        } else if self.is_infinite() {
+            String::from(if *self > 0. { "Infinity" } else { "-Infinity" })
+        } else {
+            self.to_string()
+        };
+        vec![val]
+    }
+
+    fn from_values(values: &[String]) -> Result<Self, String> {
+        match values.get(0).map(|val| val.as_str()) {
+            Some("NaN") => Ok(::std::f64::NAN),
+            Some("Infinity") => Ok(::std::f64::INFINITY),
+            Some("-Infinity") => Ok(::std::f64::NEG_INFINITY),
+            _ => parse_value(values, 0),
+        }
+    }
+}
+
+impl PostgresSchema for String {
+    fn columns() -> Vec<(&'static str, &'static str)> {
+        vec![("data", "TEXT NOT NULL")]
+    }
+
+    fn to_values(&self) -> Vec<String> {
+        vec![self.clone()]
+    }
+
+    fn from_values(values: &[String]) -> Result<Self, String> {
+        values.get(0).cloned().ok_or(String::from("Expected a column value but got none."))
+    }
+}
+
+/// Returns true if `name` can be used as a table name without quoting it.  Table names of generic ticks come from
+/// settings and are formatted into queries, so anything else is rejected.
+pub fn valid_table_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some('a'...'z') | Some('_') => (),
+        _ => return false,
+    }
+    name.len() <= 63 && chars.all(|c| match c { 'a'...'z' | '0'...'9' | '_' => true, _ => false })
+}
+
+/// Creates a table for generic ticks with the given columns and an index on their timestamps if such a table doesn't
+/// already exist.  Unlike the tables of regular ticks, timestamps don't have to be unique.
+pub fn init_generic_tick_table(
+    table_name: &str, columns: &[(&'static str, &'static str)], client: &Connection, pg_user: &str
+) -> Result<(), String> {
+    if !valid_table_name(table_name) {
+        return Err(format!("`{}` isn't a valid table name.", table_name));
+    }
+    let column_defs: Vec<String> = columns.iter().map(|&(name, sql_type)| format!(",\n      {} {}", name, sql_type)).collect();
+    let query1 = format!(
+    "CREATE TABLE IF NOT EXISTS {}
+    (
+      tick_time BIGINT NOT NULL{}
+    )
+    WITH (
+      OIDS=FALSE
+    );", table_name, column_defs.join(""));
+    let query2 = format!(
+    "CREATE INDEX IF NOT EXISTS {}_tick_time ON {} (tick_time);", table_name, table_name);
+    let query3 = format!(
+    "ALTER TABLE {}
+      OWNER TO {};", table_name, pg_user);
+    for query in &[query1, query2, query3] {
+        try!(client.execute(query, &[]).map_err(|err| format!("Error while setting up generic tick table: {:?}", err)));
+    }
+
+    Ok(())
+}
+
+/// Escapes a value for the text format of `COPY`.
+fn copy_escape(val: &str) -> String {
+    let mut escaped = String::with_capacity(val.len());
+    for c in val.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Reverses `copy_escape`, also handling the other escapes that postgres can produce.
+fn copy_unescape(val: &str) -> Result<String, String> {
+    let mut unescaped = String::with_capacity(val.len());
+    let mut chars = val.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('b') => unescaped.push('\x08'),
+            Some('f') => unescaped.push('\x0c'),
+            Some('v') => unescaped.push('\x0b'),
+            Some('N') => return Err(String::from("Generic tick columns can't be NULL.")),
+            Some(c) => unescaped.push(c),
+            None => return Err(String::from("Column value ends with an unfinished escape sequence.")),
+        }
+    }
+    Ok(unescaped)
+}
+
+/// Formats a tick as a line of `COPY` text: its timestamp followed by the values of its columns.
+pub fn copy_row(timestamp: u64, values: &[String]) -> String {
+    let mut row = timestamp.to_string();
+    for val in values {
+        row.push('\t');
+        row.push_str(&copy_escape(val));
+    }
+    row.push('\n');
+    row
+}
+
+/// Parses a line of `COPY` text created by `copy_row` (without the newline) back into a timestamp and values.
+pub fn parse_copy_row(line: &str) -> Result<(u64, Vec<String>), String> {
+    let mut fields = line.split('\t');
+    let timestamp = try!(
+        fields.next().unwrap().parse::<u64>().map_err(|err| format!("Unable to parse tick timestamp: {:?}", err))
+    );
+    let mut values = Vec::new();
+    for field in fields {
+        values.push(try!(copy_unescape(field)));
+    }
+    Ok((timestamp, values))
+}
+
+/// Copies lines of `COPY` text into the `tick_time` and `columns` columns of a table of generic ticks.  Returns the
+/// number of rows inserted.
+pub fn copy_rows_in(table_name: &str, columns: &[&str], rows: &str, client: &Connection) -> Result<u64, String> {
+    let query = format!("COPY {} (tick_time, {}) FROM STDIN;", table_name, columns.join(", "));
+    let stmt = try!(client.prepare(&query).map_err(|err| format!("{:?}", err)));
+    stmt.copy_in(&[], &mut rows.as_bytes()).map_err(|err| format!("Error while copying ticks into {}: {:?}", table_name, err))
+}
+
 /***************************
 * ADMINISTRATIVE FUNCTIONS *
 ***************************/
@@ -77,3 +454,41 @@ pub fn reset_db(client: &Connection, pg_user: &'static str) -> Result<(), Error>
             pg_user, pg_user, pg_user);
     client.batch_execute(query.as_str())
 }
+
+#[test]
+fn copy_rows() {
+    let values = vec![String::from("a\tb\nc\\d"), String::from(""), String::from("plain")];
+    let row = copy_row(1500, &values);
+    assert_eq!(row, "1500\ta\\tb\\nc\\\\d\t\tplain\n");
+    assert_eq!(parse_copy_row(row.trim_right_matches('\n')).unwrap(), (1500, values));
+    assert!(parse_copy_row("1500\t\\N").is_err());
+    assert!(parse_copy_row("abc\t1").is_err());
+
+    let tick = Tick {timestamp: 5, bid: 100, ask: 102};
+    assert_eq!(Tick::from_values(&tick.to_values()).unwrap(), tick);
+    assert_eq!(<(usize, usize)>::from_values(&(3, 4).to_values()).unwrap(), (3, 4));
+    assert!(<(usize, usize)>::from_values(&[String::from("3")]).is_err());
+    assert_eq!(f64::from_values(&(-2.5f64).to_values()).unwrap(), -2.5);
+    assert_eq!(::std::f64::INFINITY.to_values(), vec![String::from("Infinity")]);
+    assert!(f64::from_values(&::std::f64::NAN.to_values()).unwrap().is_nan());
+
+    assert!(valid_table_name("fx_ticks_2"));
+    assert!(!valid_table_name("2ticks"));
+    assert!(!valid_table_name("ticks; DROP TABLE ticks"));
+}
+
+#[test]
+fn tick_insertion_query() {
+    let ticks = [Tick {timestamp: 1, bid: 2, ask: 3}, Tick {timestamp: 4, bid: 5, ask: 6}];
+    assert_eq!(
+        insert_ticks_query("eurusd", &ticks),
+        "INSERT INTO ticks_eurusd (tick_time, bid, ask) VALUES (1, 2, 3), (4, 5, 6) ON CONFLICT DO NOTHING;"
+    );
+}
+
+#[test]
+fn like_escaping() {
+    assert_eq!(like_escape("sma_cross"), "sma\\_cross");
+    assert_eq!(like_escape("100%\\"), "100\\%\\\\");
+    assert_eq!(like_escape("plain"), "plain");
+}
@@ -3,7 +3,7 @@
 
 use std::collections::VecDeque;
 use std::thread;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, Condvar};
 
 use postgres;
 use futures::{Future, Stream};
@@ -11,14 +11,17 @@ use futures::sync::mpsc::{unbounded, UnboundedSender, UnboundedReceiver};
 use futures::sync::oneshot::{channel as oneshot, Sender};
 
 use transport::postgres::get_client;
+use transport::command_server::{CommandServer, is_offline};
 
 type SenderQueue = Arc<Mutex<VecDeque<UnboundedSender<(String, Sender<()>)>>>>;
 type QueryQueue = Arc<Mutex<VecDeque<String>>>;
+type PendingCount = Arc<(Mutex<usize>, Condvar)>;
 
 #[derive(Clone)]
 pub struct QueryServer {
     query_queue: QueryQueue, // internal query queue
     conn_queue: SenderQueue, // senders for idle query threads
+    pending: PendingCount, // number of queries that haven't finished executing yet
 }
 
 // locks the QueryQueue and returns a queued query, if there are any.
@@ -27,13 +30,25 @@ fn try_get_new_query(query_queue: &Mutex<VecDeque<String>>) -> Option<String> {
     qq_inner.pop_front()
 }
 
-// executes the query and blocks the calling thread until it completes
-fn execute_query(query: &str, client: &postgres::Connection) {
-    let _ = client.execute(query, &[]);
+// executes the query and blocks the calling thread until it completes, logging errors if a `CommandServer` is given
+fn execute_query(query: &str, client: &postgres::Connection, cs: &mut Option<CommandServer>, pending: &PendingCount) {
+    if let Err(err) = client.execute(query, &[]) {
+        if let Some(ref mut cs) = *cs {
+            cs.error(Some("Query Server"), &format!("Error while executing query: {:?}", err));
+        }
+    }
+
+    let &(ref count, ref cvar) = &**pending;
+    let mut count = count.lock().unwrap();
+    *count -= 1;
+    cvar.notify_all();
 }
 
 // Creates a query processor that awaits requests
-fn init_query_processor(rx: UnboundedReceiver<(String, Sender<()>)>, query_queue: QueryQueue) {
+fn init_query_processor(
+    rx: UnboundedReceiver<(String, Sender<()>)>, query_queue: QueryQueue, mut cs: Option<CommandServer>,
+    pending: PendingCount
+) {
     // get a connection to the postgres database
     let client = get_client().expect("Couldn't create postgres connection."); // TODO: Logging for this
     // Handler for new queries from main thread
@@ -42,10 +57,10 @@ fn init_query_processor(rx: UnboundedReceiver<(String, Sender<()>)>, query_queue
     // for the worker to push a message saying it's done before sending more messages
     for tup in rx.wait() {
         let (query, done_tx) = tup.unwrap();
-        execute_query(query.as_str(), &client);
+        execute_query(query.as_str(), &client, &mut cs, &pending);
         // keep trying to get queued queries to execute until the queue is empty
         while let Some(new_query) = try_get_new_query(&*query_queue) {
-            execute_query(new_query.as_str(), &client);
+            execute_query(new_query.as_str(), &client, &mut cs, &pending);
         }
         // Let the main thread know it's safe to use the sender again
         // This essentially indicates that the worker thread is idle
@@ -54,14 +69,29 @@ fn init_query_processor(rx: UnboundedReceiver<(String, Sender<()>)>, query_queue
 }
 
 impl QueryServer {
+    /// Creates a `QueryServer` with `conn_count` connections to the database.  In offline mode no connections are
+    /// made and queries are discarded.
     pub fn new(conn_count: usize) -> QueryServer {
+        QueryServer::init(conn_count, None)
+    }
+
+    /// Creates a `QueryServer` that logs the errors of failed queries through `cs`.
+    pub fn with_logging(conn_count: usize, cs: CommandServer) -> QueryServer {
+        QueryServer::init(conn_count, Some(cs))
+    }
+
+    fn init(conn_count: usize, cs: Option<CommandServer>) -> QueryServer {
+        let conn_count = if is_offline() { 0 } else { conn_count };
         let mut conn_queue = VecDeque::with_capacity(conn_count);
         let query_queue = Arc::new(Mutex::new(VecDeque::new()));
+        let pending = Arc::new((Mutex::new(0), Condvar::new()));
         for _ in 0..conn_count {
             // channel for getting the Sender back from the worker thread
             let (tx, rx) = unbounded::<(String, Sender<()>)>();
             let qq_copy = query_queue.clone();
-            thread::spawn(move || init_query_processor(rx, qq_copy) );
+            let cs_copy = cs.clone();
+            let pending_copy = pending.clone();
+            thread::spawn(move || init_query_processor(rx, qq_copy, cs_copy, pending_copy) );
             // store the sender which can be used to send queries
             // to the worker in the connection queue
             conn_queue.push_back(tx);
@@ -69,32 +99,56 @@ impl QueryServer {
 
         QueryServer {
             query_queue: query_queue,
-            conn_queue: Arc::new(Mutex::new(conn_queue))
+            conn_queue: Arc::new(Mutex::new(conn_queue)),
+            pending: pending,
         }
     }
 
     // Queues up a query to execute that doesn't return a result.
     pub fn execute(&mut self, query: String) {
-        // no connections available
-        let temp_lock_res = self.conn_queue.lock().unwrap().is_empty();
-        // Force the guard locking conn_queue to go out of scope
-        // this prevents the lock from being held through the entire if/else
-        let copy_res = temp_lock_res;
-        if copy_res {
-            // push query to the query queue
-            self.query_queue.lock().unwrap().push_back(query);
-        } else {
-            let tx = self.conn_queue.lock().unwrap().pop_front().unwrap();
-            let cq_clone = self.conn_queue.clone();
-            // future for notifying main thread when query is done and worker is idle
-            let (c, o) = oneshot::<()>();
-            thread::spawn(move || {
-                tx.send( (query, c) ).unwrap();
-                // Wait until the worker thread signals that it is idle
-                let _ = o.wait();
-                // Put the Sender for the newly idle worker into the connection queue
-                cq_clone.lock().unwrap().push_back(tx);
-            });
+        if is_offline() {
+            return;
+        }
+        *self.pending.0.lock().unwrap() += 1;
+        // conn_queue stays locked while the query is queued so that a worker can't become idle without seeing it
+        let mut conn_queue = self.conn_queue.lock().unwrap();
+        match conn_queue.pop_front() {
+            // no connections available; push query to the query queue
+            None => self.query_queue.lock().unwrap().push_back(query),
+            Some(tx) => {
+                drop(conn_queue);
+                let cq_clone = self.conn_queue.clone();
+                let qq_clone = self.query_queue.clone();
+                thread::spawn(move || {
+                    let mut query = query;
+                    loop {
+                        // future for notifying main thread when query is done and worker is idle
+                        let (c, o) = oneshot::<()>();
+                        tx.send( (query, c) ).unwrap();
+                        // Wait until the worker thread signals that it is idle
+                        let _ = o.wait();
+                        // Put the Sender for the newly idle worker into the connection queue unless a query was
+                        // queued after the worker last checked the query queue
+                        let mut cq_inner = cq_clone.lock().unwrap();
+                        match try_get_new_query(&*qq_clone) {
+                            Some(new_query) => query = new_query,
+                            None => {
+                                cq_inner.push_back(tx);
+                                break;
+                            },
+                        }
+                    }
+                });
+            },
+        }
+    }
+
+    /// Blocks until all queries that have been passed to `execute` have finished executing.
+    pub fn wait_idle(&self) {
+        let &(ref count, ref cvar) = &*self.pending;
+        let mut count = count.lock().unwrap();
+        while *count > 0 {
+            count = cvar.wait(count).unwrap();
         }
     }
 }
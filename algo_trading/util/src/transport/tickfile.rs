@@ -0,0 +1,542 @@
+//! A compact binary file format for historical ticks that is much faster to read than CSV.
+//!
+//! Ticks are stored in blocks of up to `DEFAULT_BLOCK_SIZE` ticks.  Within a block, each column is stored separately:
+//! first the timestamps as varint-encoded deltas from the previous tick's timestamp, then the bids and then the asks
+//! as zigzag varint-encoded deltas from the previous tick's bid and ask.  Each block is compressed with deflate.
+//!
+//! ```text
+//! header:  magic (4 bytes) | version (1 byte) | reserved (3 bytes)
+//! blocks:  compressed block 0 | compressed block 1 | ...
+//! index:   for each block: first timestamp (u64) | offset (u64) | compressed length (u32) | tick count (u32)
+//! footer:  index offset (u64) | block count (u64) | magic (4 bytes)
+//! ```
+//!
+//! All fixed-width integers are little-endian.  Since the index contains the first timestamp of every block, the
+//! reader can find the block containing any timestamp with a binary search and only decompress that block.
+
+use std::fs::File;
+use std::io::{Read, Write, BufWriter};
+use std::path::Path;
+use std::sync::Arc;
+
+use libflate::deflate::{Encoder, Decoder};
+use memmap::Mmap;
+
+use trading::tick::Tick;
+
+/// Extension of files in the binary tick format
+pub const TICK_FILE_EXTENSION: &'static str = "tkb";
+/// The number of ticks in each block unless another size is specified
+pub const DEFAULT_BLOCK_SIZE: usize = 4096;
+
+const MAGIC: &'static [u8; 4] = b"TGTK";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = 8;
+const INDEX_ENTRY_LEN: usize = 24;
+const FOOTER_LEN: usize = 20;
+/// Every tick takes up at least one byte in each of the three columns of a block
+const MIN_TICK_LEN: usize = 3;
+/// The longest that a varint can be
+const MAX_VARINT_LEN: usize = 10;
+
+/// Location and first timestamp of a block of ticks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockIndexEntry {
+    pub first_timestamp: u64,
+    /// Offset of the compressed block from the start of the file
+    pub offset: u64,
+    /// Length of the compressed block in bytes
+    pub len: u32,
+    pub tick_count: u32,
+}
+
+fn debug_err<T: ::std::fmt::Debug>(x: T) -> String {
+    format!("{:?}", x)
+}
+
+fn write_varint(buf: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        buf.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+    buf.push(n as u8);
+}
+
+fn read_varint(buf: &[u8], pos: &mut usize) -> Result<u64, String> {
+    let mut n: u64 = 0;
+    let mut shift = 0;
+    loop {
+        if *pos >= buf.len() || shift > 63 {
+            return Err(String::from("Truncated or invalid varint in tick block"));
+        }
+        let byte = buf[*pos];
+        *pos += 1;
+        n |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(n);
+        }
+        shift += 7;
+    }
+}
+
+/// Maps signed integers to unsigned ones so that numbers close to zero have short varint encodings.
+fn zigzag(n: i64) -> u64 {
+    ((n << 1) ^ (n >> 63)) as u64
+}
+
+fn unzigzag(n: u64) -> i64 {
+    ((n >> 1) as i64) ^ -((n & 1) as i64)
+}
+
+fn write_u64(buf: &mut Vec<u8>, n: u64) {
+    for i in 0..8 {
+        buf.push((n >> (i * 8)) as u8);
+    }
+}
+
+fn read_u64(buf: &[u8]) -> u64 {
+    (0..8).fold(0, |acc, i| acc | (buf[i] as u64) << (i * 8))
+}
+
+fn read_u32(buf: &[u8]) -> u32 {
+    (0..4).fold(0, |acc, i| acc | (buf[i] as u32) << (i * 8))
+}
+
+/// Encodes a block of ticks into its uncompressed columnar representation.
+fn encode_block(ticks: &[Tick]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(ticks.len() * 4);
+    let mut prev = ticks[0].timestamp;
+    for t in ticks {
+        write_varint(&mut buf, t.timestamp - prev);
+        prev = t.timestamp;
+    }
+    let mut prev = 0;
+    for t in ticks {
+        write_varint(&mut buf, zigzag(t.bid as i64 - prev));
+        prev = t.bid as i64;
+    }
+    let mut prev = 0;
+    for t in ticks {
+        write_varint(&mut buf, zigzag(t.ask as i64 - prev));
+        prev = t.ask as i64;
+    }
+
+    buf
+}
+
+/// Decodes an uncompressed block of ticks.  The index entry comes from the file as well, so nothing about it is
+/// trusted until it has been checked against the block.
+fn decode_block(buf: &[u8], entry: &BlockIndexEntry) -> Result<Vec<Tick>, String> {
+    let count = entry.tick_count as usize;
+    if count.checked_mul(MIN_TICK_LEN).map(|len| len > buf.len()).unwrap_or(true) {
+        return Err(format!("Block of {} bytes is too short to hold {} ticks", buf.len(), count));
+    }
+
+    let mut ticks = Vec::with_capacity(count);
+    let mut pos = 0;
+    let mut timestamp = entry.first_timestamp;
+    for _ in 0..count {
+        let delta = try!(read_varint(buf, &mut pos));
+        timestamp = try!(timestamp.checked_add(delta).ok_or(String::from("Timestamp overflow in tick block")));
+        ticks.push(Tick {bid: 0, ask: 0, timestamp: timestamp});
+    }
+    for &is_bid in [true, false].iter() {
+        let mut prev: i64 = 0;
+        for t in ticks.iter_mut() {
+            let delta = unzigzag(try!(read_varint(buf, &mut pos)));
+            prev = match prev.checked_add(delta) {
+                Some(price) if price >= 0 => price,
+                _ => return Err(String::from("Invalid price in tick block")),
+            };
+            if is_bid { t.bid = prev as usize } else { t.ask = prev as usize }
+        }
+    }
+
+    Ok(ticks)
+}
+
+/// Writes ticks into a binary tick file.  Ticks must be written in order of non-decreasing timestamp.  The file
+/// isn't valid until `finish()` has been called, which happens automatically when the writer is dropped.
+pub struct TickFileWriter<W: Write> {
+    out: Option<W>,
+    block_size: usize,
+    pending: Vec<Tick>,
+    index: Vec<BlockIndexEntry>,
+    /// Number of bytes written so far
+    offset: u64,
+    last_timestamp: Option<u64>,
+}
+
+impl TickFileWriter<BufWriter<File>> {
+    /// Creates a new tick file at the given path, overwriting it if it exists.
+    pub fn create(path: &Path) -> Result<TickFileWriter<BufWriter<File>>, String> {
+        let file = try!(File::create(path).map_err(|err| format!("Unable to create {:?}: {}", path, err)));
+        TickFileWriter::new(BufWriter::new(file), DEFAULT_BLOCK_SIZE)
+    }
+}
+
+impl<W: Write> TickFileWriter<W> {
+    pub fn new(mut out: W, block_size: usize) -> Result<TickFileWriter<W>, String> {
+        let mut header = MAGIC.to_vec();
+        header.extend_from_slice(&[VERSION, 0, 0, 0]);
+        try!(out.write_all(&header).map_err(debug_err));
+
+        Ok(TickFileWriter {
+            out: Some(out),
+            block_size: block_size.max(1),
+            pending: Vec::with_capacity(block_size.max(1)),
+            index: Vec::new(),
+            offset: HEADER_LEN as u64,
+            last_timestamp: None,
+        })
+    }
+
+    /// Adds a tick to the file.
+    pub fn write(&mut self, t: Tick) -> Result<(), String> {
+        if self.out.is_none() {
+            return Err(String::from("Can't write to a tick file that has been finished."));
+        }
+        if self.last_timestamp.map(|last| t.timestamp < last).unwrap_or(false) {
+            return Err(format!(
+                "Ticks must be written in order; got timestamp {} after {}", t.timestamp, self.last_timestamp.unwrap()
+            ));
+        }
+        self.last_timestamp = Some(t.timestamp);
+
+        self.pending.push(t);
+        if self.pending.len() >= self.block_size {
+            try!(self.flush_block());
+        }
+
+        Ok(())
+    }
+
+    /// Compresses the pending ticks and writes them out as a block.
+    fn flush_block(&mut self) -> Result<(), String> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        let mut encoder = Encoder::new(Vec::new());
+        try!(encoder.write_all(&encode_block(&self.pending)).map_err(debug_err));
+        let compressed = try!(encoder.finish().into_result().map_err(debug_err));
+        try!(self.out.as_mut().unwrap().write_all(&compressed).map_err(debug_err));
+
+        self.index.push(BlockIndexEntry {
+            first_timestamp: self.pending[0].timestamp,
+            offset: self.offset,
+            len: compressed.len() as u32,
+            tick_count: self.pending.len() as u32,
+        });
+        self.offset += compressed.len() as u64;
+        self.pending.clear();
+
+        Ok(())
+    }
+
+    /// Writes out the remaining ticks, the index, and the footer, returning the underlying writer.
+    pub fn finish(&mut self) -> Result<W, String> {
+        if self.out.is_none() {
+            return Err(String::from("The tick file has already been finished."));
+        }
+        try!(self.flush_block());
+
+        let mut buf = Vec::with_capacity(self.index.len() * INDEX_ENTRY_LEN + FOOTER_LEN);
+        for entry in &self.index {
+            write_u64(&mut buf, entry.first_timestamp);
+            write_u64(&mut buf, entry.offset);
+            buf.extend_from_slice(&[
+                entry.len as u8, (entry.len >> 8) as u8, (entry.len >> 16) as u8, (entry.len >> 24) as u8,
+                entry.tick_count as u8, (entry.tick_count >> 8) as u8, (entry.tick_count >> 16) as u8,
+                (entry.tick_count >> 24) as u8,
+            ]);
+        }
+        write_u64(&mut buf, self.offset);
+        write_u64(&mut buf, self.index.len() as u64);
+        buf.extend_from_slice(MAGIC);
+
+        let mut out = self.out.take().unwrap();
+        try!(out.write_all(&buf).map_err(debug_err));
+        try!(out.flush().map_err(debug_err));
+        Ok(out)
+    }
+}
+
+impl<W: Write> Drop for TickFileWriter<W> {
+    fn drop(&mut self) {
+        if self.out.is_some() {
+            if let Err(err) = self.finish() {
+                println!("Error while finishing tick file: {}", err);
+            }
+        }
+    }
+}
+
+/// A memory-mapped binary tick file.
+pub struct TickFile {
+    data: Mmap,
+    index: Vec<BlockIndexEntry>,
+}
+
+impl TickFile {
+    /// Maps the file into memory and reads its index.
+    pub fn open(path: &Path) -> Result<TickFile, String> {
+        let file = try!(File::open(path).map_err(|err| format!("Unable to open {:?}: {}", path, err)));
+        let data = try!(unsafe { Mmap::map(&file) }.map_err(|err| format!("Unable to map {:?}: {}", path, err)));
+        let index = try!(read_index(&data).map_err(|err| format!("Invalid tick file {:?}: {}", path, err)));
+
+        Ok(TickFile {
+            data: data,
+            index: index,
+        })
+    }
+
+    pub fn index(&self) -> &[BlockIndexEntry] {
+        &self.index
+    }
+
+    pub fn tick_count(&self) -> usize {
+        self.index.iter().map(|entry| entry.tick_count as usize).sum()
+    }
+
+    /// Returns the index of the first block that can contain ticks at or after the timestamp.
+    pub fn find_block(&self, timestamp: u64) -> usize {
+        match self.index.binary_search_by_key(&timestamp, |entry| entry.first_timestamp) {
+            // ticks with the same timestamp can span multiple blocks, so back up to the first of them
+            Ok(mut i) => {
+                while i > 0 && self.index[i - 1].first_timestamp == timestamp {
+                    i -= 1;
+                }
+                // the previous block can end with ticks at the timestamp as well
+                i.saturating_sub(1)
+            },
+            Err(i) => i.saturating_sub(1),
+        }
+    }
+
+    /// Decompresses and decodes the ticks of a block.
+    pub fn read_block(&self, i: usize) -> Result<Vec<Tick>, String> {
+        let entry = self.index[i];
+        // the offsets were checked against the size of the file when the index was read
+        let start = entry.offset as usize;
+        let end = start + entry.len as usize;
+
+        // don't decompress more than the block's ticks can possibly take up in case the block is corrupt
+        let max_len = (entry.tick_count as u64) * (MIN_TICK_LEN * MAX_VARINT_LEN) as u64 + 1;
+        let mut buf = Vec::new();
+        try!(Decoder::new(&self.data[start..end]).take(max_len).read_to_end(&mut buf).map_err(debug_err));
+        decode_block(&buf, &entry)
+    }
+}
+
+/// Reads the index out of the file's footer.
+fn read_index(data: &[u8]) -> Result<Vec<BlockIndexEntry>, String> {
+    if data.len() < HEADER_LEN + FOOTER_LEN || &data[0..4] != MAGIC || &data[data.len() - 4..] != MAGIC {
+        return Err(String::from("missing header or footer"));
+    }
+    if data[4] != VERSION {
+        return Err(format!("unsupported version {}", data[4]));
+    }
+
+    // everything in the footer and index comes from the file, so all of the arithmetic on it is checked
+    let footer = &data[data.len() - FOOTER_LEN..];
+    let index_offset = read_u64(&footer[0..8]);
+    let block_count = read_u64(&footer[8..16]);
+    let index_end = block_count.checked_mul(INDEX_ENTRY_LEN as u64).and_then(|len| len.checked_add(index_offset));
+    if index_offset < HEADER_LEN as u64 || index_end != Some((data.len() - FOOTER_LEN) as u64) {
+        return Err(String::from("corrupt index"));
+    }
+
+    let index_offset = index_offset as usize;
+    let mut index = Vec::with_capacity(block_count as usize);
+    for i in 0..block_count as usize {
+        let entry = &data[index_offset + i * INDEX_ENTRY_LEN..];
+        let entry = BlockIndexEntry {
+            first_timestamp: read_u64(&entry[0..8]),
+            offset: read_u64(&entry[8..16]),
+            len: read_u32(&entry[16..20]),
+            tick_count: read_u32(&entry[20..24]),
+        };
+        // blocks have to lie between the header and the index
+        let block_end = entry.offset.checked_add(entry.len as u64);
+        if entry.offset < HEADER_LEN as u64 || block_end.map(|end| end > index_offset as u64).unwrap_or(true) {
+            return Err(format!("block {} lies outside of the file's blocks", i));
+        }
+        index.push(entry);
+    }
+
+    Ok(index)
+}
+
+/// Iterates over the ticks of a `TickFile` one block at a time.  The iteration stops early if a block can't be
+/// decoded.
+pub struct TickFileIter {
+    file: Arc<TickFile>,
+    /// Index of the next block to load
+    next_block: usize,
+    /// The ticks of the current block in reverse order
+    buffer: Vec<Tick>,
+}
+
+impl TickFileIter {
+    pub fn new(file: Arc<TickFile>, start_time: Option<u64>) -> TickFileIter {
+        let mut iter = TickFileIter {
+            file: file,
+            next_block: 0,
+            buffer: Vec::new(),
+        };
+        if let Some(start_time) = start_time {
+            iter.seek(start_time);
+        }
+
+        iter
+    }
+
+    /// Moves the iterator to the first tick at or after the timestamp.
+    pub fn seek(&mut self, timestamp: u64) {
+        self.next_block = self.file.find_block(timestamp);
+        self.buffer.clear();
+        loop {
+            match self.buffer.last() {
+                Some(t) if t.timestamp >= timestamp => return,
+                Some(_) => { self.buffer.pop(); },
+                None => if !self.load_block() { return },
+            }
+        }
+    }
+
+    /// Loads the next block into the buffer, returning false if there are no more blocks or the block is invalid.
+    fn load_block(&mut self) -> bool {
+        if self.next_block >= self.file.index.len() {
+            return false;
+        }
+
+        match self.file.read_block(self.next_block) {
+            Ok(mut ticks) => {
+                ticks.reverse();
+                self.buffer = ticks;
+                self.next_block += 1;
+                true
+            },
+            Err(err) => {
+                println!("Unable to read block {} of tick file: {}", self.next_block, err);
+                self.next_block = self.file.index.len();
+                false
+            },
+        }
+    }
+}
+
+impl Iterator for TickFileIter {
+    type Item = Tick;
+
+    fn next(&mut self) -> Option<Tick> {
+        while self.buffer.is_empty() {
+            if !self.load_block() {
+                return None;
+            }
+        }
+
+        self.buffer.pop()
+    }
+}
+
+#[test]
+fn varint_encoding() {
+    let mut buf = Vec::new();
+    let values = [0, 1, 127, 128, 300, u64::max_value()];
+    for &n in values.iter() {
+        write_varint(&mut buf, n);
+    }
+    let mut pos = 0;
+    for &n in values.iter() {
+        assert_eq!(read_varint(&buf, &mut pos).unwrap(), n);
+    }
+    assert!(read_varint(&buf, &mut pos).is_err());
+
+    for &n in [0i64, -1, 1, -64, 64, i64::min_value(), i64::max_value()].iter() {
+        assert_eq!(unzigzag(zigzag(n)), n);
+    }
+    assert_eq!(zigzag(-1), 1);
+}
+
+#[test]
+fn tick_file_round_trip_and_seek() {
+    use std::env;
+
+    let ticks: Vec<Tick> = (0..1000u64).map(|i| Tick {
+        // pairs of ticks share a timestamp so that equal timestamps span block boundaries
+        timestamp: 1000 + (i / 2) * 10,
+        bid: 10000 + (i % 7) as usize * 3,
+        ask: 9990 + (i % 5) as usize,
+    }).collect();
+
+    let mut path = env::temp_dir();
+    path.push("tick_file_test.tkb");
+    {
+        let file = File::create(&path).unwrap();
+        let mut writer = TickFileWriter::new(BufWriter::new(file), 99).unwrap();
+        for t in &ticks {
+            writer.write(*t).unwrap();
+        }
+        assert!(writer.write(Tick {bid: 1, ask: 1, timestamp: 0}).is_err());
+    }
+
+    let file = Arc::new(TickFile::open(&path).unwrap());
+    assert_eq!(file.tick_count(), 1000);
+    assert_eq!(file.index().len(), 11);
+    assert_eq!(TickFileIter::new(file.clone(), None).collect::<Vec<_>>(), ticks);
+
+    // block 1 starts with the second tick at timestamp 1490
+    for &start in [0, 1490, 1495, 3000, 5990, 9999].iter() {
+        let expected: Vec<Tick> = ticks.iter().cloned().filter(|t| t.timestamp >= start).collect();
+        assert_eq!(TickFileIter::new(file.clone(), Some(start)).collect::<Vec<_>>(), expected);
+    }
+
+    let mut iter = TickFileIter::new(file.clone(), Some(5000));
+    iter.next();
+    iter.seek(1020);
+    assert_eq!(iter.next().map(|t| t.timestamp), Some(1020));
+}
+
+#[test]
+fn corrupt_tick_files() {
+    let ticks: Vec<Tick> = (0..10u64).map(|i| Tick {bid: 100 + i as usize, ask: 101 + i as usize, timestamp: i}).collect();
+    let data = {
+        let mut writer = TickFileWriter::new(Vec::new(), 4).unwrap();
+        for t in &ticks {
+            writer.write(*t).unwrap();
+        }
+        writer.finish().unwrap()
+    };
+    let index = read_index(&data).unwrap();
+    assert_eq!(index.len(), 3);
+
+    let footer_start = data.len() - FOOTER_LEN;
+    let set_u64 = |data: &mut Vec<u8>, at: usize, n: u64| {
+        let mut buf = Vec::new();
+        write_u64(&mut buf, n);
+        data[at..at + 8].copy_from_slice(&buf);
+    };
+    // a block count that overflows the size of the index
+    let mut corrupt = data.clone();
+    set_u64(&mut corrupt, footer_start + 8, u64::max_value() / 2);
+    assert!(read_index(&corrupt).is_err());
+    // an index that starts inside the header
+    let mut corrupt = data.clone();
+    set_u64(&mut corrupt, footer_start, 0);
+    assert!(read_index(&corrupt).is_err());
+    // a block that extends into the index
+    let mut corrupt = data.clone();
+    let index_offset = read_u64(&data[footer_start..]) as usize;
+    set_u64(&mut corrupt, index_offset + 8, u64::max_value() - 1);
+    assert!(read_index(&corrupt).is_err());
+
+    // tick counts and timestamps that don't fit the block
+    let block = encode_block(&ticks);
+    let entry = BlockIndexEntry {first_timestamp: 0, offset: 0, len: 0, tick_count: 10};
+    assert_eq!(decode_block(&block, &entry).unwrap(), ticks);
+    assert!(decode_block(&block, &BlockIndexEntry {tick_count: u32::max_value(), ..entry}).is_err());
+    assert!(decode_block(&block, &BlockIndexEntry {first_timestamp: u64::max_value() - 5, ..entry}).is_err());
+    assert!(decode_block(&[0, 0, 1, 0, 1, 0], &BlockIndexEntry {tick_count: 2, ..entry}).is_err());
+}
@@ -0,0 +1,58 @@
+//! A `TickGenerator` that reads historical ticks out of memory-mapped files in the binary tick format.
+
+use std::path::PathBuf;
+use std::thread;
+
+use futures::sync::mpsc::channel;
+use futures::{Future, Stream, Sink};
+use futures::stream::BoxStream;
+use trading::tick::Tick;
+use transport::tickfile::{TickFile, TickFileIter, TICK_FILE_EXTENSION};
+use conf::CONF;
+
+use super::super::*;
+
+/// Reads ticks from `{data_dir}/historical_ticks/SYMBOL.tkb`.  Seeking to a timestamp only decodes the block that
+/// contains it.
+pub struct BinaryReader {
+    pub symbol: String,
+    pub start_time: Option<u64>,
+}
+
+impl TickGenerator for BinaryReader {
+    fn get(
+        &mut self, map: Box<TickMap + Send>, cmd_handle: CommandStream
+    )-> Result<BoxStream<Tick, ()>, String> {
+        let ticks = TickFileIter::new(Arc::new(try!(open_file(&self.symbol))), self.start_time);
+
+        Ok(spawn_tickstream(ticks, map, cmd_handle, |ticks, timestamp, _| {
+            ticks.seek(timestamp);
+            true
+        }))
+    }
+
+    fn get_raw(&mut self) -> Result<BoxStream<Tick, ()>, String> {
+        let iter = TickFileIter::new(Arc::new(try!(open_file(&self.symbol))), self.start_time);
+        let (mut tx, rx) = channel(1);
+
+        thread::spawn(move || {
+            for tick in iter {
+                tx = match tx.send(tick).wait() {
+                    Ok(tx) => tx,
+                    Err(_) => break,
+                };
+            }
+        });
+
+        Ok(rx.boxed())
+    }
+}
+
+/// Maps the binary tick file for the supplied symbol into memory.
+fn open_file(symbol: &str) -> Result<TickFile, String> {
+    let mut path = PathBuf::from(CONF.data_dir);
+    path.push("historical_ticks");
+    path.push(format!("{}.{}", symbol.to_uppercase(), TICK_FILE_EXTENSION));
+
+    TickFile::open(&path)
+}
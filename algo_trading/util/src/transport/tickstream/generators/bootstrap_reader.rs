@@ -0,0 +1,71 @@
+//! A `TickGenerator` that creates alternative histories by block bootstrapping the ticks of another generator.
+
+use std::thread;
+
+use futures::sync::mpsc::channel;
+use futures::{Future, Stream, Sink};
+use futures::stream::BoxStream;
+use trading::tick::Tick;
+use trading::bootstrap::{BootstrapConfig, BootstrapTicks};
+
+use super::super::*;
+
+/// Reads all of the ticks of `source` from `history_start` up to (but not including) `history_end` and generates a
+/// bootstrapped path from them.  The source must be finite since it's read completely before the first tick is
+/// sent.  If `start_time` is set, the generated ticks before it are skipped.
+pub struct BootstrapReader {
+    pub source: Box<TickGenerator>,
+    pub history_start: Option<u64>,
+    pub history_end: Option<u64>,
+    pub config: BootstrapConfig,
+    pub start_time: Option<u64>,
+}
+
+impl BootstrapReader {
+    fn ticks(&mut self) -> Result<BootstrapTicks, String> {
+        let (history_start, history_end) = (self.history_start, self.history_end);
+        let stream = try!(self.source.get_raw());
+        let history: Vec<Tick> = stream.wait()
+            .take_while(|t| t.is_ok())
+            .map(|t| t.unwrap())
+            .skip_while(|t| history_start.map(|start| t.timestamp < start).unwrap_or(false))
+            .take_while(|t| history_end.map(|end| t.timestamp < end).unwrap_or(true))
+            .collect();
+
+        let mut ticks = try!(BootstrapTicks::new(&history, self.config.clone()));
+        if let Some(start_time) = self.start_time {
+            ticks.seek(start_time);
+        }
+
+        Ok(ticks)
+    }
+}
+
+impl TickGenerator for BootstrapReader {
+    fn get(
+        &mut self, map: Box<TickMap + Send>, cmd_handle: CommandStream
+    )-> Result<BoxStream<Tick, ()>, String> {
+        let ticks = try!(self.ticks());
+
+        Ok(spawn_tickstream(ticks, map, cmd_handle, |ticks, timestamp, _| {
+            ticks.seek(timestamp);
+            true
+        }))
+    }
+
+    fn get_raw(&mut self) -> Result<BoxStream<Tick, ()>, String> {
+        let ticks = try!(self.ticks());
+        let (mut tx, rx) = channel(1);
+
+        thread::spawn(move || {
+            for tick in ticks {
+                tx = match tx.send(tick).wait() {
+                    Ok(tx) => tx,
+                    Err(_) => break,
+                };
+            }
+        });
+
+        Ok(rx.boxed())
+    }
+}
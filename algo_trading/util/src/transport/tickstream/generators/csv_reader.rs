@@ -0,0 +1,70 @@
+//! A `TickGenerator` that reads historical ticks out of CSV files in third-party formats.
+
+use std::path::PathBuf;
+use std::thread;
+
+use futures::sync::mpsc::channel;
+use futures::{Future, Stream, Sink};
+use futures::stream::BoxStream;
+use uuid::Uuid;
+use trading::tick::Tick;
+use transport::command_server::CommandServer;
+use transport::csv_schema::{CsvSchema, CsvTickReader, ValidCsvTicks};
+use transport::flatfile::find_symbol_data;
+use conf::CONF;
+
+use super::super::*;
+
+/// Reads ticks from `{data_dir}/historical_ticks/SYMBOL.csv` or any of the compressed files or archives that
+/// `FlatfileReader` supports, parsing them with the supplied schema.  Rows that can't be parsed are skipped and
+/// logged through a `CommandServer` along with how many were skipped in total.
+pub struct CsvReader {
+    pub symbol: String,
+    pub start_time: Option<u64>,
+    pub schema: CsvSchema,
+}
+
+impl CsvReader {
+    /// Opens the symbol's data and skips to the first tick at or after `start_time`.
+    fn open_at(&self, start_time: Option<u64>) -> Result<Box<Iterator<Item=Tick> + Send>, String> {
+        let mut dir = PathBuf::from(CONF.data_dir);
+        dir.push("historical_ticks");
+        let symbol = self.symbol.to_uppercase();
+        let path = try!(find_symbol_data(&dir, &symbol, "csv").ok_or(format!("No data found for {} in {:?}", symbol, dir)));
+
+        let reader = try!(CsvTickReader::open(&path, self.schema.clone()));
+        let cs = CommandServer::new(Uuid::new_v4(), "CSV Tick Reader");
+        let ticks = ValidCsvTicks::new(reader, cs);
+
+        Ok(Box::new(ticks.skip_while(move |t| start_time.map(|start| t.timestamp < start).unwrap_or(false))))
+    }
+}
+
+impl TickGenerator for CsvReader {
+    fn get(
+        &mut self, map: Box<TickMap + Send>, cmd_handle: CommandStream
+    )-> Result<BoxStream<Tick, ()>, String> {
+        let ticks = try!(self.open_at(self.start_time));
+        let reopen = CsvReader {symbol: self.symbol.clone(), start_time: self.start_time, schema: self.schema.clone()};
+
+        Ok(spawn_tickstream(ticks, map, cmd_handle, move |ticks, timestamp, last_timestamp| {
+            seek_reopening(ticks, timestamp, last_timestamp, |timestamp| reopen.open_at(Some(timestamp)))
+        }))
+    }
+
+    fn get_raw(&mut self) -> Result<BoxStream<Tick, ()>, String> {
+        let iter = try!(self.open_at(self.start_time));
+        let (mut tx, rx) = channel(1);
+
+        thread::spawn(move || {
+            for tick in iter {
+                tx = match tx.send(tick).wait() {
+                    Ok(tx) => tx,
+                    Err(_) => break,
+                };
+            }
+        });
+
+        Ok(rx.boxed())
+    }
+}
@@ -0,0 +1,59 @@
+//! A `TickGenerator` that injects feed faults into the ticks of another generator.
+
+use std::thread;
+
+use futures::sync::mpsc::channel;
+use futures::{Future, Stream, Sink};
+use futures::stream::BoxStream;
+use trading::tick::Tick;
+
+use super::super::*;
+
+/// Passes the raw ticks of `source` through `FaultyTicks`.  The faults are added before the tickstream's own map, so
+/// maps that play ticks back in real time see the delayed timestamps.
+pub struct FaultyReader {
+    pub source: Box<TickGenerator>,
+    pub faults: FaultConfig,
+}
+
+impl FaultyReader {
+    /// Returns the faulty ticks of the source as an iterator
+    fn faulty_raw(&mut self) -> Result<FaultyTicks<Box<Iterator<Item=Tick> + Send>>, String> {
+        try!(self.faults.validate());
+        let stream = try!(self.source.get_raw());
+        let iter = stream.wait().take_while(|t| t.is_ok()).map(|t| t.unwrap());
+
+        FaultyTicks::new(Box::new(iter) as Box<Iterator<Item=Tick> + Send>, self.faults.clone())
+    }
+}
+
+impl TickGenerator for FaultyReader {
+    /// Returns a stream of the faulty ticks after applying the map.  Seek commands are dropped since the faults
+    /// depend on the position in the stream, which means that it has to be read from the start.
+    fn get(
+        &mut self, map: Box<TickMap + Send>, cmd_handle: CommandStream
+    )-> Result<BoxStream<Tick, ()>, String> {
+        let faulty = try!(self.faulty_raw());
+
+        Ok(spawn_tickstream(faulty, map, cmd_handle, |_, timestamp, _| {
+            println!("Faulty tickstreams can't seek; ignoring seek to {}", timestamp);
+            false
+        }))
+    }
+
+    fn get_raw(&mut self) -> Result<BoxStream<Tick, ()>, String> {
+        let faulty = try!(self.faulty_raw());
+        let (mut tx, rx) = channel(1);
+
+        thread::spawn(move || {
+            for tick in faulty {
+                tx = match tx.send(tick).wait() {
+                    Ok(tx) => tx,
+                    Err(_) => break,
+                };
+            }
+        });
+
+        Ok(rx.boxed())
+    }
+}
@@ -1,14 +1,13 @@
 //! A `TickGenerator` that reads historical ticks out of CSV files.
 
 use std::path::PathBuf;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
 use std::thread;
 
 use futures::sync::mpsc::channel;
 use futures::{Future, Stream, Sink};
 use futures::stream::BoxStream;
 use trading::tick::Tick;
+use transport::flatfile::{ArchiveLines, find_symbol_data};
 use conf::CONF;
 
 use super::super::*;
@@ -20,46 +19,15 @@ pub struct FlatfileReader {
 
 impl TickGenerator for FlatfileReader {
     fn get(
-        &mut self, mut map: Box<TickMap + Send>, cmd_handle: CommandStream
+        &mut self, map: Box<TickMap + Send>, cmd_handle: CommandStream
     )-> Result<BoxStream<Tick, ()>, String> {
-        // small atomic communication bus between the handle listener and worker threads
-        let internal_message: Arc<Mutex<TickstreamCommand>> = Arc::new(Mutex::new(TickstreamCommand::Stop));
-        let got_mail = Arc::new(AtomicBool::new(false));
-        let (mut sender, receiver) = channel::<Tick>(1);
-
-        // spawn the worker thread that does the blocking
-        let mut _got_mail = got_mail.clone();
-        let _internal_message = internal_message.clone();
+        // open the file and get an iterator over its lines set to the starting point
+        let ticks = try!(open_at(&self.symbol, self.start_time));
         let symbol = self.symbol.clone();
-        let start_time = self.start_time;
-        let reader_handle = thread::spawn(move || {
-            // open the file and get an iterator over its lines set to the starting point
-            let iter_ = init_reader(&symbol);
-            if iter_.is_err() {
-                println!("Unable to open the file!");
-            }
-            let iter = iter_.unwrap().skip_while(|t| {
-                start_time.is_some() && t.timestamp < start_time.unwrap()
-            });
 
-            for tick in iter {
-                if check_mail(&*got_mail, &*_internal_message) {
-                    println!("Stop command received; killing reader");
-                    break;
-                }
-
-                // apply the map
-                let t_mod = map.map(tick);
-                if t_mod.is_some() {
-                    sender = sender.send(tick).wait().expect("Unable to send through sender in `get` in flatfile_reader!");
-                }
-            }
-        }).thread().clone();
-
-        // spawn the handle listener thread that awaits commands
-        spawn_listener_thread(_got_mail, cmd_handle, internal_message, reader_handle);
-
-        Ok(receiver.boxed())
+        Ok(spawn_tickstream(ticks, map, cmd_handle, move |ticks, timestamp, last_timestamp| {
+            seek_reopening(ticks, timestamp, last_timestamp, |timestamp| open_at(&symbol, Some(timestamp)))
+        }))
     }
 
     fn get_raw(&mut self) -> Result<BoxStream<Tick, ()>, String> {
@@ -85,16 +53,30 @@ impl TickGenerator for FlatfileReader {
     }
 }
 
-/// Trys to open the file containing the historical ticks for the supplied symbol.
-pub fn init_reader(symbol: &str) -> Result<impl Iterator<Item=Tick>, String> {
-    let mut path = PathBuf::from(CONF.data_dir);
-    path.push("historical_ticks");
-    let filename = format!("{}.csv", symbol.to_uppercase());
-    path.push(filename.as_str());
+/// Opens the file containing the historical ticks for the symbol and skips to the first tick at or after
+/// `start_time`.
+fn open_at(symbol: &str, start_time: Option<u64>) -> Result<Box<Iterator<Item=Tick> + Send>, String> {
+    let iter = try!(init_reader(symbol));
+    Ok(Box::new(iter.skip_while(move |t| start_time.is_some() && t.timestamp < start_time.unwrap())))
+}
 
-    let file = try!(File::open(path).map_err( |e| e.to_string() ));
-    Ok(BufReader::new(file).lines().map( |line| {
-        Tick::from_csv_string(line.unwrap().as_str())
+/// Trys to open the file containing the historical ticks for the supplied symbol.  The ticks can be stored in
+/// `SYMBOL.csv`, a compressed `SYMBOL.csv.gz`, `SYMBOL.csv.zst`, or `SYMBOL.csv.xz`, or an archive directory
+/// `SYMBOL/` of such files, all of which are read in order as one stream.  Every file starts with a header row.
+pub fn init_reader(symbol: &str) -> Result<impl Iterator<Item=Tick>, String> {
+    let mut dir = PathBuf::from(CONF.data_dir);
+    dir.push("historical_ticks");
+    let symbol = symbol.to_uppercase();
+    let path = try!(find_symbol_data(&dir, &symbol, "csv").ok_or(format!("No data found for {} in {:?}", symbol, dir)));
+
+    // skip the header row of every file
+    let lines = try!(ArchiveLines::open(&path, true));
+    Ok(lines.filter_map(|line| match line {
+        Ok(line) => Some(Tick::from_csv_string(line.as_str())),
+        Err(err) => {
+            println!("Error while reading historical ticks: {}", err);
+            None
+        },
     }))
 }
 
@@ -0,0 +1,135 @@
+//! Merges the ticks of several `TickGenerator`s into a single stream ordered by timestamp so that backtests can
+//! be run over multiple symbols at once.
+
+use std::thread;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use futures::sync::mpsc::channel;
+use futures::{Future, Stream, Sink};
+use futures::stream::BoxStream;
+
+use trading::tick::Tick;
+
+use super::super::*;
+
+/// Iterator that performs a k-way merge of several timestamp-ordered sources of ticks, yielding each tick along
+/// with the index of the source it came from.  Ticks with equal timestamps are yielded in source order.
+pub struct MergedTicks<I: Iterator<Item=Tick>> {
+    sources: Vec<I>,
+    /// Timestamp and source index of the next tick of every source that hasn't run out
+    heap: BinaryHeap<Reverse<(u64, usize)>>,
+    /// The next tick of each source
+    pending: Vec<Option<Tick>>,
+}
+
+impl<I: Iterator<Item=Tick>> MergedTicks<I> {
+    pub fn new(sources: Vec<I>) -> MergedTicks<I> {
+        let source_count = sources.len();
+        let mut merged = MergedTicks {
+            sources: sources,
+            heap: BinaryHeap::with_capacity(source_count),
+            pending: vec![None; source_count],
+        };
+        for i in 0..source_count {
+            merged.advance(i);
+        }
+
+        merged
+    }
+
+    /// Pulls the next tick out of source `i`
+    fn advance(&mut self, i: usize) {
+        self.pending[i] = self.sources[i].next();
+        if let Some(t) = self.pending[i] {
+            self.heap.push(Reverse((t.timestamp, i)));
+        }
+    }
+}
+
+impl<I: Iterator<Item=Tick>> Iterator for MergedTicks<I> {
+    type Item = (usize, Tick);
+
+    fn next(&mut self) -> Option<(usize, Tick)> {
+        let i = match self.heap.pop() {
+            Some(Reverse((_, i))) => i,
+            None => return None,
+        };
+        let t = self.pending[i].take().unwrap();
+        self.advance(i);
+
+        Some((i, t))
+    }
+}
+
+/// Reads ticks from several generators and merges them into one stream.  Every tick of the output is paired with
+/// the index of the generator that produced it.  The tickstream stops once all generators have run out of ticks.
+pub struct MergedReader {
+    pub generators: Vec<Box<TickGenerator>>,
+}
+
+impl MergedReader {
+    pub fn new(generators: Vec<Box<TickGenerator>>) -> MergedReader {
+        MergedReader {
+            generators: generators,
+        }
+    }
+
+    /// Returns the merged ticks of all generators as an iterator
+    fn merged_raw(&mut self) -> Result<MergedTicks<Box<Iterator<Item=Tick> + Send>>, String> {
+        let mut sources = Vec::with_capacity(self.generators.len());
+        for gen in self.generators.iter_mut() {
+            let stream = try!(gen.get_raw());
+            let iter = stream.wait().take_while(|t| t.is_ok()).map(|t| t.unwrap());
+            sources.push(Box::new(iter) as Box<Iterator<Item=Tick> + Send>);
+        }
+
+        Ok(MergedTicks::new(sources))
+    }
+
+    /// Returns a stream of the merged ticks after applying the map.  The stream supports all `TickstreamCommand`s
+    /// except for seeking since the underlying generators can't be rewound; seek commands are dropped and the
+    /// stream keeps playing from where it was.
+    pub fn get(
+        &mut self, map: Box<TickMap + Send>, cmd_handle: CommandStream
+    ) -> Result<BoxStream<(usize, Tick), ()>, String> {
+        let merged = try!(self.merged_raw());
+
+        // the merged sources are plain streams that can't be rewound or skipped
+        Ok(spawn_tickstream(merged, map, cmd_handle, |_, timestamp, _| {
+            println!("Merged tickstreams can't seek; ignoring seek to {}", timestamp);
+            false
+        }))
+    }
+
+    /// Returns a stream of the merged ticks without any map or command handler.
+    pub fn get_raw(&mut self) -> Result<BoxStream<(usize, Tick), ()>, String> {
+        let merged = try!(self.merged_raw());
+        let (mut tx, rx) = channel(1);
+
+        thread::spawn(move || {
+            for next in merged {
+                tx = match tx.send(next).wait() {
+                    Ok(tx) => tx,
+                    Err(_) => break,
+                };
+            }
+        });
+
+        Ok(rx.boxed())
+    }
+}
+
+#[test]
+fn merged_ticks_ordering() {
+    let tick = |timestamp| Tick {bid: 1, ask: 1, timestamp: timestamp};
+    let a = vec![tick(1), tick(4), tick(4), tick(9)];
+    let b = vec![tick(2), tick(4), tick(10)];
+    let c: Vec<Tick> = Vec::new();
+    let d = vec![tick(0)];
+
+    let merged: Vec<(usize, u64)> = MergedTicks::new(vec![a.into_iter(), b.into_iter(), c.into_iter(), d.into_iter()])
+        .map(|(i, t)| (i, t.timestamp))
+        .collect();
+    assert_eq!(merged, vec![(3, 0), (0, 1), (1, 2), (0, 4), (0, 4), (1, 4), (0, 9), (1, 10)]);
+}
@@ -1,7 +1,16 @@
 //! Tick generators take data from an external source and create a stream of time series data out of it that can be saved to storage, used
 //! for a backtest, or fed into strategies during a live trading system.
 
+pub mod binary_reader;
+pub mod bootstrap_reader;
+pub mod csv_reader;
+pub mod faulty_reader;
 pub mod flatfile_reader;
+pub mod merged_reader;
+pub mod parquet_reader;
+pub mod postgres_generator;
 pub mod postgres_reader;
 pub mod random_reader;
+pub mod redis_channel_generator;
 pub mod redis_reader;
+pub mod synthetic_reader;
@@ -0,0 +1,91 @@
+//! A `TickGenerator` that reads historical ticks out of Apache Parquet files.
+
+#[cfg(feature = "parquet")]
+use std::path::PathBuf;
+#[cfg(feature = "parquet")]
+use std::thread;
+
+#[cfg(feature = "parquet")]
+use futures::sync::mpsc::channel;
+#[cfg(feature = "parquet")]
+use futures::{Future, Stream, Sink};
+use futures::stream::BoxStream;
+use trading::tick::Tick;
+#[cfg(feature = "parquet")]
+use transport::parquet_file::{ParquetTicks, ParquetQuery, PARQUET_EXTENSION};
+#[cfg(not(feature = "parquet"))]
+use transport::NO_PARQUET_SUPPORT;
+#[cfg(feature = "parquet")]
+use conf::CONF;
+
+use super::super::*;
+
+/// Reads ticks from `{data_dir}/historical_ticks/SYMBOL.parquet`.  Only the `timestamp`, `bid`, and `ask` columns are
+/// read, and row groups that only contain ticks before `start_time` or after `end_time` are skipped.
+///
+/// Without the `parquet` feature, starting the generator returns an error.
+pub struct ParquetReader {
+    pub symbol: String,
+    pub start_time: Option<u64>,
+    pub end_time: Option<u64>,
+}
+
+#[cfg(feature = "parquet")]
+impl ParquetReader {
+    /// Opens the Parquet file of the reader's symbol.
+    fn open(&self) -> Result<ParquetTicks, String> {
+        let mut path = PathBuf::from(CONF.data_dir);
+        path.push("historical_ticks");
+        path.push(format!("{}.{}", self.symbol.to_uppercase(), PARQUET_EXTENSION));
+
+        let query = ParquetQuery {
+            start_time: self.start_time,
+            end_time: self.end_time,
+            ..ParquetQuery::default()
+        };
+        ParquetTicks::open(&path, query)
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl TickGenerator for ParquetReader {
+    fn get(
+        &mut self, map: Box<TickMap + Send>, cmd_handle: CommandStream
+    )-> Result<BoxStream<Tick, ()>, String> {
+        let ticks = try!(self.open());
+
+        Ok(spawn_tickstream(ticks, map, cmd_handle, |ticks, timestamp, _| {
+            ticks.seek(timestamp);
+            true
+        }))
+    }
+
+    fn get_raw(&mut self) -> Result<BoxStream<Tick, ()>, String> {
+        let ticks = try!(self.open());
+        let (mut tx, rx) = channel(1);
+
+        thread::spawn(move || {
+            for tick in ticks {
+                tx = match tx.send(tick).wait() {
+                    Ok(tx) => tx,
+                    Err(_) => break,
+                };
+            }
+        });
+
+        Ok(rx.boxed())
+    }
+}
+
+#[cfg(not(feature = "parquet"))]
+impl TickGenerator for ParquetReader {
+    fn get(
+        &mut self, _map: Box<TickMap + Send>, _cmd_handle: CommandStream
+    )-> Result<BoxStream<Tick, ()>, String> {
+        Err(String::from(NO_PARQUET_SUPPORT))
+    }
+
+    fn get_raw(&mut self) -> Result<BoxStream<Tick, ()>, String> {
+        Err(String::from(NO_PARQUET_SUPPORT))
+    }
+}
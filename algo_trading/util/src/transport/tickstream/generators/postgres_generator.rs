@@ -0,0 +1,144 @@
+//! A `GenTickGenerator` that reads back the generic ticks stored by a `PostgresSink`.
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::marker::PhantomData;
+use std::thread;
+
+use futures::{Future, Sink};
+use futures::sync::mpsc::{channel, Sender, Receiver};
+
+use trading::tick::GenTick;
+use transport::tickstream::GenTickGenerator;
+use transport::postgres::{get_client, valid_table_name, parse_copy_row, PostgresSchema};
+
+pub struct PostgresGenerator<T> {
+    table: String,
+    start_time: Option<u64>,
+    end_time: Option<u64>,
+    ghost: PhantomData<T>,
+}
+
+/// Reads ticks out of a table created by a `PostgresSink` with the same kind of data, ordered by timestamp.  The
+/// rows are streamed out of the database with `COPY` so the table doesn't have to fit into memory.
+/// Requires that the setting `table` be supplied in the settings `HashMap`.  The optional `start_time` and
+/// `end_time` settings limit the ticks to those with timestamps in `[start_time, end_time)`.
+impl<T> GenTickGenerator<T> for PostgresGenerator<T> where T : PostgresSchema + Send + 'static {
+    fn new(settings: HashMap<String, String>) -> Result<Self, String> {
+        let table = match settings.get("table") {
+            Some(t) => t.clone(),
+            None => { return Err(String::from("You must supply a `table` argument in the input `HashMap`~")) },
+        };
+        if !valid_table_name(&table) {
+            return Err(format!("`{}` isn't a valid table name.", table));
+        }
+        let parse_time = |key: &str| -> Result<Option<u64>, String> {
+            match settings.get(key) {
+                Some(time) => time.parse().map(Some).map_err(|_| format!("Invalid {}: {}", key, time)),
+                None => Ok(None),
+            }
+        };
+
+        Ok(PostgresGenerator {
+            start_time: parse_time("start_time")?,
+            end_time: parse_time("end_time")?,
+            table: table,
+            ghost: PhantomData{},
+        })
+    }
+
+    fn get_stream(&mut self) -> Receiver<GenTick<T>> {
+        let (tx, rx) = channel(1);
+        let columns: Vec<&str> = T::columns().iter().map(|&(name, _)| name).collect();
+        let query = format!(
+            "COPY (SELECT tick_time, {} FROM {} WHERE tick_time >= {} AND tick_time < {} ORDER BY tick_time) TO STDOUT;",
+            columns.join(", "),
+            self.table,
+            self.start_time.unwrap_or(0),
+            self.end_time.map(|time| time.to_string()).unwrap_or(String::from("9223372036854775807"))
+        );
+
+        thread::spawn(move || {
+            let client = match get_client() {
+                Ok(client) => client,
+                Err(e) => {
+                    println!("Unable to connect to postgres: {:?}", e);
+                    return;
+                },
+            };
+            let stmt = match client.prepare(&query) {
+                Ok(stmt) => stmt,
+                Err(e) => {
+                    println!("Error while querying generic ticks: {:?}", e);
+                    return;
+                },
+            };
+
+            let mut writer = CopyOutWriter {
+                line: Vec::new(),
+                tx: Some(tx),
+            };
+            // the writer stops the copy by returning an error once the stream is dropped, so that isn't reported
+            if let Err(e) = stmt.copy_out(&[], &mut writer) {
+                if writer.tx.is_some() {
+                    println!("Error while reading generic ticks: {:?}", e);
+                }
+            }
+        });
+
+        rx
+    }
+}
+
+/// Parses the rows written out by `COPY ... TO STDOUT` into ticks and sends them down the stream.
+struct CopyOutWriter<T> {
+    /// The part of the current row received so far
+    line: Vec<u8>,
+    /// Dropped once the receiving end of the stream is
+    tx: Option<Sender<GenTick<T>>>,
+}
+
+impl<T> CopyOutWriter<T> where T : PostgresSchema {
+    fn send_line(&mut self) -> io::Result<()> {
+        let tick = {
+            let line = String::from_utf8_lossy(&self.line);
+            parse_copy_row(&line).and_then(|(timestamp, values)| {
+                T::from_values(&values).map(|data| GenTick {timestamp: timestamp, data: data})
+            })
+        };
+        self.line.clear();
+
+        let tick = match tick {
+            Ok(tick) => tick,
+            Err(e) => {
+                println!("Unable to parse generic tick: {}", e);
+                return Ok(());
+            },
+        };
+        match self.tx.take().unwrap().send(tick).wait() {
+            Ok(tx) => {
+                self.tx = Some(tx);
+                Ok(())
+            },
+            Err(_) => Err(io::Error::new(io::ErrorKind::BrokenPipe, "The tick stream was dropped.")),
+        }
+    }
+}
+
+impl<T> Write for CopyOutWriter<T> where T : PostgresSchema {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &b in buf {
+            if b == b'\n' {
+                try!(self.send_line());
+            } else {
+                self.line.push(b);
+            }
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -1,8 +1,6 @@
 //! Reads ticks out of a Postgres database
 
 use std::thread;
-use std::sync::{Arc, Mutex};
-use std::sync::atomic::AtomicBool;
 
 use futures::{Future, Stream, Sink};
 use futures::stream::BoxStream;
@@ -25,15 +23,14 @@ impl TickGenerator for PostgresReader {
     fn get(
         &mut self, mut map: Box<TickMap + Send>, cmd_handle: CommandStream
     ) -> Result<BoxStream<Tick, ()>, String> {
-        // small atomic communication bus between the handle listener and worker threads
-        let internal_message: Arc<Mutex<TickstreamCommand>> = Arc::new(Mutex::new(TickstreamCommand::Stop));
-        let got_mail = Arc::new(AtomicBool::new(false));
         let (mut tx, rx) = channel::<Tick>(1);
 
-        let _got_mail = got_mail.clone();
         let symbol = self.symbol.clone();
         let start_time = self.start_time;
-        let reader_handle = thread::spawn(move || {
+        thread::spawn(move || {
+            // commands aren't handled, but the handle is kept for as long as the reader runs so that sending them
+            // doesn't fail
+            let _cmd_handle = cmd_handle;
             let conn_opt = get_client();
             if conn_opt.is_err() {
                 return Err("Unable to create Postgres client".to_string())
@@ -67,10 +64,7 @@ impl TickGenerator for PostgresReader {
             }
 
             Ok(()) // ???
-        }).thread().clone();
-
-        // spawn the handle listener thread that awaits commands
-        spawn_listener_thread(_got_mail, cmd_handle, internal_message, reader_handle);
+        });
 
         Ok(rx.boxed())
     }
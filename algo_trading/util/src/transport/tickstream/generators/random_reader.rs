@@ -1,8 +1,6 @@
 //! A `TickGenerator` that generates random ticks.
 
 use std::thread;
-use std::sync::{Arc, Mutex};
-use std::sync::atomic::AtomicBool;
 use rand::{thread_rng, ThreadRng};
 use rand::distributions::{IndependentSample, Range};
 
@@ -23,18 +21,12 @@ impl TickGenerator for RandomReader {
         let (mut tx, rx) = channel::<Tick>(1);
         let mut timestamp = 0;
 
-        // small atomic communication bus between the handle listener and worker threads
-        let internal_message: Arc<Mutex<TickstreamCommand>> = Arc::new(Mutex::new(TickstreamCommand::Stop));
-        let _internal_message = internal_message.clone();
-        let got_mail = Arc::new(AtomicBool::new(false));
-        let mut _got_mail = got_mail.clone();
-
-        let reader_handle = thread::spawn(move || {
-            thread::park();
-
+        thread::spawn(move || {
             let mut rng = thread_rng();
+            // the tickstream starts out paused until it's resumed
+            let mut playback = Playback::Paused;
             loop {
-                if check_mail(&*got_mail, &*_internal_message) {
+                if check_mail(&cmd_handle, &mut playback) {
                     println!("Stop command received; killing reader");
                     break;
                 }
@@ -48,10 +40,7 @@ impl TickGenerator for RandomReader {
                     tx = tx.send(mod_t.unwrap()).wait().expect("Unable to send tick to sink in random_reader.rs");
                 }
             }
-        }).thread().clone();
-
-        // spawn the handle listener thread that awaits commands
-        spawn_listener_thread(_got_mail, cmd_handle, internal_message, reader_handle);
+        });
 
         Ok(rx.boxed())
     }
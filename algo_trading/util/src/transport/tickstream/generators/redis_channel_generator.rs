@@ -0,0 +1,78 @@
+//! A `GenTickGenerator` that receives the generic ticks published by a `RedisChannelSink`.
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::thread;
+
+use futures::{Future, Sink, Stream};
+use futures::sync::mpsc::{channel, Receiver};
+use serde::{Serialize, Deserialize};
+use serde_json;
+
+use trading::tick::GenTick;
+use transport::tickstream::GenTickGenerator;
+use transport::redis::sub_channel;
+use conf::CONF;
+
+pub struct RedisChannelGenerator<T> {
+    redis_host: String,
+    channel: String,
+    ghost: PhantomData<T>,
+}
+
+/// Subscribes to a Redis channel and parses the messages published on it by a `RedisChannelSink` with the same kind
+/// of data into ticks.  Messages that can't be parsed are skipped.  The stream never ends by itself.
+/// Requires that the setting `channel` be supplied in the settings `HashMap`.  The optional `redis_host` setting
+/// defaults to the configured Redis host.
+impl<T> GenTickGenerator<T> for RedisChannelGenerator<T>
+    where T : Serialize, T : for<'de> Deserialize<'de>, T : Send + 'static
+{
+    fn new(settings: HashMap<String, String>) -> Result<Self, String> {
+        let channel = match settings.get("channel") {
+            Some(c) => c.clone(),
+            None => { return Err(String::from("You must supply a `channel` argument in the input `HashMap`~")) },
+        };
+
+        Ok(RedisChannelGenerator {
+            redis_host: settings.get("redis_host").cloned().unwrap_or(String::from(CONF.redis_host)),
+            channel: channel,
+            ghost: PhantomData{},
+        })
+    }
+
+    fn get_stream(&mut self) -> Receiver<GenTick<T>> {
+        let (mut tx, rx) = channel(1);
+        // subscribe before returning so that no ticks published after this are missed
+        let messages = sub_channel(&self.redis_host, &self.channel);
+
+        thread::spawn(move || {
+            for msg in messages.wait() {
+                let msg = match msg {
+                    Ok(msg) => msg,
+                    Err(_) => break,
+                };
+                match serde_json::from_str::<GenTick<T>>(&msg) {
+                    Ok(tick) => tx = match tx.send(tick).wait() {
+                        Ok(tx) => tx,
+                        Err(_) => break,
+                    },
+                    Err(e) => println!("Unable to parse generic tick from message {}: {:?}", msg, e),
+                }
+            }
+        });
+
+        rx
+    }
+}
+
+#[test]
+fn generic_tick_messages() {
+    // the messages sent by the sink are parsed back into the same ticks
+    let tick = GenTick {timestamp: 10, data: (String::from("EURUSD"), 1.25f64)};
+    let msg = serde_json::to_string(&tick).unwrap();
+    assert_eq!(msg, "{\"timestamp\":10,\"data\":[\"EURUSD\",1.25]}");
+    let parsed: GenTick<(String, f64)> = serde_json::from_str(&msg).unwrap();
+    assert_eq!(parsed.timestamp, tick.timestamp);
+    assert_eq!(parsed.data, tick.data);
+    assert!(serde_json::from_str::<GenTick<(String, f64)>>("{\"timestamp\":10,\"data\":3}").is_err());
+}
@@ -24,19 +24,14 @@ impl TickGenerator for RedisReader {
         let host = self.redis_host.clone();
         let input_channel = self.channel.clone();
 
-        // small atomic communication bus between the handle listener and worker threads
-        let internal_message: Arc<Mutex<TickstreamCommand>> = Arc::new(Mutex::new(TickstreamCommand::Stop));
-        let _internal_message = internal_message.clone();
-        let got_mail = Arc::new(AtomicBool::new(false));
-        let mut _got_mail = got_mail.clone();
-
         let (mut tx, rx) = channel::<Tick>(1);
 
-        let reader_handle = thread::spawn(move || {
+        thread::spawn(move || {
             let in_rx = sub_channel(host.as_str(), input_channel.as_str());
 
+            let mut playback = Playback::Playing;
             for t_string in in_rx.wait() {
-                if check_mail(&*got_mail, &*_internal_message) {
+                if check_mail(&cmd_handle, &mut playback) {
                     println!("Stop command received; killing reader");
                     break;
                 }
@@ -48,10 +43,7 @@ impl TickGenerator for RedisReader {
                     tx = tx.send(t_mod.unwrap()).wait().expect("Unable to send through tx in `get` redis_reader!");
                 }
             }
-        }).thread().clone();
-
-        // spawn the handle listener thread that awaits commands
-        spawn_listener_thread(_got_mail, cmd_handle, internal_message, reader_handle);
+        });
 
         Ok(rx.boxed())
     }
@@ -0,0 +1,59 @@
+//! A `TickGenerator` that creates seeded synthetic ticks from a stochastic price model.
+
+use std::thread;
+
+use futures::sync::mpsc::channel;
+use futures::{Future, Stream, Sink};
+use futures::stream::BoxStream;
+use trading::tick::Tick;
+use trading::synthetic::{SyntheticConfig, SyntheticTicks};
+
+use super::super::*;
+
+/// Generates ticks as described by `config`.  The same config always produces the same ticks, so runs over synthetic
+/// data can be reproduced; symbols that should move independently need different seeds.  If `start_time` is set, the
+/// ticks before it are skipped.
+pub struct SyntheticReader {
+    pub config: SyntheticConfig,
+    pub start_time: Option<u64>,
+}
+
+impl SyntheticReader {
+    fn ticks(&self) -> Result<SyntheticTicks, String> {
+        let mut ticks = try!(SyntheticTicks::new(self.config.clone()));
+        if let Some(start_time) = self.start_time {
+            ticks.seek(start_time);
+        }
+
+        Ok(ticks)
+    }
+}
+
+impl TickGenerator for SyntheticReader {
+    fn get(
+        &mut self, map: Box<TickMap + Send>, cmd_handle: CommandStream
+    )-> Result<BoxStream<Tick, ()>, String> {
+        let ticks = try!(self.ticks());
+
+        Ok(spawn_tickstream(ticks, map, cmd_handle, |ticks, timestamp, _| {
+            ticks.seek(timestamp);
+            true
+        }))
+    }
+
+    fn get_raw(&mut self) -> Result<BoxStream<Tick, ()>, String> {
+        let ticks = try!(self.ticks());
+        let (mut tx, rx) = channel(1);
+
+        thread::spawn(move || {
+            for tick in ticks {
+                tx = match tx.send(tick).wait() {
+                    Ok(tx) => tx,
+                    Err(_) => break,
+                };
+            }
+        });
+
+        Ok(rx.boxed())
+    }
+}
@@ -11,7 +11,6 @@
 use std::collections::HashMap;
 
 use futures::sync::mpsc::Receiver;
-use serde::{Serialize, Deserialize};
 
 use trading::tick::GenTick;
 use transport::command_server::CommandServer;
@@ -27,8 +26,9 @@ pub trait GenTickGenerator<T> {
 
 /// Represents a transformation applied to ticks.
 pub trait GenTickMap<T, O> {
-    /// Given some settings and a `CommandServer` for logging purposes, returns a new instance of a map
-    fn new(settings: HashMap<String, String>, cs: CommandServer) -> Self where Self:Sized;
+    /// Given some settings and a `CommandServer` for logging purposes, returns a new instance of a map or an error if
+    /// the settings are invalid
+    fn new(settings: HashMap<String, String>, cs: CommandServer) -> Result<Self, String> where Self:Sized;
 
     /// Processes a tick through the map, optionally returning a new tick.
     fn map(&mut self, t: GenTick<T>) -> Option<GenTick<O>>;
@@ -42,37 +42,3 @@ pub trait GenTickSink<T> {
     /// Processes a tick into the sink.
     fn tick(&mut self, t: GenTick<T>);
 }
-
-// some example implementations of generic tick sinks
-
-struct PostgresSink<T> {
-    buffer: Vec<GenTick<T>>,
-}
-
-// An example of specifically implementing this trait for a specific kind of data.  Since postgres requires that the
-// schema of data be known in order for it to be stored, the kind of generic tick in the implementation must also be known.
-impl GenTickSink<(usize, usize)> for PostgresSink<(usize, usize)> {
-    fn new(settings: HashMap<String, String>) -> Result<Self, String> {
-        unimplemented!();
-    }
-
-    fn tick(&mut self, t: GenTick<(usize, usize)>) {
-        unimplemented!();
-    }
-}
-
-struct RedisChannelSink<T> {
-    buffer: Vec<GenTick<T>>,
-}
-
-// An example of generically implementing these traits for a struct.  Since Redis doesn't care the schema of the data
-// you send over it (as long as you can make it into a string) any kind of generic tick is acceptable.
-impl<T> GenTickSink<T> for RedisChannelSink<T> where T:Serialize, for<'de> T:Deserialize<'de> {
-    fn new(settings: HashMap<String, String>) -> Result<Self, String> {
-        unimplemented!();
-    }
-
-    fn tick(&mut self, t: GenTick<T>) {
-        unimplemented!();
-    }
-}
@@ -0,0 +1,176 @@
+//! Maps that aggregate ticks into bars.
+
+use std::collections::{HashMap, VecDeque};
+
+use futures::stream::{self, BoxStream};
+use futures::Stream;
+use serde_json;
+
+use trading::tick::{Tick, GenTick};
+use trading::bar::{Bar, BarBuilder, BarDefinition};
+use transport::command_server::CommandServer;
+use transport::tickstream::generics::GenTickMap;
+
+/// Aggregates ticks into bars, each stamped with the timestamp of the tick that completed it.  Accepts either plain
+/// ticks, which are each counted as one unit of volume, or ticks paired with their volume.
+///
+/// As a `GenTickMap` it emits one bar per tick.  The first tick of a session can close the bar left over from the
+/// previous session and complete its own; the second of those bars is then held back until the next tick.  Use
+/// `BarTicks` or `bar_stream` to get every bar as soon as it's completed.
+pub struct BarMap {
+    pub builder: BarBuilder,
+    /// bars completed but not yet emitted by `map`
+    pending: VecDeque<GenTick<Bar>>,
+}
+
+impl BarMap {
+    /// Creates a map for the bars in the definition, returning an error if the definition is invalid.
+    pub fn from_definition(def: BarDefinition) -> Result<BarMap, String> {
+        Ok(BarMap {
+            builder: try!(BarBuilder::new(def)),
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Adds a tick with the given volume, returning all of the bars that it completed in order.
+    pub fn push(&mut self, t: &Tick, volume: usize) -> Vec<GenTick<Bar>> {
+        let timestamp = t.timestamp;
+        self.builder.push(t, volume).into_iter()
+            .map(|bar| GenTick {timestamp: timestamp, data: bar.data})
+            .collect()
+    }
+
+    /// Closes the current bar on the clock if it can no longer receive ticks at `timestamp`.
+    pub fn close_until(&mut self, timestamp: u64) -> Option<GenTick<Bar>> {
+        self.builder.close_until(timestamp).map(|bar| GenTick {timestamp: timestamp, data: bar.data})
+    }
+
+    fn map_one(&mut self, t: &Tick, volume: usize) -> Option<GenTick<Bar>> {
+        let bars = self.push(t, volume);
+        self.pending.extend(bars);
+        self.pending.pop_front()
+    }
+}
+
+/// Reads the `BarDefinition` out of the `definition` setting.
+fn parse_definition(settings: &HashMap<String, String>) -> Result<BarMap, String> {
+    settings.get("definition")
+        .ok_or(String::from("No `definition` setting supplied."))
+        .and_then(|s| serde_json::from_str::<BarDefinition>(s).map_err(|err| format!("{:?}", err)))
+        .and_then(BarMap::from_definition)
+        .map_err(|err| format!("Unable to create bar map: {}", err))
+}
+
+impl GenTickMap<Tick, Bar> for BarMap {
+    fn new(settings: HashMap<String, String>, _: CommandServer) -> Result<Self, String> {
+        parse_definition(&settings)
+    }
+
+    fn map(&mut self, t: GenTick<Tick>) -> Option<GenTick<Bar>> {
+        self.map_one(&t.data, 1)
+    }
+}
+
+impl GenTickMap<(Tick, usize), Bar> for BarMap {
+    fn new(settings: HashMap<String, String>, _: CommandServer) -> Result<Self, String> {
+        parse_definition(&settings)
+    }
+
+    fn map(&mut self, t: GenTick<(Tick, usize)>) -> Option<GenTick<Bar>> {
+        let (tick, volume) = t.data;
+        self.map_one(&tick, volume)
+    }
+}
+
+/// Aggregates a sequence of ticks into bars with a `BarMap`, counting each tick as one unit of volume.  The bar of
+/// the last tick isn't closed, so it isn't returned.
+pub struct BarTicks<I> {
+    ticks: I,
+    map: BarMap,
+    pending: VecDeque<GenTick<Bar>>,
+}
+
+impl<I: Iterator<Item=Tick>> BarTicks<I> {
+    pub fn new(ticks: I, def: BarDefinition) -> Result<BarTicks<I>, String> {
+        Ok(BarTicks {
+            ticks: ticks,
+            map: try!(BarMap::from_definition(def)),
+            pending: VecDeque::new(),
+        })
+    }
+}
+
+impl<I: Iterator<Item=Tick>> Iterator for BarTicks<I> {
+    type Item = GenTick<Bar>;
+
+    fn next(&mut self) -> Option<GenTick<Bar>> {
+        while self.pending.is_empty() {
+            let t = match self.ticks.next() {
+                Some(t) => t,
+                None => return None,
+            };
+            self.pending.extend(self.map.push(&t, 1));
+        }
+
+        self.pending.pop_front()
+    }
+}
+
+/// Aggregates a tickstream into bars, emitting each bar as soon as the tick that completes it arrives.
+pub fn bar_stream<S>(ticks: S, def: BarDefinition) -> Result<BoxStream<GenTick<Bar>, ()>, String>
+    where S: Stream<Item=Tick, Error=()> + Send + 'static
+{
+    let mut map = try!(BarMap::from_definition(def));
+
+    Ok(ticks.map(move |t| stream::iter(map.push(&t, 1).into_iter().map(Ok))).flatten().boxed())
+}
+
+#[test]
+fn bar_map_volume_ticks() {
+    use trading::bar::BarType;
+
+    let mut map = BarMap::from_definition(BarDefinition {bar_type: BarType::Volume{volume: 5}, session: None}).unwrap();
+    let t = Tick {bid: 1, ask: 2, timestamp: 1};
+    assert!(GenTickMap::<(Tick, usize), Bar>::map(&mut map, GenTick {timestamp: 1, data: (t, 3)}).is_none());
+    let bar = GenTickMap::<(Tick, usize), Bar>::map(&mut map, GenTick {timestamp: 2, data: (t, 3)}).unwrap();
+    assert_eq!(bar.data.volume, 6);
+    assert_eq!(bar.data.tick_count, 2);
+
+    let mut settings = HashMap::new();
+    settings.insert(String::from("definition"), String::from(r#"{"bar_type": {"Time": {"period": 0}}, "session": null}"#));
+    assert!(parse_definition(&settings).is_err());
+}
+
+#[test]
+fn bars_across_sessions() {
+    use futures::stream;
+    use trading::bar::{BarType, Session};
+
+    let hour = 60 * 60 * 1000;
+    let session = Some(Session {start: 8 * hour, end: 16 * hour});
+    let tick = |price, timestamp| Tick {bid: price, ask: price, timestamp: timestamp};
+    let volume_tick = |timestamp, volume| GenTick {timestamp: timestamp, data: (tick(1, timestamp), volume)};
+
+    // the first tick of a session closes the bar left over from the previous one and completes a bar by itself; the
+    // map emits the first of them and holds the second back until the next tick
+    let def = BarDefinition {bar_type: BarType::Volume{volume: 10}, session: session};
+    let mut map = BarMap::from_definition(def).unwrap();
+    assert!(GenTickMap::<(Tick, usize), Bar>::map(&mut map, volume_tick(9 * hour, 4)).is_none());
+    let bar = GenTickMap::<(Tick, usize), Bar>::map(&mut map, volume_tick(33 * hour, 15)).unwrap();
+    assert_eq!((bar.timestamp, bar.data.volume), (33 * hour, 4));
+    let bar = GenTickMap::<(Tick, usize), Bar>::map(&mut map, volume_tick(34 * hour, 1)).unwrap();
+    assert_eq!((bar.timestamp, bar.data.volume), (33 * hour, 15));
+
+    // at the stream level both bars are emitted with the tick that completed them
+    let def = BarDefinition {bar_type: BarType::Renko{brick_size: 5}, session: session};
+    let ticks = vec![tick(100, 9 * hour), tick(102, 15 * hour), tick(110, 33 * hour), tick(111, 34 * hour)];
+    let bars: Vec<GenTick<Bar>> = BarTicks::new(ticks.clone().into_iter(), def).unwrap().collect();
+    assert_eq!(bars.len(), 2);
+    assert_eq!((bars[0].timestamp, bars[0].data.mid.close), (33 * hour, 102));
+    assert_eq!((bars[1].timestamp, bars[1].data.mid.close), (33 * hour, 110));
+
+    let streamed: Vec<(u64, Bar)> = bar_stream(stream::iter(ticks.into_iter().map(Ok)), def).unwrap().wait()
+        .map(|bar| bar.map(|bar| (bar.timestamp, bar.data)).unwrap())
+        .collect();
+    assert_eq!(streamed, bars.iter().map(|bar| (bar.timestamp, bar.data)).collect::<Vec<_>>());
+}
@@ -0,0 +1,340 @@
+//! Injects the faults of real data feeds into a tickstream so that strategies can be tested against them: lost,
+//! duplicated, and reordered ticks, latency, wide spreads, and bad prints.  Every fault is off by default and all
+//! randomness comes from seeded PRNGs, so a backtest over faulty data can be reproduced.
+//!
+//! `FaultMap` adds the faults that only involve a single tick.  Duplicating and reordering ticks changes the number
+//! and order of the ticks in the stream, so that's done by `FaultyTicks`, which wraps the whole stream.
+
+use std::collections::VecDeque;
+use std::fmt;
+
+use rand::{Rng, SeedableRng, Isaac64Rng};
+use rand::distributions::{IndependentSample, Exp, LogNormal};
+
+use super::*;
+
+/// How long ticks are delayed.  The delay is added to the timestamp of each tick.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum LatencyModel {
+    None,
+    Fixed{ms: u64},
+    Uniform{min_ms: u64, max_ms: u64},
+    Exponential{mean_ms: f64},
+    /// Usually close to `median_ms` with a long tail of slow ticks; `sigma` is the standard deviation of the log
+    /// of the latency.
+    LogNormal{median_ms: f64, sigma: f64},
+}
+
+impl Default for LatencyModel {
+    fn default() -> LatencyModel { LatencyModel::None }
+}
+
+fn default_spread_factor() -> f64 { 1. }
+
+/// Settings for `FaultyTicks`.  Probabilities are per tick.  A `FaultMap` only uses the settings for single-tick
+/// faults and can't duplicate or reorder ticks.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FaultConfig {
+    pub seed: u64,
+    #[serde(default)]
+    pub drop_probability: f64,
+    #[serde(default)]
+    pub duplicate_probability: f64,
+    /// Probability that a tick is swapped with one of the `reorder_window` ticks before it
+    #[serde(default)]
+    pub reorder_probability: f64,
+    #[serde(default)]
+    pub reorder_window: usize,
+    #[serde(default)]
+    pub latency: LatencyModel,
+    /// Spreads are multiplied by this around the mid price.
+    #[serde(default = "default_spread_factor")]
+    pub spread_factor: f64,
+    #[serde(default)]
+    pub bad_print_probability: f64,
+    /// How far bad prints are from the real price as a fraction of it; each bad print is randomly above or below.
+    #[serde(default)]
+    pub bad_print_size: f64,
+}
+
+impl FaultConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        let probabilities = [
+            self.drop_probability, self.duplicate_probability, self.reorder_probability, self.bad_print_probability,
+        ];
+        if probabilities.iter().any(|&p| !(p >= 0. && p <= 1.)) {
+            return Err(String::from("Fault probabilities must be between 0 and 1."));
+        }
+        if self.reorder_probability > 0. && self.reorder_window == 0 {
+            return Err(String::from("Reordering ticks needs a reorder window of at least 1."));
+        }
+        if !(self.spread_factor >= 0.) || !(self.bad_print_size >= 0. && self.bad_print_size < 1.) {
+            return Err(String::from("The spread factor can't be negative and the bad print size must be below 1."));
+        }
+        match self.latency {
+            LatencyModel::Uniform{min_ms, max_ms} if min_ms > max_ms => {
+                return Err(String::from("The minimum latency can't be larger than the maximum."));
+            },
+            LatencyModel::Exponential{mean_ms} if !(mean_ms > 0.) => {
+                return Err(String::from("The mean latency must be positive."));
+            },
+            LatencyModel::LogNormal{median_ms, sigma} if !(median_ms > 0.) || sigma < 0. => {
+                return Err(String::from("The median latency must be positive and sigma can't be negative."));
+            },
+            _ => (),
+        }
+
+        Ok(())
+    }
+}
+
+/// What a `FaultMap` has done to the ticks that passed through it
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct FaultSummary {
+    pub ticks_in: usize,
+    pub ticks_out: usize,
+    pub dropped: usize,
+    pub duplicated: usize,
+    pub reordered: usize,
+    pub bad_prints: usize,
+    /// Sum of the latency added to all ticks
+    pub total_latency_ms: u64,
+}
+
+impl fmt::Display for FaultSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(writeln!(f, "{} ticks in, {} ticks out", self.ticks_in, self.ticks_out));
+        let mean_latency = if self.ticks_in == 0 { 0. } else { self.total_latency_ms as f64 / self.ticks_in as f64 };
+        writeln!(
+            f, "dropped: {}, duplicated: {}, reordered: {}, bad prints: {}, mean latency: {:.1}ms",
+            self.dropped, self.duplicated, self.reordered, self.bad_prints, mean_latency
+        )
+    }
+}
+
+/// Injects the faults that affect single ticks into a tickstream: lost ticks, latency, wide spreads, and bad prints.
+pub struct FaultMap {
+    config: FaultConfig,
+    rng: Isaac64Rng,
+    summary: Arc<Mutex<FaultSummary>>,
+}
+
+impl FaultMap {
+    /// Returns an error if the settings are invalid or ask for duplicated or reordered ticks, which a `TickMap`
+    /// can't produce.
+    pub fn new(config: FaultConfig) -> Result<FaultMap, String> {
+        try!(config.validate());
+        if config.duplicate_probability > 0. || config.reorder_probability > 0. {
+            return Err(String::from("Ticks can only be duplicated or reordered by a `FaultyReader`."));
+        }
+
+        Ok(FaultMap {
+            rng: Isaac64Rng::from_seed(&[config.seed][..]),
+            summary: Arc::new(Mutex::new(FaultSummary::default())),
+            config: config,
+        })
+    }
+
+    /// Returns a handle to the summary of the faults added by the map that can be read while the map is in use.
+    pub fn summary_handle(&self) -> Arc<Mutex<FaultSummary>> {
+        self.summary.clone()
+    }
+
+    fn latency(&mut self) -> u64 {
+        match self.config.latency {
+            LatencyModel::None => 0,
+            LatencyModel::Fixed{ms} => ms,
+            LatencyModel::Uniform{min_ms, max_ms} => self.rng.gen_range(min_ms, max_ms + 1),
+            LatencyModel::Exponential{mean_ms} => Exp::new(1. / mean_ms).ind_sample(&mut self.rng).round() as u64,
+            LatencyModel::LogNormal{median_ms, sigma} => {
+                LogNormal::new(median_ms.ln(), sigma).ind_sample(&mut self.rng).round() as u64
+            },
+        }
+    }
+
+    /// Widens the spread of the tick around its mid price.
+    fn widen(&self, t: &mut Tick) {
+        let spread = t.ask.saturating_sub(t.bid) as f64;
+        let extra = (spread * (self.config.spread_factor - 1.)).round();
+        let half = (extra / 2.).round();
+        t.bid = (t.bid as f64 - half).max(0.) as usize;
+        t.ask = (t.ask as f64 + extra - half).max(t.bid as f64) as usize;
+    }
+
+    /// Moves the tick's prices away from the real price.
+    fn bad_print(&mut self, t: &mut Tick) {
+        let mid = (t.bid + t.ask) as f64 / 2.;
+        let offset = (mid * self.config.bad_print_size).round() as usize;
+        if self.rng.gen::<bool>() {
+            t.bid += offset;
+            t.ask += offset;
+        } else {
+            t.bid = t.bid.saturating_sub(offset);
+            t.ask = t.ask.saturating_sub(offset);
+        }
+    }
+}
+
+impl TickMap for FaultMap {
+    fn map(&mut self, t: Tick) -> Option<Tick> {
+        let summary = self.summary.clone();
+        let mut summary = summary.lock().unwrap();
+        summary.ticks_in += 1;
+
+        if self.rng.gen::<f64>() < self.config.drop_probability {
+            summary.dropped += 1;
+            return None;
+        }
+
+        let mut tick = t;
+        if self.config.spread_factor != 1. {
+            self.widen(&mut tick);
+        }
+        if self.rng.gen::<f64>() < self.config.bad_print_probability {
+            self.bad_print(&mut tick);
+            summary.bad_prints += 1;
+        }
+        let latency = self.latency();
+        tick.timestamp += latency;
+        summary.total_latency_ms += latency;
+        summary.ticks_out += 1;
+
+        Some(tick)
+    }
+}
+
+/// Injects all kinds of feed faults into a sequence of ticks.  Each tick first goes through a `FaultMap` and is
+/// then held back along with the `reorder_window` ticks before it so that it can be duplicated and swapped with
+/// them.  The held ticks are all sent once `ticks` runs out.
+pub struct FaultyTicks<I> {
+    ticks: I,
+    map: FaultMap,
+    config: FaultConfig,
+    rng: Isaac64Rng,
+    held: VecDeque<Tick>,
+    summary: Arc<Mutex<FaultSummary>>,
+}
+
+impl<I: Iterator<Item=Tick>> FaultyTicks<I> {
+    pub fn new(ticks: I, config: FaultConfig) -> Result<FaultyTicks<I>, String> {
+        try!(config.validate());
+        let map = try!(FaultMap::new(FaultConfig {
+            duplicate_probability: 0.,
+            reorder_probability: 0.,
+            ..config.clone()
+        }));
+
+        Ok(FaultyTicks {
+            ticks: ticks,
+            summary: map.summary_handle(),
+            map: map,
+            // a different seed than the map's so the two don't draw the same numbers
+            rng: Isaac64Rng::from_seed(&[config.seed, 1][..]),
+            held: VecDeque::with_capacity(config.reorder_window + 2),
+            config: config,
+        })
+    }
+
+    /// Returns a handle to the summary of the faults added so far that can be read while the ticks are in use.
+    /// Ticks are counted as sent once they're held.
+    pub fn summary_handle(&self) -> Arc<Mutex<FaultSummary>> {
+        self.summary.clone()
+    }
+
+    /// Holds the tick, possibly duplicating it and swapping it with one of the held ticks before it.
+    fn hold(&mut self, tick: Tick) {
+        let mut summary = self.summary.lock().unwrap();
+        self.held.push_back(tick);
+        if self.rng.gen::<f64>() < self.config.duplicate_probability {
+            self.held.push_back(tick);
+            summary.duplicated += 1;
+            summary.ticks_out += 1;
+        }
+
+        let last = self.held.len() - 1;
+        let window = self.config.reorder_window.min(last);
+        if window > 0 && self.rng.gen::<f64>() < self.config.reorder_probability {
+            let other = last - self.rng.gen_range(1, window + 1);
+            self.held.swap(last, other);
+            summary.reordered += 1;
+        }
+    }
+}
+
+impl<I: Iterator<Item=Tick>> Iterator for FaultyTicks<I> {
+    type Item = Tick;
+
+    fn next(&mut self) -> Option<Tick> {
+        while self.held.len() <= self.config.reorder_window {
+            let t = match self.ticks.next() {
+                Some(t) => t,
+                None => break,
+            };
+            if let Some(tick) = self.map.map(t) {
+                self.hold(tick);
+            }
+        }
+
+        self.held.pop_front()
+    }
+}
+
+#[test]
+fn fault_map() {
+    let ticks: Vec<Tick> = (0..10_000).map(|i| Tick {timestamp: i * 1000, bid: 10000, ask: 10004}).collect();
+    let run = |config: FaultConfig| -> (Vec<Tick>, FaultSummary) {
+        let faulty = FaultyTicks::new(ticks.iter().cloned(), config).unwrap();
+        let summary = faulty.summary_handle();
+        let out = faulty.collect();
+        let summary = summary.lock().unwrap().clone();
+        (out, summary)
+    };
+    let config = FaultConfig {
+        seed: 7,
+        drop_probability: 0.,
+        duplicate_probability: 0.,
+        reorder_probability: 0.,
+        reorder_window: 0,
+        latency: LatencyModel::None,
+        spread_factor: 1.,
+        bad_print_probability: 0.,
+        bad_print_size: 0.,
+    };
+    assert!(run(config.clone()).0 == ticks);
+
+    let (out, summary) = run(FaultConfig {drop_probability: 0.1, duplicate_probability: 0.05, ..config.clone()});
+    assert!(summary.dropped > 900 && summary.dropped < 1100);
+    assert!(summary.duplicated > 400 && summary.duplicated < 600);
+    assert_eq!(out.len(), summary.ticks_out);
+    assert_eq!(out.len(), ticks.len() - summary.dropped + summary.duplicated);
+    assert!(out.windows(2).filter(|w| w[0] == w[1]).count() > 0);
+    // the same seed gives the same faults
+    assert!(run(FaultConfig {drop_probability: 0.1, duplicate_probability: 0.05, ..config.clone()}).0 == out);
+
+    // duplicates outnumbering dropped ticks don't pile up
+    let (out, summary) = run(FaultConfig {drop_probability: 0.01, duplicate_probability: 0.5, ..config.clone()});
+    assert_eq!(out.len(), ticks.len() - summary.dropped + summary.duplicated);
+
+    // the ticks held for reordering are sent at the end
+    let (out, summary) = run(FaultConfig {reorder_probability: 0.2, reorder_window: 3, ..config.clone()});
+    assert_eq!(out.len(), ticks.len());
+    assert_eq!(out.iter().map(|t| t.timestamp).sum::<u64>(), ticks.iter().map(|t| t.timestamp).sum::<u64>());
+    assert!(summary.reordered > 1800 && summary.reordered < 2200);
+    let out_of_order = out.windows(2).filter(|w| w[1].timestamp < w[0].timestamp).count();
+    assert!(out_of_order > 0);
+
+    let latency = LatencyModel::Uniform{min_ms: 10, max_ms: 20};
+    let (out, summary) = run(FaultConfig {latency: latency, spread_factor: 2.5, ..config.clone()});
+    assert!(out.iter().zip(ticks.iter()).all(|(o, t)| o.timestamp >= t.timestamp + 10 && o.timestamp <= t.timestamp + 20));
+    assert!(out.iter().all(|t| t.bid == 9997 && t.ask == 10007));
+    assert!(summary.total_latency_ms > 140_000 && summary.total_latency_ms < 160_000);
+
+    let (out, summary) = run(FaultConfig {bad_print_probability: 0.01, bad_print_size: 0.01, ..config.clone()});
+    let bad = out.iter().filter(|t| t.bid != 10000).count();
+    assert_eq!(bad, summary.bad_prints);
+    assert!(out.iter().all(|t| t.bid == 10000 || t.bid == 10100 || t.bid == 9900));
+
+    assert!(FaultMap::new(FaultConfig {drop_probability: 1.5, ..config.clone()}).is_err());
+    assert!(FaultyTicks::new(ticks.iter().cloned(), FaultConfig {reorder_probability: 0.5, ..config.clone()}).is_err());
+    assert!(FaultMap::new(FaultConfig {duplicate_probability: 0.5, ..config}).is_err());
+}
@@ -3,8 +3,18 @@
 use super::*;
 
 pub mod poloniex;
+pub mod pipeline;
+pub mod bars;
+pub mod quality;
+pub mod faults;
+pub mod resample;
 
 pub use self::poloniex::*;
+pub use self::pipeline::*;
+pub use self::bars::*;
+pub use self::quality::*;
+pub use self::faults::*;
+pub use self::resample::*;
 
 /// Inserts a static delay between each tick.
 pub struct FastMap {
@@ -19,30 +29,44 @@ impl TickMap for FastMap {
     }
 }
 
-/// Plays ticks back at the rate that they were recorded.
+/// Plays ticks back at the rate that they were recorded multiplied by a speed multiplier that can be changed
+/// while the map is running.
 pub struct LiveMap {
-    pub last_tick_timestamp: u64
+    pub last_tick_timestamp: u64,
+    /// How many times faster than real time ticks are played back
+    pub speed: Arc<Mutex<f64>>,
 }
 
 impl TickMap for LiveMap {
     fn map(&mut self, t: Tick) -> Option<Tick> {
-        if self.last_tick_timestamp == 0 {
-            self.last_tick_timestamp = t.timestamp;
-            return None
+        if self.last_tick_timestamp != 0 && t.timestamp > self.last_tick_timestamp {
+            let diff_ms = (t.timestamp - self.last_tick_timestamp) as f64;
+            let speed = *self.speed.lock().unwrap();
+            let nanos = (diff_ms * 1_000_000. / speed) as u64;
+            thread::sleep(Duration::new(nanos / 1_000_000_000, (nanos % 1_000_000_000) as u32));
         }
 
-        let diff = t.timestamp - self.last_tick_timestamp;
-        thread::sleep(Duration::from_millis(diff as u64));
+        self.last_tick_timestamp = t.timestamp;
         Some(t)
     }
+
+    fn reset(&mut self) {
+        self.last_tick_timestamp = 0;
+    }
 }
 
 impl LiveMap {
     pub fn new() -> LiveMap {
         LiveMap {
-            last_tick_timestamp: 0
+            last_tick_timestamp: 0,
+            speed: Arc::new(Mutex::new(1.)),
         }
     }
+
+    /// Returns a handle that can be used to change the playback speed while the map is in use.
+    pub fn speed_handle(&self) -> Arc<Mutex<f64>> {
+        self.speed.clone()
+    }
 }
 
 pub struct NullMap {}
@@ -0,0 +1,459 @@
+//! Composable indicator pipelines.  A pipeline is a small graph of indicators defined by a serializable
+//! `PipelineDefinition` so that the same pipeline can be run live in the tick parser and in a backtest.
+//!
+//! Each node of the graph has a unique name and takes its inputs from nodes defined before it, which lets
+//! indicators be fed the output of other indicators (RSI of an EMA, z-score of the spread between two symbols,
+//! etc.).  Nodes that combine several inputs use the latest value of each input, so ticks from different symbols
+//! are aligned by timestamp as-of the most recent tick.
+
+use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+
+use serde_json;
+
+use trading::tick::{GenTick, SymbolTick, Tick};
+use trading::indicators::*;
+use transport::command_server::CommandServer;
+use transport::tickstream::generics::{GenTickMap, GenTickSink};
+
+/// The kinds of nodes that can make up a pipeline.  `input`, `a`, and `b` are names of other nodes.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum NodeKind {
+    /// The price of ticks for a symbol; the roots of the graph.
+    Price{symbol: String, field: PriceField},
+    Sma{input: String, period: usize},
+    Ema{input: String, period: usize},
+    Wma{input: String, period: usize},
+    Rsi{input: String, period: usize},
+    StdDev{input: String, period: usize},
+    /// Number of standard deviations the input is from its mean over the last `period` values.
+    ZScore{input: String, period: usize},
+    /// `a - b`
+    Spread{a: String, b: String},
+    /// `a / b`
+    Ratio{a: String, b: String},
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NodeDefinition {
+    pub name: String,
+    pub kind: NodeKind,
+}
+
+/// Serializable description of a pipeline.  Nodes must be listed so that every node comes after the nodes it
+/// takes input from.  `outputs` are the names of the nodes whose values are emitted by the pipeline.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PipelineDefinition {
+    pub nodes: Vec<NodeDefinition>,
+    pub outputs: Vec<String>,
+}
+
+/// How a node computes its value from its inputs.
+enum NodeState {
+    Price{symbol: String, field: PriceField},
+    Indicator{input: usize, indicator: Box<Indicator<Input=f64, Output=f64> + Send>},
+    ZScore{input: usize, stddev: RollingStdDev},
+    Spread{a: usize, b: usize},
+    Ratio{a: usize, b: usize},
+}
+
+struct Node {
+    name: String,
+    state: NodeState,
+    value: Option<f64>,
+    /// set if the node's value changed during the current tick
+    updated: bool,
+}
+
+/// Runs a `PipelineDefinition` over a stream of `SymbolTick`s, emitting the values of the output nodes.
+pub struct Pipeline {
+    nodes: Vec<Node>,
+    outputs: Vec<usize>,
+}
+
+/// Returns the index of the node with the given name, making sure it was defined before node `cur`.
+fn find_node(nodes: &[Node], name: &str, cur: &str) -> Result<usize, String> {
+    nodes.iter().position(|n| n.name == name).ok_or(
+        format!("Node \"{}\" references \"{}\" which isn't defined before it.", cur, name)
+    )
+}
+
+impl Pipeline {
+    /// Creates a new pipeline from a definition, returning an error if the definition is invalid.
+    pub fn from_definition(def: &PipelineDefinition) -> Result<Pipeline, String> {
+        let mut nodes: Vec<Node> = Vec::with_capacity(def.nodes.len());
+
+        for node_def in &def.nodes {
+            if nodes.iter().any(|n| n.name == node_def.name) {
+                return Err(format!("Duplicate node name: \"{}\"", node_def.name));
+            }
+
+            let name = node_def.name.as_str();
+            let period = match node_def.kind {
+                NodeKind::Sma{period, ..} | NodeKind::Ema{period, ..} | NodeKind::Wma{period, ..} |
+                NodeKind::Rsi{period, ..} | NodeKind::StdDev{period, ..} | NodeKind::ZScore{period, ..} => period,
+                _ => 1,
+            };
+            if period == 0 {
+                return Err(format!("The period of node \"{}\" must be greater than zero.", name));
+            }
+
+            let state = match node_def.kind {
+                NodeKind::Price{ref symbol, field} => NodeState::Price{symbol: symbol.clone(), field: field},
+                NodeKind::Sma{ref input, period} => NodeState::Indicator{
+                    input: find_node(&nodes, input, name)?, indicator: Box::new(Sma::new(period)),
+                },
+                NodeKind::Ema{ref input, period} => NodeState::Indicator{
+                    input: find_node(&nodes, input, name)?, indicator: Box::new(Ema::new(period)),
+                },
+                NodeKind::Wma{ref input, period} => NodeState::Indicator{
+                    input: find_node(&nodes, input, name)?, indicator: Box::new(Wma::new(period)),
+                },
+                NodeKind::Rsi{ref input, period} => NodeState::Indicator{
+                    input: find_node(&nodes, input, name)?, indicator: Box::new(Rsi::new(period)),
+                },
+                NodeKind::StdDev{ref input, period} => NodeState::Indicator{
+                    input: find_node(&nodes, input, name)?, indicator: Box::new(RollingStdDev::new(period)),
+                },
+                NodeKind::ZScore{ref input, period} => NodeState::ZScore{
+                    input: find_node(&nodes, input, name)?, stddev: RollingStdDev::new(period),
+                },
+                NodeKind::Spread{ref a, ref b} => NodeState::Spread{
+                    a: find_node(&nodes, a, name)?, b: find_node(&nodes, b, name)?,
+                },
+                NodeKind::Ratio{ref a, ref b} => NodeState::Ratio{
+                    a: find_node(&nodes, a, name)?, b: find_node(&nodes, b, name)?,
+                },
+            };
+
+            nodes.push(Node {
+                name: node_def.name.clone(),
+                state: state,
+                value: None,
+                updated: false,
+            });
+        }
+
+        let mut outputs = Vec::with_capacity(def.outputs.len());
+        for output in &def.outputs {
+            outputs.push(find_node(&nodes, output, "outputs")?);
+        }
+
+        Ok(Pipeline {
+            nodes: nodes,
+            outputs: outputs,
+        })
+    }
+
+    /// Returns the list of symbols that the pipeline takes prices for.
+    pub fn symbols(&self) -> Vec<String> {
+        let mut symbols = Vec::new();
+        for node in &self.nodes {
+            if let NodeState::Price{ref symbol, ..} = node.state {
+                if !symbols.contains(symbol) {
+                    symbols.push(symbol.clone());
+                }
+            }
+        }
+
+        symbols
+    }
+
+    /// Returns the current value of a node, if it has one.
+    pub fn value(&self, name: &str) -> Option<f64> {
+        self.nodes.iter().find(|n| n.name == name).and_then(|n| n.value)
+    }
+
+    /// Pushes a tick through the pipeline.  Returns the values of all output nodes if all of them have values
+    /// and at least one of them changed as a result of the tick.
+    pub fn update(&mut self, st: &SymbolTick) -> Option<Vec<(String, f64)>> {
+        let tick = Tick {bid: st.bid, ask: st.ask, timestamp: st.timestamp};
+        let mut any_updated = false;
+
+        for i in 0..self.nodes.len() {
+            // nodes only take inputs from nodes before them, so they can be split off to be read while the
+            // current node is being updated.
+            let (prev, rest) = self.nodes.split_at_mut(i);
+            let node = &mut rest[0];
+
+            let new_val = match node.state {
+                NodeState::Price{ref symbol, field} => {
+                    if *symbol == st.symbol { Some(field.get(&tick)) } else { None }
+                },
+                NodeState::Indicator{input, ref mut indicator} => {
+                    if prev[input].updated { indicator.update(prev[input].value.unwrap()) } else { None }
+                },
+                NodeState::ZScore{input, ref mut stddev} => {
+                    if prev[input].updated {
+                        let x = prev[input].value.unwrap();
+                        match stddev.update(x) {
+                            Some(sd) if sd != 0.0 => Some((x - stddev.mean()) / sd),
+                            Some(_) => Some(0.0),
+                            None => None,
+                        }
+                    } else { None }
+                },
+                NodeState::Spread{a, b} => combine(&prev[a], &prev[b], |a, b| Some(a - b)),
+                NodeState::Ratio{a, b} => combine(&prev[a], &prev[b], |a, b| {
+                    if b == 0.0 { None } else { Some(a / b) }
+                }),
+            };
+
+            node.updated = new_val.is_some();
+            if new_val.is_some() {
+                node.value = new_val;
+            }
+        }
+
+        for &output in &self.outputs {
+            let node = &self.nodes[output];
+            if node.value.is_none() {
+                return None;
+            }
+            any_updated |= node.updated;
+        }
+
+        if !any_updated {
+            return None;
+        }
+
+        Some(self.outputs.iter().map(|&i| (self.nodes[i].name.clone(), self.nodes[i].value.unwrap())).collect())
+    }
+}
+
+/// Combines the latest values of two nodes if either of them was updated and both have values.
+fn combine<F>(a: &Node, b: &Node, f: F) -> Option<f64> where F: Fn(f64, f64) -> Option<f64> {
+    if !(a.updated || b.updated) {
+        return None;
+    }
+
+    match (a.value, b.value) {
+        (Some(a), Some(b)) => f(a, b),
+        _ => None,
+    }
+}
+
+impl GenTickMap<SymbolTick, Vec<(String, f64)>> for Pipeline {
+    /// Expects a `definition` setting containing a JSON-encoded `PipelineDefinition`.
+    fn new(settings: HashMap<String, String>, _: CommandServer) -> Result<Self, String> {
+        settings.get("definition")
+            .ok_or(String::from("No `definition` setting supplied."))
+            .and_then(|s| serde_json::from_str::<PipelineDefinition>(s).map_err(|err| format!("{:?}", err)))
+            .and_then(|def| Pipeline::from_definition(&def))
+            .map_err(|err| format!("Unable to create pipeline: {}", err))
+    }
+
+    fn map(&mut self, t: GenTick<SymbolTick>) -> Option<GenTick<Vec<(String, f64)>>> {
+        self.update(&t.data).map(|vals| GenTick {
+            timestamp: t.timestamp,
+            data: vals,
+        })
+    }
+}
+
+/// Merges ticks from several symbols that may arrive out of order relative to each other into a single stream
+/// ordered by timestamp.  A tick is released once every symbol has produced a tick at or after its timestamp.
+pub struct SymbolAligner {
+    symbols: Vec<String>,
+    /// timestamp of the latest tick seen for each symbol
+    watermarks: HashMap<String, u64>,
+    /// ticks waiting to be released, keyed by timestamp and arrival order
+    buffer: BTreeMap<(u64, usize), SymbolTick>,
+    seq: usize,
+}
+
+impl SymbolAligner {
+    pub fn new(symbols: Vec<String>) -> SymbolAligner {
+        SymbolAligner {
+            symbols: symbols,
+            watermarks: HashMap::new(),
+            buffer: BTreeMap::new(),
+            seq: 0,
+        }
+    }
+
+    /// Buffers the tick and returns all ticks that can now be released, in timestamp order.
+    pub fn push(&mut self, t: SymbolTick) -> Vec<SymbolTick> {
+        self.watermarks.insert(t.symbol.clone(), t.timestamp);
+        self.buffer.insert((t.timestamp, self.seq), t);
+        self.seq += 1;
+
+        let mut low_watermark = ::std::u64::MAX;
+        for symbol in &self.symbols {
+            match self.watermarks.get(symbol) {
+                Some(&ts) => low_watermark = ::std::cmp::min(low_watermark, ts),
+                // nothing can be released until every symbol has been seen
+                None => return Vec::new(),
+            }
+        }
+
+        let remaining = self.buffer.split_off(&(low_watermark + 1, 0));
+        let released = ::std::mem::replace(&mut self.buffer, remaining);
+        released.into_iter().map(|(_, t)| t).collect()
+    }
+
+    /// Returns all buffered ticks in timestamp order; used once all sources have finished.
+    pub fn flush(&mut self) -> Vec<SymbolTick> {
+        let released = ::std::mem::replace(&mut self.buffer, BTreeMap::new());
+        released.into_iter().map(|(_, t)| t).collect()
+    }
+}
+
+/// Sends each tick it receives to every one of its child sinks.
+pub struct FanOutSink<T> {
+    sinks: Vec<Box<GenTickSink<T> + Send>>,
+}
+
+impl<T> FanOutSink<T> {
+    pub fn add_sink(&mut self, sink: Box<GenTickSink<T> + Send>) {
+        self.sinks.push(sink);
+    }
+}
+
+impl<T> GenTickSink<T> for FanOutSink<T> where T:Clone {
+    /// Creates an empty `FanOutSink`; child sinks are added with `add_sink()`.
+    fn new(_: HashMap<String, String>) -> Result<Self, String> {
+        Ok(FanOutSink {sinks: Vec::new()})
+    }
+
+    fn tick(&mut self, t: GenTick<T>) {
+        for sink in self.sinks.iter_mut() {
+            sink.tick(t.clone());
+        }
+    }
+}
+
+/// Feeds the output of one `GenTickMap` into another.
+pub struct Chain<A, B, M> {
+    pub first: A,
+    pub second: B,
+    _m: PhantomData<M>,
+}
+
+impl<A, B, M> Chain<A, B, M> {
+    pub fn from_maps(first: A, second: B) -> Chain<A, B, M> {
+        Chain {
+            first: first,
+            second: second,
+            _m: PhantomData,
+        }
+    }
+}
+
+impl<T, M, O, A, B> GenTickMap<T, O> for Chain<A, B, M> where A: GenTickMap<T, M>, B: GenTickMap<M, O> {
+    /// Creates both maps with the same settings.
+    fn new(settings: HashMap<String, String>, cs: CommandServer) -> Result<Self, String> {
+        Ok(Chain::from_maps(A::new(settings.clone(), cs.clone())?, B::new(settings, cs)?))
+    }
+
+    fn map(&mut self, t: GenTick<T>) -> Option<GenTick<O>> {
+        match self.first.map(t) {
+            Some(t) => self.second.map(t),
+            None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+fn stick(symbol: &str, bid: usize, timestamp: u64) -> SymbolTick {
+    SymbolTick {bid: bid, ask: bid, timestamp: timestamp, symbol: String::from(symbol)}
+}
+
+#[test]
+fn pipeline_rsi_of_ema() {
+    let def_json = r#"{
+        "nodes": [
+            {"name": "price", "kind": {"Price": {"symbol": "EURUSD", "field": "Mid"}}},
+            {"name": "ema", "kind": {"Ema": {"input": "price", "period": 5}}},
+            {"name": "rsi", "kind": {"Rsi": {"input": "ema", "period": 3}}}
+        ],
+        "outputs": ["rsi"]
+    }"#;
+    let def: PipelineDefinition = serde_json::from_str(def_json).unwrap();
+    let mut pipeline = Pipeline::from_definition(&def).unwrap();
+
+    let prices = [100, 102, 101, 105, 107, 104, 108, 110, 109, 113, 115, 111];
+    let mut ema = Ema::new(5);
+    let mut rsi = Rsi::new(3);
+    for (i, &p) in prices.iter().enumerate() {
+        let expected = ema.update(p as f64).and_then(|e| rsi.update(e));
+        let res = pipeline.update(&stick("EURUSD", p, i as u64));
+        assert_eq!(res.map(|v| v[0].1), expected);
+    }
+    assert!(pipeline.value("rsi").is_some());
+
+    // ticks for other symbols don't change anything
+    assert_eq!(pipeline.update(&stick("GBPUSD", 1, 100)), None);
+}
+
+#[test]
+fn pipeline_spread_zscore() {
+    let def = PipelineDefinition {
+        nodes: vec![
+            NodeDefinition {name: String::from("a"), kind: NodeKind::Price{symbol: String::from("A"), field: PriceField::Bid}},
+            NodeDefinition {name: String::from("b"), kind: NodeKind::Price{symbol: String::from("B"), field: PriceField::Bid}},
+            NodeDefinition {name: String::from("spread"), kind: NodeKind::Spread{a: String::from("a"), b: String::from("b")}},
+            NodeDefinition {name: String::from("z"), kind: NodeKind::ZScore{input: String::from("spread"), period: 3}},
+        ],
+        outputs: vec![String::from("spread"), String::from("z")],
+    };
+    let mut pipeline = Pipeline::from_definition(&def).unwrap();
+    assert_eq!(pipeline.symbols(), vec![String::from("A"), String::from("B")]);
+
+    // no spread until both symbols have a price
+    assert_eq!(pipeline.update(&stick("A", 10, 1)), None);
+    assert_eq!(pipeline.value("spread"), None);
+    pipeline.update(&stick("B", 4, 2));
+    assert_eq!(pipeline.value("spread"), Some(6.0));
+    pipeline.update(&stick("A", 12, 3));
+    let res = pipeline.update(&stick("B", 3, 4)).unwrap();
+
+    // spreads are 6, 8, 9
+    let mean = 23.0 / 3.0;
+    let sd = ((6.0f64 - mean).powi(2) + (8.0f64 - mean).powi(2) + (9.0f64 - mean).powi(2)) / 3.0;
+    assert_eq!(res[0], (String::from("spread"), 9.0));
+    assert_close(res[1].1, (9.0 - mean) / sd.sqrt());
+}
+
+#[test]
+fn pipeline_invalid_definitions() {
+    let def = PipelineDefinition {
+        nodes: vec![
+            NodeDefinition {name: String::from("sma"), kind: NodeKind::Sma{input: String::from("price"), period: 3}},
+            NodeDefinition {name: String::from("price"), kind: NodeKind::Price{symbol: String::from("A"), field: PriceField::Bid}},
+        ],
+        outputs: vec![String::from("sma")],
+    };
+    assert!(Pipeline::from_definition(&def).is_err());
+
+    let def = PipelineDefinition {
+        nodes: vec![
+            NodeDefinition {name: String::from("price"), kind: NodeKind::Price{symbol: String::from("A"), field: PriceField::Bid}},
+        ],
+        outputs: vec![String::from("missing")],
+    };
+    assert!(Pipeline::from_definition(&def).is_err());
+
+    let def = PipelineDefinition {
+        nodes: vec![
+            NodeDefinition {name: String::from("price"), kind: NodeKind::Price{symbol: String::from("A"), field: PriceField::Bid}},
+            NodeDefinition {name: String::from("ema"), kind: NodeKind::Ema{input: String::from("price"), period: 0}},
+        ],
+        outputs: vec![String::from("ema")],
+    };
+    assert!(Pipeline::from_definition(&def).is_err());
+}
+
+#[test]
+fn symbol_aligner_orders_ticks() {
+    let mut aligner = SymbolAligner::new(vec![String::from("A"), String::from("B")]);
+    assert!(aligner.push(stick("A", 1, 1)).is_empty());
+    assert!(aligner.push(stick("A", 1, 5)).is_empty());
+    let released: Vec<u64> = aligner.push(stick("B", 1, 3)).iter().map(|t| t.timestamp).collect();
+    assert_eq!(released, vec![1, 3]);
+    let released: Vec<u64> = aligner.push(stick("B", 1, 7)).iter().map(|t| t.timestamp).collect();
+    assert_eq!(released, vec![5]);
+    let flushed: Vec<u64> = aligner.flush().iter().map(|t| t.timestamp).collect();
+    assert_eq!(flushed, vec![7]);
+}
@@ -46,10 +46,10 @@ fn parse_book_modification_hashmap(hm: &HashMap<String, String>) -> Result<Polni
 }
 
 impl GenTickMap<String, PolniexOrderBookModification> for PoloniexBookModifyMap {
-    fn new(_: HashMap<String, String>, cs: CommandServer) -> Self {
-        PoloniexBookModifyMap {
+    fn new(_: HashMap<String, String>, cs: CommandServer) -> Result<Self, String> {
+        Ok(PoloniexBookModifyMap {
             cs: cs,
-        }
+        })
     }
 
     fn map(&mut self, tick: GenTick<String>) -> Option<GenTick<PolniexOrderBookModification>> {
@@ -102,10 +102,10 @@ fn parse_book_removal_hashmap(hm: &HashMap<String, String>) -> Result<PoloniexOr
 }
 
 impl GenTickMap<String, PoloniexOrderBookRemoval> for PoloniexBookRemovalMap {
-    fn new(_: HashMap<String, String>, cs: CommandServer) -> Self {
-        PoloniexBookRemovalMap {
+    fn new(_: HashMap<String, String>, cs: CommandServer) -> Result<Self, String> {
+        Ok(PoloniexBookRemovalMap {
             cs: cs,
-        }
+        })
     }
 
     fn map(&mut self, tick: GenTick<String>) -> Option<GenTick<PoloniexOrderBookRemoval>> {
@@ -154,10 +154,10 @@ pub struct PoloniexTrade {
 }
 
 impl GenTickMap<String, PoloniexTrade> for PoloniexTradeMap {
-    fn new(_: HashMap<String, String>, cs: CommandServer) -> Self {
-        PoloniexTradeMap {
+    fn new(_: HashMap<String, String>, cs: CommandServer) -> Result<Self, String> {
+        Ok(PoloniexTradeMap {
             cs: cs,
-        }
+        })
     }
 
     fn map(&mut self, tick: GenTick<String>) -> Option<GenTick<PoloniexTrade>> {
@@ -220,7 +220,7 @@ fn poloniex_trade_map() {
         is_buy: false,
     };
 
-    let mut map = PoloniexTradeMap::new(HashMap::new(), CommandServer::new(Uuid::new_v4(), "`PoloniexTradeMap` Test"));
+    let mut map = PoloniexTradeMap::new(HashMap::new(), CommandServer::new(Uuid::new_v4(), "`PoloniexTradeMap` Test")).unwrap();
     let parsed_tick = map.map(GenTick { timestamp: 1, data: raw}).unwrap();
     assert_eq!(real, parsed_tick.data);
 }
@@ -236,7 +236,7 @@ fn poloniex_book_removal() {
         is_bid: false,
     };
 
-    let mut map = PoloniexBookRemovalMap::new(HashMap::new(), CommandServer::new(Uuid::new_v4(), "`PoloniexBookRemovalMap` Test"));
+    let mut map = PoloniexBookRemovalMap::new(HashMap::new(), CommandServer::new(Uuid::new_v4(), "`PoloniexBookRemovalMap` Test")).unwrap();
     let parsed_tick = map.map(GenTick { timestamp: 1, data: raw}).unwrap();
     assert_eq!(real, parsed_tick.data);
 }
@@ -253,7 +253,7 @@ fn poloniex_book_modification() {
         amount: 3.32349029f32,
     };
 
-    let mut map = PoloniexBookModifyMap::new(HashMap::new(), CommandServer::new(Uuid::new_v4(), "`PoloniexBookModifyMap` Test"));
+    let mut map = PoloniexBookModifyMap::new(HashMap::new(), CommandServer::new(Uuid::new_v4(), "`PoloniexBookModifyMap` Test")).unwrap();
     let parsed_tick = map.map(GenTick { timestamp: 1, data: raw}).unwrap();
     assert_eq!(real, parsed_tick.data);
 }
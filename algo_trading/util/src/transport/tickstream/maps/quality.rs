@@ -0,0 +1,674 @@
+//! Checks historical ticks for data quality problems and cleans them up before they're used in a backtest.
+//!
+//! `check_source` scans a tickstream and produces a `QualityReport` listing out-of-order timestamps, duplicate
+//! ticks, crossed quotes, zero prices, stale prices, price spikes, and gaps.  `CleaningMap` is a `TickMap` that
+//! finds the same problems while a tickstream runs and drops or repairs the affected ticks, keeping a
+//! `CleaningSummary` of everything it changed.  Timestamps are assumed to be in milliseconds.
+
+use std::fmt;
+
+use futures::Stream;
+
+use trading::indicators::{Indicator, RollingStdDev};
+
+use super::*;
+
+const HOUR_MS: u64 = 60 * 60 * 1000;
+const DAY_MS: u64 = 24 * HOUR_MS;
+const WEEK_MS: u64 = 7 * DAY_MS;
+/// The Unix epoch was on a Thursday, so this many milliseconds are added to timestamps to count weeks from Monday.
+const EPOCH_WEEK_OFFSET: u64 = 3 * DAY_MS;
+
+fn default_spike_rebaseline() -> usize { 5 }
+
+/// A period of each week during which the market is closed and gaps in the data are expected.  Times are counted
+/// in milliseconds since Monday 00:00 UTC.  If `end` is before `start`, the break wraps around into the next week.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct SessionBreak {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl SessionBreak {
+    /// The weekend break of the FX market, from Friday 21:00 to Sunday 23:00 UTC.  This includes an hour on each
+    /// side to cover the shift of the open and close with daylight saving time in New York.
+    pub fn fx_weekend() -> SessionBreak {
+        SessionBreak {
+            start: 4 * DAY_MS + 21 * HOUR_MS,
+            end: 6 * DAY_MS + 23 * HOUR_MS,
+        }
+    }
+
+    /// Returns how many milliseconds of the period from `from` to `to` fall within the break.
+    fn overlap(&self, from: u64, to: u64) -> u64 {
+        let len = if self.end >= self.start { self.end - self.start } else { self.end + WEEK_MS - self.start };
+        // shift the period so that weeks start at multiples of `WEEK_MS`
+        let (from, to) = (from + EPOCH_WEEK_OFFSET, to + EPOCH_WEEK_OFFSET);
+        let mut overlap = 0;
+        // start one week early to include breaks that wrap around from the week before
+        let mut week_start = (from / WEEK_MS * WEEK_MS).saturating_sub(WEEK_MS);
+        while week_start < to {
+            let (break_start, break_end) = (week_start + self.start, week_start + self.start + len);
+            if break_start < to && break_end > from {
+                overlap += break_end.min(to) - break_start.max(from);
+            }
+            week_start += WEEK_MS;
+        }
+
+        overlap
+    }
+}
+
+/// Settings for finding data quality problems
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QualityConfig {
+    /// Ticks whose mid price changes by more than this many standard deviations of recent changes are spikes
+    pub spike_sigmas: f64,
+    /// Number of recent mid price changes that the standard deviation for finding spikes is computed over
+    pub spike_window: usize,
+    /// After this many spikes in a row the price is taken to have moved to a new level, so ticks are compared
+    /// against the last spike instead of the last accepted tick until one is accepted.
+    #[serde(default = "default_spike_rebaseline")]
+    pub spike_rebaseline: usize,
+    /// Prices that don't change for longer than this many milliseconds are stale
+    pub max_stale_ms: u64,
+    /// Gaps between ticks longer than this many milliseconds are reported, not counting time spent in session breaks
+    pub max_gap_ms: u64,
+    pub session_breaks: Vec<SessionBreak>,
+}
+
+impl Default for QualityConfig {
+    fn default() -> QualityConfig {
+        QualityConfig {
+            spike_sigmas: 10.,
+            spike_window: 500,
+            spike_rebaseline: default_spike_rebaseline(),
+            max_stale_ms: HOUR_MS,
+            max_gap_ms: 5 * 60 * 1000,
+            session_breaks: vec![SessionBreak::fx_weekend()],
+        }
+    }
+}
+
+/// The kinds of data quality problems that can be found
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum IssueKind {
+    /// The tick's timestamp is before that of the tick before it
+    OutOfOrder,
+    /// The tick is identical to the tick before it
+    Duplicate,
+    /// The bid is greater than the ask
+    CrossedQuote,
+    /// The bid or ask is zero
+    ZeroPrice,
+    /// The bid and ask haven't changed for longer than `max_stale_ms`
+    Stale,
+    /// The mid price moved by more than `spike_sigmas` standard deviations
+    Spike,
+    /// No ticks were received for longer than `max_gap_ms` outside of session breaks
+    Gap,
+}
+
+/// A data quality problem found at a tick
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QualityIssue {
+    pub kind: IssueKind,
+    /// Index of the tick in the tickstream
+    pub index: usize,
+    pub tick: Tick,
+    pub detail: String,
+}
+
+/// Number of ticks affected by each kind of problem
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct IssueCounts {
+    pub out_of_order: usize,
+    pub duplicate: usize,
+    pub crossed_quote: usize,
+    pub zero_price: usize,
+    pub stale: usize,
+    pub spike: usize,
+    pub gap: usize,
+}
+
+impl IssueCounts {
+    pub fn add(&mut self, kind: IssueKind) {
+        *self.get_mut(kind) += 1;
+    }
+
+    pub fn get(&self, kind: IssueKind) -> usize {
+        match kind {
+            IssueKind::OutOfOrder => self.out_of_order,
+            IssueKind::Duplicate => self.duplicate,
+            IssueKind::CrossedQuote => self.crossed_quote,
+            IssueKind::ZeroPrice => self.zero_price,
+            IssueKind::Stale => self.stale,
+            IssueKind::Spike => self.spike,
+            IssueKind::Gap => self.gap,
+        }
+    }
+
+    fn get_mut(&mut self, kind: IssueKind) -> &mut usize {
+        match kind {
+            IssueKind::OutOfOrder => &mut self.out_of_order,
+            IssueKind::Duplicate => &mut self.duplicate,
+            IssueKind::CrossedQuote => &mut self.crossed_quote,
+            IssueKind::ZeroPrice => &mut self.zero_price,
+            IssueKind::Stale => &mut self.stale,
+            IssueKind::Spike => &mut self.spike,
+            IssueKind::Gap => &mut self.gap,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.out_of_order + self.duplicate + self.crossed_quote + self.zero_price + self.stale + self.spike + self.gap
+    }
+}
+
+impl fmt::Display for IssueCounts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f, "out of order: {}, duplicates: {}, crossed quotes: {}, zero prices: {}, stale: {}, spikes: {}, gaps: {}",
+            self.out_of_order, self.duplicate, self.crossed_quote, self.zero_price, self.stale, self.spike, self.gap
+        )
+    }
+}
+
+/// Finds data quality problems in a stream of ticks.  Each tick is first checked with `check` and then passed to
+/// `accept` if it's kept, so that later ticks are compared against the ticks that are actually used, or to `reject`
+/// if it isn't.
+pub struct QualityChecker {
+    config: QualityConfig,
+    last: Option<Tick>,
+    /// Timestamp at which the current bid and ask were first seen
+    unchanged_since: u64,
+    /// Set once the current run of unchanged prices has been reported as stale
+    stale_reported: bool,
+    returns: RollingStdDev,
+    /// Standard deviation of recent mid price changes
+    sigma: Option<f64>,
+    /// Number of ticks in a row that have been rejected as spikes
+    spike_run: usize,
+    /// The last tick that was rejected as a spike
+    last_spike: Option<Tick>,
+}
+
+impl QualityChecker {
+    pub fn new(config: QualityConfig) -> QualityChecker {
+        QualityChecker {
+            returns: RollingStdDev::new(config.spike_window.max(2)),
+            config: config,
+            last: None,
+            unchanged_since: 0,
+            stale_reported: false,
+            sigma: None,
+            spike_run: 0,
+            last_spike: None,
+        }
+    }
+
+    /// Returns the problems with the tick along with a description of each one.
+    pub fn check(&self, t: &Tick) -> Vec<(IssueKind, String)> {
+        let mut issues = Vec::new();
+        if t.bid == 0 || t.ask == 0 {
+            issues.push((IssueKind::ZeroPrice, format!("bid: {}, ask: {}", t.bid, t.ask)));
+        } else if t.bid > t.ask {
+            issues.push((IssueKind::CrossedQuote, format!("bid {} is above ask {}", t.bid, t.ask)));
+        }
+
+        let last = match self.last {
+            Some(last) => last,
+            None => return issues,
+        };
+        if t.timestamp < last.timestamp {
+            issues.push((IssueKind::OutOfOrder, format!("{} comes after {}", t.timestamp, last.timestamp)));
+            return issues;
+        }
+        if *t == last {
+            issues.push((IssueKind::Duplicate, String::new()));
+            return issues;
+        }
+
+        let gap = self.gap(last.timestamp, t.timestamp);
+        if gap > self.config.max_gap_ms {
+            issues.push((IssueKind::Gap, format!("{} ms without ticks since {}", gap, last.timestamp)));
+        }
+        let unchanged = t.bid == last.bid && t.ask == last.ask;
+        if unchanged && !self.stale_reported && t.timestamp - self.unchanged_since > self.config.max_stale_ms {
+            issues.push((IssueKind::Stale, format!("prices unchanged since {}", self.unchanged_since)));
+        }
+        // the mid price of a quote with a zero price is meaningless
+        let zero_price = t.bid == 0 || t.ask == 0;
+        let base = self.spike_base().unwrap_or(last);
+        if let (Some(sigma), Some(change), false) = (self.sigma, mid_change(&base, t), zero_price) {
+            if sigma > 0. && (change - self.returns.mean()).abs() > self.config.spike_sigmas * sigma {
+                issues.push((IssueKind::Spike, format!("mid price changed by {:.6} with a standard deviation of {:.6}", change, sigma)));
+            }
+        }
+
+        issues
+    }
+
+    /// Records a tick as having been kept in the tickstream.
+    pub fn accept(&mut self, t: &Tick) {
+        if let Some(last) = self.last {
+            if t.bid == last.bid && t.ask == last.ask {
+                if t.timestamp - self.unchanged_since > self.config.max_stale_ms {
+                    self.stale_reported = true;
+                }
+            } else {
+                self.unchanged_since = t.timestamp;
+                self.stale_reported = false;
+            }
+            let base = self.spike_base().unwrap_or(last);
+            if let Some(change) = mid_change(&base, t) {
+                if let Some(sigma) = self.returns.update(change) {
+                    self.sigma = Some(sigma);
+                }
+            }
+        } else {
+            self.unchanged_since = t.timestamp;
+        }
+
+        self.last = Some(*t);
+        self.spike_run = 0;
+        self.last_spike = None;
+    }
+
+    /// Records a tick as having been dropped or otherwise not used to judge later ticks.  Consecutive spikes are
+    /// counted so that a lasting move to a new price level stops being reported once `spike_rebaseline` is reached.
+    pub fn reject(&mut self, t: &Tick) {
+        if self.check(t).iter().any(|&(kind, _)| kind == IssueKind::Spike) {
+            self.spike_run += 1;
+            self.last_spike = Some(*t);
+        }
+    }
+
+    /// Records a repaired tick as having been kept in the tickstream.  Repaired spikes still count towards
+    /// `spike_rebaseline` so that a move to a new level isn't repaired away forever.
+    pub fn accept_repaired(&mut self, original: &Tick, repaired: &Tick) {
+        let spike = self.check(original).iter().any(|&(kind, _)| kind == IssueKind::Spike);
+        let spike_run = self.spike_run;
+        self.accept(repaired);
+        if spike {
+            self.spike_run = spike_run + 1;
+            self.last_spike = Some(*original);
+        }
+    }
+
+    /// Returns the tick that price changes are measured from if it isn't the last accepted tick.
+    fn spike_base(&self) -> Option<Tick> {
+        if self.spike_run >= self.config.spike_rebaseline.max(1) { self.last_spike } else { None }
+    }
+
+    /// Returns the last tick that was accepted.
+    pub fn last(&self) -> Option<Tick> {
+        self.last
+    }
+
+    /// Returns the length of the gap between two timestamps that doesn't fall within a session break.
+    fn gap(&self, from: u64, to: u64) -> u64 {
+        let in_breaks: u64 = self.config.session_breaks.iter().map(|b| b.overlap(from, to)).sum();
+        (to - from).saturating_sub(in_breaks)
+    }
+
+    pub fn reset(&mut self) {
+        *self = QualityChecker::new(self.config.clone());
+    }
+}
+
+/// Returns the relative change in mid price between two ticks.
+fn mid_change(prev: &Tick, t: &Tick) -> Option<f64> {
+    let (prev_mid, mid) = ((prev.bid + prev.ask) as f64 / 2., (t.bid + t.ask) as f64 / 2.);
+    if prev_mid == 0. || mid == 0. {
+        return None;
+    }
+
+    Some(mid / prev_mid - 1.)
+}
+
+/// Returns true if ticks with the problem shouldn't be used to judge later ticks.
+fn is_bad(kind: IssueKind) -> bool {
+    match kind {
+        IssueKind::Stale | IssueKind::Gap => false,
+        _ => true,
+    }
+}
+
+/// The results of checking a tickstream for data quality problems
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QualityReport {
+    pub tick_count: usize,
+    pub first_timestamp: Option<u64>,
+    pub last_timestamp: Option<u64>,
+    pub counts: IssueCounts,
+    /// The first `max_issues` problems that were found
+    pub issues: Vec<QualityIssue>,
+}
+
+impl QualityReport {
+    /// Checks a sequence of ticks, keeping details about up to `max_issues` problems.
+    pub fn from_ticks<I: IntoIterator<Item=Tick>>(ticks: I, config: QualityConfig, max_issues: usize) -> QualityReport {
+        let mut checker = QualityChecker::new(config);
+        let mut report = QualityReport {
+            tick_count: 0,
+            first_timestamp: None,
+            last_timestamp: None,
+            counts: IssueCounts::default(),
+            issues: Vec::new(),
+        };
+
+        for (i, t) in ticks.into_iter().enumerate() {
+            report.tick_count += 1;
+            report.first_timestamp = Some(report.first_timestamp.map(|ts| ts.min(t.timestamp)).unwrap_or(t.timestamp));
+            report.last_timestamp = Some(report.last_timestamp.map(|ts| ts.max(t.timestamp)).unwrap_or(t.timestamp));
+
+            let issues = checker.check(&t);
+            let bad = issues.iter().any(|&(kind, _)| is_bad(kind));
+            for (kind, detail) in issues {
+                report.counts.add(kind);
+                if report.issues.len() < max_issues {
+                    report.issues.push(QualityIssue {kind: kind, index: i, tick: t, detail: detail});
+                }
+            }
+            if bad {
+                checker.reject(&t);
+            } else {
+                checker.accept(&t);
+            }
+        }
+
+        report
+    }
+
+    /// Returns true if no problems were found.
+    pub fn is_clean(&self) -> bool {
+        self.counts.total() == 0
+    }
+}
+
+impl fmt::Display for QualityReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(writeln!(
+            f, "{} ticks from {} to {}", self.tick_count,
+            self.first_timestamp.map(|ts| ts.to_string()).unwrap_or(String::from("-")),
+            self.last_timestamp.map(|ts| ts.to_string()).unwrap_or(String::from("-"))
+        ));
+        try!(writeln!(f, "{}", self.counts));
+        for issue in &self.issues {
+            try!(writeln!(f, "  #{} at {}: {:?} {}", issue.index, issue.tick.timestamp, issue.kind, issue.detail));
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads all ticks out of the source and checks them for data quality problems.  Sources that never end, such as
+/// `RandomReader` and `RedisReader`, can't be checked.
+pub fn check_source(source: &TickGenerators, config: QualityConfig, max_issues: usize) -> Result<QualityReport, String> {
+    if !source.is_finite() {
+        return Err(String::from("Only sources that end can be checked."));
+    }
+
+    let stream = try!(source.get().get_raw());
+    let ticks = stream.wait().take_while(|t| t.is_ok()).map(|t| t.unwrap());
+    Ok(QualityReport::from_ticks(ticks, config, max_issues))
+}
+
+/// What `CleaningMap` does with ticks that have a problem
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum CleaningAction {
+    /// Pass the tick through unchanged
+    Keep,
+    Drop,
+    /// Fix the tick.  Out-of-order ticks are moved to the timestamp of the tick before them, crossed quotes have
+    /// their bid and ask swapped, and zero prices and spikes are replaced with the prices of the tick before
+    /// them.  Duplicate and stale ticks can't be repaired and are dropped instead.
+    Repair,
+}
+
+/// The action taken for each kind of problem.  Gaps can't be dropped or repaired, so they're only counted.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CleaningActions {
+    pub out_of_order: CleaningAction,
+    pub duplicate: CleaningAction,
+    pub crossed_quote: CleaningAction,
+    pub zero_price: CleaningAction,
+    pub stale: CleaningAction,
+    pub spike: CleaningAction,
+}
+
+impl Default for CleaningActions {
+    fn default() -> CleaningActions {
+        CleaningActions {
+            out_of_order: CleaningAction::Drop,
+            duplicate: CleaningAction::Drop,
+            crossed_quote: CleaningAction::Repair,
+            zero_price: CleaningAction::Drop,
+            stale: CleaningAction::Keep,
+            spike: CleaningAction::Drop,
+        }
+    }
+}
+
+impl CleaningActions {
+    fn get(&self, kind: IssueKind) -> CleaningAction {
+        match kind {
+            IssueKind::OutOfOrder => self.out_of_order,
+            IssueKind::Duplicate => self.duplicate,
+            IssueKind::CrossedQuote => self.crossed_quote,
+            IssueKind::ZeroPrice => self.zero_price,
+            IssueKind::Stale => self.stale,
+            IssueKind::Spike => self.spike,
+            IssueKind::Gap => CleaningAction::Keep,
+        }
+    }
+}
+
+/// What a `CleaningMap` has done to the ticks that passed through it
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct CleaningSummary {
+    pub ticks_in: usize,
+    pub ticks_out: usize,
+    /// Problems found in ticks that were dropped
+    pub dropped: IssueCounts,
+    /// Problems found in ticks that were repaired
+    pub repaired: IssueCounts,
+    /// Problems found in ticks that were passed through unchanged
+    pub kept: IssueCounts,
+}
+
+impl fmt::Display for CleaningSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(writeln!(f, "{} ticks in, {} ticks out", self.ticks_in, self.ticks_out));
+        try!(writeln!(f, "dropped: {}", self.dropped));
+        try!(writeln!(f, "repaired: {}", self.repaired));
+        writeln!(f, "kept: {}", self.kept)
+    }
+}
+
+/// Drops or repairs ticks with data quality problems.
+pub struct CleaningMap {
+    pub actions: CleaningActions,
+    checker: QualityChecker,
+    summary: Arc<Mutex<CleaningSummary>>,
+}
+
+impl CleaningMap {
+    pub fn new(config: QualityConfig, actions: CleaningActions) -> CleaningMap {
+        CleaningMap {
+            actions: actions,
+            checker: QualityChecker::new(config),
+            summary: Arc::new(Mutex::new(CleaningSummary::default())),
+        }
+    }
+
+    /// Returns a handle to the summary of the changes made by the map that can be read while the map is in use.
+    pub fn summary_handle(&self) -> Arc<Mutex<CleaningSummary>> {
+        self.summary.clone()
+    }
+
+    /// Applies a repair for the problem to the tick.  Returns false if the problem can't be repaired.
+    fn repair(&self, kind: IssueKind, t: &mut Tick) -> bool {
+        let last = self.checker.last();
+        match (kind, last) {
+            (IssueKind::OutOfOrder, Some(last)) => t.timestamp = last.timestamp,
+            (IssueKind::CrossedQuote, _) => {
+                let bid = t.bid;
+                t.bid = t.ask;
+                t.ask = bid;
+            },
+            (IssueKind::ZeroPrice, Some(last)) | (IssueKind::Spike, Some(last)) => {
+                t.bid = last.bid;
+                t.ask = last.ask;
+            },
+            _ => return false,
+        }
+
+        true
+    }
+}
+
+impl TickMap for CleaningMap {
+    fn map(&mut self, t: Tick) -> Option<Tick> {
+        let mut summary = self.summary.lock().unwrap();
+        summary.ticks_in += 1;
+
+        let mut tick = t;
+        let issues = self.checker.check(&t);
+        // problems whose actions are taken, which are all of them unless the tick is dropped
+        let mut handled = Vec::with_capacity(issues.len());
+        for (kind, _) in issues {
+            let action = match self.actions.get(kind) {
+                CleaningAction::Repair if self.repair(kind, &mut tick) => CleaningAction::Repair,
+                CleaningAction::Repair => CleaningAction::Drop,
+                action => action,
+            };
+            if action == CleaningAction::Drop {
+                summary.dropped.add(kind);
+                self.checker.reject(&t);
+                return None;
+            }
+            handled.push((kind, action));
+        }
+
+        for (kind, action) in handled {
+            match action {
+                CleaningAction::Repair => summary.repaired.add(kind),
+                _ => summary.kept.add(kind),
+            }
+        }
+        // kept ticks that are still bad, such as crossed quotes, aren't used to judge the ticks after them
+        let still_bad = self.checker.check(&tick).iter().any(|&(kind, _)| is_bad(kind) && kind != IssueKind::Duplicate);
+        if still_bad {
+            self.checker.reject(&tick);
+        } else if tick != t {
+            self.checker.accept_repaired(&t, &tick);
+        } else {
+            self.checker.accept(&tick);
+        }
+        summary.ticks_out += 1;
+
+        Some(tick)
+    }
+
+    fn reset(&mut self) {
+        self.checker.reset();
+    }
+}
+
+#[cfg(test)]
+fn quality_test_ticks() -> Vec<Tick> {
+    let tick = |timestamp, bid, ask| Tick {timestamp: timestamp, bid: bid, ask: ask};
+    let mut ticks: Vec<Tick> = (0..100).map(|i| tick(i as u64 * 1000, 10000 + i % 3, 10002 + i % 3)).collect();
+    ticks.push(tick(98_500, 10001, 10003)); // out of order
+    ticks.push(tick(100_000, 10001, 10003));
+    ticks.push(tick(100_000, 10001, 10003)); // duplicate
+    ticks.push(tick(101_000, 10005, 10003)); // crossed
+    ticks.push(tick(102_000, 0, 10003)); // zero
+    ticks.push(tick(103_000, 12000, 12002)); // spike
+    ticks.push(tick(104_000, 10000, 10002));
+    ticks.push(tick(104_000 + 2 * HOUR_MS, 10000, 10002)); // stale and a gap
+    ticks
+}
+
+#[test]
+fn quality_report() {
+    // breaks count from Monday so the gap in the middle of the break is excused while the rest isn't
+    let weekend = SessionBreak::fx_weekend();
+    let friday = 1 * DAY_MS; // 1970-01-02
+    assert_eq!(weekend.overlap(friday, friday + DAY_MS), 3 * HOUR_MS);
+    assert_eq!(weekend.overlap(friday + 22 * HOUR_MS, friday + 2 * DAY_MS + 22 * HOUR_MS), 2 * DAY_MS);
+    let wrapping = SessionBreak {start: 6 * DAY_MS, end: DAY_MS};
+    assert_eq!(wrapping.overlap(friday + 3 * DAY_MS, friday + 4 * DAY_MS), DAY_MS);
+
+    let config = QualityConfig {spike_window: 20, ..QualityConfig::default()};
+    let report = QualityReport::from_ticks(quality_test_ticks(), config.clone(), 100);
+    assert_eq!(report.tick_count, 108);
+    assert_eq!(report.counts, IssueCounts {
+        out_of_order: 1, duplicate: 1, crossed_quote: 1, zero_price: 1, stale: 1, spike: 1, gap: 1,
+    });
+    assert_eq!(report.issues[0].index, 100);
+    assert_eq!(report.issues[0].kind, IssueKind::OutOfOrder);
+
+    let report = QualityReport::from_ticks(quality_test_ticks(), config, 2);
+    assert_eq!(report.issues.len(), 2);
+    assert_eq!(report.counts.total(), 7);
+}
+
+#[test]
+fn cleaning_map() {
+    let config = QualityConfig {spike_window: 20, ..QualityConfig::default()};
+    let mut map = CleaningMap::new(config.clone(), CleaningActions::default());
+    let summary = map.summary_handle();
+    let cleaned: Vec<Tick> = quality_test_ticks().into_iter().filter_map(|t| map.map(t)).collect();
+
+    let summary = summary.lock().unwrap().clone();
+    assert_eq!((summary.ticks_in, summary.ticks_out), (108, 104));
+    assert_eq!(summary.dropped, IssueCounts {out_of_order: 1, duplicate: 1, zero_price: 1, spike: 1, ..IssueCounts::default()});
+    assert_eq!(summary.repaired.crossed_quote, 1);
+    assert_eq!((summary.kept.stale, summary.kept.gap), (1, 1));
+    assert_eq!(cleaned[101], Tick {timestamp: 101_000, bid: 10003, ask: 10005});
+
+    // the cleaned ticks only have the problems that were kept
+    let report = QualityReport::from_ticks(cleaned, config.clone(), 0);
+    assert_eq!(report.counts, IssueCounts {stale: 1, gap: 1, ..IssueCounts::default()});
+
+    let actions = CleaningActions {
+        out_of_order: CleaningAction::Repair, zero_price: CleaningAction::Repair, spike: CleaningAction::Repair,
+        ..CleaningActions::default()
+    };
+    let mut map = CleaningMap::new(config, actions);
+    let cleaned: Vec<Tick> = quality_test_ticks().into_iter().filter_map(|t| map.map(t)).collect();
+    assert_eq!(cleaned.len(), 107);
+    assert_eq!(cleaned[100].timestamp, 99_000);
+    assert_eq!(cleaned[103], Tick {timestamp: 102_000, bid: 10003, ask: 10005});
+    assert_eq!(cleaned[104], Tick {timestamp: 103_000, bid: 10003, ask: 10005});
+}
+
+#[test]
+fn spike_rebaselining() {
+    let tick = |timestamp, bid, ask| Tick {timestamp: timestamp, bid: bid, ask: ask};
+    // the price jumps to a new level and stays there
+    let ticks: Vec<Tick> = (0..200).map(|i| {
+        let level = if i < 100 { 10000 } else { 12000 };
+        tick(i as u64 * 1000, level + i % 3, level + 2 + i % 3)
+    }).collect();
+    let config = QualityConfig {spike_window: 20, spike_rebaseline: 3, ..QualityConfig::default()};
+
+    let report = QualityReport::from_ticks(ticks.clone(), config.clone(), 10);
+    assert_eq!(report.counts, IssueCounts {spike: 3, ..IssueCounts::default()});
+    assert_eq!(report.issues.iter().map(|issue| issue.index).collect::<Vec<_>>(), vec![100, 101, 102]);
+
+    let mut map = CleaningMap::new(config.clone(), CleaningActions::default());
+    let cleaned: Vec<Tick> = ticks.iter().filter_map(|t| map.map(*t)).collect();
+    assert_eq!(cleaned.len(), 197);
+    assert_eq!(cleaned[100], ticks[103]);
+
+    let actions = CleaningActions {spike: CleaningAction::Repair, ..CleaningActions::default()};
+    let mut map = CleaningMap::new(config, actions);
+    let cleaned: Vec<Tick> = ticks.iter().filter_map(|t| map.map(*t)).collect();
+    assert_eq!(cleaned.len(), 200);
+    assert_eq!(cleaned[102].bid, ticks[99].bid);
+    assert_eq!(cleaned[103], ticks[103]);
+}
@@ -0,0 +1,509 @@
+//! Resampling of ticks to a fixed clock and alignment of the ticks of several symbols into snapshots.
+//!
+//! Samples are stamped with the end of their interval, which is when they become known, so strategies that use them
+//! can't see into the future.  An interval is closed by the first tick after it.  Resampling emits a sample for
+//! every interval and alignment with forward filling emits a snapshot for every interval, carrying the last ticks
+//! forward through intervals without any ticks, so a single tick can close several intervals.  `push` returns all of
+//! them, and so do `ResampledTicks` and `resample_stream` or `AlignedTicks` and `align_stream` at the stream level.
+//! As a `GenTickMap`, `AlignMap` can only emit one snapshot per tick, so the others are held back and emitted with
+//! the ticks that follow.
+
+use std::collections::{HashMap, VecDeque};
+
+use futures::stream::{self, BoxStream};
+use futures::Stream;
+use serde::Deserialize;
+use serde_json;
+
+use trading::tick::{Tick, GenTick, SymbolTick};
+use transport::command_server::CommandServer;
+use transport::tickstream::generics::GenTickMap;
+
+/// How the ticks of an interval are turned into a sample.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum SampleMethod {
+    /// The last tick at or before the end of the interval
+    Last,
+    /// The bid and ask averaged over the interval, weighted by how long each tick was the latest one
+    TimeWeighted,
+}
+
+/// Settings for a `ResampleMap`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ResampleConfig {
+    pub interval_ms: u64,
+    pub method: SampleMethod,
+}
+
+impl ResampleConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.interval_ms == 0 {
+            return Err(String::from("The resampling interval must be positive."));
+        }
+
+        Ok(())
+    }
+}
+
+fn default_forward_fill() -> bool { true }
+
+/// Settings for an `AlignMap`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AlignConfig {
+    /// The symbols included in the snapshots, in order
+    pub symbols: Vec<String>,
+    /// Emit a snapshot at the end of every interval; if not set, a snapshot is emitted on every tick of any symbol.
+    #[serde(default)]
+    pub interval_ms: Option<u64>,
+    pub method: SampleMethod,
+    /// Include symbols without ticks in an interval using their last value.  Snapshots are only emitted once every
+    /// symbol has a value.  If false, symbols without ticks in an interval are left out of its snapshot.
+    #[serde(default = "default_forward_fill")]
+    pub forward_fill: bool,
+}
+
+impl AlignConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.symbols.is_empty() {
+            return Err(String::from("At least one symbol must be aligned."));
+        }
+        if self.symbols.iter().enumerate().any(|(i, s)| self.symbols[..i].contains(s)) {
+            return Err(String::from("Each symbol can only be aligned once."));
+        }
+        if self.interval_ms == Some(0) {
+            return Err(String::from("The alignment interval must be positive."));
+        }
+
+        Ok(())
+    }
+}
+
+/// Tracks the ticks of one symbol during the current interval.
+#[derive(Debug, Clone, Default)]
+struct Sampler {
+    last: Option<Tick>,
+    /// time-weighted sums of the bid and ask since the start of the interval and the time they cover
+    bid_sum: f64,
+    ask_sum: f64,
+    covered_ms: u64,
+    /// time up to which the last tick has been added to the sums
+    since: u64,
+    /// set if a tick was received during the current interval
+    updated: bool,
+}
+
+impl Sampler {
+    fn accumulate(&mut self, until: u64) {
+        if let Some(last) = self.last {
+            let dt = until.saturating_sub(self.since);
+            self.bid_sum += last.bid as f64 * dt as f64;
+            self.ask_sum += last.ask as f64 * dt as f64;
+            self.covered_ms += dt;
+        }
+        self.since = ::std::cmp::max(self.since, until);
+    }
+
+    fn push(&mut self, t: &Tick) {
+        self.accumulate(t.timestamp);
+        self.last = Some(*t);
+        self.updated = true;
+    }
+
+    /// Returns the sample of the interval ending at `end`, or `None` if no ticks have been received yet.
+    fn sample(&mut self, end: u64, method: SampleMethod) -> Option<Tick> {
+        self.accumulate(end);
+        let last = match self.last {
+            Some(last) => last,
+            None => return None,
+        };
+
+        let (bid, ask) = match method {
+            SampleMethod::TimeWeighted if self.covered_ms > 0 => {
+                let covered = self.covered_ms as f64;
+                ((self.bid_sum / covered).round() as usize, (self.ask_sum / covered).round() as usize)
+            },
+            _ => (last.bid, last.ask),
+        };
+        Some(Tick {bid: bid, ask: ask, timestamp: end})
+    }
+
+    /// Starts a new interval at `start`; the last tick is carried into it.
+    fn start(&mut self, start: u64) {
+        self.bid_sum = 0.;
+        self.ask_sum = 0.;
+        self.covered_ms = 0;
+        self.since = start;
+        self.updated = false;
+    }
+}
+
+/// Returns the start of the interval containing `timestamp`.
+fn interval_start(timestamp: u64, interval_ms: u64) -> u64 {
+    timestamp - timestamp % interval_ms
+}
+
+/// Resamples the ticks of a single symbol to a fixed clock, emitting one tick per interval from the interval of the
+/// first tick on.
+pub struct ResampleMap {
+    pub config: ResampleConfig,
+    sampler: Sampler,
+    /// start of the current interval; set once the first tick is received
+    current: Option<u64>,
+}
+
+impl ResampleMap {
+    pub fn from_config(config: ResampleConfig) -> Result<ResampleMap, String> {
+        try!(config.validate());
+
+        Ok(ResampleMap {
+            config: config,
+            sampler: Sampler::default(),
+            current: None,
+        })
+    }
+
+    /// Adds a tick, returning the samples of all intervals that it closed in order.  Intervals without any ticks
+    /// are filled with the last tick before them.
+    pub fn push(&mut self, t: &Tick) -> Vec<Tick> {
+        let interval_ms = self.config.interval_ms;
+        let start = interval_start(t.timestamp, interval_ms);
+        let mut samples = Vec::new();
+        match self.current {
+            Some(mut current) if t.timestamp >= current + interval_ms => {
+                while current < start {
+                    let end = current + interval_ms;
+                    if let Some(sample) = self.sampler.sample(end, self.config.method) {
+                        samples.push(sample);
+                    }
+                    self.sampler.start(end);
+                    current = end;
+                }
+                self.current = Some(start);
+            },
+            Some(_) => (),
+            None => {
+                self.sampler.start(start);
+                self.current = Some(start);
+            },
+        }
+
+        self.sampler.push(t);
+        samples
+    }
+}
+
+/// Resamples a sequence of ticks with a `ResampleMap`.  The interval of the last tick isn't closed, so it doesn't
+/// produce a sample.
+pub struct ResampledTicks<I> {
+    ticks: I,
+    map: ResampleMap,
+    pending: VecDeque<Tick>,
+}
+
+impl<I: Iterator<Item=Tick>> ResampledTicks<I> {
+    pub fn new(ticks: I, config: ResampleConfig) -> Result<ResampledTicks<I>, String> {
+        Ok(ResampledTicks {
+            ticks: ticks,
+            map: try!(ResampleMap::from_config(config)),
+            pending: VecDeque::new(),
+        })
+    }
+}
+
+impl<I: Iterator<Item=Tick>> Iterator for ResampledTicks<I> {
+    type Item = Tick;
+
+    fn next(&mut self) -> Option<Tick> {
+        while self.pending.is_empty() {
+            let t = match self.ticks.next() {
+                Some(t) => t,
+                None => return None,
+            };
+            self.pending.extend(self.map.push(&t));
+        }
+
+        self.pending.pop_front()
+    }
+}
+
+/// Resamples a tickstream, emitting each sample as soon as the tick that closes its interval arrives.
+pub fn resample_stream<S>(ticks: S, config: ResampleConfig) -> Result<BoxStream<Tick, ()>, String>
+    where S: Stream<Item=Tick, Error=()> + Send + 'static
+{
+    let mut map = try!(ResampleMap::from_config(config));
+
+    Ok(ticks.map(move |t| stream::iter(map.push(&t).into_iter().map(Ok))).flatten().boxed())
+}
+
+/// Reads a JSON-encoded config out of the `config` setting.
+fn parse_config<C>(settings: &HashMap<String, String>, map_name: &str) -> Result<C, String>
+    where C: for<'de> Deserialize<'de>
+{
+    settings.get("config")
+        .ok_or(String::from("No `config` setting supplied."))
+        .and_then(|s| serde_json::from_str::<C>(s).map_err(|err| format!("{:?}", err)))
+        .map_err(|err| format!("Unable to parse the config of the {}: {}", map_name, err))
+}
+
+/// Aligns the ticks of several symbols into snapshots containing a tick for each symbol, either at a fixed interval
+/// or whenever any of the symbols updates.  Ticks of symbols that aren't part of the config are ignored.
+pub struct AlignMap {
+    pub config: AlignConfig,
+    /// a sampler for each of the configured symbols, in order
+    samplers: Vec<Sampler>,
+    index: HashMap<String, usize>,
+    /// start of the current interval; set once the first tick is received
+    current: Option<u64>,
+    /// snapshots taken but not yet emitted by `map`
+    pending: VecDeque<GenTick<Vec<(String, Tick)>>>,
+}
+
+impl AlignMap {
+    pub fn from_config(config: AlignConfig) -> Result<AlignMap, String> {
+        try!(config.validate());
+
+        let index = config.symbols.iter().enumerate().map(|(i, s)| (s.clone(), i)).collect();
+        Ok(AlignMap {
+            samplers: vec![Sampler::default(); config.symbols.len()],
+            index: index,
+            current: None,
+            config: config,
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Adds a tick, returning the snapshots that are due in order.  Snapshots taken at an interval are stamped with
+    /// its end and snapshots taken on updates with the timestamp of the tick.
+    pub fn push(&mut self, t: &SymbolTick) -> Vec<GenTick<Vec<(String, Tick)>>> {
+        let i = match self.index.get(&t.symbol) {
+            Some(&i) => i,
+            None => return Vec::new(),
+        };
+        let tick = Tick {bid: t.bid, ask: t.ask, timestamp: t.timestamp};
+
+        let interval_ms = match self.config.interval_ms {
+            Some(interval_ms) => interval_ms,
+            None => {
+                self.samplers[i].push(&tick);
+                let snapshot = self.snapshot(|sampler| sampler.last);
+                for sampler in self.samplers.iter_mut() {
+                    sampler.updated = false;
+                }
+                return snapshot.into_iter().map(|snapshot| GenTick {timestamp: t.timestamp, data: snapshot}).collect();
+            },
+        };
+
+        let start = interval_start(t.timestamp, interval_ms);
+        let mut snapshots = Vec::new();
+        match self.current {
+            Some(mut current) if t.timestamp >= current + interval_ms => {
+                while current < start {
+                    let (end, method) = (current + interval_ms, self.config.method);
+                    if let Some(snapshot) = self.snapshot(|sampler| sampler.sample(end, method)) {
+                        snapshots.push(GenTick {timestamp: end, data: snapshot});
+                    }
+                    for sampler in self.samplers.iter_mut() {
+                        sampler.start(end);
+                    }
+                    current = end;
+                }
+                self.current = Some(start);
+            },
+            Some(_) => (),
+            None => {
+                for sampler in self.samplers.iter_mut() {
+                    sampler.start(start);
+                }
+                self.current = Some(start);
+            },
+        }
+
+        self.samplers[i].push(&tick);
+        snapshots
+    }
+
+    /// Builds a snapshot out of the value `f` returns for each symbol, leaving out or forward filling symbols that
+    /// weren't updated.
+    fn snapshot<F>(&mut self, mut f: F) -> Option<Vec<(String, Tick)>> where F: FnMut(&mut Sampler) -> Option<Tick> {
+        let mut snapshot = Vec::with_capacity(self.samplers.len());
+        let mut complete = true;
+        for (symbol, sampler) in self.config.symbols.iter().zip(self.samplers.iter_mut()) {
+            let updated = sampler.updated;
+            match f(sampler) {
+                Some(t) if updated || self.config.forward_fill => snapshot.push((symbol.clone(), t)),
+                Some(_) => (),
+                None => complete = false,
+            }
+        }
+
+        if snapshot.is_empty() || (self.config.forward_fill && !complete) {
+            None
+        } else {
+            Some(snapshot)
+        }
+    }
+}
+
+impl GenTickMap<SymbolTick, Vec<(String, Tick)>> for AlignMap {
+    /// Expects a `config` setting containing a JSON-encoded `AlignConfig`.
+    fn new(settings: HashMap<String, String>, _: CommandServer) -> Result<Self, String> {
+        AlignMap::from_config(try!(parse_config(&settings, "AlignMap")))
+    }
+
+    fn map(&mut self, t: GenTick<SymbolTick>) -> Option<GenTick<Vec<(String, Tick)>>> {
+        let snapshots = self.push(&t.data);
+        self.pending.extend(snapshots);
+        self.pending.pop_front()
+    }
+}
+
+/// Aligns a sequence of ticks with an `AlignMap`.  When aligning at an interval, the interval of the last tick isn't
+/// closed, so it doesn't produce a snapshot.
+pub struct AlignedTicks<I> {
+    ticks: I,
+    map: AlignMap,
+    pending: VecDeque<GenTick<Vec<(String, Tick)>>>,
+}
+
+impl<I: Iterator<Item=SymbolTick>> AlignedTicks<I> {
+    pub fn new(ticks: I, config: AlignConfig) -> Result<AlignedTicks<I>, String> {
+        Ok(AlignedTicks {
+            ticks: ticks,
+            map: try!(AlignMap::from_config(config)),
+            pending: VecDeque::new(),
+        })
+    }
+}
+
+impl<I: Iterator<Item=SymbolTick>> Iterator for AlignedTicks<I> {
+    type Item = GenTick<Vec<(String, Tick)>>;
+
+    fn next(&mut self) -> Option<GenTick<Vec<(String, Tick)>>> {
+        while self.pending.is_empty() {
+            let t = match self.ticks.next() {
+                Some(t) => t,
+                None => return None,
+            };
+            self.pending.extend(self.map.push(&t));
+        }
+
+        self.pending.pop_front()
+    }
+}
+
+/// Aligns a tickstream of several symbols, emitting each snapshot as soon as the tick that makes it due arrives.
+pub fn align_stream<S>(ticks: S, config: AlignConfig) -> Result<BoxStream<GenTick<Vec<(String, Tick)>>, ()>, String>
+    where S: Stream<Item=SymbolTick, Error=()> + Send + 'static
+{
+    let mut map = try!(AlignMap::from_config(config));
+
+    Ok(ticks.map(move |t| stream::iter(map.push(&t).into_iter().map(Ok))).flatten().boxed())
+}
+
+#[test]
+fn resample_map() {
+    let tick = |timestamp, bid| Tick {bid: bid, ask: bid + 2, timestamp: timestamp};
+    let mut last = ResampleMap::from_config(ResampleConfig {interval_ms: 1000, method: SampleMethod::Last}).unwrap();
+    let mut twap = ResampleMap::from_config(ResampleConfig {interval_ms: 1000, method: SampleMethod::TimeWeighted}).unwrap();
+    let ticks = [tick(100, 10), tick(600, 20), tick(1200, 30), tick(3500, 40)];
+    let last_samples: Vec<Tick> = ticks.iter().flat_map(|t| last.push(t)).collect();
+    let twap_samples: Vec<Tick> = ticks.iter().flat_map(|t| twap.push(t)).collect();
+
+    // the empty interval ending at 3000 is filled with the last tick
+    assert_eq!(last_samples, vec![tick(1000, 20), tick(2000, 30), tick(3000, 30)]);
+    // 10 for 500ms and 20 for 400ms, then 20 carried over for 200ms and 30 for 800ms
+    assert_eq!(twap_samples[0].bid, 14);
+    assert_eq!(twap_samples[1], Tick {bid: 28, ask: 30, timestamp: 2000});
+    assert_eq!(twap_samples[2], Tick {bid: 30, ask: 32, timestamp: 3000});
+
+    let config = ResampleConfig {interval_ms: 1000, method: SampleMethod::Last};
+    let resampled: Vec<Tick> = ResampledTicks::new(ticks.iter().cloned(), config.clone()).unwrap().collect();
+    assert_eq!(resampled, last_samples);
+    let stream = resample_stream(stream::iter(ticks.to_vec().into_iter().map(Ok)), config).unwrap();
+    assert_eq!(stream.wait().map(|t| t.unwrap()).collect::<Vec<Tick>>(), last_samples);
+}
+
+#[test]
+fn align_map() {
+    let stick = |symbol: &str, timestamp, bid| SymbolTick {bid: bid, ask: bid + 1, timestamp: timestamp, symbol: String::from(symbol)};
+    let symbols = vec![String::from("EURUSD"), String::from("GBPUSD")];
+
+    // on every update once both symbols have been seen
+    let mut map = AlignMap::from_config(AlignConfig {
+        symbols: symbols.clone(), interval_ms: None, method: SampleMethod::Last, forward_fill: true,
+    }).unwrap();
+    assert!(map.push(&stick("EURUSD", 1, 10)).is_empty());
+    assert!(map.push(&stick("USDJPY", 2, 10)).is_empty());
+    let snapshots = map.push(&stick("GBPUSD", 3, 20));
+    assert_eq!(snapshots.len(), 1);
+    assert_eq!(snapshots[0].timestamp, 3);
+    assert_eq!(snapshots[0].data, vec![
+        (String::from("EURUSD"), Tick {bid: 10, ask: 11, timestamp: 1}),
+        (String::from("GBPUSD"), Tick {bid: 20, ask: 21, timestamp: 3}),
+    ]);
+    assert_eq!(map.push(&stick("EURUSD", 4, 11))[0].data[0].1.bid, 11);
+
+    // at fixed intervals, with and without forward filling
+    let ticks = [stick("EURUSD", 100, 10), stick("GBPUSD", 200, 20), stick("EURUSD", 1100, 11), stick("EURUSD", 2100, 12)];
+    let mut filled = AlignMap::from_config(AlignConfig {
+        symbols: symbols.clone(), interval_ms: Some(1000), method: SampleMethod::Last, forward_fill: true,
+    }).unwrap();
+    let snapshots: Vec<_> = ticks.iter().flat_map(|t| filled.push(t)).map(|snapshot| snapshot.data).collect();
+    assert_eq!(snapshots.len(), 2);
+    assert_eq!(snapshots[1], vec![
+        (String::from("EURUSD"), Tick {bid: 11, ask: 12, timestamp: 2000}),
+        (String::from("GBPUSD"), Tick {bid: 20, ask: 21, timestamp: 2000}),
+    ]);
+
+    let mut sparse = AlignMap::from_config(AlignConfig {
+        symbols: symbols, interval_ms: Some(1000), method: SampleMethod::Last, forward_fill: false,
+    }).unwrap();
+    let snapshots: Vec<_> = ticks.iter().flat_map(|t| sparse.push(t)).map(|snapshot| snapshot.data).collect();
+    assert_eq!(snapshots[0].len(), 2);
+    assert_eq!(snapshots[1], vec![(String::from("EURUSD"), Tick {bid: 11, ask: 12, timestamp: 2000})]);
+
+    assert!(AlignMap::from_config(AlignConfig {
+        symbols: Vec::new(), interval_ms: None, method: SampleMethod::Last, forward_fill: true,
+    }).is_err());
+    assert!(AlignMap::from_config(AlignConfig {
+        symbols: vec![String::from("EURUSD")], interval_ms: Some(0), method: SampleMethod::Last, forward_fill: true,
+    }).is_err());
+    assert!(ResampleMap::from_config(ResampleConfig {interval_ms: 0, method: SampleMethod::Last}).is_err());
+}
+
+#[test]
+fn align_across_gaps() {
+    let stick = |symbol: &str, timestamp, bid| SymbolTick {bid: bid, ask: bid + 1, timestamp: timestamp, symbol: String::from(symbol)};
+    let config = AlignConfig {
+        symbols: vec![String::from("EURUSD"), String::from("GBPUSD")], interval_ms: Some(1000),
+        method: SampleMethod::Last, forward_fill: true,
+    };
+    // the tick at 4500 closes the interval ending at 1000 and the three empty intervals after it
+    let ticks = vec![stick("EURUSD", 100, 10), stick("GBPUSD", 200, 20), stick("EURUSD", 4500, 11), stick("GBPUSD", 5100, 21)];
+    let mut map = AlignMap::from_config(config.clone()).unwrap();
+    let snapshots: Vec<_> = ticks.iter().flat_map(|t| map.push(t)).collect();
+    let timestamps: Vec<u64> = snapshots.iter().map(|snapshot| snapshot.timestamp).collect();
+    assert_eq!(timestamps, vec![1000, 2000, 3000, 4000, 5000]);
+    for snapshot in &snapshots[..4] {
+        assert_eq!((snapshot.data[0].1.bid, snapshot.data[1].1.bid), (10, 20));
+        assert_eq!(snapshot.data[0].1.timestamp, snapshot.timestamp);
+    }
+    assert_eq!((snapshots[4].data[0].1.bid, snapshots[4].data[1].1.bid), (11, 20));
+
+    // as a map, the snapshots that a tick closes are emitted one per tick
+    let mut map = AlignMap::from_config(config.clone()).unwrap();
+    let mapped: Vec<u64> = ticks.iter()
+        .filter_map(|t| map.map(GenTick {timestamp: t.timestamp, data: t.clone()}))
+        .map(|snapshot| snapshot.timestamp)
+        .collect();
+    assert_eq!(mapped, vec![1000, 2000]);
+
+    let aligned: Vec<u64> = AlignedTicks::new(ticks.clone().into_iter(), config.clone()).unwrap()
+        .map(|snapshot| snapshot.timestamp)
+        .collect();
+    assert_eq!(aligned, timestamps);
+    let streamed: Vec<u64> = align_stream(stream::iter(ticks.into_iter().map(Ok)), config).unwrap().wait()
+        .map(|snapshot| snapshot.unwrap().timestamp)
+        .collect();
+    assert_eq!(streamed, timestamps);
+}
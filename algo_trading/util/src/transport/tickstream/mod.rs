@@ -1,17 +1,27 @@
 //! Trait definitions and implementations for tick generators, maps, and sinks.
 
-use std::thread::{self, Thread};
+use std::iter;
+use std::mem;
+use std::path::PathBuf;
+use std::thread;
 use std::time::Duration;
 use std::sync::{Arc, Mutex, mpsc};
-use std::sync::atomic::{Ordering, AtomicBool};
 #[allow(unused_imports)]
 use test;
 
-use futures::sync::mpsc::UnboundedReceiver;
+use futures::{Future, Sink, Stream};
+use futures::sync::mpsc::{channel, UnboundedReceiver};
 use futures::stream::BoxStream;
 
 use trading::tick::Tick;
+use trading::synthetic::SyntheticConfig;
+use trading::bootstrap::BootstrapConfig;
 use transport::redis::get_client as get_redis_client;
+use transport::csv_schema::CsvSchema;
+use transport::flatfile::{archive_files, find_symbol_data};
+use transport::tickfile::TICK_FILE_EXTENSION;
+#[cfg(feature = "parquet")]
+use transport::parquet_file::PARQUET_EXTENSION;
 use conf::CONF;
 
 pub mod generators;
@@ -19,10 +29,20 @@ pub mod maps;
 pub mod sinks;
 pub mod generics;
 
+pub use self::generators::binary_reader::*;
+pub use self::generators::bootstrap_reader::*;
+pub use self::generators::csv_reader::*;
+pub use self::generators::faulty_reader::*;
 pub use self::generators::flatfile_reader::*;
+pub use self::generators::merged_reader::*;
+pub use self::generators::parquet_reader::*;
+pub use self::generators::postgres_generator::*;
 pub use self::generators::postgres_reader::*;
 pub use self::generators::random_reader::*;
+pub use self::generators::redis_channel_generator::*;
 pub use self::generators::redis_reader::*;
+pub use self::generators::synthetic_reader::*;
+pub use self::sinks::bar_sink::*;
 pub use self::sinks::console_sink::*;
 pub use self::sinks::null_sink::*;
 pub use self::sinks::redis_sink::*;
@@ -36,9 +56,23 @@ pub type CommandStream = mpsc::Receiver<TickstreamCommand>;
 #[derive(Serialize, Deserialize)]
 pub enum TickGenerators {
     FlatfileReader{symbol: String, start_time: Option<u64>},
+    BinaryReader{symbol: String, start_time: Option<u64>},
+    CsvReader{symbol: String, start_time: Option<u64>, schema: CsvSchema},
+    ParquetReader{symbol: String, start_time: Option<u64>, end_time: Option<u64>},
     PostgresReader{symbol: String, start_time: Option<u64>},
     RandomReader,
     RedisReader{symbol: String, redis_host: String, channel: String},
+    SyntheticReader{config: SyntheticConfig, start_time: Option<u64>},
+    /// Bootstraps a new history out of the ticks of `source` between `history_start` and `history_end`
+    BootstrapReader{
+        source: Box<TickGenerators>,
+        history_start: Option<u64>,
+        history_end: Option<u64>,
+        config: BootstrapConfig,
+        start_time: Option<u64>,
+    },
+    /// Injects faults into the ticks of `source`
+    FaultyReader{source: Box<TickGenerators>, faults: FaultConfig},
 }
 
 impl TickGenerators {
@@ -46,11 +80,103 @@ impl TickGenerators {
     pub fn get(&self) -> Box<TickGenerator> {
         match self {
             &TickGenerators::FlatfileReader{ref symbol, start_time} => Box::new(FlatfileReader{symbol: symbol.clone(), start_time: start_time}),
+            &TickGenerators::BinaryReader{ref symbol, start_time} => Box::new(BinaryReader{symbol: symbol.clone(), start_time: start_time}),
+            &TickGenerators::CsvReader{ref symbol, start_time, ref schema} => {
+                Box::new(CsvReader{symbol: symbol.clone(), start_time: start_time, schema: schema.clone()})
+            },
+            &TickGenerators::ParquetReader{ref symbol, start_time, end_time} => {
+                Box::new(ParquetReader{symbol: symbol.clone(), start_time: start_time, end_time: end_time})
+            },
             &TickGenerators::PostgresReader{ref symbol, start_time} => Box::new(PostgresReader{symbol: symbol.clone(), start_time: start_time}),
             &TickGenerators::RandomReader => Box::new(RandomReader {}),
             &TickGenerators::RedisReader{ref symbol, ref redis_host, ref channel} => {
                 Box::new(RedisReader{symbol: symbol.clone(), redis_host: redis_host.clone(), channel: channel.clone()})
             },
+            &TickGenerators::SyntheticReader{ref config, start_time} => {
+                Box::new(SyntheticReader{config: config.clone(), start_time: start_time})
+            },
+            &TickGenerators::BootstrapReader{ref source, history_start, history_end, ref config, start_time} => {
+                Box::new(BootstrapReader{
+                    source: source.get(),
+                    history_start: history_start,
+                    history_end: history_end,
+                    config: config.clone(),
+                    start_time: start_time,
+                })
+            },
+            &TickGenerators::FaultyReader{ref source, ref faults} => {
+                Box::new(FaultyReader{source: source.get(), faults: faults.clone()})
+            },
+        }
+    }
+
+    /// Makes the generator skip all ticks with timestamps before `timestamp` without replaying them.  If the
+    /// generator already starts later than that, it is left unchanged.  Returns `false` if the generator can't
+    /// be started at a point in time, in which case it is also left unchanged.
+    pub fn start_at(&mut self, timestamp: u64) -> bool {
+        match *self {
+            TickGenerators::FlatfileReader{ref mut start_time, ..} |
+            TickGenerators::BinaryReader{ref mut start_time, ..} |
+            TickGenerators::CsvReader{ref mut start_time, ..} |
+            TickGenerators::ParquetReader{ref mut start_time, ..} |
+            TickGenerators::PostgresReader{ref mut start_time, ..} |
+            TickGenerators::SyntheticReader{ref mut start_time, ..} |
+            TickGenerators::BootstrapReader{ref mut start_time, ..} => {
+                *start_time = Some(match *start_time {
+                    Some(start) if start > timestamp => start,
+                    _ => timestamp,
+                });
+                true
+            },
+            // the faults injected depend on the position in the stream, so it has to be read from the start
+            TickGenerators::RandomReader | TickGenerators::RedisReader{..} | TickGenerators::FaultyReader{..} => false,
+        }
+    }
+
+    /// Returns whether the generator's stream ends.  Endless streams can't be read completely, which is needed to
+    /// check or bootstrap them.
+    pub fn is_finite(&self) -> bool {
+        match *self {
+            TickGenerators::RandomReader | TickGenerators::RedisReader{..} => false,
+            TickGenerators::SyntheticReader{ref config, ..} => config.tick_count.is_some(),
+            TickGenerators::BootstrapReader{ref source, ..} | TickGenerators::FaultyReader{ref source, ..} => {
+                source.is_finite()
+            },
+            _ => true,
+        }
+    }
+
+    /// Returns the files that the generator reads its ticks from, which is empty if the ticks only depend on the
+    /// generator's settings.  Returns `None` if the ticks come from somewhere else, such as a database.
+    pub fn data_files(&self) -> Option<Vec<PathBuf>> {
+        let mut dir = PathBuf::from(CONF.data_dir);
+        dir.push("historical_ticks");
+        let path = match *self {
+            TickGenerators::FlatfileReader{ref symbol, ..} | TickGenerators::CsvReader{ref symbol, ..} => {
+                find_symbol_data(&dir, &symbol.to_uppercase(), "csv")
+            },
+            TickGenerators::BinaryReader{ref symbol, ..} => {
+                Some(dir.join(format!("{}.{}", symbol.to_uppercase(), TICK_FILE_EXTENSION)))
+            },
+            #[cfg(feature = "parquet")]
+            TickGenerators::ParquetReader{ref symbol, ..} => {
+                Some(dir.join(format!("{}.{}", symbol.to_uppercase(), PARQUET_EXTENSION)))
+            },
+            #[cfg(not(feature = "parquet"))]
+            TickGenerators::ParquetReader{..} => return None,
+            TickGenerators::SyntheticReader{..} => return Some(Vec::new()),
+            TickGenerators::BootstrapReader{ref source, ..} | TickGenerators::FaultyReader{ref source, ..} => {
+                return source.data_files();
+            },
+            TickGenerators::PostgresReader{..} | TickGenerators::RandomReader | TickGenerators::RedisReader{..} => {
+                return None;
+            },
+        };
+
+        // a missing file is left for the generator to report
+        match path {
+            Some(path) => archive_files(&path).ok(),
+            None => Some(Vec::new()),
         }
     }
 }
@@ -61,16 +187,26 @@ pub enum TickMaps {
     FastMap{delay_ms: usize},
     LiveMap{last_tick_timestamp: u64},
     NullMap,
+    CleaningMap{config: QualityConfig, actions: CleaningActions},
+    /// Only adds faults to single ticks; duplicated and reordered ticks need a `FaultyReader`.
+    FaultMap{config: FaultConfig},
 }
 
 impl TickMaps {
-    /// Depending on variant, returns a `TickMap` based on the supplied params.
-    pub fn get(&self) -> Box<TickMap> {
-        match self {
+    /// Depending on variant, returns a `TickMap` based on the supplied params.  Returns an error if the params are
+    /// invalid.
+    pub fn get(&self) -> Result<Box<TickMap>, String> {
+        Ok(match self {
             &TickMaps::FastMap{delay_ms} => Box::new(FastMap{delay_ms: delay_ms}),
-            &TickMaps::LiveMap{last_tick_timestamp} => Box::new(LiveMap{last_tick_timestamp: last_tick_timestamp}),
+            &TickMaps::LiveMap{last_tick_timestamp} => {
+                let mut map = LiveMap::new();
+                map.last_tick_timestamp = last_tick_timestamp;
+                Box::new(map)
+            },
             &TickMaps::NullMap => Box::new(NullMap {}),
-        }
+            &TickMaps::CleaningMap{ref config, ref actions} => Box::new(CleaningMap::new(config.clone(), actions.clone())),
+            &TickMaps::FaultMap{ref config} => Box::new(try!(FaultMap::new(config.clone()))),
+        })
     }
 }
 
@@ -97,11 +233,29 @@ impl TickSinks {
 }
 
 /// Commands for controlling the flow of ticks in a tickstream
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum TickstreamCommand {
     Pause,
     Resume,
     Stop,
+    /// Send a single tick and then pause
+    Step,
+    /// Send the given number of ticks and then pause
+    StepN(usize),
+    /// Jump to the first tick at or after the timestamp.  Only supported by file-backed and generated tickstreams of a
+    /// single symbol; others ignore it.
+    Seek(u64),
+}
+
+/// What a tickstream's worker thread should do after checking for new commands.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MailAction {
+    /// Keep sending ticks
+    Continue,
+    /// Stop the tickstream
+    Stop,
+    /// Continue from the first tick at or after the timestamp
+    Seek(u64),
 }
 
 /// Creates a Stream of Ticks from some source.
@@ -127,69 +281,314 @@ pub trait TickSink {
 /// latency, simulate slippage/lost ticks, etc.
 pub trait TickMap {
     fn map(&mut self, Tick) -> Option<Tick>;
+
+    /// Called when the tickstream jumps to a different point in time, such as after a seek, so that maps that
+    /// depend on earlier ticks can start over.
+    fn reset(&mut self) {}
+}
+
+/// Whether a tickstream is sending ticks.  This is kept by the tickstream's worker thread and updated by
+/// `read_mail()` as commands arrive.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Playback {
+    Playing,
+    Paused,
+    /// Send the given number of ticks and then pause
+    Stepping(usize),
 }
 
 /// Handles backtest messages within the backtest's worker thread.  If this returns true,
-/// it means the caller has to die.
-pub fn check_mail(got_mail: &AtomicBool, message: &Mutex<TickstreamCommand>) -> bool {
-    // nothing to do if we have no new commands to process
-    if !got_mail.load(Ordering::Relaxed) { return false }
+/// it means the caller has to die.  Seek commands are ignored; generators that support seeking
+/// should use `read_mail()` instead.
+pub fn check_mail(mail: &CommandStream, playback: &mut Playback) -> bool {
+    read_mail(mail, playback) == MailAction::Stop
+}
+
+/// Applies a command to the playback state, returning the action to take if the command needs the worker to do
+/// more than change its playback.
+fn apply_command(cmd: TickstreamCommand, playback: &mut Playback) -> Option<MailAction> {
+    match cmd {
+        TickstreamCommand::Stop => return Some(MailAction::Stop),
+        TickstreamCommand::Pause => *playback = Playback::Paused,
+        TickstreamCommand::Resume => *playback = Playback::Playing,
+        TickstreamCommand::Step => *playback = Playback::Stepping(1),
+        TickstreamCommand::StepN(n) => *playback = Playback::Stepping(n),
+        // a paused tickstream stays paused after seeking
+        TickstreamCommand::Seek(timestamp) => return Some(MailAction::Seek(timestamp)),
+    }
+
+    None
+}
+
+/// Handles backtest messages within the backtest's worker thread, blocking while the tickstream is paused.  This
+/// should be called once before each tick is sent with the worker's `Playback`, which starts as `Playing`.
+///
+/// All commands waiting in `mail` are applied in the order they were sent.  Draining stops early at a stop or seek
+/// so that it can be acted on; the commands sent after it are applied on the next call.  A paused tickstream whose
+/// command handle has been dropped can never be resumed, so it's stopped.
+pub fn read_mail(mail: &CommandStream, playback: &mut Playback) -> MailAction {
+    loop {
+        while let Ok(cmd) = mail.try_recv() {
+            if let Some(action) = apply_command(cmd, playback) {
+                return action;
+            }
+        }
+
+        match *playback {
+            Playback::Playing => return MailAction::Continue,
+            Playback::Stepping(n) if n > 0 => {
+                *playback = Playback::Stepping(n - 1);
+                return MailAction::Continue;
+            },
+            Playback::Stepping(_) | Playback::Paused => {
+                *playback = Playback::Paused;
+                // block until the next command arrives
+                match mail.recv() {
+                    Ok(cmd) => if let Some(action) = apply_command(cmd, playback) {
+                        return action;
+                    },
+                    Err(_) => return MailAction::Stop,
+                }
+            },
+        }
+    }
+}
+
+/// An item of a tickstream that `TickMap`s can be applied to.
+pub trait StreamTick: Sized + Send + 'static {
+    fn timestamp(&self) -> u64;
 
-    let cur_command: TickstreamCommand;
-    {
-        let atom = message.lock().unwrap();
-        // handing the command may block for a long time, so make sure mutex is unlocked first.
-        cur_command = atom.clone();
+    /// Applies the map to the item's tick, returning None if the map drops it.
+    fn apply_map(self, map: &mut TickMap) -> Option<Self>;
+}
+
+impl StreamTick for Tick {
+    fn timestamp(&self) -> u64 {
+        self.timestamp
     }
 
-    match cur_command {
-        TickstreamCommand::Pause => thread::park(),
-        TickstreamCommand::Stop => return true,
-        // _ => println!("Backtest worker can't handle command: {:?}", cur_command),
-        _ => (),
+    fn apply_map(self, map: &mut TickMap) -> Option<Tick> {
+        map.map(self)
     }
+}
 
-    got_mail.store(false, Ordering::Relaxed);
+/// A tick paired with the index of the source that it came from
+impl StreamTick for (usize, Tick) {
+    fn timestamp(&self) -> u64 {
+        self.1.timestamp
+    }
 
-    false
+    fn apply_map(self, map: &mut TickMap) -> Option<(usize, Tick)> {
+        let (i, t) = self;
+        map.map(t).map(|t| (i, t))
+    }
 }
 
-/// Spawns the thread that listens for new `TickstreamCommand`s and relays them
-/// internally to the worker thread.
-pub fn spawn_listener_thread(
-    got_mail: Arc<AtomicBool>, cmd_handle: CommandStream,
-    internal_message: Arc<Mutex<TickstreamCommand>>, reader_handle: Thread
-) {
+/// Plays back `ticks` through the map on a worker thread that is controlled by the commands sent through
+/// `cmd_handle`, returning the stream of mapped ticks.  The stream starts playing right away and ends once the ticks
+/// run out, a stop command is received, or the stream is dropped.
+///
+/// Seek commands are passed to `seek` along with the iterator and the timestamp of the last tick that was read.  It
+/// returns whether it moved the iterator; if it did, the map is reset, and if it didn't, playback continues from
+/// where it was.
+pub fn spawn_tickstream<T, I, F>(
+    mut ticks: I, mut map: Box<TickMap + Send>, cmd_handle: CommandStream, mut seek: F
+) -> BoxStream<T, ()> where
+    T: StreamTick, I: Iterator<Item=T> + Send + 'static, F: FnMut(&mut I, u64, u64) -> bool + Send + 'static
+{
+    let (mut sender, receiver) = channel::<T>(1);
+
+    // spawn the worker thread that does the blocking
     thread::spawn(move || {
-        // block until new backtest command received
-        for cmd in cmd_handle.iter() {
-            // println!("Received backtest command: {:?}", cmd);
-            match cmd {
-                TickstreamCommand::Stop | TickstreamCommand::Pause => {
-                    let mut lock = internal_message.lock().unwrap();
-                    *lock = cmd.clone();
-                    got_mail.store(true, Ordering::Relaxed);
+        let mut last_timestamp = 0;
+        let mut playback = Playback::Playing;
+        loop {
+            match read_mail(&cmd_handle, &mut playback) {
+                MailAction::Stop => {
+                    println!("Stop command received; killing reader");
+                    break;
+                },
+                MailAction::Seek(timestamp) => if seek(&mut ticks, timestamp, last_timestamp) {
+                    map.reset();
                 },
-                TickstreamCommand::Resume => reader_handle.unpark(),
+                MailAction::Continue => (),
+            }
+
+            let tick = match ticks.next() {
+                Some(tick) => tick,
+                None => break,
+            };
+            last_timestamp = tick.timestamp();
+
+            // apply the map
+            if let Some(t_mod) = tick.apply_map(&mut *map) {
+                sender = match sender.send(t_mod).wait() {
+                    Ok(sender) => sender,
+                    Err(_) => break,
+                };
             }
         }
     });
+
+    receiver.boxed()
 }
 
-/// See how fast we can check the value of the atomic bool
+/// Seeks the ticks of a file that can only be read forward for `spawn_tickstream()`.  Seeking forward skips ahead in
+/// the open file but seeking backward has to re-open it with `reopen`, which is given the timestamp to seek to.
+pub fn seek_reopening<F>(
+    ticks: &mut Box<Iterator<Item=Tick> + Send>, timestamp: u64, last_timestamp: u64, reopen: F
+) -> bool where F: FnOnce(u64) -> Result<Box<Iterator<Item=Tick> + Send>, String> {
+    if timestamp >= last_timestamp {
+        let rest = mem::replace(ticks, Box::new(iter::empty()));
+        *ticks = Box::new(rest.skip_while(move |t| t.timestamp < timestamp));
+        return true;
+    }
+
+    match reopen(timestamp) {
+        Ok(reopened) => {
+            *ticks = reopened;
+            true
+        },
+        Err(err) => {
+            println!("Unable to re-open the file to seek: {}", err);
+            false
+        },
+    }
+}
+
+/// See how fast we can check for commands when there aren't any
 #[bench]
 fn mail_check_no_messages(b: &mut test::Bencher) {
-    let msg = Mutex::new(TickstreamCommand::Stop);
+    let (_tx, rx) = mpsc::channel();
+    let mut playback = Playback::Playing;
     b.iter(|| {
-        check_mail(&AtomicBool::new(false), &msg)
+        check_mail(&rx, &mut playback)
     })
 }
 
-/// How fast we can check the atomic bool and unlock the mutex
+/// How fast we can receive and apply a command
 #[bench]
 fn mail_check_messages(b: &mut test::Bencher) {
-    let msg = Mutex::new(TickstreamCommand::Stop);
+    let (tx, rx) = mpsc::channel();
+    let mut playback = Playback::Playing;
     b.iter(|| {
-        check_mail(&AtomicBool::new(true), &msg)
+        tx.send(TickstreamCommand::Resume).unwrap();
+        check_mail(&rx, &mut playback)
     })
 }
+
+#[test]
+fn mail_stepping_and_seeking() {
+    let (tx, rx) = mpsc::channel();
+    let mut playback = Playback::Playing;
+    tx.send(TickstreamCommand::StepN(2)).unwrap();
+    assert_eq!(read_mail(&rx, &mut playback), MailAction::Continue);
+    assert_eq!(read_mail(&rx, &mut playback), MailAction::Continue);
+    // the step count is kept by the worker and the command is only read once
+    assert_eq!(playback, Playback::Stepping(0));
+
+    // a paused tickstream stays paused after seeking
+    playback = Playback::Paused;
+    tx.send(TickstreamCommand::Seek(42)).unwrap();
+    assert_eq!(read_mail(&rx, &mut playback), MailAction::Seek(42));
+    assert_eq!(playback, Playback::Paused);
+
+    tx.send(TickstreamCommand::Stop).unwrap();
+    assert!(check_mail(&rx, &mut playback));
+}
+
+#[test]
+fn queued_commands_are_applied_in_order() {
+    let (tx, rx) = mpsc::channel();
+    let mut playback = Playback::Playing;
+    // none of the commands sent between two ticks are lost
+    let cmds = vec![
+        TickstreamCommand::Pause, TickstreamCommand::Seek(10), TickstreamCommand::StepN(3), TickstreamCommand::Seek(20),
+        TickstreamCommand::Resume,
+    ];
+    for cmd in cmds {
+        tx.send(cmd).unwrap();
+    }
+    assert_eq!(read_mail(&rx, &mut playback), MailAction::Seek(10));
+    assert_eq!(playback, Playback::Paused);
+    assert_eq!(read_mail(&rx, &mut playback), MailAction::Seek(20));
+    assert_eq!(playback, Playback::Stepping(3));
+    assert_eq!(read_mail(&rx, &mut playback), MailAction::Continue);
+    assert_eq!(playback, Playback::Playing);
+
+    // commands sent after a stop don't replace it
+    tx.send(TickstreamCommand::Stop).unwrap();
+    tx.send(TickstreamCommand::Resume).unwrap();
+    assert_eq!(read_mail(&rx, &mut playback), MailAction::Stop);
+    assert_eq!(read_mail(&rx, &mut playback), MailAction::Continue);
+
+    // nothing can resume a paused tickstream once its handle is dropped
+    tx.send(TickstreamCommand::Pause).unwrap();
+    drop(tx);
+    assert_eq!(read_mail(&rx, &mut playback), MailAction::Stop);
+}
+
+#[test]
+fn mail_commands_while_paused() {
+    let (tx, rx) = mpsc::channel();
+    let (done_tx, done_rx) = mpsc::channel();
+    let worker = thread::spawn(move || {
+        let mut playback = Playback::Paused;
+        let mut actions = Vec::new();
+        loop {
+            let action = read_mail(&rx, &mut playback);
+            actions.push(action);
+            if action == MailAction::Stop {
+                break;
+            }
+            done_tx.send(()).unwrap();
+        }
+        actions
+    });
+
+    // the worker blocks until it's stepped
+    thread::sleep(Duration::from_millis(50));
+    assert!(done_rx.try_recv().is_err());
+
+    tx.send(TickstreamCommand::Step).unwrap();
+    done_rx.recv().unwrap();
+    thread::sleep(Duration::from_millis(50));
+    assert!(done_rx.try_recv().is_err());
+
+    tx.send(TickstreamCommand::Stop).unwrap();
+    assert_eq!(worker.join().unwrap(), vec![MailAction::Continue, MailAction::Stop]);
+}
+
+#[test]
+fn spawned_tickstream_playback() {
+    let ticks: Vec<Tick> = (0..10).map(|i| Tick {bid: 1, ask: 1, timestamp: i}).collect();
+    let (tx, rx) = mpsc::sync_channel(5);
+    let stream = spawn_tickstream(ticks.into_iter(), Box::new(NullMap {}), rx, |_, _, _| false);
+    let played: Vec<u64> = stream.wait().map(|t| t.unwrap().timestamp).collect();
+    assert_eq!(played, (0..10).collect::<Vec<u64>>());
+    drop(tx);
+
+    // the stream ends after a stop command
+    let ticks = (0..).map(|i| (i % 2, Tick {bid: 1, ask: 1, timestamp: i as u64}));
+    let (tx, rx) = mpsc::sync_channel(5);
+    let mut stream = spawn_tickstream(ticks, Box::new(NullMap {}), rx, |_, _, _| false).wait();
+    assert_eq!(stream.next().unwrap().unwrap().0, 0);
+    tx.send(TickstreamCommand::Stop).unwrap();
+    thread::sleep(Duration::from_millis(50));
+    assert!(stream.take(10).count() < 10);
+}
+
+#[test]
+fn generators_start_at() {
+    let mut gen = TickGenerators::BinaryReader{symbol: String::from("TEST"), start_time: None};
+    assert!(gen.start_at(10));
+    // generators that already start later keep their start time
+    let mut later = TickGenerators::PostgresReader{symbol: String::from("TEST"), start_time: Some(20)};
+    assert!(later.start_at(10));
+    match (gen, later) {
+        (TickGenerators::BinaryReader{start_time: Some(10), ..}, TickGenerators::PostgresReader{start_time: Some(20), ..}) => (),
+        _ => panic!("Generators weren't started at the right time"),
+    }
+
+    assert!(!TickGenerators::RandomReader.start_at(10));
+}
@@ -0,0 +1,35 @@
+//! Aggregates ticks into bars and publishes the completed bars on a Redis channel.
+
+use redis::Client;
+use serde_json;
+
+use trading::tick::Tick;
+use trading::bar::{BarBuilder, BarDefinition};
+use transport::redis::{get_client, publish};
+use transport::tickstream::TickSink;
+
+pub struct RedisBarSink {
+    pub builder: BarBuilder,
+    pub tx_channel: String,
+    pub client: Client,
+}
+
+impl TickSink for RedisBarSink {
+    fn tick(&mut self, t: Tick) {
+        for bar in self.builder.push(&t, 1) {
+            let bar_json = serde_json::to_string(&bar.data).expect("Unable to serialize bar");
+            publish(&self.client, &self.tx_channel, &bar_json);
+        }
+    }
+}
+
+impl RedisBarSink {
+    /// Creates a sink for the bars in the definition, returning an error if the definition is invalid.
+    pub fn new(def: BarDefinition, tx_channel: String, redis_host: &str) -> Result<RedisBarSink, String> {
+        Ok(RedisBarSink {
+            builder: try!(BarBuilder::new(def)),
+            tx_channel: tx_channel,
+            client: get_client(redis_host),
+        })
+    }
+}
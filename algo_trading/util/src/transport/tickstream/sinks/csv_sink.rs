@@ -1,9 +1,6 @@
-//! Saves data to a CSV flatfile.
-
-// TODO: Enable compression/decompression and transferring to/from archives
+//! Saves data to a CSV flatfile, compressing it if the output path ends with `.gz`, `.zst`, or `.xz`.
 
 use std::collections::HashMap;
-use std::fs::File;
 use std::path::Path;
 use std::marker::PhantomData;
 
@@ -12,12 +9,12 @@ use serde::{Serialize, Deserialize};
 
 use trading::tick::GenTick;
 use transport::tickstream::GenTickSink;
-use transport::textlog::debug;
+use transport::flatfile::{FlatfileWriter, create_file};
 
 // TODO: Non `CommandServer`-Based Logging
 
 pub struct CsvSink<T> {
-    writer: Writer<File>,
+    writer: Writer<FlatfileWriter>,
     ghost: PhantomData<T>,
 }
 
@@ -31,7 +28,7 @@ impl<T> GenTickSink<T> for CsvSink<T> where T : Serialize, T : for<'de> Deserial
         };
 
         Ok(CsvSink {
-            writer: Writer::from_path(Path::new(output_path)).map_err(debug)?,
+            writer: Writer::from_writer(create_file(Path::new(output_path), false)?),
             ghost: PhantomData{},
         })
     }
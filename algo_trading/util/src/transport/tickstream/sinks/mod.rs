@@ -1,8 +1,13 @@
 //! Tick sinks server as the consumers of time series data streams within the platform.  They can be things such as databases where the data
 //! is stored, backtests, or strategy executors.
 
+pub mod bar_sink;
 pub mod console_sink;
 pub mod csv_sink;
 pub mod null_sink;
+#[cfg(feature = "parquet")]
+pub mod parquet_sink;
+pub mod postgres_sink;
+pub mod redis_channel_sink;
 pub mod redis_sink;
 pub mod stream_sink;
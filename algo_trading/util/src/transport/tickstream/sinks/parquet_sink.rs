@@ -0,0 +1,130 @@
+//! Saves data to an Apache Parquet file.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::marker::PhantomData;
+
+use serde::{Serialize, Deserialize};
+use serde_json::{self, Value};
+
+use trading::tick::GenTick;
+use transport::tickstream::GenTickSink;
+use transport::parquet_file::{ParquetWriter, Column, ColumnType, DEFAULT_ROW_GROUP_SIZE};
+
+pub struct ParquetSink<T> {
+    output_path: PathBuf,
+    row_group_size: usize,
+    /// Created once the first tick is received since the columns are determined by its data
+    writer: Option<ParquetWriter>,
+    ghost: PhantomData<T>,
+}
+
+/// A tick sink that writes data to a Parquet file.  The file has a `timestamp` column followed by one column for
+/// every field of the data, which is determined from the first tick that the sink receives.  Data that isn't a
+/// struct or map is stored in a single `data` column and sequences are stored in `data_0`, `data_1`, etc.  Nested
+/// values are stored as JSON strings.
+/// Requires that the setting `output_path` be supplied in the settings `HashMap`.  The number of rows in each row
+/// group can be set with the optional `row_group_size` setting.
+impl<T> GenTickSink<T> for ParquetSink<T> where T : Serialize, T : for<'de> Deserialize<'de> {
+    fn new(settings: HashMap<String, String>) -> Result<Self, String> {
+        let output_path = match settings.get("output_path") {
+            Some(p) => p,
+            None => { return Err(String::from("You must supply an `output_path` argument in the input `HashMap`~")) },
+        };
+        let row_group_size = match settings.get("row_group_size") {
+            Some(size) => size.parse().map_err(|_| format!("Invalid row group size: {}", size))?,
+            None => DEFAULT_ROW_GROUP_SIZE,
+        };
+
+        Ok(ParquetSink {
+            output_path: PathBuf::from(output_path),
+            row_group_size: row_group_size,
+            writer: None,
+            ghost: PhantomData{},
+        })
+    }
+
+    fn tick(&mut self, t: GenTick<T>) {
+        let data = match serde_json::to_value(&t.data) {
+            Ok(data) => data,
+            Err(e) => {
+                println!("Error while serializing tick: {:?}", e);
+                return;
+            },
+        };
+
+        if self.writer.is_none() {
+            match ParquetWriter::create(Path::new(&self.output_path), data_columns(&data), self.row_group_size) {
+                Ok(writer) => self.writer = Some(writer),
+                Err(e) => {
+                    println!("Error while creating output file: {}", e);
+                    return;
+                },
+            }
+        }
+
+        let writer = self.writer.as_mut().unwrap();
+        let row = data_row(t.timestamp, &data, writer.columns());
+        if let Err(e) = writer.write_row(&row) {
+            println!("Error while writing row to file: {}", e);
+        }
+    }
+}
+
+/// Returns the columns that data shaped like `data` is stored in.
+fn data_columns(data: &Value) -> Vec<Column> {
+    let mut columns = vec![Column::new("timestamp", ColumnType::Long, false)];
+    match *data {
+        Value::Object(ref map) => for (k, v) in map {
+            columns.push(Column::new(k, ColumnType::of_value(v), true));
+        },
+        Value::Array(ref vals) => for (i, v) in vals.iter().enumerate() {
+            columns.push(Column::new(&format!("data_{}", i), ColumnType::of_value(v), true));
+        },
+        ref other => columns.push(Column::new("data", ColumnType::of_value(other), true)),
+    }
+
+    columns
+}
+
+/// Splits a tick's data into the values of the columns.  Fields that don't have a column are dropped and columns
+/// that don't have a field are null.
+fn data_row(timestamp: u64, data: &Value, columns: &[Column]) -> Vec<Value> {
+    let mut row = vec![Value::from(timestamp)];
+    for (i, column) in columns.iter().enumerate().skip(1) {
+        let val = match *data {
+            Value::Object(ref map) => map.get(&column.name),
+            Value::Array(ref vals) => vals.get(i - 1),
+            ref other => Some(other),
+        };
+        row.push(val.cloned().unwrap_or(Value::Null));
+    }
+
+    row
+}
+
+#[test]
+fn parquet_sink_columns() {
+    #[derive(Serialize)]
+    struct Trade {
+        price: f64,
+        amount: u64,
+        buy: bool,
+        tags: Vec<String>,
+    }
+
+    let data = serde_json::to_value(&Trade {price: 1.5, amount: 3, buy: true, tags: vec![String::from("a")]}).unwrap();
+    let columns = data_columns(&data);
+    let types: Vec<(&str, ColumnType)> = columns.iter().map(|c| (c.name.as_str(), c.column_type)).collect();
+    assert_eq!(types, vec![
+        ("timestamp", ColumnType::Long), ("amount", ColumnType::Long), ("buy", ColumnType::Bool),
+        ("price", ColumnType::Double), ("tags", ColumnType::Str),
+    ]);
+    let row = data_row(10, &data, &columns);
+    assert_eq!(row[0], Value::from(10));
+    assert_eq!(row[3], Value::from(1.5));
+
+    let columns = data_columns(&Value::from(7));
+    assert_eq!(columns[1].name, "data");
+    assert_eq!(data_row(1, &Value::from(7), &columns), vec![Value::from(1), Value::from(7)]);
+}
@@ -0,0 +1,93 @@
+//! Saves data to a table in the postgres database, creating the table from the data's schema if it doesn't exist.
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use postgres::Connection;
+
+use trading::tick::GenTick;
+use transport::tickstream::GenTickSink;
+use transport::postgres::{get_client, init_generic_tick_table, copy_row, copy_rows_in, PostgresSchema};
+use conf::CONF;
+
+pub const DEFAULT_POSTGRES_BATCH_SIZE: usize = 1000;
+
+pub struct PostgresSink<T> {
+    client: Connection,
+    table: String,
+    columns: Vec<&'static str>,
+    batch_size: usize,
+    /// Rows waiting to be inserted in the text format of `COPY`
+    buffer: String,
+    buffered: usize,
+    ghost: PhantomData<T>,
+}
+
+/// A tick sink that stores data in a postgres table.  Since postgres needs to know the schema of the data, it must
+/// implement `PostgresSchema`; the table is created with a `tick_time` column for the timestamps followed by the
+/// schema's columns.  Ticks are buffered and inserted in batches with `COPY`; the remaining ticks are inserted when
+/// the sink is dropped.
+/// Requires that the setting `table` be supplied in the settings `HashMap`.  The number of ticks in each batch can be
+/// set with the optional `batch_size` setting.
+impl<T> GenTickSink<T> for PostgresSink<T> where T : PostgresSchema {
+    fn new(settings: HashMap<String, String>) -> Result<Self, String> {
+        let table = match settings.get("table") {
+            Some(t) => t.clone(),
+            None => { return Err(String::from("You must supply a `table` argument in the input `HashMap`~")) },
+        };
+        let batch_size = match settings.get("batch_size") {
+            Some(size) => match size.parse() {
+                Ok(size) if size > 0 => size,
+                _ => { return Err(format!("Invalid batch size: {}", size)) },
+            },
+            None => DEFAULT_POSTGRES_BATCH_SIZE,
+        };
+
+        let client = get_client().map_err(|err| format!("Unable to connect to postgres: {:?}", err))?;
+        let columns = T::columns();
+        init_generic_tick_table(&table, &columns, &client, CONF.postgres_user)?;
+
+        Ok(PostgresSink {
+            client: client,
+            table: table,
+            columns: columns.iter().map(|&(name, _)| name).collect(),
+            batch_size: batch_size,
+            buffer: String::new(),
+            buffered: 0,
+            ghost: PhantomData{},
+        })
+    }
+
+    fn tick(&mut self, t: GenTick<T>) {
+        self.buffer.push_str(&copy_row(t.timestamp, &t.data.to_values()));
+        self.buffered += 1;
+
+        if self.buffered >= self.batch_size {
+            if let Err(e) = self.flush() {
+                println!("{}", e);
+            }
+        }
+    }
+}
+
+impl<T> PostgresSink<T> {
+    /// Inserts all buffered ticks into the table.  The buffer is emptied even if the insert fails.
+    pub fn flush(&mut self) -> Result<(), String> {
+        if self.buffered == 0 {
+            return Ok(());
+        }
+
+        let res = copy_rows_in(&self.table, &self.columns, &self.buffer, &self.client);
+        self.buffer.clear();
+        self.buffered = 0;
+        res.map(|_| ())
+    }
+}
+
+impl<T> Drop for PostgresSink<T> {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            println!("{}", e);
+        }
+    }
+}
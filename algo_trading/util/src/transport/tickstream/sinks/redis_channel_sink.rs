@@ -0,0 +1,95 @@
+//! Publishes data over a Redis pub/sub channel as JSON.
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use redis::{self, Client};
+use serde::{Serialize, Deserialize};
+use serde_json;
+
+use trading::tick::GenTick;
+use transport::tickstream::GenTickSink;
+use transport::redis::get_client;
+use conf::CONF;
+
+pub const DEFAULT_REDIS_BATCH_SIZE: usize = 100;
+
+pub struct RedisChannelSink<T> {
+    client: Client,
+    channel: String,
+    batch_size: usize,
+    /// Serialized ticks waiting to be published
+    buffer: Vec<String>,
+    ghost: PhantomData<T>,
+}
+
+/// A tick sink that publishes ticks over a Redis channel.  Since Redis doesn't care about the schema of the data
+/// sent over it, any data that can be serialized is accepted.  Every tick is published as its own message, a JSON
+/// object with `timestamp` and `data` fields, but messages are buffered and sent in pipelined batches; the remaining
+/// messages are sent when the sink is dropped.
+/// Requires that the setting `channel` be supplied in the settings `HashMap`.  The optional `redis_host` setting
+/// defaults to the configured Redis host and the number of ticks in each batch can be set with `batch_size`.
+impl<T> GenTickSink<T> for RedisChannelSink<T> where T : Serialize, T : for<'de> Deserialize<'de> {
+    fn new(settings: HashMap<String, String>) -> Result<Self, String> {
+        let channel = match settings.get("channel") {
+            Some(c) => c.clone(),
+            None => { return Err(String::from("You must supply a `channel` argument in the input `HashMap`~")) },
+        };
+        let batch_size = match settings.get("batch_size") {
+            Some(size) => match size.parse() {
+                Ok(size) if size > 0 => size,
+                _ => { return Err(format!("Invalid batch size: {}", size)) },
+            },
+            None => DEFAULT_REDIS_BATCH_SIZE,
+        };
+        let redis_host = settings.get("redis_host").map(|host| host.as_str()).unwrap_or(CONF.redis_host);
+
+        Ok(RedisChannelSink {
+            client: get_client(redis_host),
+            channel: channel,
+            batch_size: batch_size,
+            buffer: Vec::with_capacity(batch_size),
+            ghost: PhantomData{},
+        })
+    }
+
+    fn tick(&mut self, t: GenTick<T>) {
+        match serde_json::to_string(&t) {
+            Ok(msg) => self.buffer.push(msg),
+            Err(e) => println!("Error while serializing tick: {:?}", e),
+        }
+
+        if self.buffer.len() >= self.batch_size {
+            if let Err(e) = self.flush() {
+                println!("{}", e);
+            }
+        }
+    }
+}
+
+impl<T> RedisChannelSink<T> {
+    /// Publishes all buffered ticks.  The buffer is emptied even if publishing fails.
+    pub fn flush(&mut self) -> Result<(), String> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        let mut pipe = redis::pipe();
+        for msg in self.buffer.drain(..) {
+            pipe.cmd("PUBLISH")
+                .arg(&self.channel)
+                .arg(msg)
+                .ignore();
+        }
+        pipe.query::<()>(&self.client)
+            .map_err(|err| format!("Error while publishing ticks to {}: {:?}", self.channel, err))
+    }
+}
+
+impl<T> Drop for RedisChannelSink<T> {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            println!("{}", e);
+        }
+    }
+}